@@ -4,30 +4,71 @@
 use anyhow::{ensure, Context, Result};
 use directories::ProjectDirs;
 use figment::{
-    providers::{Env, Format, Serialized, Toml},
+    providers::{Env, Format, Json, Serialized, Toml},
     Figment,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub const MAX_TRANSFER_CHUNK_SIZE_BYTES: u64 = 10 * 1024 * 1024;
+use super::data_dir::{default_data_dir, DataDir};
+use crate::utils::size::{format_size, parse_size};
+
+pub const MAX_TRANSFER_CHUNK_SIZE_BYTES: u64 = 128 * 1024 * 1024;
+/// Smallest chunk `--chunk-size` accepts
+pub const MIN_CHUNK_SIZE_OVERRIDE: u64 = 64 * 1024;
+/// Room in an upload request for multipart framing and the form fields
+const UPLOAD_FRAMING_BYTES: u64 = 64 * 1024;
+
+/// Request body cap for uploading one chunk of `chunk_size` bytes.
+///
+/// A tenth on top covers the GCM tag and FormData encoding overhead.
+pub fn receive_body_limit(chunk_size: u64) -> usize {
+    let limit = chunk_size
+        .saturating_add(chunk_size / 10)
+        .saturating_add(UPLOAD_FRAMING_BYTES);
+    usize::try_from(limit).unwrap_or(usize::MAX)
+}
 const MAX_CONCURRENCY: usize = 256;
+/// Range `--concurrency` is clamped to; browsers gain nothing past this
+pub const CLI_CONCURRENCY_RANGE: std::ops::RangeInclusive<usize> = 1..=64;
+const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
+const DEFAULT_GLOBAL_CONCURRENCY: usize = 64;
+/// Auto `max_connections` per unit of transport `concurrency`: the browser
+/// works on up to four files at once, each with `concurrency` chunk fetches,
+/// and retries can briefly double that
+const CONNECTIONS_PER_CONCURRENCY: usize = 8;
+/// Auto `max_connections` headroom for page assets and status polls
+const CONNECTION_HEADROOM: usize = 16;
+/// Far above what a browser at full speed issues (chunks are 1-10 MiB), so
+/// only runaway loops hit it
+const DEFAULT_CLIENT_RATE_PER_SEC: u32 = 500;
+const DEFAULT_CLIENT_RATE_BURST: u32 = 1000;
 
 const LOCAL_TRANSFER: TransferSettings = TransferSettings {
     chunk_size: 10 * 1024 * 1024,
     concurrency: 8,
+    retry_min_ms: 250,
+    retry_max_ms: 4_000,
 };
 
+// Tunnels drop requests in bursts (edge restarts, relay hiccups), so
+// retries wait longer before giving up
 const CLOUDFLARE_TRANSFER: TransferSettings = TransferSettings {
     chunk_size: 1024 * 1024,
     concurrency: 2,
+    retry_min_ms: 1_000,
+    retry_max_ms: 30_000,
 };
 
 const TAILSCALE_TRANSFER: TransferSettings = TransferSettings {
     chunk_size: 2 * 1024 * 1024,
     concurrency: 4,
+    retry_min_ms: 1_000,
+    retry_max_ms: 15_000,
 };
 
+const NGROK_TRANSFER: TransferSettings = CLOUDFLARE_TRANSFER;
+
 pub fn config_path() -> PathBuf {
     ProjectDirs::from("", "", "archdrop")
         .map(|p| p.config_dir().join("config.toml"))
@@ -42,6 +83,17 @@ pub enum Transport {
     Local,
     Cloudflare,
     Tailscale,
+    Ngrok,
+}
+
+/// Lowest TLS protocol version the HTTPS server accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[default]
+    #[serde(rename = "1.3")]
+    Tls13,
 }
 
 /// Transfer tuning parameters shared by all transports.
@@ -51,6 +103,10 @@ pub struct TransferSettings {
     pub chunk_size: u64,
     /// Max concurrent chunks per transfer
     pub concurrency: usize,
+    /// First retry delay the web client uses after a failed chunk request
+    pub retry_min_ms: u64,
+    /// Cap on the doubling retry delay
+    pub retry_max_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +130,13 @@ pub struct TailscaleSettings {
     pub transfer: TransferSettings,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NgrokSettings {
+    pub port: u16,
+    #[serde(flatten)]
+    pub transfer: TransferSettings,
+}
+
 impl Default for LocalSettings {
     fn default() -> Self {
         Self {
@@ -101,11 +164,22 @@ impl Default for TailscaleSettings {
     }
 }
 
+impl Default for NgrokSettings {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            transfer: NGROK_TRANSFER,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct TuiSettings {
     pub show_qr: bool,
     pub show_url: bool,
+    /// Pack token/key/nonce into one fragment value for a smaller QR code.
+    pub compact_url: bool,
 }
 
 impl Default for TuiSettings {
@@ -113,6 +187,79 @@ impl Default for TuiSettings {
         Self {
             show_qr: true,
             show_url: true,
+            compact_url: false,
+        }
+    }
+}
+
+/// TCP tuning for the local listening socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Pending-connection queue length passed to `listen(2)`
+    pub backlog: u32,
+    /// Disable Nagle on accepted connections (many small chunk exchanges)
+    pub nodelay: bool,
+    /// In-flight chunk requests across all clients
+    pub global_concurrency: usize,
+    /// In-flight chunk requests per client (0 = transport `concurrency`)
+    pub client_concurrency: usize,
+    /// Requests in flight at once before new ones get `503`
+    /// (0 = derived from transport `concurrency`)
+    pub max_connections: usize,
+    /// Chunk requests per second per client (0 = unlimited)
+    pub client_rate_per_sec: u32,
+    /// Requests a client may issue back to back before the rate applies
+    pub client_rate_burst: u32,
+    /// Lowest TLS version offered by the local HTTPS server
+    pub min_tls: TlsVersion,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            backlog: DEFAULT_LISTEN_BACKLOG,
+            nodelay: true,
+            global_concurrency: DEFAULT_GLOBAL_CONCURRENCY,
+            client_concurrency: 0,
+            max_connections: 0,
+            client_rate_per_sec: DEFAULT_CLIENT_RATE_PER_SEC,
+            client_rate_burst: DEFAULT_CLIENT_RATE_BURST,
+            min_tls: TlsVersion::default(),
+        }
+    }
+}
+
+/// Server-side caps on in-flight chunk requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    pub global: usize,
+    pub per_client: usize,
+}
+
+impl ConcurrencyLimits {
+    /// Limits matching what a client is told to use, with default global headroom.
+    pub fn for_transfer(transfer: TransferSettings) -> Self {
+        Self {
+            global: DEFAULT_GLOBAL_CONCURRENCY.max(transfer.concurrency),
+            per_client: transfer.concurrency,
+        }
+    }
+}
+
+/// Server-side token bucket on chunk requests per client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Sustained requests per second; 0 disables limiting
+    pub per_sec: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            per_sec: DEFAULT_CLIENT_RATE_PER_SEC,
+            burst: DEFAULT_CLIENT_RATE_BURST,
         }
     }
 }
@@ -126,16 +273,27 @@ pub struct AppConfig {
     pub local: LocalSettings,
     pub cloudflare: CloudflareSettings,
     pub tailscale: TailscaleSettings,
+    pub ngrok: NgrokSettings,
     pub tui: TuiSettings,
+    pub network: NetworkSettings,
+    /// Where persisted state lives (default: the platform data dir)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
 }
 
 impl AppConfig {
+    /// Data directory from `--data-dir`/config, or the platform default.
+    pub fn data_dir(&self) -> DataDir {
+        DataDir::new(self.data_dir.clone().unwrap_or_else(default_data_dir))
+    }
+
     /// Returns transfer settings for the selected transport.
     pub fn transfer_settings(&self, transport: Transport) -> TransferSettings {
         match transport {
             Transport::Local => self.local.transfer,
             Transport::Cloudflare => self.cloudflare.transfer,
             Transport::Tailscale => self.tailscale.transfer,
+            Transport::Ngrok => self.ngrok.transfer,
         }
     }
 
@@ -145,6 +303,38 @@ impl AppConfig {
             Transport::Local => self.local.port,
             Transport::Cloudflare => self.cloudflare.port,
             Transport::Tailscale => self.tailscale.port,
+            Transport::Ngrok => self.ngrok.port,
+        }
+    }
+
+    /// Returns server-side concurrency caps for the selected transport.
+    pub fn concurrency_limits(&self, transport: Transport) -> ConcurrencyLimits {
+        let per_client = match self.network.client_concurrency {
+            0 => self.transfer_settings(transport).concurrency,
+            limit => limit,
+        };
+        ConcurrencyLimits {
+            global: self.network.global_concurrency,
+            per_client,
+        }
+    }
+
+    /// Returns the cap on requests in flight at once for `transport`.
+    pub fn max_connections(&self, transport: Transport) -> usize {
+        match self.network.max_connections {
+            0 => {
+                self.transfer_settings(transport).concurrency * CONNECTIONS_PER_CONCURRENCY
+                    + CONNECTION_HEADROOM
+            }
+            limit => limit,
+        }
+    }
+
+    /// Returns the per-client chunk request rate limit.
+    pub fn rate_limits(&self) -> RateLimits {
+        RateLimits {
+            per_sec: self.network.client_rate_per_sec,
+            burst: self.network.client_rate_burst,
         }
     }
 
@@ -153,6 +343,23 @@ impl AppConfig {
         Self::validate_transfer("local", self.local.transfer)?;
         Self::validate_transfer("cloudflare", self.cloudflare.transfer)?;
         Self::validate_transfer("tailscale", self.tailscale.transfer)?;
+        Self::validate_transfer("ngrok", self.ngrok.transfer)?;
+        ensure!(
+            self.network.backlog >= 1,
+            "Invalid config: network.backlog must be >= 1"
+        );
+        ensure!(
+            (1..=MAX_CONCURRENCY).contains(&self.network.global_concurrency),
+            "Invalid config: network.global_concurrency must be between 1 and {MAX_CONCURRENCY}"
+        );
+        ensure!(
+            self.network.client_concurrency <= MAX_CONCURRENCY,
+            "Invalid config: network.client_concurrency must be <= {MAX_CONCURRENCY}"
+        );
+        ensure!(
+            self.network.client_rate_per_sec == 0 || self.network.client_rate_burst >= 1,
+            "Invalid config: network.client_rate_burst must be >= 1 when rate limiting is on"
+        );
         Ok(())
     }
 
@@ -161,6 +368,16 @@ impl AppConfig {
             Transport::Local => self.local.port = port,
             Transport::Cloudflare => self.cloudflare.port = port,
             Transport::Tailscale => self.tailscale.port = port,
+            Transport::Ngrok => self.ngrok.port = port,
+        }
+    }
+
+    fn transfer_settings_mut(&mut self, transport: Transport) -> &mut TransferSettings {
+        match transport {
+            Transport::Local => &mut self.local.transfer,
+            Transport::Cloudflare => &mut self.cloudflare.transfer,
+            Transport::Tailscale => &mut self.tailscale.transfer,
+            Transport::Ngrok => &mut self.ngrok.transfer,
         }
     }
 
@@ -181,6 +398,14 @@ impl AppConfig {
             transfer.concurrency <= MAX_CONCURRENCY,
             "Invalid config: {name}.concurrency must be <= {MAX_CONCURRENCY}"
         );
+        ensure!(
+            transfer.retry_min_ms >= 1,
+            "Invalid config: {name}.retry_min_ms must be >= 1"
+        );
+        ensure!(
+            transfer.retry_max_ms >= transfer.retry_min_ms,
+            "Invalid config: {name}.retry_max_ms must be >= {name}.retry_min_ms"
+        );
         Ok(())
     }
 }
@@ -193,7 +418,10 @@ impl Default for AppConfig {
             local: LocalSettings::default(),
             cloudflare: CloudflareSettings::default(),
             tailscale: TailscaleSettings::default(),
+            ngrok: NgrokSettings::default(),
             tui: TuiSettings::default(),
+            network: NetworkSettings::default(),
+            data_dir: None,
         }
     }
 }
@@ -204,15 +432,61 @@ pub struct ConfigOverrides {
     pub transport: Option<Transport>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_tls: Option<TlsVersion>,
+    /// Parallel chunk requests for the effective transport (clamped to 1-64)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    /// Chunk size in bytes for the effective transport
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u64>,
+    /// Requests in flight at once before the server answers `503`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
+}
+
+/// Parse `--chunk-size` (`8M`, `512K`, plain bytes) and check its bounds.
+///
+/// Usable directly as a clap `value_parser`.
+pub fn parse_chunk_size(input: &str) -> Result<u64, String> {
+    let bytes = parse_size(input)?;
+    if !(MIN_CHUNK_SIZE_OVERRIDE..=MAX_TRANSFER_CHUNK_SIZE_BYTES).contains(&bytes) {
+        return Err(format!(
+            "chunk size must be between {} and {}",
+            format_size(MIN_CHUNK_SIZE_OVERRIDE),
+            format_size(MAX_TRANSFER_CHUNK_SIZE_BYTES)
+        ));
+    }
+    Ok(bytes)
 }
 
 /// Loads config from defaults/file/env.
 pub fn load_config() -> Result<AppConfig> {
-    let path = config_path();
+    load_layers(&config_path())
+}
+
+/// Loads config with `path` in place of the default config file.
+///
+/// The file is TOML, or JSON when it ends in `.json`. Unlike the default
+/// file, an explicitly chosen one must exist.
+pub fn load_config_from(path: &Path) -> Result<AppConfig> {
+    ensure!(path.is_file(), "Config file not found: {}", path.display());
+    load_layers(path)
+}
+
+fn load_layers(path: &Path) -> Result<AppConfig> {
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let file = if is_json {
+        Figment::from(Json::file(path))
+    } else {
+        Figment::from(Toml::file(path))
+    };
 
     let config: AppConfig = Figment::new()
         .merge(Serialized::defaults(AppConfig::default()))
-        .merge(Toml::file(&path))
+        .merge(file)
         .merge(Env::prefixed("ARCHDROP_").split("_"))
         .extract()
         .context("Failed to load configuration")?;
@@ -228,6 +502,29 @@ pub fn apply_overrides(mut config: AppConfig, overrides: &ConfigOverrides) -> Ap
         let transport = overrides.transport.unwrap_or(config.default_transport);
         config.set_port(transport, port);
     }
+    if let Some(min_tls) = overrides.min_tls {
+        config.network.min_tls = min_tls;
+    }
+    if let Some(requested) = overrides.concurrency {
+        let concurrency =
+            requested.clamp(*CLI_CONCURRENCY_RANGE.start(), *CLI_CONCURRENCY_RANGE.end());
+        if concurrency != requested {
+            tracing::warn!(
+                "Concurrency {} out of range, using {}",
+                requested,
+                concurrency
+            );
+        }
+        let transport = overrides.transport.unwrap_or(config.default_transport);
+        config.transfer_settings_mut(transport).concurrency = concurrency;
+    }
+    if let Some(chunk_size) = overrides.chunk_size {
+        let transport = overrides.transport.unwrap_or(config.default_transport);
+        config.transfer_settings_mut(transport).chunk_size = chunk_size;
+    }
+    if let Some(max_connections) = overrides.max_connections {
+        config.network.max_connections = max_connections;
+    }
 
     config
 }
@@ -0,0 +1,151 @@
+//! Per-user data directory for state that outlives a single transfer.
+//!
+//! Subsystems that persist files (session state, caches, logs) keep them in
+//! named subdirectories here instead of the temp dir or the working directory.
+
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Platform data directory, e.g. `~/.local/share/archdrop` on Linux.
+pub fn default_data_dir() -> PathBuf {
+    ProjectDirs::from("", "", "archdrop")
+        .map(|p| p.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from(".archdrop"))
+}
+
+/// Root of ArchDrop's persisted files. Created lazily on first use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Create the root (owner-only on Unix) if needed and return it.
+    pub fn ensure(&self) -> Result<&Path> {
+        create_private_dir(&self.root)?;
+        Ok(&self.root)
+    }
+
+    /// Create and return a named subdirectory, e.g. `sessions`.
+    pub fn subdir(&self, name: &str) -> Result<PathBuf> {
+        self.ensure()?;
+        let dir = self.root.join(name);
+        create_private_dir(&dir)?;
+        Ok(dir)
+    }
+
+    /// Write `value` as JSON to `<subdir>/<file>`, replacing it atomically.
+    pub fn write_state<T: Serialize>(
+        &self,
+        subdir: &str,
+        file: &str,
+        value: &T,
+    ) -> Result<PathBuf> {
+        let path = self.subdir(subdir)?.join(file);
+        let tmp = path.with_extension("tmp");
+        let json = serde_json::to_vec_pretty(value).context("Failed to serialize state")?;
+
+        write_private_file(&tmp, &json)?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Read JSON state written by `write_state`; None when it does not exist.
+    pub fn read_state<T: DeserializeOwned>(&self, subdir: &str, file: &str) -> Result<Option<T>> {
+        let path = self.root.join(subdir).join(file);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .with_context(|| format!("Corrupt state file {}", path.display()))
+    }
+}
+
+fn create_private_dir(dir: &Path) -> Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(dir)
+        .with_context(|| format!("Cannot create data directory {}", dir.display()))
+}
+
+fn write_private_file(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+    std::io::Write::write_all(&mut file, contents)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{PersistedSessionStatus, SessionSnapshot};
+
+    #[test]
+    fn creates_dir_on_first_use_and_persists_state() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let data_dir = DataDir::new(temp.path().join("nested").join("archdrop"));
+        assert!(!data_dir.path().exists());
+
+        let snapshot = SessionSnapshot {
+            token: "token-1".to_string(),
+            status: PersistedSessionStatus::Completed,
+        };
+        let written = data_dir
+            .write_state("sessions", "token-1.json", &snapshot)
+            .expect("write state");
+
+        assert!(written.starts_with(data_dir.path()));
+        let loaded: Option<SessionSnapshot> = data_dir
+            .read_state("sessions", "token-1.json")
+            .expect("read state");
+        assert_eq!(loaded, Some(snapshot));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(data_dir.path()), 0o700);
+            assert_eq!(mode(&data_dir.path().join("sessions")), 0o700);
+            assert_eq!(mode(&written), 0o600);
+        }
+    }
+
+    #[test]
+    fn missing_state_reads_as_none() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let data_dir = DataDir::new(temp.path().to_path_buf());
+        let loaded: Option<SessionSnapshot> =
+            data_dir.read_state("sessions", "absent.json").unwrap();
+        assert!(loaded.is_none());
+    }
+}
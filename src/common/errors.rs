@@ -3,6 +3,7 @@ use axum::{
     response::{IntoResponse, Json as AxumJson, Response},
 };
 use serde_json::json;
+use std::io;
 use thiserror::Error;
 
 /// Structured error types for HTTP status code mapping
@@ -23,11 +24,46 @@ pub enum AppError {
     #[error("Insufficient storage: {0}")]
     InsufficientStorage(String),
 
+    #[error("Range not satisfiable: {0}")]
+    RangeNotSatisfiable(String),
+
+    /// The client spent its request budget; it should slow down and retry
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    /// Serving more would pass the `--max-transfer` cap
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    /// Every `--max-connections` slot is busy; retry once one frees up
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// The route exists but not for this method; the router adds `Allow`
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
     /// Catch-all for unexpected errors - logs full context internally
     #[error("Internal server error")]
     Internal(#[from] anyhow::Error),
 }
 
+/// True when an error chain bottoms out in the peer closing the connection.
+///
+/// Receivers cancelling mid-response is routine, not a server fault.
+pub fn is_client_disconnect(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|io_err| {
+            matches!(
+                io_err.kind(),
+                io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            )
+        })
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_type, message) = match self {
@@ -40,6 +76,32 @@ impl IntoResponse for AppError {
                 "insufficient_storage",
                 msg,
             ),
+            AppError::RangeNotSatisfiable(msg) => (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "range_not_satisfiable",
+                msg,
+            ),
+            AppError::TooManyRequests(msg) => {
+                (StatusCode::TOO_MANY_REQUESTS, "too_many_requests", msg)
+            }
+            AppError::PayloadTooLarge(msg) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", msg)
+            }
+            AppError::MethodNotAllowed(msg) => {
+                (StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed", msg)
+            }
+            AppError::ServiceUnavailable(msg) => {
+                (StatusCode::SERVICE_UNAVAILABLE, "service_unavailable", msg)
+            }
+            AppError::Internal(ref err) if is_client_disconnect(err) => {
+                // The client hung up; nobody is left to read the response
+                tracing::debug!(error = %err, "Client disconnected mid-transfer");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal_error",
+                    "An internal error occurred".to_string(),
+                )
+            }
             AppError::Internal(ref err) => {
                 // Log full error with backtrace server-side
                 tracing::error!(
@@ -66,3 +128,64 @@ impl IntoResponse for AppError {
         (status, body).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn logs_for(err: AppError) -> String {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = err.into_response();
+        });
+        let bytes = logs.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn detects_disconnect_kinds_anywhere_in_chain() {
+        let reset: anyhow::Error = io::Error::from(io::ErrorKind::ConnectionReset).into();
+        assert!(is_client_disconnect(&reset.context("write chunk")));
+
+        let not_found: anyhow::Error = io::Error::from(io::ErrorKind::NotFound).into();
+        assert!(!is_client_disconnect(&not_found));
+        assert!(!is_client_disconnect(&anyhow::anyhow!("boom")));
+    }
+
+    #[test]
+    fn dropped_connection_logs_at_debug_not_error() {
+        let broken_pipe = Err::<(), _>(io::Error::from(io::ErrorKind::BrokenPipe))
+            .context("write chunk")
+            .unwrap_err();
+
+        let logs = logs_for(AppError::Internal(broken_pipe));
+        assert!(logs.contains("DEBUG"), "logs: {logs}");
+        assert!(logs.contains("Client disconnected"), "logs: {logs}");
+        assert!(!logs.contains("ERROR"), "logs: {logs}");
+
+        let logs = logs_for(AppError::Internal(anyhow::anyhow!("disk on fire")));
+        assert!(logs.contains("ERROR"), "logs: {logs}");
+    }
+}
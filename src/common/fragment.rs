@@ -0,0 +1,213 @@
+//! Transfer link fragment encoding.
+//!
+//! The default fragment spells out `token=`, `key=` and `nonce=` params. The
+//! packed form carries the same values as one base64url blob (`p=`), which
+//! keeps the URL, and therefore the QR code, noticeably smaller.
+//!
+//! Passphrase links (`--password`) replace `key=` with `salt=` and are never
+//! packed.
+//!
+//! Links for [`CipherSuite::PositionBound`] sessions add `v=2` (params) or
+//! carry the version in the flags byte (packed). A link without either is v1.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::crypto::password::KeySalt;
+use crate::crypto::types::{decode_url_base64, EncryptionKey, Nonce};
+use crate::crypto::CipherSuite;
+
+/// Packed layout: token (16) || key (32) || nonce (8) || flags (1), where
+/// the flags byte is the [`CipherSuite::version`].
+const TOKEN_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 8;
+const PACKED_LEN: usize = TOKEN_LEN + KEY_LEN + NONCE_LEN + 1;
+
+/// Values the web client needs from the URL fragment.
+#[derive(Debug, Clone)]
+pub struct LinkFragment {
+    pub token: String,
+    pub key: EncryptionKey,
+    pub nonce: Nonce,
+    /// Present when the key is passphrase-derived; the key is then omitted
+    pub salt: Option<KeySalt>,
+    pub suite: CipherSuite,
+}
+
+impl LinkFragment {
+    pub fn new(token: &str, key: &EncryptionKey, nonce: &Nonce) -> Self {
+        Self {
+            token: token.to_string(),
+            key: key.clone(),
+            nonce: nonce.clone(),
+            salt: None,
+            suite: CipherSuite::PositionBound,
+        }
+    }
+
+    /// Advertise `suite` instead of the default v2.
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    /// Send `salt` in place of the key.
+    pub fn with_salt(mut self, salt: KeySalt) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Render the fragment (without `#`), packed when `compact` is set.
+    ///
+    /// Tokens that are not UUIDs cannot be packed and fall back to params.
+    pub fn encode(&self, compact: bool) -> String {
+        if compact {
+            if let Some(packed) = self.to_packed() {
+                return format!("p={}", packed);
+            }
+        }
+        self.to_params()
+    }
+
+    /// Separate `token`/`key`/`nonce` query-style params.
+    pub fn to_params(&self) -> String {
+        let params = match &self.salt {
+            Some(salt) => format!(
+                "token={}&salt={}&nonce={}",
+                self.token,
+                salt.to_base64(),
+                self.nonce.to_base64()
+            ),
+            None => format!(
+                "token={}&key={}&nonce={}",
+                self.token,
+                self.key.to_base64(),
+                self.nonce.to_base64()
+            ),
+        };
+        match self.suite {
+            // v1 links predate the param; keep them byte-for-byte the same
+            CipherSuite::Legacy => params,
+            suite => format!("{}&v={}", params, suite.version()),
+        }
+    }
+
+    /// base64url of the packed binary layout, or None if the token isn't a UUID.
+    pub fn to_packed(&self) -> Option<String> {
+        if self.salt.is_some() {
+            return None;
+        }
+        let token = Uuid::parse_str(&self.token).ok()?;
+
+        let mut packed = Zeroizing::new(Vec::with_capacity(PACKED_LEN));
+        packed.extend_from_slice(token.as_bytes());
+        packed.extend_from_slice(self.key.as_bytes());
+        packed.extend_from_slice(self.nonce.as_bytes());
+        packed.push(self.suite.version());
+
+        Some(general_purpose::URL_SAFE_NO_PAD.encode(&*packed))
+    }
+
+    /// Decode a packed fragment value produced by `to_packed`.
+    pub fn from_packed(packed: &str) -> Result<Self> {
+        let bytes =
+            Zeroizing::new(decode_url_base64(packed).context("Invalid packed fragment encoding")?);
+        anyhow::ensure!(bytes.len() == PACKED_LEN, "Invalid packed fragment length");
+
+        let flags = bytes[PACKED_LEN - 1];
+        let suite = CipherSuite::from_version(flags)
+            .with_context(|| format!("Unsupported packed fragment flags: {:#04x}", flags))?;
+
+        let (token, rest) = bytes.split_at(TOKEN_LEN);
+        let (key, rest) = rest.split_at(KEY_LEN);
+        let nonce = &rest[..NONCE_LEN];
+
+        let token = Uuid::from_slice(token).context("Invalid packed token")?;
+        Ok(Self {
+            token: token.hyphenated().to_string(),
+            key: EncryptionKey::from_bytes(key.try_into().context("Invalid packed key")?),
+            nonce: Nonce::from_base64(&general_purpose::URL_SAFE_NO_PAD.encode(nonce))?,
+            salt: None,
+            suite,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> LinkFragment {
+        let token = Uuid::new_v4().to_string();
+        LinkFragment::new(&token, &EncryptionKey::new(), &Nonce::new())
+    }
+
+    #[test]
+    fn packed_fragment_round_trips_token_key_and_nonce() {
+        let fragment = sample();
+        let packed = fragment.to_packed().expect("uuid token packs");
+
+        let decoded = LinkFragment::from_packed(&packed).expect("decode packed");
+
+        assert_eq!(decoded.token, fragment.token);
+        assert_eq!(decoded.key.as_bytes(), fragment.key.as_bytes());
+        assert_eq!(decoded.nonce.as_bytes(), fragment.nonce.as_bytes());
+        assert_eq!(decoded.suite, CipherSuite::PositionBound);
+    }
+
+    #[test]
+    fn suite_version_is_carried_in_both_forms() {
+        let legacy = sample().with_suite(CipherSuite::Legacy);
+        assert!(!legacy.to_params().contains("v="));
+        let packed = legacy.to_packed().unwrap();
+        assert_eq!(
+            LinkFragment::from_packed(&packed).unwrap().suite,
+            CipherSuite::Legacy
+        );
+
+        assert!(sample().to_params().ends_with("&v=2"));
+    }
+
+    #[test]
+    fn packed_fragment_is_shorter_than_params() {
+        let fragment = sample();
+        assert!(fragment.encode(true).len() < fragment.encode(false).len());
+        assert!(fragment.encode(true).starts_with("p="));
+    }
+
+    #[test]
+    fn non_uuid_token_falls_back_to_params() {
+        let fragment = LinkFragment::new("custom-token", &EncryptionKey::new(), &Nonce::new());
+        assert!(fragment.to_packed().is_none());
+        assert!(fragment.encode(true).starts_with("token=custom-token&key="));
+    }
+
+    #[test]
+    fn passphrase_link_carries_salt_and_never_the_key() {
+        let key = EncryptionKey::new();
+        let salt = KeySalt::new();
+        let token = Uuid::new_v4().to_string();
+        let fragment = LinkFragment::new(&token, &key, &Nonce::new()).with_salt(salt.clone());
+
+        for compact in [false, true] {
+            let encoded = fragment.encode(compact);
+            assert!(encoded.contains(&format!("salt={}", salt.to_base64())));
+            assert!(!encoded.contains(&key.to_base64()));
+            assert!(!encoded.starts_with("p="));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_or_unknown_packed_values() {
+        let packed = sample().to_packed().unwrap();
+        assert!(LinkFragment::from_packed(&packed[..packed.len() - 4]).is_err());
+
+        let mut bytes = general_purpose::URL_SAFE_NO_PAD.decode(&packed).unwrap();
+        *bytes.last_mut().unwrap() = 0x80;
+        let unknown = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        assert!(LinkFragment::from_packed(&unknown).is_err());
+    }
+}
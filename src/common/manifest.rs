@@ -2,12 +2,33 @@
 
 use super::TransferSettings;
 use anyhow::{Context, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
-use crate::{crypto::types::Nonce, utils::security};
+use crate::{
+    crypto::{self, signing, signing::SigningKey, types::EncryptionKey, types::Nonce},
+    utils::{run_blocking, security},
+};
 
-const MAX_CHUNKS_PER_FILE: u64 = (u32::MAX as u64) + 1;
+/// The counter's top bit marks compressed chunks, leaving 31 bits of index.
+const MAX_CHUNKS_PER_FILE: u64 = crypto::COMPRESSED_COUNTER_FLAG as u64;
+
+/// Draw a nonce base not yet in `used`, redrawing on collision.
+///
+/// Two files sharing a base under one session key would reuse every
+/// (base, counter) pair they have in common, which breaks AES-GCM.
+fn unique_nonce(used: &mut HashSet<[u8; 8]>, rng: &mut impl RngCore) -> Nonce {
+    loop {
+        let nonce = Nonce::from_rng(rng);
+        if used.insert(*nonce.as_bytes()) {
+            return nonce;
+        }
+        tracing::warn!("Nonce base collision between files - regenerating");
+    }
+}
 
 /// Validates that file chunk count fits encryption nonce/counter limits.
 pub fn validate_nonce_counter_chunks(
@@ -26,6 +47,38 @@ pub fn validate_nonce_counter_chunks(
     Ok(())
 }
 
+/// Preflight check that a source file can be opened for reading.
+async fn ensure_readable(path: &Path) -> Result<()> {
+    match tokio::fs::File::open(path).await {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+            anyhow::bail!("Permission denied: cannot read {}", path.display())
+        }
+        Err(err) => Err(err).context(format!("Cannot open {} for reading", path.display())),
+    }
+}
+
+/// Split a concurrency budget across files in proportion to their chunk counts.
+///
+/// Every file with chunks gets at least one slot and never more than it has
+/// chunks, so large files are not starved behind many small ones.
+pub fn parallelism_weights(chunk_counts: &[u64], budget: usize) -> Vec<usize> {
+    let budget = budget.max(1);
+    let total: u64 = chunk_counts.iter().sum();
+    chunk_counts
+        .iter()
+        .map(|&chunks| {
+            if chunks == 0 || total == 0 {
+                return 1;
+            }
+            let share = (budget as u128 * chunks as u128).div_ceil(total as u128) as usize;
+            share
+                .clamp(1, budget)
+                .min(chunks.min(budget as u64) as usize)
+        })
+        .collect()
+}
+
 /// Metadata for a single file during transfer
 #[derive(Serialize, Deserialize, Clone)]
 pub struct FileEntry {
@@ -36,6 +89,33 @@ pub struct FileEntry {
     pub relative_path: String,
     pub size: u64,
     pub nonce: String,
+    /// Suggested concurrent chunk requests for this file (0 = use config)
+    #[serde(default)]
+    pub parallelism: usize,
+    /// SHA-256 of the contents (hex), for the receiver to check after
+    /// reassembly. Only set up front for signed manifests and streams; other
+    /// files are hashed on request once downloaded (`GET /send/:file_index/hash`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Whole-file MAC over `hash` (hex), set by [`Manifest::add_file_macs`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+}
+
+/// Sender identity signature over [`Manifest::signing_payload`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestSignature {
+    /// Ed25519 public key, URL-safe base64
+    pub public_key: String,
+    /// Ed25519 signature, URL-safe base64
+    pub signature: String,
+}
+
+/// Compression a sender may apply to chunk plaintext before encryption.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkCompression {
+    Zstd,
 }
 
 /// Contains all files to be transfered & config
@@ -43,16 +123,25 @@ pub struct FileEntry {
 pub struct Manifest {
     pub files: Vec<FileEntry>,
     pub config: TransferSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ManifestSignature>,
+    /// Offered to clients that ask for it; each compressed chunk says so
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<ChunkCompression>,
 }
 
 impl Manifest {
     /// Builds a manifest from input paths and assigns one unique nonce per file.
+    ///
+    /// Files are not read here; see [`Manifest::hash_files`] for when their
+    /// hashes have to be known before serving.
     pub async fn new(
         file_paths: Vec<PathBuf>,
         base_path: Option<&Path>,
         config: TransferSettings,
     ) -> Result<Self> {
         let mut files = Vec::new();
+        let mut used_nonces = HashSet::new();
 
         // determine common base, no base, use parent
         let base = base_path.map(|p| p.to_path_buf()).unwrap_or_else(|| {
@@ -67,6 +156,9 @@ impl Manifest {
                 .await
                 .context(format!("Failed to read metadata for: {}", path.display()))?;
 
+            // Fail before serving rather than as a 500 mid-transfer
+            ensure_readable(&path).await?;
+
             let relative = path
                 .strip_prefix(&base)
                 .unwrap_or(path.as_path())
@@ -84,7 +176,7 @@ impl Manifest {
             security::validate_filename(&name).context("Invalid fine name")?;
 
             // Unique nonce for each file
-            let nonce = Nonce::new();
+            let nonce = unique_nonce(&mut used_nonces, &mut OsRng);
 
             files.push(FileEntry {
                 index,
@@ -93,10 +185,169 @@ impl Manifest {
                 relative_path: relative,
                 nonce: nonce.to_base64(),
                 full_path: path,
+                parallelism: 0,
+                hash: None,
+                mac: None,
             });
         }
 
-        Ok(Manifest { files, config })
+        let chunk_counts: Vec<u64> = files
+            .iter()
+            .map(|f| f.size.div_ceil(config.chunk_size))
+            .collect();
+        for (file, weight) in files
+            .iter_mut()
+            .zip(parallelism_weights(&chunk_counts, config.concurrency))
+        {
+            file.parallelism = weight;
+        }
+
+        Ok(Manifest {
+            files,
+            config,
+            signature: None,
+            compression: None,
+        })
+    }
+
+    /// One-file manifest for content that is produced on demand rather than
+    /// read from a path (`send --tar`). The sender supplies its size and hash.
+    pub fn for_stream(
+        name: &str,
+        size: u64,
+        hash: String,
+        config: TransferSettings,
+    ) -> Result<Self> {
+        security::validate_filename(name).context("Invalid file name")?;
+        validate_nonce_counter_chunks(size, config.chunk_size, name)?;
+
+        let chunks = size.div_ceil(config.chunk_size);
+        Ok(Manifest {
+            files: vec![FileEntry {
+                index: 0,
+                name: name.to_string(),
+                full_path: PathBuf::new(),
+                relative_path: name.to_string(),
+                size,
+                nonce: Nonce::new().to_base64(),
+                parallelism: parallelism_weights(&[chunks], config.concurrency)[0],
+                hash: Some(hash),
+                mac: None,
+            }],
+            config,
+            signature: None,
+            compression: None,
+        })
+    }
+
+    /// Strong ETag over the serialized manifest (quoted, as sent in headers).
+    pub fn etag(&self) -> String {
+        let serialized = serde_json::to_string(self).unwrap_or_default();
+        format!("\"{}\"", security::hash_path(&serialized))
+    }
+
+    /// Advertise the single file under `name` instead of its basename.
+    ///
+    /// Must run before signing, since the name is part of the signed path.
+    pub fn set_download_name(&mut self, name: &str) -> Result<()> {
+        security::validate_filename(name)
+            .with_context(|| format!("Invalid download name '{}'", name))?;
+        let [file] = self.files.as_mut_slice() else {
+            anyhow::bail!(
+                "--name needs exactly one file to send ({} given); add --zip to send several",
+                self.files.len()
+            );
+        };
+        file.name = name.to_string();
+        file.relative_path = name.to_string();
+        Ok(())
+    }
+
+    /// Bytes covered by the sender signature.
+    ///
+    /// A compact JSON array so the web client can rebuild it exactly with
+    /// `JSON.stringify`: `["archdrop-manifest-v2", chunk_size,
+    /// [[index, relative_path, size, nonce, sha256], ...]]`. The hash is
+    /// `null` for a file that was not hashed.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let files: Vec<_> = self
+            .files
+            .iter()
+            .map(|f| serde_json::json!([f.index, f.relative_path, f.size, f.nonce, f.hash]))
+            .collect();
+        serde_json::json!(["archdrop-manifest-v2", self.config.chunk_size, files])
+            .to_string()
+            .into_bytes()
+    }
+
+    /// Attach a signature made with the sender's long-term key.
+    pub fn sign(&mut self, key: &SigningKey) {
+        self.signature = Some(ManifestSignature {
+            public_key: key.public_key_base64(),
+            signature: key.sign(&self.signing_payload()),
+        });
+    }
+
+    /// Check the signature against a public key the receiver already trusts.
+    pub fn verify_signature(&self, pinned_public_key: &str) -> Result<()> {
+        let signature = self.signature.as_ref().context("Manifest is not signed")?;
+        anyhow::ensure!(
+            signature.public_key == pinned_public_key,
+            "Manifest was signed by a different key"
+        );
+        signing::verify(
+            pinned_public_key,
+            &self.signing_payload(),
+            &signature.signature,
+        )
+        .context("Manifest signature is invalid")
+    }
+
+    /// Hash every file that has no hash yet, reading each one in full.
+    ///
+    /// Only needed when the hashes must be in the manifest itself, i.e. before
+    /// [`Manifest::sign`]; otherwise the sender hashes a file the first time
+    /// a client asks for its hash after downloading it.
+    pub async fn hash_files(&mut self) -> Result<()> {
+        for file in &mut self.files {
+            if file.hash.is_some() {
+                continue;
+            }
+            let path = file.full_path.clone();
+            file.hash =
+                Some(run_blocking("file hash", move || crypto::calculate_file_hash(&path)).await?);
+        }
+        Ok(())
+    }
+
+    /// MAC every hashed file under the session key.
+    ///
+    /// Works from the digests of the hash pass, so files are not read again.
+    pub fn add_file_macs(&mut self, key: &EncryptionKey) -> Result<()> {
+        for file in &mut self.files {
+            if let Some(hash) = &file.hash {
+                file.mac = Some(crypto::hash::file_mac(key, hash)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail unless every file has its own valid nonce base.
+    ///
+    /// Checked again before serving, since a resumed session's manifest comes
+    /// from disk rather than from [`Manifest::new`].
+    pub fn ensure_unique_nonces(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for file in &self.files {
+            let nonce = Nonce::from_base64(&file.nonce)
+                .with_context(|| format!("Invalid nonce for {}", file.name))?;
+            anyhow::ensure!(
+                seen.insert(*nonce.as_bytes()),
+                "Nonce base reused by {}; refusing to serve",
+                file.name
+            );
+        }
+        Ok(())
     }
 
     /// Calculate total chunks needed for all files in manifest
@@ -110,16 +361,72 @@ mod tests {
     use super::*;
 
     #[test]
-    fn allows_max_u32_counter_space() {
-        let max_chunks = (u32::MAX as u64) + 1;
+    fn allows_max_counter_space() {
+        let max_chunks = 1u64 << 31;
         let file_size = max_chunks * 1024;
         let result = validate_nonce_counter_chunks(file_size, 1024, "max.bin");
         assert!(result.is_ok());
     }
 
     #[test]
-    fn rejects_more_than_u32_counter_space() {
-        let file_size = ((u32::MAX as u64) + 2) * 1024;
+    fn larger_files_get_more_parallelism() {
+        // 1 GiB next to two tiny files with 1 MiB chunks and a budget of 8
+        let weights = parallelism_weights(&[1024, 1, 2], 8);
+        assert_eq!(weights[0], 8);
+        assert_eq!(weights[1], 1);
+        assert_eq!(weights[2], 1);
+        assert!(weights[0] > weights[1]);
+
+        let even = parallelism_weights(&[10, 10], 8);
+        assert_eq!(even, vec![4, 4]);
+
+        // Empty files still get a slot; a single chunk never gets more than one
+        assert_eq!(parallelism_weights(&[0, 1], 4), vec![1, 1]);
+    }
+
+    #[test]
+    fn colliding_nonce_base_is_regenerated() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut used = HashSet::new();
+        let first = unique_nonce(&mut used, &mut StdRng::seed_from_u64(7));
+        // Same seed, so the first draw repeats `first` and must be redrawn
+        let second = unique_nonce(&mut used, &mut StdRng::seed_from_u64(7));
+
+        assert_ne!(first.as_bytes(), second.as_bytes());
+        assert_eq!(used.len(), 2);
+    }
+
+    #[test]
+    fn shared_nonce_base_is_refused_before_serving() {
+        let file = |name: &str, nonce: &Nonce| FileEntry {
+            index: 0,
+            name: name.to_string(),
+            full_path: PathBuf::new(),
+            relative_path: name.to_string(),
+            size: 1,
+            nonce: nonce.to_base64(),
+            parallelism: 0,
+            hash: None,
+            mac: None,
+        };
+        let shared = Nonce::new();
+        let mut manifest = Manifest {
+            files: vec![file("a.bin", &shared), file("b.bin", &Nonce::new())],
+            config: crate::common::config::LocalSettings::default().transfer,
+            signature: None,
+            compression: None,
+        };
+        assert!(manifest.ensure_unique_nonces().is_ok());
+
+        manifest.files[1].nonce = shared.to_base64();
+        let err = manifest.ensure_unique_nonces().unwrap_err();
+        assert!(err.to_string().contains("b.bin"), "{err}");
+    }
+
+    #[test]
+    fn rejects_more_than_counter_space() {
+        let file_size = ((1u64 << 31) + 1) * 1024;
         let result = validate_nonce_counter_chunks(file_size, 1024, "too-large.bin");
         assert!(result.is_err());
     }
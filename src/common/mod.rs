@@ -3,16 +3,24 @@
 //! Exposes config, error mapping, manifest metadata, and session primitives.
 pub mod config;
 pub mod config_commands;
+pub mod data_dir;
 pub mod errors;
+pub mod fragment;
 pub mod manifest;
 pub mod progress;
 pub mod session_core;
 
-pub use config::{AppConfig, ConfigOverrides, TransferSettings, Transport};
+pub use config::{AppConfig, ConfigOverrides, TlsVersion, TransferSettings, Transport};
+pub use data_dir::DataDir;
 pub use errors::AppError;
-pub use manifest::{FileEntry, Manifest};
-pub use progress::{FileProgress, FileStatus, TransferProgress};
-pub use session_core::{ClaimError, Session, SessionState};
+pub use fragment::LinkFragment;
+pub use manifest::{ChunkCompression, FileEntry, Manifest, ManifestSignature};
+pub use progress::{
+    FileProgress, FileStatus, Throughput, TransferEvent, TransferProgress, TransferStats,
+};
+pub use session_core::{
+    ClaimError, PersistedSessionStatus, Session, SessionSnapshot, SessionState,
+};
 
 /// Runtime contract for send/receive state implementations.
 #[async_trait::async_trait]
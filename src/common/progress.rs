@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 /// Status of an individual file transfer.
 #[derive(Clone, Debug, PartialEq)]
 pub enum FileStatus {
@@ -21,10 +23,65 @@ pub struct TransferProgress {
     pub files: Vec<FileProgress>,
     pub completed: usize,
     pub total: usize,
+    /// `(completed, target)` downloads when serving more than one client.
+    pub downloads: Option<(usize, usize)>,
+    /// Latest lifecycle event; terminal once the transfer has finished.
+    pub event: TransferEvent,
+    /// Recent payload rate and time-remaining estimate.
+    pub throughput: Throughput,
 }
 
 impl TransferProgress {
+    /// True when the transfer completed, failed, or was cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.event.is_terminal() || self.is_complete()
+    }
+
     pub fn is_complete(&self) -> bool {
-        self.total > 0 && self.completed >= self.total
+        let downloads_done = match self.downloads {
+            Some((completed, target)) => completed >= target,
+            None => true,
+        };
+        self.total > 0 && self.completed >= self.total && downloads_done
+    }
+}
+
+/// Payload rate over a recent window plus the estimated time remaining.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Throughput {
+    pub bytes_per_sec: f64,
+    /// None until a rate is known, or while the transfer is stalled
+    pub eta: Option<Duration>,
+}
+
+/// Summary carried by a successful completion event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferStats {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Transfer lifecycle event published on the tracker's progress channel.
+///
+/// `Progress` carries the overall chunk percentage; the other variants are
+/// terminal and distinguish an explicit completion from a failure or abort.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransferEvent {
+    Progress(f64),
+    Completed { stats: TransferStats },
+    Failed { reason: String },
+    Cancelled,
+}
+
+impl Default for TransferEvent {
+    fn default() -> Self {
+        Self::Progress(0.0)
+    }
+}
+
+impl TransferEvent {
+    /// True once the transfer has reached a final state.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Progress(_))
     }
 }
@@ -1,10 +1,20 @@
 //! Session authentication and lock lifecycle primitives.
 
+use crate::crypto::password::KeySalt;
 use crate::crypto::types::EncryptionKey;
+use crate::crypto::CipherSuite;
 use aws_lc_rs::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
+use aws_lc_rs::constant_time;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// A multi-use claim with no request for this long gives its slot back.
+pub const CLAIM_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
 fn generate_lock_token() -> String {
     Uuid::new_v4().to_string()
 }
@@ -15,29 +25,100 @@ pub enum ClaimError {
     InvalidToken,
     AlreadyClaimed,
     Completed,
+    Expired,
+    Revoked,
 }
 
 /// Session lock state machine for transfer ownership.
 #[derive(Debug, Clone)]
 pub enum SessionState {
     Unclaimed,
-    Active { lock_token: String },
+    Active {
+        lock_token: String,
+    },
+    Completed,
+    /// The sender withdrew the link; nothing is served again
+    Revoked,
+}
+
+/// Lifecycle status recorded when a session is persisted.
+///
+/// Lock tokens are process-local and never written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistedSessionStatus {
+    Unclaimed,
+    Active,
     Completed,
 }
 
+/// Serializable view of a session used to survive process restarts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub token: String,
+    pub status: PersistedSessionStatus,
+}
+
+/// Claims held by a multi-use session (`--expires-after-downloads`).
+///
+/// Each claim gets its own lock token. A finished download uses up one slot
+/// for good; a released or idle claim gives its slot back.
+struct ClaimPool {
+    /// Lock token to the time it was last presented
+    active: DashMap<String, Instant>,
+    remaining: AtomicU64,
+}
+
+impl ClaimPool {
+    /// Drop claims that have made no request within `idle_timeout`.
+    fn expire_idle(&self, idle_timeout: Duration) {
+        self.active.retain(|_, last_seen| {
+            let live = last_seen.elapsed() < idle_timeout;
+            if !live {
+                tracing::info!("Idle claim expired, slot freed");
+            }
+            live
+        });
+    }
+}
+
 /// Shared session context containing auth token, encryption key, cipher, and lock state.
 pub struct Session {
     token: String,
+    revoke_secret: String, // sender-only; never part of the link
     session_key: EncryptionKey,
     cipher: Arc<LessSafeKey>,
+    cipher_suite: CipherSuite,
     state: Arc<RwLock<SessionState>>, // RwLock inside Arc for concurrent safe access
+    expires_at: Option<Instant>,
+    key_salt: Option<KeySalt>, // set when the key is passphrase-derived
+    claims: Option<Arc<ClaimPool>>, // set when more than one client may claim
+    claim_idle_timeout: Duration,
 }
 
 impl Session {
     /// Creates a new session with fresh token, key-backed cipher, and unclaimed state.
     pub fn new(session_key: EncryptionKey) -> Self {
         let token = Uuid::new_v4().to_string();
+        Self::with_state(token, session_key, SessionState::Unclaimed)
+    }
+
+    /// Rebuilds a session from a persisted snapshot.
+    ///
+    /// Completed sessions stay terminal so a reloaded token can never be
+    /// claimed again. Active sessions come back unclaimed because their lock
+    /// token did not survive the restart.
+    pub fn restore(session_key: EncryptionKey, snapshot: SessionSnapshot) -> Self {
+        let state = match snapshot.status {
+            PersistedSessionStatus::Completed => SessionState::Completed,
+            PersistedSessionStatus::Unclaimed | PersistedSessionStatus::Active => {
+                SessionState::Unclaimed
+            }
+        };
+        Self::with_state(snapshot.token, session_key, state)
+    }
 
+    fn with_state(token: String, session_key: EncryptionKey, state: SessionState) -> Self {
         let unbound = UnboundKey::new(&AES_256_GCM, session_key.as_bytes())
             .expect("valid 32-byte AES-256 key");
         let cipher = Arc::new(LessSafeKey::new(unbound));
@@ -46,12 +127,90 @@ impl Session {
 
         Self {
             token,
+            revoke_secret: Uuid::new_v4().to_string(),
             session_key,
             cipher,
-            state: Arc::new(RwLock::new(SessionState::Unclaimed)),
+            cipher_suite: CipherSuite::PositionBound,
+            state: Arc::new(RwLock::new(state)),
+            expires_at: None,
+            key_salt: None,
+            claims: None,
+            claim_idle_timeout: CLAIM_IDLE_TIMEOUT,
         }
     }
 
+    /// Seals chunks under `suite` instead of the default
+    /// [`CipherSuite::PositionBound`]; resumed sessions keep their link's suite.
+    pub fn with_cipher_suite(mut self, suite: CipherSuite) -> Self {
+        self.cipher_suite = suite;
+        self
+    }
+
+    /// Limits how long the link may be claimed, counted from now.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(Instant::now() + ttl);
+        self
+    }
+
+    /// Lets up to `downloads` distinct clients claim the session, each
+    /// completion using up one. A value of 1 keeps the single-claim lock.
+    pub fn with_download_capacity(mut self, downloads: u64) -> Self {
+        self.claims = (downloads > 1).then(|| {
+            Arc::new(ClaimPool {
+                active: DashMap::new(),
+                remaining: AtomicU64::new(downloads),
+            })
+        });
+        self
+    }
+
+    /// Free a multi-use claim's slot after `timeout` without a request
+    /// instead of [`CLAIM_IDLE_TIMEOUT`].
+    pub fn with_claim_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.claim_idle_timeout = timeout;
+        self
+    }
+
+    /// Downloads still available, or None for a single-claim session.
+    pub fn remaining_downloads(&self) -> Option<u64> {
+        self.claims
+            .as_ref()
+            .map(|pool| pool.remaining.load(Ordering::Acquire))
+    }
+
+    /// Number of clients currently holding a claim.
+    pub fn active_claims(&self) -> usize {
+        match &self.claims {
+            Some(pool) => {
+                pool.expire_idle(self.claim_idle_timeout);
+                pool.active.len()
+            }
+            None => usize::from(self.snapshot().status == PersistedSessionStatus::Active),
+        }
+    }
+
+    /// Marks the key as derived from a passphrase with `salt`, so links
+    /// carry the salt instead of the key.
+    pub fn with_key_salt(mut self, salt: KeySalt) -> Self {
+        self.key_salt = Some(salt);
+        self
+    }
+
+    pub fn key_salt(&self) -> Option<&KeySalt> {
+        self.key_salt.as_ref()
+    }
+
+    /// Time left before the link expires, or None when it never does.
+    pub fn remaining_ttl(&self) -> Option<Duration> {
+        self.expires_at
+            .map(|expires_at| expires_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns true once a configured TTL has run out.
+    pub fn is_expired(&self) -> bool {
+        self.remaining_ttl().is_some_and(|left| left.is_zero())
+    }
+
     pub fn token(&self) -> &str {
         &self.token
     }
@@ -64,6 +223,10 @@ impl Session {
         &self.cipher
     }
 
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
     pub fn session_key_b64(&self) -> String {
         self.session_key.to_base64()
     }
@@ -85,7 +248,14 @@ impl Session {
                 poisoned.into_inner()
             }
         };
+        if let Some(pool) = &self.claims {
+            return Self::claim_slot(pool, self.claim_idle_timeout, &mut state, self.is_expired());
+        }
         match &*state {
+            SessionState::Unclaimed if self.is_expired() => {
+                tracing::warn!("Session claim rejected: link expired");
+                Err(ClaimError::Expired)
+            }
             SessionState::Unclaimed => {
                 let lock_token = generate_lock_token();
                 tracing::debug!("Session claimed");
@@ -96,10 +266,58 @@ impl Session {
             }
             SessionState::Active { .. } => Err(ClaimError::AlreadyClaimed),
             SessionState::Completed => Err(ClaimError::Completed),
+            SessionState::Revoked => Err(ClaimError::Revoked),
         }
     }
 
+    /// Multi-use claim: any free slot may be taken while the link is live.
+    /// Runs under the state write lock so the capacity check cannot race.
+    ///
+    /// The pool holds every claim's lock token; the session state only moves
+    /// from unclaimed to active, so a new claim never replaces a live one.
+    fn claim_slot(
+        pool: &ClaimPool,
+        idle_timeout: Duration,
+        state: &mut SessionState,
+        expired: bool,
+    ) -> Result<String, ClaimError> {
+        match state {
+            SessionState::Completed => return Err(ClaimError::Completed),
+            SessionState::Revoked => return Err(ClaimError::Revoked),
+            SessionState::Unclaimed | SessionState::Active { .. } => {}
+        }
+        if expired {
+            tracing::warn!("Session claim rejected: link expired");
+            return Err(ClaimError::Expired);
+        }
+        pool.expire_idle(idle_timeout);
+        let remaining = pool.remaining.load(Ordering::Acquire);
+        if pool.active.len() as u64 >= remaining {
+            tracing::debug!(
+                "Session claim rejected: all {} download(s) in use",
+                remaining
+            );
+            return Err(ClaimError::AlreadyClaimed);
+        }
+
+        let lock_token = generate_lock_token();
+        pool.active.insert(lock_token.clone(), Instant::now());
+        tracing::debug!(
+            "Session claimed ({}/{} slots in use)",
+            pool.active.len(),
+            remaining
+        );
+        if matches!(state, SessionState::Unclaimed) {
+            *state = SessionState::Active {
+                lock_token: lock_token.clone(),
+            };
+        }
+        Ok(lock_token)
+    }
+
     /// Returns true only when both session token and lock token match active state.
+    ///
+    /// A live multi-use claim counts as used again, postponing its idle expiry.
     pub fn is_active(&self, token: &str, lock_token: &str) -> bool {
         if token != self.token {
             return false;
@@ -108,6 +326,15 @@ impl Session {
         if lock_token.trim().is_empty() {
             return false;
         }
+        if let Some(pool) = &self.claims {
+            return match pool.active.get_mut(lock_token) {
+                Some(mut last_seen) if last_seen.elapsed() < self.claim_idle_timeout => {
+                    *last_seen = Instant::now();
+                    true
+                }
+                _ => false,
+            };
+        }
 
         let state = match self.state.read() {
             Ok(guard) => guard,
@@ -123,6 +350,9 @@ impl Session {
     }
 
     /// Marks session completed when caller holds valid active ownership tokens.
+    ///
+    /// A multi-use session only gives up the caller's download and stays
+    /// claimable until the last one completes.
     pub fn complete(&self, token: &str, lock_token: &str) -> bool {
         if !self.is_active(token, lock_token) {
             return false;
@@ -135,11 +365,172 @@ impl Session {
                 poisoned.into_inner()
             }
         };
+        if let Some(pool) = &self.claims {
+            // Only this claim is done; the link stays up while downloads remain.
+            // A concurrent retry may have removed it first: count it once
+            if pool.active.remove(lock_token).is_none() {
+                return false;
+            }
+            let remaining = pool.remaining.fetch_sub(1, Ordering::AcqRel) - 1;
+            if remaining > 0 {
+                tracing::info!("Download complete, {} remaining", remaining);
+                return true;
+            }
+        }
         tracing::info!("Session completed");
         *state = SessionState::Completed;
         true
     }
 
+    /// Issues a fresh lock token for an already active session.
+    ///
+    /// The previous lock token stops working. Callers must authenticate the
+    /// client (for example with a resume token) before re-attaching it.
+    pub fn reattach(&self, token: &str) -> Option<String> {
+        // Several clients may hold a multi-use session; none can be singled out
+        if token != self.token || self.claims.is_some() {
+            return None;
+        }
+
+        let mut state = match self.state.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during reattach, recovering");
+                poisoned.into_inner()
+            }
+        };
+        match &*state {
+            SessionState::Active { .. } => {
+                let lock_token = generate_lock_token();
+                tracing::debug!("Session re-attached with new lock token");
+                *state = SessionState::Active {
+                    lock_token: lock_token.clone(),
+                };
+                Some(lock_token)
+            }
+            SessionState::Unclaimed | SessionState::Completed | SessionState::Revoked => None,
+        }
+    }
+
+    /// Returns an active session to unclaimed so another client may claim it.
+    ///
+    /// Used when a transfer should serve more than one download.
+    pub fn release(&self, token: &str, lock_token: &str) -> bool {
+        if !self.is_active(token, lock_token) {
+            return false;
+        }
+
+        let mut state = match self.state.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during release, recovering");
+                poisoned.into_inner()
+            }
+        };
+        if let Some(pool) = &self.claims {
+            pool.active.remove(lock_token);
+            tracing::debug!("Session slot released for next client");
+            return true;
+        }
+        tracing::debug!("Session released for next client");
+        *state = SessionState::Unclaimed;
+        true
+    }
+
+    /// Secret that revokes the link. Only the sender sees it; a fresh one
+    /// is made per process, so it is not persisted with the session.
+    pub fn revoke_secret(&self) -> &str {
+        &self.revoke_secret
+    }
+
+    /// Withdraws the link for good: claims, chunks and completions are
+    /// refused from now on. Returns false if `secret` is not the revoke secret.
+    pub fn revoke(&self, secret: &str) -> bool {
+        if constant_time::verify_slices_are_equal(secret.as_bytes(), self.revoke_secret.as_bytes())
+            .is_err()
+        {
+            tracing::warn!("Session revoke rejected: secret mismatch");
+            return false;
+        }
+
+        let mut state = match self.state.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during revoke, recovering");
+                poisoned.into_inner()
+            }
+        };
+        if let Some(pool) = &self.claims {
+            pool.active.clear();
+        }
+        tracing::info!("Session revoked");
+        *state = SessionState::Revoked;
+        true
+    }
+
+    /// Returns true while `lock_token` holds a claim, without counting as use.
+    pub fn holds_claim(&self, lock_token: &str) -> bool {
+        if let Some(pool) = &self.claims {
+            return pool
+                .active
+                .get(lock_token)
+                .is_some_and(|last_seen| last_seen.elapsed() < self.claim_idle_timeout);
+        }
+        let state = match self.state.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during holds_claim check, recovering");
+                poisoned.into_inner()
+            }
+        };
+        matches!(&*state, SessionState::Active { lock_token: active } if active == lock_token)
+    }
+
+    /// Returns true once the link has been revoked.
+    pub fn is_revoked(&self) -> bool {
+        let state = match self.state.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during is_revoked check, recovering");
+                poisoned.into_inner()
+            }
+        };
+        matches!(&*state, SessionState::Revoked)
+    }
+
+    /// Captures token and lifecycle status for persistence.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        let state = match self.state.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during snapshot, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let status = match &*state {
+            SessionState::Unclaimed => PersistedSessionStatus::Unclaimed,
+            SessionState::Active { .. } => PersistedSessionStatus::Active,
+            // A restarted sender must not bring a revoked link back
+            SessionState::Completed | SessionState::Revoked => PersistedSessionStatus::Completed,
+        };
+        SessionSnapshot {
+            token: self.token.clone(),
+            status,
+        }
+    }
+
+    /// Returns true while no client holds or has finished the session.
+    pub fn is_unclaimed(&self) -> bool {
+        let state = match self.state.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::error!("Session lock poisoned during is_unclaimed check, recovering");
+                poisoned.into_inner()
+            }
+        };
+        matches!(&*state, SessionState::Unclaimed)
+    }
+
     /// Returns true when session has entered terminal completed state.
     pub fn is_completed(&self) -> bool {
         let state = match self.state.read() {
@@ -160,9 +551,15 @@ impl Clone for Session {
     fn clone(&self) -> Self {
         Self {
             token: self.token.clone(),
+            revoke_secret: self.revoke_secret.clone(),
             session_key: self.session_key.clone(),
             cipher: self.cipher.clone(),
+            cipher_suite: self.cipher_suite,
             state: self.state.clone(),
+            expires_at: self.expires_at,
+            key_salt: self.key_salt.clone(),
+            claims: self.claims.clone(),
+            claim_idle_timeout: self.claim_idle_timeout,
         }
     }
 }
@@ -3,11 +3,146 @@
 //! - Each file has a random 8-byte nonce base
 //! - Per-chunk nonce = base + chunk_index (4-byte big-endian counter)
 //! - Client derives same nonce from chunk position (no transmission overhead)
+//! - Suite v2 also seals each chunk's [`ChunkPosition`] as associated data, so
+//!   ciphertext only opens at the file, index and file length it was made for
+//! - Compressed chunks set the counter's top bit, so a chunk sealed both
+//!   compressed and plain never shares a nonce
 //!
 
 use crate::crypto::types::Nonce;
 use anyhow::Result;
 use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce as AeadNonce};
+use serde::{Deserialize, Serialize};
+
+/// Chunk encryption format, advertised to the browser through the link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CipherSuite {
+    /// v1: no associated data; links from older releases
+    Legacy,
+    /// v2: each chunk's [`ChunkPosition`] is the associated data
+    PositionBound,
+}
+
+impl CipherSuite {
+    /// Version number carried in the link fragment.
+    pub fn version(self) -> u8 {
+        match self {
+            CipherSuite::Legacy => 1,
+            CipherSuite::PositionBound => 2,
+        }
+    }
+
+    /// The part of a position's AAD bytes this suite authenticates.
+    fn associated_data(self, aad: &[u8; CHUNK_AAD_LEN]) -> &[u8] {
+        match self {
+            CipherSuite::Legacy => &[],
+            CipherSuite::PositionBound => aad,
+        }
+    }
+
+    /// Suite for a link's version number, if this build knows it.
+    pub fn from_version(version: u8) -> Option<Self> {
+        match version {
+            1 => Some(CipherSuite::Legacy),
+            2 => Some(CipherSuite::PositionBound),
+            _ => None,
+        }
+    }
+}
+
+/// Counter bit set for chunks whose plaintext was zstd-compressed.
+pub const COMPRESSED_COUNTER_FLAG: u32 = 1 << 31;
+
+/// Length of [`ChunkPosition::to_aad`].
+pub const CHUNK_AAD_LEN: usize = 17;
+
+/// Where a chunk belongs: the manifest file, the chunk within it, the
+/// file's plaintext length, and whether the sealed bytes are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkPosition {
+    pub file_index: u32,
+    pub chunk_index: u32,
+    pub total_len: u64,
+    pub compressed: bool,
+}
+
+impl ChunkPosition {
+    pub fn new(file_index: usize, chunk_index: usize, total_len: u64) -> Self {
+        Self {
+            file_index: file_index as u32,
+            chunk_index: chunk_index as u32,
+            total_len,
+            compressed: false,
+        }
+    }
+
+    /// Mark the chunk's plaintext as zstd-compressed.
+    pub fn with_compression(mut self, compressed: bool) -> Self {
+        self.compressed = compressed;
+        self
+    }
+
+    /// Nonce counter: the chunk index, with the top bit for compressed chunks.
+    pub fn counter(&self) -> u32 {
+        match self.compressed {
+            true => self.chunk_index | COMPRESSED_COUNTER_FLAG,
+            false => self.chunk_index,
+        }
+    }
+
+    /// file_index (u32 BE) || chunk_index (u32 BE) || total_len (u64 BE) ||
+    /// flags (1, bit 0 = compressed), the same bytes the web client builds
+    /// with a DataView.
+    pub fn to_aad(&self) -> [u8; CHUNK_AAD_LEN] {
+        let mut aad = [0u8; CHUNK_AAD_LEN];
+        aad[..4].copy_from_slice(&self.file_index.to_be_bytes());
+        aad[4..8].copy_from_slice(&self.chunk_index.to_be_bytes());
+        aad[8..16].copy_from_slice(&self.total_len.to_be_bytes());
+        aad[16] = self.compressed as u8;
+        aad
+    }
+}
+
+/// Debug-build record of the plaintext each (nonce base, counter) has sealed.
+///
+/// Re-sealing the same chunk (client retries, repeat downloads) is harmless
+/// because it yields the same ciphertext. Sealing different data under a used
+/// nonce is the GCM failure this catches. Release builds keep nothing.
+#[derive(Debug, Default)]
+pub struct NonceLedger {
+    #[cfg(debug_assertions)]
+    sealed: std::sync::Mutex<std::collections::HashMap<[u8; 12], u64>>,
+}
+
+impl NonceLedger {
+    /// Note that `plaintext` is about to be sealed at `counter`; in debug
+    /// builds, panic if that nonce already sealed something else.
+    pub fn record(&self, nonce_base: &Nonce, counter: u32, plaintext: &[u8]) {
+        #[cfg(debug_assertions)]
+        {
+            use std::hash::{Hash, Hasher};
+
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            plaintext.hash(&mut hasher);
+            let digest = hasher.finish();
+            let mut sealed = self
+                .sealed
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let previous = *sealed
+                .entry(nonce_base.with_counter(counter))
+                .or_insert(digest);
+            assert_eq!(
+                previous, digest,
+                "nonce reuse: counter {} of this nonce base already sealed other data",
+                counter
+            );
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = (nonce_base, counter, plaintext);
+    }
+}
 
 pub fn encrypt_chunk_in_place(
     key: &LessSafeKey,
@@ -38,3 +173,43 @@ pub fn decrypt_chunk_in_place(
     buffer.truncate(plaintext_len);
     Ok(())
 }
+
+/// Encrypt the chunk at `position`, binding it there when `suite` asks for it.
+///
+/// The nonce counter is [`ChunkPosition::counter`].
+pub fn encrypt_chunk_at_position(
+    key: &LessSafeKey,
+    nonce_base: &Nonce,
+    buffer: &mut Vec<u8>,
+    position: &ChunkPosition,
+    suite: CipherSuite,
+) -> Result<()> {
+    let nonce = AeadNonce::assume_unique_for_key(nonce_base.with_counter(position.counter()));
+    let aad = position.to_aad();
+    let aad = Aad::from(suite.associated_data(&aad));
+
+    key.seal_in_place_append_tag(nonce, aad, buffer)
+        .map_err(|e| anyhow::anyhow!("Encryption failed: {:?}", e))
+}
+
+/// Decrypt a chunk sealed by [`encrypt_chunk_at_position`].
+///
+/// Under [`CipherSuite::PositionBound`], any other position fails to open.
+pub fn decrypt_chunk_at_position(
+    key: &LessSafeKey,
+    nonce_base: &Nonce,
+    buffer: &mut Vec<u8>,
+    position: &ChunkPosition,
+    suite: CipherSuite,
+) -> Result<()> {
+    let nonce = AeadNonce::assume_unique_for_key(nonce_base.with_counter(position.counter()));
+    let aad = position.to_aad();
+    let aad = Aad::from(suite.associated_data(&aad));
+
+    let plaintext_len = key
+        .open_in_place(nonce, aad, buffer)
+        .map_err(|e| anyhow::anyhow!("Decryption failed: {:?}", e))?
+        .len();
+    buffer.truncate(plaintext_len);
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Whole-file SHA-256 digests for end-to-end integrity checks.
+
+use anyhow::{Context, Result};
+use aws_lc_rs::{hkdf, hmac};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::crypto::types::EncryptionKey;
+use crate::utils::log_path;
+
+/// Read buffer for hashing (64KB).
+const HASH_BUFFER_BYTES: usize = 64 * 1024;
+
+/// HKDF `info` for the whole-file MAC key; shared.js uses the same label.
+const FILE_MAC_INFO: &[u8] = b"archdrop-file-mac-v1";
+
+/// Hex-encoded SHA-256 of the file at `path`. Blocking; call from `run_blocking`.
+pub fn calculate_file_hash(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Cannot open {} for hashing", log_path(path)))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_BYTES];
+    loop {
+        let n = file
+            .read(&mut buffer)
+            .with_context(|| format!("Cannot read {} for hashing", log_path(path)))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Whole-file MAC: hex HMAC-SHA256 over the raw bytes of the file's SHA-256
+/// digest, under a key derived from the session key (see [`file_mac_key`]).
+///
+/// Keyed, so unlike the bare digest it can only come from a key holder; the
+/// receiver recomputes the digest from the reassembled plaintext.
+pub fn file_mac(key: &EncryptionKey, sha256_hex: &str) -> Result<String> {
+    let digest = hex::decode(sha256_hex).context("File hash is not valid hex")?;
+    Ok(hex::encode(hmac::sign(&file_mac_key(key), &digest)))
+}
+
+/// Check a [`file_mac`] against the digest of the reassembled file.
+pub fn verify_file_mac(key: &EncryptionKey, sha256_hex: &str, mac_hex: &str) -> bool {
+    let (Ok(digest), Ok(tag)) = (hex::decode(sha256_hex), hex::decode(mac_hex)) else {
+        return false;
+    };
+    hmac::verify(&file_mac_key(key), &digest, &tag).is_ok()
+}
+
+/// HKDF-SHA256 of the session key with an empty salt and [`FILE_MAC_INFO`],
+/// so the MAC never runs under the AES-GCM key itself.
+fn file_mac_key(key: &EncryptionKey) -> hmac::Key {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &[]).extract(key.as_bytes());
+    let okm = prk
+        .expand(&[FILE_MAC_INFO], hmac::HMAC_SHA256)
+        .expect("HMAC-SHA256 key length is a valid HKDF output length");
+    hmac::Key::from(okm)
+}
+
+/// True when `value` is a hex SHA-256 digest (either case).
+pub fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Compare two hex digests, ignoring case.
+pub fn hashes_match(actual: &str, expected: &str) -> bool {
+    actual.eq_ignore_ascii_case(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_hash_matches_known_digest() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("abc.txt");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let hash = calculate_file_hash(&path).unwrap();
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(is_sha256_hex(&hash));
+        assert!(hashes_match(&hash, &hash.to_uppercase()));
+        assert!(!is_sha256_hex("abc"));
+    }
+
+    // Same vector as tests/web_asset_handlers_tests.rs runs through shared.js
+    #[test]
+    fn file_mac_matches_known_answer() {
+        let key = EncryptionKey::from_bytes(std::array::from_fn(|i| i as u8));
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        let mac = file_mac(&key, digest).unwrap();
+        assert_eq!(
+            mac,
+            "4e17d5e6be2501ed95d75582cfa602739ad4102108f385c68263def47ba55676"
+        );
+        assert!(verify_file_mac(&key, digest, &mac));
+
+        // Not a plain HMAC under the AES key
+        let raw = hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes());
+        let raw_mac = hex::encode(hmac::sign(&raw, &hex::decode(digest).unwrap()));
+        assert!(!verify_file_mac(&key, digest, &raw_mac));
+    }
+}
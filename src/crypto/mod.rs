@@ -1,5 +1,13 @@
 pub mod encryption;
+pub mod hash;
+pub mod password;
+pub mod signing;
 pub mod types;
 
-pub use encryption::{decrypt_chunk_in_place, encrypt_chunk_in_place};
+pub use encryption::{
+    decrypt_chunk_at_position, decrypt_chunk_in_place, encrypt_chunk_at_position,
+    encrypt_chunk_in_place, ChunkPosition, CipherSuite, NonceLedger, CHUNK_AAD_LEN,
+    COMPRESSED_COUNTER_FLAG,
+};
+pub use hash::calculate_file_hash;
 pub use types::{EncryptionKey, Nonce};
@@ -0,0 +1,116 @@
+//! Passphrase-derived session keys (`--password`).
+//!
+//! The link then carries a random salt instead of the key, and the key is
+//! Argon2id(passphrase, salt). The web client runs the same derivation in
+//! `shared.js`, so the parameters below must stay in sync with it.
+
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::crypto::types::{decode_url_base64, EncryptionKey};
+use crate::utils::run_blocking;
+
+pub const SALT_LEN: usize = 16;
+/// Argon2id memory cost in KiB (19 MiB, the OWASP baseline).
+pub const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+pub const ARGON2_ITERATIONS: u32 = 2;
+pub const ARGON2_PARALLELISM: u32 = 1;
+
+/// Per-session random salt, sent in the link in place of the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySalt([u8; SALT_LEN]);
+
+impl KeySalt {
+    pub fn new() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self(salt)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SALT_LEN] {
+        &self.0
+    }
+
+    pub fn to_base64(&self) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(self.0)
+    }
+
+    pub fn from_base64(b64: &str) -> Result<Self> {
+        let bytes = decode_url_base64(b64)?;
+        let salt = bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid salt length"))?;
+        Ok(Self(salt))
+    }
+}
+
+impl Default for KeySalt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Derive the 32-byte session key. CPU and memory heavy; prefer
+/// [`derive_key_blocking`] from async code.
+pub fn derive_key(passphrase: &str, salt: &KeySalt) -> Result<EncryptionKey> {
+    anyhow::ensure!(!passphrase.is_empty(), "Passphrase must not be empty");
+
+    let params = Params::new(
+        ARGON2_MEMORY_KIB,
+        ARGON2_ITERATIONS,
+        ARGON2_PARALLELISM,
+        Some(32),
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt.as_bytes(), key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Argon2 key derivation failed: {}", e))?;
+    Ok(EncryptionKey::from_bytes(*key))
+}
+
+/// Run [`derive_key`] on the blocking pool so it never stalls the runtime.
+pub async fn derive_key_blocking(passphrase: String, salt: KeySalt) -> Result<EncryptionKey> {
+    let passphrase = Zeroizing::new(passphrase);
+    run_blocking("argon2 key derivation", move || {
+        derive_key(&passphrase, &salt)
+    })
+    .await
+    .context("Failed to derive key from passphrase")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_answer_pins_argon2_parameters() {
+        let salt = KeySalt(*b"archdrop-salt-16");
+        let key = derive_key("correct horse battery staple", &salt).unwrap();
+        assert_eq!(
+            hex::encode(key.as_bytes()),
+            "e457f589dd571ced3f54d4448a497e778e12894cddd52575ee2a0320b26dfb28"
+        );
+    }
+
+    #[tokio::test]
+    async fn blocking_derivation_matches_and_salt_round_trips() {
+        let salt = KeySalt::new();
+        assert_eq!(KeySalt::from_base64(&salt.to_base64()).unwrap(), salt);
+
+        let a = derive_key_blocking("hunter2".to_string(), salt.clone())
+            .await
+            .unwrap();
+        let b = derive_key("hunter2", &salt).unwrap();
+        let other = derive_key("hunter3", &salt).unwrap();
+        assert_eq!(a.as_bytes(), b.as_bytes());
+        assert_ne!(a.as_bytes(), other.as_bytes());
+
+        assert!(derive_key("", &salt).is_err());
+        assert!(KeySalt::from_base64("c2hvcnQ").is_err());
+    }
+}
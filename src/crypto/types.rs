@@ -1,18 +1,45 @@
 use base64::{engine::general_purpose, Engine};
 use rand::rngs::OsRng;
 use rand::RngCore;
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
 
 // OSRng pulls from Operating system
 // It is more cryptographically secure than PRNG, but slower
 
-/// AES-256-GCM encryption key (32 bytes)
-#[derive(Debug, Clone)]
+/// Decode base64 from a link or manifest.
+///
+/// Everything is written URL-safe without padding, but links from older
+/// releases used the standard alphabet, and some clients turn `+` into a
+/// space, so those forms and trailing `=` padding are accepted too.
+pub fn decode_url_base64(b64: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let normalized: String = b64
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' | ' ' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect();
+    general_purpose::URL_SAFE_NO_PAD.decode(normalized)
+}
+
+/// AES-256-GCM encryption key (32 bytes), wiped from memory on drop.
+#[derive(Clone)]
+#[repr(transparent)]
 pub struct EncryptionKey([u8; 32]);
 
 impl EncryptionKey {
     pub fn new() -> Self {
-        let mut key = [0u8; 32];
-        OsRng.fill_bytes(&mut key);
+        // Fill in place so no unwiped copy is left on the stack
+        let mut key = Self([0u8; 32]);
+        OsRng.fill_bytes(&mut key.0);
+        key
+    }
+
+    /// Take ownership of raw key bytes. The caller should wipe its own copy.
+    pub fn from_bytes(key: [u8; 32]) -> Self {
         Self(key)
     }
 
@@ -26,13 +53,13 @@ impl EncryptionKey {
     }
 
     pub fn from_base64(b64: &str) -> anyhow::Result<Self> {
-        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(b64)?;
+        let bytes = Zeroizing::new(decode_url_base64(b64)?);
         if bytes.len() != 32 {
             anyhow::bail!("Invalid key length");
         }
-        let mut key = [0u8; 32];
-        key.copy_from_slice(&bytes);
-        Ok(Self(key))
+        let mut key = Self([0u8; 32]);
+        key.0.copy_from_slice(&bytes);
+        Ok(key)
     }
 }
 
@@ -42,17 +69,36 @@ impl Default for EncryptionKey {
     }
 }
 
+impl Drop for EncryptionKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+// Never print key material, even in debug logs
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
 /// 8-byte base + 4-byte counter (chunk index) for positioned encryption.
 ///
 /// Full nonce = [8-byte random | 4-byte counter]. Enables out-of-order decryption.
 #[derive(Debug, Clone)]
+#[repr(transparent)]
 pub struct Nonce([u8; 8]);
 
 impl Nonce {
     pub fn new() -> Self {
-        let mut nonce = [0u8; 8];
-        OsRng.fill_bytes(&mut nonce);
-        Self(nonce)
+        Self::from_rng(&mut OsRng)
+    }
+
+    /// Draw a nonce base from `rng` (seeded generators make tests repeatable).
+    pub fn from_rng(rng: &mut impl RngCore) -> Self {
+        let mut nonce = Self([0u8; 8]);
+        rng.fill_bytes(&mut nonce.0);
+        nonce
     }
 
     // raw bytes (for creating stream encryptor/decryptor)
@@ -66,13 +112,13 @@ impl Nonce {
     }
 
     pub fn from_base64(b64: &str) -> anyhow::Result<Self> {
-        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(b64)?;
+        let bytes = decode_url_base64(b64)?;
         if bytes.len() != 8 {
             anyhow::bail!("Invalid nonce length");
         }
-        let mut nonce = [0u8; 8];
-        nonce.copy_from_slice(&bytes);
-        Ok(Self(nonce))
+        let mut nonce = Self([0u8; 8]);
+        nonce.0.copy_from_slice(&bytes);
+        Ok(nonce)
     }
 
     /// Returns 12-byte nonce: [8-byte base | 4-byte big-endian counter].
@@ -89,3 +135,88 @@ impl Default for Nonce {
         Self::new()
     }
 }
+
+impl Drop for Nonce {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+
+    /// Bytes of `slot` after dropping the value it holds in place.
+    ///
+    /// The storage stays owned by the test, so peeking at it after the drop
+    /// shows exactly what `Drop` left behind.
+    fn bytes_after_drop<T, const N: usize>(value: T) -> ([u8; N], [u8; N]) {
+        assert_eq!(std::mem::size_of::<T>(), N);
+        let mut slot = MaybeUninit::new(value);
+        let ptr = slot.as_ptr() as *const [u8; N];
+        // SAFETY: slot holds an initialized T of exactly N plain bytes
+        let before = unsafe { std::ptr::read_volatile(ptr) };
+        // SAFETY: dropped exactly once; slot is never read as T again
+        unsafe { slot.assume_init_drop() };
+        let after = unsafe { std::ptr::read_volatile(ptr) };
+        (before, after)
+    }
+
+    #[test]
+    fn key_and_nonce_bytes_are_wiped_on_drop() {
+        let (before, after) = bytes_after_drop::<_, 32>(EncryptionKey::new());
+        assert_ne!(before, [0u8; 32]);
+        assert_eq!(after, [0u8; 32]);
+
+        let (before, after) = bytes_after_drop::<_, 8>(Nonce::new());
+        assert_ne!(before, [0u8; 8]);
+        assert_eq!(after, [0u8; 8]);
+    }
+
+    #[test]
+    fn debug_output_hides_key_bytes() {
+        let key = EncryptionKey::new();
+        let debug = format!("{:?}", key);
+        assert_eq!(debug, "EncryptionKey(..)");
+        assert!(!debug.contains(&key.to_base64()));
+    }
+
+    #[test]
+    fn base64_is_url_safe_and_accepts_the_old_alphabet() {
+        // fb ff bf is `+/+/` in the standard alphabet
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = [0xfb, 0xff, 0xbf][i % 3];
+        }
+        let key = EncryptionKey::from_bytes(bytes);
+        let encoded = key.to_base64();
+        assert!(!encoded.contains(['+', '/', '=']), "{}", encoded);
+        assert!(encoded.starts_with("-_-_"), "{}", encoded);
+        assert_eq!(
+            EncryptionKey::from_base64(&encoded).unwrap().as_bytes(),
+            &bytes
+        );
+
+        // Links from older releases, and ones where `+` became a space
+        let legacy = general_purpose::STANDARD.encode(bytes);
+        assert!(legacy.starts_with("+/+/") && legacy.ends_with('='));
+        assert_eq!(
+            EncryptionKey::from_base64(&legacy).unwrap().as_bytes(),
+            &bytes
+        );
+        let mangled = legacy.replace('+', " ");
+        assert_eq!(
+            EncryptionKey::from_base64(&mangled).unwrap().as_bytes(),
+            &bytes
+        );
+
+        let nonce_bytes = [0xfb, 0xff, 0xbf, 0xfb, 0xff, 0xbf, 0xfb, 0xff];
+        let nonce = Nonce::from_base64(&general_purpose::STANDARD.encode(nonce_bytes)).unwrap();
+        assert_eq!(nonce.to_base64(), "-_-_-_-_-_8");
+        assert_eq!(
+            Nonce::from_base64(&nonce.to_base64()).unwrap().as_bytes(),
+            &nonce_bytes
+        );
+    }
+}
@@ -1,6 +1,7 @@
 pub mod common;
 pub mod crypto;
 pub mod receive;
+pub mod relay;
 pub mod send;
 pub mod server;
 mod transport;
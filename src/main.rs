@@ -1,12 +1,19 @@
 use anyhow::{ensure, Context, Result};
 use archdrop::{
-    common::{config, config_commands, ConfigOverrides, Manifest, Transport},
-    send, server,
+    common::{
+        config, config_commands, AppConfig, ConfigOverrides, Manifest, TlsVersion, Transport,
+    },
+    crypto, receive, relay, send, server, ui, utils,
 };
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
-use tracing_subscriber::EnvFilter;
-use walkdir::WalkDir;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
 
 // Clap for CLI w/ arg parsing
 #[derive(Parser)]
@@ -16,6 +23,22 @@ struct Cli {
     // subcommands
     #[command(subcommand)]
     command: Commands,
+
+    /// Disable colored output (also honors NO_COLOR and non-TTY stdout)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Read settings from this TOML or JSON file instead of the default config
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Log a short hash in place of file paths (for shared log collectors)
+    #[arg(long, global = true)]
+    redact_paths: bool,
+
+    /// Also append logs (without colors) to this file; `RUST_LOG` applies to both
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -35,12 +58,30 @@ enum ConfigAction {
 #[derive(Subcommand)]
 enum Commands {
     Send {
-        #[arg(required = true, help = "Files or directories to send")]
+        #[arg(
+            required_unless_present = "resume",
+            help = "Files, directories, or glob patterns to send (`-` reads stdin)"
+        )]
         path: Vec<PathBuf>,
 
         #[arg(long, help = "Zip inputs into a temporary archive before sending")]
         zip: bool,
 
+        #[arg(
+            long,
+            conflicts_with = "zip",
+            help = "Send inputs as one tar archive built on the fly (no temp file)"
+        )]
+        tar: bool,
+
+        #[arg(
+            long,
+            value_name = "FILENAME",
+            value_parser = utils::security::parse_download_name,
+            help = "Filename the recipient downloads (single file, zip archive, or stdin)"
+        )]
+        name: Option<String>,
+
         #[arg(
             long = "no-zip",
             conflicts_with = "zip",
@@ -48,6 +89,111 @@ enum Commands {
         )]
         no_zip: bool,
 
+        #[arg(
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Keep serving until this many distinct clients finish downloading"
+        )]
+        downloads: u64,
+
+        #[arg(
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u64).range(1..),
+            conflicts_with = "downloads",
+            help = "Let up to N recipients claim the same link, at the same time if they like"
+        )]
+        expires_after_downloads: Option<u64>,
+
+        #[arg(
+            long,
+            conflicts_with = "no_dedup",
+            help = "Save the session so a restarted sender can resume it (see --resume)"
+        )]
+        resumable: bool,
+
+        #[arg(
+            long,
+            value_name = "TOKEN",
+            conflicts_with_all = ["path", "zip", "tar", "name", "sign_key", "resumable", "chunk_size", "exclude"],
+            help = "Resume a saved --resumable session; the original link keeps working"
+        )]
+        resume: Option<String>,
+
+        #[arg(
+            long,
+            help = "Hash the file while it is served and report SHA-256 at the end"
+        )]
+        hash: bool,
+
+        #[arg(
+            long = "expected-hash",
+            value_name = "SHA256",
+            help = "Verify the streamed SHA-256 against this hex digest (implies --hash)"
+        )]
+        expected_hash: Option<String>,
+
+        #[arg(
+            long = "link-ttl",
+            value_name = "SECS",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Expire the link if nobody claims it within this many seconds"
+        )]
+        link_ttl: Option<u64>,
+
+        #[arg(
+            long = "no-dedup",
+            help = "Don't track sent chunks; saves memory but counts client retries twice"
+        )]
+        no_dedup: bool,
+
+        #[arg(
+            long = "require-claim",
+            help = "Serve the manifest only after the client claims the link (POST /send/claim)"
+        )]
+        require_claim: bool,
+
+        #[arg(
+            long = "max-transfer",
+            value_name = "SIZE",
+            value_parser = utils::size::parse_size,
+            help = "Abort once this many bytes have been served, e.g. 2G"
+        )]
+        max_transfer: Option<u64>,
+
+        #[arg(
+            long = "file-mac",
+            help = "Send a whole-file MAC the browser checks after reassembly"
+        )]
+        file_mac: bool,
+
+        #[arg(
+            long,
+            help = "Offer zstd-compressed chunks to browsers that can decode them"
+        )]
+        compress: bool,
+
+        #[arg(
+            long = "sign-key",
+            value_name = "PATH",
+            help = "Sign the manifest with this Ed25519 PKCS#8 key (PEM or DER)"
+        )]
+        sign_key: Option<PathBuf>,
+
+        #[arg(
+            long,
+            value_name = "GLOB",
+            help = "Skip matching files inside directory inputs (repeatable; .archdropignore too)"
+        )]
+        exclude: Vec<String>,
+
+        #[arg(
+            long = "dry-run",
+            help = "Print the files, sizes and chunk counts that would be sent, then exit"
+        )]
+        dry_run: bool,
+
         #[command(flatten)]
         args: CliArgs,
     },
@@ -55,6 +201,31 @@ enum Commands {
         #[arg(default_value = ".", help = "Destination directory")]
         destination: PathBuf,
 
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with = "destination",
+            help = "Write received files as one tar stream to PATH (`-` for stdout)"
+        )]
+        tar: Option<PathBuf>,
+
+        #[arg(
+            long = "received-out",
+            value_name = "PATH",
+            help = "Write names, sizes, SHA-256 and paths of received files as JSON to PATH"
+        )]
+        received_out: Option<PathBuf>,
+
+        #[arg(
+            long,
+            help = "Re-read each file after it is written and check its size and SHA-256"
+        )]
+        verify: bool,
+
+        /// What to do when a received file's name already exists
+        #[arg(long, value_enum, default_value_t = CliConflictPolicy::Rename)]
+        on_conflict: CliConflictPolicy,
+
         #[command(flatten)]
         args: CliArgs,
     },
@@ -62,6 +233,60 @@ enum Commands {
         #[command(subcommand)]
         action: ConfigAction,
     },
+    /// Measure loopback send throughput through the real router and crypto path
+    Bench {
+        #[arg(
+            long = "size-mb",
+            default_value_t = 256,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Payload size in MiB"
+        )]
+        size_mb: u64,
+
+        /// Transport whose transfer settings to benchmark (overrides config default)
+        #[arg(long, value_enum)]
+        via: Option<CliTransport>,
+
+        /// Parallel chunk requests, 1-64 (overrides the transport default)
+        #[arg(long, value_name = "N")]
+        concurrency: Option<usize>,
+
+        /// Chunk size, e.g. 512K or 8M (overrides the transport default)
+        #[arg(long, value_name = "SIZE", value_parser = config::parse_chunk_size)]
+        chunk_size: Option<u64>,
+    },
+    /// List ArchDrop sessions advertised on the LAN with `--mdns`
+    Discover {
+        #[arg(
+            long,
+            value_name = "SECS",
+            default_value_t = 3,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Seconds to listen for advertisements"
+        )]
+        timeout: u64,
+    },
+    /// Run a rendezvous relay that pipes encrypted transfers between peers
+    Relay {
+        #[arg(
+            long,
+            default_value = ":8080",
+            help = "Address to listen on (host:port or :port)"
+        )]
+        bind: String,
+    },
+}
+
+impl Commands {
+    /// True when transfer data, not status text, goes to stdout.
+    fn writes_data_to_stdout(&self) -> bool {
+        matches!(self, Commands::Receive { tar: Some(target), .. } if receive::TarSink::targets_stdout(target))
+    }
+
+    /// `--json` progress events own stdout.
+    fn writes_json_to_stdout(&self) -> bool {
+        matches!(self, Commands::Send { args, .. } | Commands::Receive { args, .. } if args.json)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -69,6 +294,7 @@ enum CliTransport {
     Local,
     Cloudflare,
     Tailscale,
+    Ngrok,
 }
 
 impl From<CliTransport> for Transport {
@@ -77,6 +303,44 @@ impl From<CliTransport> for Transport {
             CliTransport::Local => Transport::Local,
             CliTransport::Cloudflare => Transport::Cloudflare,
             CliTransport::Tailscale => Transport::Tailscale,
+            CliTransport::Ngrok => Transport::Ngrok,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CliTlsVersion {
+    #[value(name = "1.2")]
+    Tls12,
+    #[value(name = "1.3")]
+    Tls13,
+}
+
+impl From<CliTlsVersion> for TlsVersion {
+    fn from(value: CliTlsVersion) -> Self {
+        match value {
+            CliTlsVersion::Tls12 => TlsVersion::Tls12,
+            CliTlsVersion::Tls13 => TlsVersion::Tls13,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CliConflictPolicy {
+    /// Keep both, saving the new file as `name (1).ext`
+    Rename,
+    /// Replace the existing file
+    Overwrite,
+    /// Keep the existing file and skip the upload
+    Skip,
+}
+
+impl From<CliConflictPolicy> for receive::ConflictPolicy {
+    fn from(value: CliConflictPolicy) -> Self {
+        match value {
+            CliConflictPolicy::Rename => receive::ConflictPolicy::Rename,
+            CliConflictPolicy::Overwrite => receive::ConflictPolicy::Overwrite,
+            CliConflictPolicy::Skip => receive::ConflictPolicy::Skip,
         }
     }
 }
@@ -84,12 +348,141 @@ impl From<CliTransport> for Transport {
 #[derive(Args, Debug, Clone, Default)]
 struct CliArgs {
     /// Transport method (overrides config default)
-    #[arg(long, value_enum)]
+    #[arg(long, value_enum, visible_alias = "tunnel")]
     via: Option<CliTransport>,
 
     /// Port override for the selected/default transport (0 = auto-assign)
     #[arg(long)]
     port: Option<u16>,
+
+    /// Lowest TLS version the local HTTPS server accepts (default 1.3)
+    #[arg(long, value_enum)]
+    min_tls: Option<CliTlsVersion>,
+
+    /// Parallel chunk requests per client, 1-64 (overrides the transport default)
+    #[arg(long, value_name = "N")]
+    concurrency: Option<usize>,
+
+    /// Chunk size, e.g. 512K or 8M (overrides the transport default)
+    #[arg(long, value_name = "SIZE", value_parser = config::parse_chunk_size)]
+    chunk_size: Option<u64>,
+
+    /// Requests handled at once before new ones get 503 (default: from --concurrency)
+    #[arg(long, value_name = "N")]
+    max_connections: Option<usize>,
+
+    /// Put this network interface's address in local links (e.g. eth0)
+    #[arg(long, value_name = "NAME", conflicts_with = "bind")]
+    interface: Option<String>,
+
+    /// Listen on and link to this local address only
+    #[arg(long, value_name = "IP")]
+    bind: Option<std::net::IpAddr>,
+
+    /// Write transfer statistics as JSON to this file when the session ends
+    #[arg(long, value_name = "PATH")]
+    stats_out: Option<PathBuf>,
+
+    /// Directory for persisted state and caches (default: platform data dir)
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Shut down if nobody connects within this many seconds (0 = never)
+    #[arg(long, value_name = "SECS", default_value_t = 0)]
+    timeout: u64,
+
+    /// Derive the key from a passphrase (prompted for) shared out of band
+    #[arg(long)]
+    password: bool,
+
+    /// Also require this secret (browsers prompt for it as a PIN; at least 6 characters,
+    /// 5 wrong tries lock the transfer); never put in the link
+    #[arg(
+        long,
+        value_name = "SECRET",
+        value_parser = clap::builder::NonEmptyStringValueParser::new()
+    )]
+    auth_token: Option<String>,
+
+    /// POST a JSON completion event to this URL when the transfer finishes
+    #[arg(long, value_name = "URL")]
+    webhook: Option<reqwest::Url>,
+
+    /// Advertise this session on the LAN over mDNS (local transport only)
+    #[arg(long)]
+    mdns: bool,
+
+    /// Seconds in-flight responses may finish after Ctrl+C or completion
+    #[arg(long, value_name = "SECS", default_value_t = 2)]
+    shutdown_grace: u64,
+
+    /// Print newline-delimited JSON progress events on stdout instead of the TUI
+    #[arg(long)]
+    json: bool,
+
+    /// Print the QR code and link once, then plain progress lines (for SSH)
+    #[arg(long, visible_alias = "qr-only", conflicts_with = "json")]
+    no_tui: bool,
+
+    /// Copy the transfer link to the system clipboard
+    #[arg(long)]
+    clipboard: bool,
+
+    /// Also write the QR code to this file (PNG for `.png`, SVG otherwise)
+    #[arg(long, value_name = "PATH")]
+    qr_file: Option<PathBuf>,
+}
+
+impl CliArgs {
+    fn idle_timeout(&self) -> Option<Duration> {
+        (self.timeout > 0).then(|| Duration::from_secs(self.timeout))
+    }
+
+    fn address_selector(&self) -> server::AddressSelector {
+        match (&self.interface, self.bind) {
+            (Some(name), _) => server::AddressSelector::Interface(name.clone()),
+            (None, Some(ip)) => server::AddressSelector::Ip(ip),
+            (None, None) => server::AddressSelector::Auto,
+        }
+    }
+
+    /// Prompt for the `--password` passphrase, if requested.
+    fn passphrase(&self) -> Result<Option<String>> {
+        if !self.password {
+            return Ok(None);
+        }
+        ui::prompt::read_new_passphrase().map(Some)
+    }
+}
+
+/// Load the default config file, or `--config` when given.
+fn load_base_config(path: Option<&Path>) -> Result<AppConfig> {
+    match path {
+        Some(path) => config::load_config_from(path),
+        None => config::load_config(),
+    }
+}
+
+/// Load config and apply every CLI-level override.
+fn load_effective_config(
+    config_file: Option<&Path>,
+    args: &CliArgs,
+    overrides: &ConfigOverrides,
+) -> Result<AppConfig> {
+    let mut config = config::apply_overrides(load_base_config(config_file)?, overrides);
+    if let Some(dir) = &args.data_dir {
+        config.data_dir = Some(dir.clone());
+    }
+    Ok(config)
+}
+
+/// Open `--log-file` for appending, creating it if needed.
+fn open_log_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))
 }
 
 impl From<&CliArgs> for ConfigOverrides {
@@ -97,102 +490,249 @@ impl From<&CliArgs> for ConfigOverrides {
         Self {
             transport: args.via.map(Into::into),
             port: args.port,
+            min_tls: args.min_tls.map(Into::into),
+            concurrency: args.concurrency,
+            chunk_size: args.chunk_size,
+            max_connections: args.max_connections,
         }
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let color = ui::color::init(cli.no_color);
+    utils::security::set_redact_paths(cli.redact_paths);
+    let config_file = cli.config.as_deref();
+
     if std::env::var("TOKIO_CONSOLE").is_ok() {
         eprintln!("tokio-console enabled, listening on 127.0.0.1:6669");
         console_subscriber::init();
     } else {
-        tracing_subscriber::fmt()
-            .with_env_filter(
+        // `receive --tar -` and `--json` own stdout, so logs move to stderr
+        let writer = if cli.command.writes_data_to_stdout() || cli.command.writes_json_to_stdout() {
+            BoxMakeWriter::new(std::io::stderr)
+        } else {
+            BoxMakeWriter::new(std::io::stdout)
+        };
+        let file_layer = cli
+            .log_file
+            .as_deref()
+            .map(open_log_file)
+            .transpose()?
+            .map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file)));
+        tracing_subscriber::registry()
+            .with(
                 EnvFilter::try_from_default_env()
                     .unwrap_or_else(|_| EnvFilter::new("info,reqwest=warn,hyper_util=warn")),
             )
+            .with(fmt::layer().with_ansi(color).with_writer(writer))
+            .with(file_layer)
             .init();
     }
 
-    let cli = Cli::parse();
-
     match cli.command {
         Commands::Send {
             path,
             zip,
+            tar,
+            name,
             no_zip,
+            downloads,
+            expires_after_downloads,
+            resumable,
+            resume,
+            hash,
+            expected_hash,
+            link_ttl,
+            no_dedup,
+            require_claim,
+            max_transfer,
+            file_mac,
+            compress,
+            sign_key,
+            exclude,
+            dry_run,
             args,
         } => {
             let overrides = ConfigOverrides::from(&args);
-            let config = config::apply_overrides(config::load_config()?, &overrides);
-            let use_zip = resolve_zip_enabled(zip, no_zip, config.zip);
-
-            // Best-effort cleanup: hard kill (SIGKILL) can leave temp zips behind.
-            let mut temp_archive: Option<send::TempArchive> = None;
-
-            // collect all files
-            let files_to_send = if use_zip {
-                let archive = send::create_temp_zip_archive(&path)?;
-                let archive_path = archive.path().to_path_buf();
-                temp_archive = Some(archive);
-                vec![archive_path]
-            } else {
-                let mut files = Vec::new();
-                for file in path {
-                    // fail fast on no file
-                    ensure!(file.exists(), "File not found: {}", file.display());
-
-                    if file.is_dir() {
-                        // Add files in dir recursively
-                        // handle nested directories
-                        for entry in WalkDir::new(&file)
-                            .into_iter()
-                            .filter_map(|e| e.ok())
-                            .filter(|e| e.path().is_file())
-                        {
-                            files.push(entry.path().to_path_buf());
+            let config = load_effective_config(config_file, &args, &overrides)?;
+            let use_zip = !tar && resolve_zip_enabled(zip, no_zip, config.zip);
+
+            let transport = overrides.transport.unwrap_or(config.default_transport);
+            let resume = resume
+                .map(|token| send::PersistedSend::load(&config.data_dir(), &token))
+                .transpose()?;
+            let (manifest, password, temp_archive, stdin_payload, tar_stream) = match &resume {
+                // Same files, key and link as before; nothing is re-read from the inputs
+                Some(saved) => (saved.manifest().await?, None, None, None, None),
+                None => {
+                    // `-` reads the payload from stdin; it is buffered before anything is served
+                    let stdin_payload = send::is_stdin_input(&path)
+                        .then(|| {
+                            let stdin_name = name.as_deref().unwrap_or(send::DEFAULT_STDIN_NAME);
+                            send::buffer_stdin(&path, stdin_name)
+                        })
+                        .transpose()?;
+                    // Temp inputs are deleted on exit, so a restart would find nothing to resume
+                    ensure!(
+                        !resumable || (stdin_payload.is_none() && !use_zip && !tar),
+                        "--resumable needs the inputs on disk; it cannot be used with stdin, --zip or --tar"
+                    );
+                    let path = match &stdin_payload {
+                        Some(payload) => vec![payload.path().to_path_buf()],
+                        None => {
+                            // Expand glob arguments (shells on Windows leave them unexpanded)
+                            let path = send::expand_send_inputs(path)?;
+                            send::validate_send_inputs(&path)?;
+                            path
                         }
+                    };
+                    let excludes = send::ExcludeRules::new(exclude)?;
+                    let signing_key = sign_key
+                        .map(|key_path| crypto::signing::SigningKey::load(&key_path))
+                        .transpose()?;
+                    // Nothing is encrypted in a dry run, so don't ask for a passphrase
+                    let password = if dry_run { None } else { args.passphrase()? };
+
+                    // Best-effort cleanup: hard kill (SIGKILL) can leave temp zips behind.
+                    let mut temp_archive: Option<send::TempArchive> = None;
+                    let transfer_settings = config.transfer_settings(transport);
+
+                    let (mut manifest, tar_stream) = if tar {
+                        // Entries are read from the inputs as chunks are requested
+                        let stream = send::TarStream::build(&path, &excludes)?;
+                        let hash = stream.sha256().context("Failed to hash tar stream")?;
+                        let manifest = Manifest::for_stream(
+                            &send::TarStream::default_name(&path),
+                            stream.len(),
+                            hash,
+                            transfer_settings,
+                        )?;
+                        (manifest, Some(std::sync::Arc::new(stream)))
                     } else {
-                        files.push(file); // single file
+                        // collect all files
+                        let files_to_send = if use_zip {
+                            let archive = send::create_temp_zip_archive(&path, &excludes)?;
+                            let archive_path = archive.path().to_path_buf();
+                            temp_archive = Some(archive);
+                            vec![archive_path]
+                        } else {
+                            send::collect_send_files(path, &excludes)?
+                        };
+
+                        ensure!(!files_to_send.is_empty(), "No files to send");
+
+                        // Send needs to build a manifest of file metadata
+                        // to send to the receiver before download begins
+                        let manifest = Manifest::new(files_to_send, None, transfer_settings)
+                            .await
+                            .context("Failed to create manifest")?;
+                        (manifest, None)
+                    };
+                    if let Some(name) = &name {
+                        manifest.set_download_name(name)?;
+                    }
+                    if let Some(key) = &signing_key {
+                        // The signature covers each file's hash
+                        manifest
+                            .hash_files()
+                            .await
+                            .context("Failed to hash files for signing")?;
+                        manifest.sign(key);
+                        eprintln!("Manifest signed by {}", key.public_key_base64());
                     }
+                    (manifest, password, temp_archive, stdin_payload, tar_stream)
                 }
-                files
             };
 
-            ensure!(!files_to_send.is_empty(), "No files to send");
-
-            // Send needs to build a manifest of file metadata
-            // to send to the receiver before download begins
-            let transport = overrides.transport.unwrap_or(config.default_transport);
-            let transfer_settings = config.transfer_settings(transport);
-            let manifest = Manifest::new(files_to_send, None, transfer_settings)
-                .await
-                .context("Failed to create manifest")?;
+            if dry_run {
+                let report = send::DryRunReport::from_manifest(&manifest);
+                let mut stdout = std::io::stdout().lock();
+                if args.json {
+                    report.write_json(&mut stdout)?;
+                } else {
+                    report.write_text(&mut stdout)?;
+                }
+                return Ok(());
+            }
 
-            server::start_send_server(manifest, transport, &config).await?;
+            let idle_timeout = args.idle_timeout();
+            let address = args.address_selector();
+            let options = server::SendOptions {
+                downloads: expires_after_downloads.unwrap_or(downloads),
+                concurrent_downloads: expires_after_downloads.is_some(),
+                streaming_hash: hash,
+                expected_hash,
+                stats_out: args.stats_out,
+                link_ttl: link_ttl.map(Duration::from_secs),
+                idle_timeout,
+                dedup_chunks: !no_dedup,
+                require_claim,
+                max_transfer,
+                file_mac,
+                compress,
+                json_progress: args.json,
+                plain_output: args.no_tui,
+                clipboard: args.clipboard,
+                qr_file: args.qr_file.clone(),
+                password,
+                auth_token: args.auth_token.clone(),
+                shutdown_grace: Some(Duration::from_secs(args.shutdown_grace)),
+                address,
+                webhook: args.webhook.clone(),
+                mdns: args.mdns,
+                tar_stream,
+                resumable,
+                resume,
+            };
+            server::start_send_server(manifest, transport, &config, options).await?;
 
             drop(temp_archive);
+            drop(stdin_payload);
         }
-        Commands::Receive { destination, args } => {
+        Commands::Receive {
+            destination,
+            tar,
+            received_out,
+            verify,
+            on_conflict,
+            args,
+        } => {
             let overrides = ConfigOverrides::from(&args);
-            let config = config::apply_overrides(config::load_config()?, &overrides);
-
-            if !destination.exists() {
-                tokio::fs::create_dir_all(&destination)
-                    .await
-                    .context(format!("Cannot create directory {}", destination.display()))?;
-            }
+            let config = load_effective_config(config_file, &args, &overrides)?;
 
             ensure!(
-                destination.is_dir(),
-                "{} is not a directory",
-                destination.display()
+                !(args.json && tar.as_deref().is_some_and(receive::TarSink::targets_stdout)),
+                "--json and --tar - both need stdout; write the tar stream to a file"
             );
+            if tar.is_none() {
+                prepare_receive_dir(&destination).await?;
+            }
+            let password = args.passphrase()?;
 
             let transport = overrides.transport.unwrap_or(config.default_transport);
 
-            server::start_receive_server(destination, transport, &config)
+            let address = args.address_selector();
+            let options = server::ReceiveOptions {
+                idle_timeout: args.idle_timeout(),
+                stats_out: args.stats_out,
+                tar_output: tar,
+                received_out,
+                verify,
+                on_conflict: on_conflict.into(),
+                json_progress: args.json,
+                plain_output: args.no_tui,
+                clipboard: args.clipboard,
+                qr_file: args.qr_file.clone(),
+                password,
+                auth_token: args.auth_token.clone(),
+                shutdown_grace: Some(Duration::from_secs(args.shutdown_grace)),
+                address,
+                webhook: args.webhook.clone(),
+                mdns: args.mdns,
+            };
+            server::start_receive_server(destination, transport, &config, options)
                 .await
                 .context("Failed to start file receiver")?;
         }
@@ -210,7 +750,59 @@ async fn main() -> Result<()> {
                 let _ = config_commands::run_config_reset(yes)?;
             }
         },
+        Commands::Bench {
+            size_mb,
+            via,
+            concurrency,
+            chunk_size,
+        } => {
+            let overrides = ConfigOverrides {
+                transport: via.map(Into::into),
+                concurrency,
+                chunk_size,
+                ..Default::default()
+            };
+            let config = config::apply_overrides(load_base_config(config_file)?, &overrides);
+            let transport = overrides.transport.unwrap_or(config.default_transport);
+            let report = server::bench::run_bench(&config, transport, size_mb * 1024 * 1024)
+                .await
+                .context("Benchmark failed")?;
+            report.print();
+        }
+        Commands::Discover { timeout } => {
+            let peers = server::discover(Duration::from_secs(timeout))
+                .await
+                .context("mDNS discovery failed")?;
+            if peers.is_empty() {
+                eprintln!("No ArchDrop sessions found on the LAN");
+            }
+            for peer in peers {
+                println!("{}  {}", peer.url, peer.name);
+            }
+        }
+        Commands::Relay { bind } => {
+            let addr = relay::parse_bind_addr(&bind)?;
+            server::start_relay_server(addr)
+                .await
+                .context("Failed to start relay")?;
+        }
+    }
+    Ok(())
+}
+
+/// Create the receive destination if missing; reject anything but a directory.
+async fn prepare_receive_dir(destination: &Path) -> Result<()> {
+    if !destination.exists() {
+        tokio::fs::create_dir_all(destination)
+            .await
+            .context(format!("Cannot create directory {}", destination.display()))?;
     }
+
+    ensure!(
+        destination.is_dir(),
+        "{} is a file, not a directory. Pass the directory to save received files into",
+        destination.display()
+    );
     Ok(())
 }
 
@@ -226,8 +818,11 @@ fn resolve_zip_enabled(zip: bool, no_zip: bool, config_zip: bool) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{resolve_zip_enabled, Cli, Commands};
+    use super::{
+        prepare_receive_dir, resolve_zip_enabled, Cli, CliConflictPolicy, Commands, ConfigOverrides,
+    };
     use clap::Parser;
+    use std::path::Path;
 
     #[test]
     fn send_zip_flag_parses() {
@@ -265,6 +860,250 @@ mod tests {
         }
     }
 
+    #[test]
+    fn send_accepts_multiple_paths() {
+        let cli = Cli::parse_from(["archdrop", "send", "a.txt", "b.txt", "dir/"]);
+        match cli.command {
+            Commands::Send { path, .. } => assert_eq!(
+                path,
+                vec![Path::new("a.txt"), Path::new("b.txt"), Path::new("dir/")]
+            ),
+            _ => panic!("expected send command"),
+        }
+    }
+
+    #[test]
+    fn send_downloads_flag_parses_and_rejects_zero() {
+        let cli = Cli::parse_from(["archdrop", "send", "--downloads", "5", "file.txt"]);
+        match cli.command {
+            Commands::Send { downloads, .. } => assert_eq!(downloads, 5),
+            _ => panic!("expected send command"),
+        }
+
+        assert!(Cli::try_parse_from(["archdrop", "send", "--downloads", "0", "file.txt"]).is_err());
+    }
+
+    #[test]
+    fn expires_after_downloads_conflicts_with_downloads() {
+        let cli = Cli::parse_from(["archdrop", "send", "--expires-after-downloads", "3", "f"]);
+        match cli.command {
+            Commands::Send {
+                expires_after_downloads,
+                ..
+            } => assert_eq!(expires_after_downloads, Some(3)),
+            _ => panic!("expected send command"),
+        }
+
+        assert!(Cli::try_parse_from([
+            "archdrop",
+            "send",
+            "--expires-after-downloads",
+            "3",
+            "--downloads",
+            "2",
+            "f"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn send_name_flag_rejects_path_traversal() {
+        let cli = Cli::parse_from(["archdrop", "send", "--name", "notes.txt", "-"]);
+        match cli.command {
+            Commands::Send { name, .. } => assert_eq!(name.as_deref(), Some("notes.txt")),
+            _ => panic!("expected send command"),
+        }
+
+        assert!(Cli::try_parse_from(["archdrop", "send", "--name", "../x", "f"]).is_err());
+        assert!(Cli::try_parse_from(["archdrop", "send", "--name", "a/b", "f"]).is_err());
+    }
+
+    #[test]
+    fn resume_replaces_paths_and_conflicts_with_inputs() {
+        let token = "5f0c2b1e-8d4a-4c39-9f2e-0a1b2c3d4e5f";
+        let cli = Cli::parse_from(["archdrop", "send", "--resume", token]);
+        match cli.command {
+            Commands::Send { resume, path, .. } => {
+                assert_eq!(resume.as_deref(), Some(token));
+                assert!(path.is_empty());
+            }
+            _ => panic!("expected send command"),
+        }
+
+        assert!(Cli::try_parse_from(["archdrop", "send", "--resume", token, "f"]).is_err());
+        assert!(
+            Cli::try_parse_from(["archdrop", "send", "--resumable", "--no-dedup", "f"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["archdrop", "send"]).is_err());
+    }
+
+    #[test]
+    fn receive_parses_destination_and_defaults_to_cwd() {
+        let cli = Cli::parse_from(["archdrop", "receive", "./out"]);
+        match cli.command {
+            Commands::Receive { destination, .. } => {
+                assert_eq!(destination, Path::new("./out"))
+            }
+            _ => panic!("expected receive command"),
+        }
+
+        let cli = Cli::parse_from(["archdrop", "receive"]);
+        match cli.command {
+            Commands::Receive { destination, .. } => assert_eq!(destination, Path::new(".")),
+            _ => panic!("expected receive command"),
+        }
+    }
+
+    #[test]
+    fn receive_tar_to_stdout_claims_stdout() {
+        let cli = Cli::parse_from(["archdrop", "receive", "--tar", "-"]);
+        assert!(cli.command.writes_data_to_stdout());
+
+        let cli = Cli::parse_from(["archdrop", "receive", "--tar", "out.tar"]);
+        assert!(!cli.command.writes_data_to_stdout());
+
+        // The tar stream replaces the destination directory
+        assert!(Cli::try_parse_from(["archdrop", "receive", "./out", "--tar", "-"]).is_err());
+    }
+
+    #[test]
+    fn json_flag_claims_stdout_for_send_and_receive() {
+        let cli = Cli::parse_from(["archdrop", "send", "--json", "file.txt"]);
+        assert!(cli.command.writes_json_to_stdout());
+        assert!(!cli.command.writes_data_to_stdout());
+
+        let cli = Cli::parse_from(["archdrop", "receive", "--json"]);
+        assert!(cli.command.writes_json_to_stdout());
+
+        let cli = Cli::parse_from(["archdrop", "receive"]);
+        assert!(!cli.command.writes_json_to_stdout());
+    }
+
+    #[test]
+    fn interface_and_bind_pick_the_address_and_conflict() {
+        let cli = Cli::parse_from(["archdrop", "receive", "--interface", "eth0"]);
+        let Commands::Receive { args, .. } = cli.command else {
+            panic!("expected receive");
+        };
+        assert_eq!(
+            args.address_selector(),
+            archdrop::server::AddressSelector::Interface("eth0".to_string())
+        );
+
+        let cli = Cli::parse_from(["archdrop", "send", "--bind", "::1", "file.txt"]);
+        let Commands::Send { args, .. } = cli.command else {
+            panic!("expected send");
+        };
+        assert_eq!(
+            args.address_selector(),
+            archdrop::server::AddressSelector::Ip("::1".parse().unwrap())
+        );
+
+        assert!(Cli::try_parse_from([
+            "archdrop",
+            "receive",
+            "--interface",
+            "eth0",
+            "--bind",
+            "10.0.0.1"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn bench_takes_chunk_size_and_concurrency_overrides() {
+        let cli = Cli::parse_from([
+            "archdrop",
+            "bench",
+            "--chunk-size",
+            "1M",
+            "--concurrency",
+            "12",
+        ]);
+        let Commands::Bench {
+            concurrency,
+            chunk_size,
+            ..
+        } = cli.command
+        else {
+            panic!("expected bench");
+        };
+        assert_eq!(concurrency, Some(12));
+        assert_eq!(chunk_size, Some(1024 * 1024));
+
+        assert!(Cli::try_parse_from(["archdrop", "bench", "--chunk-size", "32K"]).is_err());
+    }
+
+    #[test]
+    fn chunk_size_flag_accepts_human_sizes_within_bounds() {
+        let cli = Cli::parse_from(["archdrop", "send", "--chunk-size", "512K", "f"]);
+        let Commands::Send { args, .. } = cli.command else {
+            panic!("expected send");
+        };
+        assert_eq!(ConfigOverrides::from(&args).chunk_size, Some(512 * 1024));
+
+        for size in ["32K", "256M", "lots"] {
+            assert!(
+                Cli::try_parse_from(["archdrop", "send", "--chunk-size", size, "f"]).is_err(),
+                "{size}"
+            );
+        }
+    }
+
+    #[test]
+    fn send_dry_run_flag_parses() {
+        let cli = Cli::parse_from(["archdrop", "send", "--dry-run", "--json", "dir"]);
+        let Commands::Send { dry_run, args, .. } = cli.command else {
+            panic!("expected send");
+        };
+        assert!(dry_run);
+        assert!(args.json);
+    }
+
+    #[test]
+    fn discover_timeout_parses_and_rejects_zero() {
+        let cli = Cli::parse_from(["archdrop", "discover", "--timeout", "5"]);
+        assert!(matches!(cli.command, Commands::Discover { timeout: 5 }));
+        assert!(Cli::try_parse_from(["archdrop", "discover", "--timeout", "0"]).is_err());
+
+        let cli = Cli::parse_from(["archdrop", "send", "--mdns", "f"]);
+        let Commands::Send { args, .. } = cli.command else {
+            panic!("expected send");
+        };
+        assert!(args.mdns);
+    }
+
+    #[test]
+    fn on_conflict_defaults_to_rename() {
+        let cli = Cli::parse_from(["archdrop", "receive"]);
+        let Commands::Receive { on_conflict, .. } = cli.command else {
+            panic!("expected receive");
+        };
+        assert_eq!(on_conflict, CliConflictPolicy::Rename);
+
+        let cli = Cli::parse_from(["archdrop", "receive", "--on-conflict", "skip"]);
+        let Commands::Receive { on_conflict, .. } = cli.command else {
+            panic!("expected receive");
+        };
+        assert_eq!(on_conflict, CliConflictPolicy::Skip);
+    }
+
+    #[tokio::test]
+    async fn receive_dir_is_created_and_files_are_rejected() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let nested = temp.path().join("a").join("b");
+        prepare_receive_dir(&nested).await.expect("create dir");
+        assert!(nested.is_dir());
+
+        let file = temp.path().join("file.txt");
+        std::fs::write(&file, b"x").unwrap();
+        let err = prepare_receive_dir(&file).await.unwrap_err();
+        assert!(
+            err.to_string().contains("is a file, not a directory"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn no_zip_overrides_config_zip_true() {
         assert!(!resolve_zip_enabled(false, true, true));
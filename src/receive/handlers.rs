@@ -3,13 +3,17 @@
 use std::collections::HashSet;
 use std::sync::Arc;
 
+use crate::common::config::MAX_TRANSFER_CHUNK_SIZE_BYTES;
 use crate::common::manifest::validate_nonce_counter_chunks;
 use crate::common::AppError;
-use crate::crypto::types::Nonce;
+use crate::crypto::{self, types::Nonce, ChunkPosition};
+use crate::receive::received::ReceivedFile;
 use crate::receive::state::{FileReceiveState, ReceiveAppState};
-use crate::receive::storage::{self, ChunkStorage};
+use crate::receive::storage::{self, ChunkStorage, ConflictPolicy, HashMismatch};
 use crate::server::auth::{self, BearerToken, LockToken};
-use crate::utils::security;
+use crate::server::bandwidth::AEAD_TAG_BYTES;
+use crate::server::status::TransferStatus;
+use crate::utils::{log_path, run_blocking, security};
 use anyhow::{Context, Result};
 use axum::extract::{Multipart, State};
 use axum::Json;
@@ -23,6 +27,9 @@ use tokio_util::bytes::Bytes;
 pub struct ClientManifestEntry {
     pub relative_path: String,
     pub size: u64,
+    /// SHA-256 of the file (hex); the reassembled file must match it
+    #[serde(default)]
+    pub hash: Option<String>,
 }
 
 /// Client manifest used to pre-create receive sessions.
@@ -31,10 +38,21 @@ pub struct ClientManifest {
     pub files: Vec<ClientManifestEntry>,
 }
 
+/// Body for re-attaching an uploader to its receive session.
+#[derive(serde::Deserialize)]
+pub struct ResumeRequest {
+    #[serde(rename = "resumeToken")]
+    pub resume_token: String,
+}
+
+// The `chunk` form limit below must admit the largest chunk the config allows
+const _: () = assert!(MAX_TRANSFER_CHUNK_SIZE_BYTES + AEAD_TAG_BYTES <= 129 * 1024 * 1024);
+
 /// Multipart payload for one encrypted chunk upload.
 #[derive(TryFromMultipart)]
 pub struct ChunkUploadRequest {
-    #[form_data(limit = "12MB")]
+    // Largest chunk plus its tag; the handler checks the session's own chunk size
+    #[form_data(limit = "129MiB")]
     pub chunk: Bytes,
     #[form_data(field_name = "relativePath")]
     pub relative_path: String,
@@ -43,6 +61,21 @@ pub struct ChunkUploadRequest {
     pub nonce: Option<String>,
 }
 
+/// Progress for scripts polling without the TUI. Needs no token; the
+/// display name is included only with the session token.
+pub async fn status_handler(
+    bearer: Option<BearerToken>,
+    State(state): State<ReceiveAppState>,
+) -> Json<TransferStatus> {
+    Json(TransferStatus::collect(
+        "receive",
+        &state.progress,
+        &state.session,
+        &state.display_name,
+        bearer.as_ref().map(|BearerToken(token)| token.as_str()),
+    ))
+}
+
 /// Claim session, validate manifest, and initialize receive state.
 pub async fn receive_manifest(
     BearerToken(token): BearerToken,
@@ -53,6 +86,7 @@ pub async fn receive_manifest(
 
     // Claim session with manifest
     let lock_token = auth::claim_session(&state.session, &token)?;
+    state.progress.record_client(&lock_token);
 
     let receive_session = &state.receive_sessions;
 
@@ -74,11 +108,20 @@ pub async fn receive_manifest(
         validate_nonce_counter_chunks(file.size, chunk_size, &file.relative_path)
             .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
+        if let Some(hash) = &file.hash {
+            if !crypto::hash::is_sha256_hex(hash) {
+                return Err(AppError::BadRequest(format!(
+                    "invalid sha256 for {}",
+                    file.relative_path
+                )));
+            }
+        }
+
         total_size = total_size
             .checked_add(file.size)
             .ok_or_else(|| AppError::BadRequest("manifest size overflow".to_string()))?;
     }
-    storage::check_disk_space(destination, total_size)
+    storage::check_disk_space(destination, total_size, state.space_probe)
         .map_err(|e| AppError::InsufficientStorage(e.to_string()))?;
 
     let mut session_total_chunks = 0;
@@ -87,11 +130,12 @@ pub async fn receive_manifest(
     // Collect file names and chunk totals for progress tracker
     let mut progress_names: Vec<String> = Vec::with_capacity(file_count);
     let mut progress_totals: Vec<u64> = Vec::with_capacity(file_count);
+    // `--on-conflict skip`: files the uploader should not send at all
+    let mut skipped: Vec<(usize, String)> = Vec::new();
 
     // Precreate file sessions to prevent race conditions during parallel upload
     for (file_index, file) in manifest.files.into_iter().enumerate() {
         let file_chunks = file.size.div_ceil(chunk_size);
-        session_total_chunks += file_chunks;
 
         let file_id = security::hash_path(&file.relative_path);
 
@@ -110,9 +154,23 @@ pub async fn receive_manifest(
             .map_err(|e| AppError::BadRequest(format!("bad path: {}", e)))?;
 
         // Initialize storage (creates/truncates file) safely here in serial order
-        let storage = ChunkStorage::new(dest_path, file.size, chunk_size)
-            .await
-            .context("create storage")?;
+        let storage = match state.on_conflict {
+            ConflictPolicy::Skip if tokio::fs::try_exists(&dest_path).await.unwrap_or(false) => {
+                tracing::info!(path = %log_path(&file.relative_path), "Skipped: already exists");
+                skipped.push((file_index, file.relative_path));
+                continue;
+            }
+            _ if state.tar_sink.is_some() => ChunkStorage::staged(dest_path, file.size, chunk_size)
+                .await
+                .context("create storage")?,
+            ConflictPolicy::Overwrite => ChunkStorage::replacing(dest_path, file.size, chunk_size)
+                .await
+                .context("create storage")?,
+            _ => ChunkStorage::new(dest_path, file.size, chunk_size)
+                .await
+                .context("create storage")?,
+        };
+        session_total_chunks += file_chunks;
 
         let new_state = FileReceiveState {
             storage,
@@ -121,6 +179,7 @@ pub async fn receive_manifest(
             relative_path: file.relative_path,
             file_size: file.size,
             file_index,
+            expected_hash: file.hash,
         };
 
         receive_session.insert(file_id, Arc::new(Mutex::new(new_state)));
@@ -131,12 +190,62 @@ pub async fn receive_manifest(
 
     // Initialize progress tracker with all files at once
     state.progress.init_files(progress_names, progress_totals);
+    for (file_index, _) in &skipped {
+        state
+            .progress
+            .file_skipped(*file_index, "already exists".to_string());
+    }
 
     Ok(Json(json!({
         "success": true,
         "total_chunks": session_total_chunks,
+        "skipped": skipped.into_iter().map(|(_, path)| path).collect::<Vec<_>>(),
         "config": state.config,
-        "lockToken": lock_token
+        "lockToken": lock_token,
+        "resumeToken": state.issue_resume_token(),
+    })))
+}
+
+/// Re-attach an uploader to the active receive session using its resume token.
+///
+/// Issues a new lock token (the old one stops working) and reports which
+/// chunks each file already has, so the client only uploads the rest.
+pub async fn resume_upload(
+    BearerToken(token): BearerToken,
+    State(state): State<ReceiveAppState>,
+    Json(request): Json<ResumeRequest>,
+) -> Result<axum::Json<Value>, AppError> {
+    if !state.resume_token_matches(&request.resume_token) {
+        return Err(AppError::Unauthorized("invalid resume token".to_string()));
+    }
+    let lock_token = state
+        .session
+        .reattach(&token)
+        .ok_or_else(|| AppError::Conflict("session not active".to_string()))?;
+
+    let file_states: Vec<_> = state
+        .receive_sessions
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    let mut files = Vec::with_capacity(file_states.len());
+    for file_state in file_states {
+        let file = file_state.lock().await;
+        files.push(json!({
+            "relative_path": file.relative_path,
+            "index": file.file_index,
+            "nonce": file.nonce,
+            "received": file.storage.received_chunks(),
+        }));
+    }
+
+    let (_received, total_chunks) = state.get_progress();
+    Ok(Json(json!({
+        "success": true,
+        "total_chunks": total_chunks,
+        "config": state.config,
+        "lockToken": lock_token,
+        "files": files,
     })))
 }
 
@@ -159,6 +268,17 @@ pub async fn receive_handler(
     let file_id = security::hash_path(&relative_path);
     auth::require_active_session(&state.session, &token, &lock_token)?;
 
+    // The form limit covers the largest allowed chunk; hold each upload to this session's
+    if chunk.len() as u64 > state.config.chunk_size + AEAD_TAG_BYTES {
+        return Err(AppError::PayloadTooLarge(
+            "chunk larger than the session chunk size".to_string(),
+        ));
+    }
+
+    state.rate_limiter.check(&lock_token)?;
+    // Held until the chunk is decrypted and written
+    let _permit = state.limiter.acquire(&lock_token).await;
+
     // Sessions are made in manifest, so well just get
     let file_session_mutex = receive_sessions
         .get(&file_id)
@@ -172,23 +292,23 @@ pub async fn receive_handler(
     }
     let nonce = Nonce::from_base64(&nonce_string)?;
 
+    // Index and size are fixed at manifest time; read them before decrypting
+    let position = {
+        let file = file_session_mutex.lock().await;
+        ChunkPosition::new(file.file_index, chunk_index, file.file_size)
+    };
     let cipher = state.session.cipher().clone();
+    let suite = state.session.cipher_suite();
     let mut chunk_data = chunk.to_vec();
     let nonce_val = nonce;
 
     let decrypt_bytes = chunk_data.len();
     let decrypt_start = std::time::Instant::now();
-    let decrypted_data = tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<u8>> {
-        crate::crypto::decrypt_chunk_in_place(
-            &cipher,
-            &nonce_val,
-            &mut chunk_data,
-            chunk_index as u32,
-        )?;
+    let decrypted_data = run_blocking("decrypt", move || -> anyhow::Result<Vec<u8>> {
+        crypto::decrypt_chunk_at_position(&cipher, &nonce_val, &mut chunk_data, &position, suite)?;
         Ok(chunk_data)
     })
     .await
-    .context("decrypt task panicked")?
     .context("decrypt failed")?;
     tracing::debug!(
         chunk_index,
@@ -213,6 +333,7 @@ pub async fn receive_handler(
 
     // Check duplicates
     if session.storage.has_chunk(chunk_index) {
+        state.progress.record_retry();
         return Ok(axum::Json(json!({
             "success": true,
             "duplicate": true,
@@ -234,6 +355,10 @@ pub async fn receive_handler(
     // Track progress
     let (_chunks_processed, _total_chunks) = state.increment_received_chunk();
     state.progress.increment_file(session.file_index);
+    state
+        .progress
+        .bandwidth()
+        .record_chunk(decrypted_data.len() as u64);
 
     Ok(Json(json!({
         "success": true,
@@ -286,8 +411,57 @@ pub async fn finalize_upload(
         )));
     }
 
-    // Finalize storage
-    let computed_hash = session.storage.finalize().await?;
+    // Finalize storage, checking the uploader's hash when it sent one
+    let expected_hash = session.expected_hash.clone();
+    let computed_hash = match session
+        .storage
+        .finalize_verified(expected_hash.as_deref())
+        .await
+    {
+        Ok(hash) => hash,
+        Err(err) if err.is::<HashMismatch>() => {
+            // Retrying cannot repair the data; drop the session and its partial file
+            receive_sessions.remove(&file_id);
+            tracing::warn!(path = %log_path(&relative_path), error = %err, "Rejected corrupt upload");
+            state
+                .progress
+                .file_failed(session.file_index, err.to_string());
+            return Err(AppError::BadRequest(format!("{}: {}", relative_path, err)));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // `--verify`: read the file back before anyone relies on it
+    if state.verify {
+        let expected = expected_hash.as_deref().unwrap_or(&computed_hash);
+        let path = session.storage.get_path();
+        if let Err(err) = storage::verify_received(path, session.file_size, expected).await {
+            receive_sessions.remove(&file_id);
+            tracing::warn!(path = %log_path(&relative_path), error = %err, "Verification failed");
+            state
+                .progress
+                .file_failed(session.file_index, format!("{:#}", err));
+            return Err(err.into());
+        }
+        tracing::info!(path = %log_path(&relative_path), "Verified");
+    }
+
+    // Frame the assembled file into the tar stream, dropping the staged copy
+    let final_path = match state.tar_sink.clone() {
+        Some(sink) => {
+            let staged = session.storage.get_path().clone();
+            let entry_path = session.relative_path.clone();
+            run_blocking("tar entry", move || sink.append_file(&entry_path, &staged)).await?;
+            None
+        }
+        None => Some(session.storage.get_path().clone()),
+    };
+    state.received.record(ReceivedFile {
+        name: session.relative_path.clone(),
+        size: session.file_size,
+        sha256: computed_hash.clone(),
+        path: final_path,
+    });
 
     // Remove only after successful finalize so retries remain possible on incomplete files.
     receive_sessions.remove(&file_id);
@@ -308,7 +482,21 @@ pub async fn complete_transfer(
     State(state): State<ReceiveAppState>,
 ) -> Result<axum::Json<Value>, AppError> {
     auth::require_active_session(&state.session, &token, &lock_token)?;
+    if let Some(sink) = state.tar_sink.clone() {
+        run_blocking("tar finish", move || sink.finish()).await?;
+    }
     state.session.complete(&token, &lock_token);
+    state.limiter.forget_client(&lock_token);
+    state.rate_limiter.forget_client(&lock_token);
+    state.progress.bandwidth().summary().log();
+    if let Some(path) = state.received_out.clone() {
+        let record = state.clone();
+        let written = run_blocking("received files", move || record.received.write(&path)).await;
+        if let Err(e) = written {
+            tracing::warn!("{:#}", e);
+        }
+    }
+    state.progress.complete();
 
     Ok(Json(
         json!({"success": true, "message": "Transfer complete"}),
@@ -1,8 +1,12 @@
 //! Receive state, storage, and request handlers.
 
 pub mod handlers;
+mod received;
 mod state;
 mod storage;
+mod tar_sink;
 
-pub use state::ReceiveAppState;
-pub use storage::ChunkStorage;
+pub use received::{ReceivedFile, ReceivedFiles};
+pub use state::{ReceiveAppState, ReceiveAppStateBuilder};
+pub use storage::{verify_received, ChunkStorage, ConflictPolicy, SpaceProbe};
+pub use tar_sink::TarSink;
@@ -0,0 +1,54 @@
+//! Record of the files a receive session wrote (`--received-out`).
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// One finalized file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReceivedFile {
+    /// Path as sent by the uploader
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+    /// Where the file landed; null when it went into a `--tar` stream
+    pub path: Option<PathBuf>,
+}
+
+/// JSON document written at completion.
+#[derive(Debug, Serialize)]
+struct ReceivedReport<'a> {
+    files: &'a [ReceivedFile],
+}
+
+/// Files finalized so far, in finalize order.
+#[derive(Debug, Default)]
+pub struct ReceivedFiles(Mutex<Vec<ReceivedFile>>);
+
+impl ReceivedFiles {
+    /// Add a finalized file.
+    pub fn record(&self, file: ReceivedFile) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(file);
+    }
+
+    /// Copy of every recorded file.
+    pub fn files(&self) -> Vec<ReceivedFile> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Write the list as pretty JSON, replacing any existing file.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let files = self.files();
+        let json = serde_json::to_vec_pretty(&ReceivedReport { files: &files })
+            .context("Failed to serialize received files")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write received files to {}", path.display()))
+    }
+}
@@ -2,15 +2,22 @@
 
 use crate::common::config::TransferSettings;
 use crate::common::{Session, TransferState};
+use crate::crypto::password::KeySalt;
 use crate::crypto::types::EncryptionKey;
-use crate::receive::storage::ChunkStorage;
+use crate::receive::received::ReceivedFiles;
+use crate::receive::storage::{self, ChunkStorage, ConflictPolicy, SpaceProbe};
+use crate::receive::tar_sink::TarSink;
+use crate::server::auth::AuthSecret;
+use crate::server::limits::{ConcurrencyLimiter, ConcurrencyLimits};
 use crate::server::progress::ProgressTracker;
+use crate::server::rate_limit::{RateLimiter, RateLimits};
 use dashmap::DashMap;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tokio::sync::Mutex;
+use uuid::Uuid;
 
 /// Per-file receive state tracked across chunk uploads.
 pub struct FileReceiveState {
@@ -20,6 +27,8 @@ pub struct FileReceiveState {
     pub relative_path: String,
     pub file_size: u64,
     pub file_index: usize,
+    /// SHA-256 (hex) declared by the uploader, checked at finalize
+    pub expected_hash: Option<String>,
 }
 
 /// Cheaply cloned handle to receive state
@@ -35,6 +44,25 @@ pub struct ReceiveAppStateInner {
     pub progress: Arc<ProgressTracker>,
     pub receive_sessions: Arc<DashMap<String, Arc<Mutex<FileReceiveState>>>>,
     pub config: TransferSettings,
+    pub limiter: Arc<ConcurrencyLimiter>,
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Set by `--tar`: finalized files go into this stream instead of staying on disk
+    pub tar_sink: Option<Arc<TarSink>>,
+    /// Every finalized file, for `--received-out`
+    pub received: ReceivedFiles,
+    /// Write `received` as JSON here when the transfer completes
+    pub received_out: Option<PathBuf>,
+    /// Set by `--verify`: re-read each file after it is finalized
+    pub verify: bool,
+    /// Set by `--on-conflict`: what to do with names already at the destination
+    pub on_conflict: ConflictPolicy,
+    /// Destination label, as shown in the TUI and `GET /status`
+    pub display_name: String,
+    /// Set by `--auth-token`: required alongside the session token
+    pub auth_secret: Option<AuthSecret>,
+    /// Free-space lookup checked against each incoming manifest
+    pub space_probe: SpaceProbe,
+    resume_token: OnceLock<String>,
     total_chunks: Arc<AtomicU64>,
     chunks_received: Arc<AtomicU64>,
 }
@@ -47,32 +75,163 @@ impl Deref for ReceiveAppState {
     }
 }
 
-impl ReceiveAppState {
-    /// Build receive state from session key, destination, and settings.
+/// Receive state under construction; options are set here, before any
+/// clone of the finished state can exist.
+pub struct ReceiveAppStateBuilder {
+    inner: ReceiveAppStateInner,
+}
+
+impl ReceiveAppStateBuilder {
+    /// Start from `session` with every option at its default.
     pub fn new(
-        session_key: EncryptionKey,
+        session: Session,
         destination: PathBuf,
         progress: Arc<ProgressTracker>,
         config: TransferSettings,
     ) -> Self {
         Self {
-            inner: Arc::new(ReceiveAppStateInner {
-                session: Session::new(session_key),
+            inner: ReceiveAppStateInner {
+                session,
                 destination,
                 progress,
                 receive_sessions: Arc::new(DashMap::new()),
                 config,
+                limiter: Arc::new(ConcurrencyLimiter::new(ConcurrencyLimits::for_transfer(
+                    config,
+                ))),
+                rate_limiter: Arc::new(RateLimiter::new(RateLimits::default())),
+                tar_sink: None,
+                received: ReceivedFiles::default(),
+                received_out: None,
+                verify: false,
+                on_conflict: ConflictPolicy::default(),
+                display_name: String::new(),
+                auth_secret: None,
+                space_probe: storage::available_space,
+                resume_token: OnceLock::new(),
                 total_chunks: Arc::new(AtomicU64::new(0)),
                 chunks_received: Arc::new(AtomicU64::new(0)),
-            }),
+            },
+        }
+    }
+
+    /// Replace the default concurrency caps.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.inner.limiter = Arc::new(ConcurrencyLimiter::new(limits));
+        self
+    }
+
+    /// Replace the default per-client request rate.
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.inner.rate_limiter = Arc::new(RateLimiter::new(limits));
+        self
+    }
+
+    /// Mark the session key as passphrase-derived from `salt`.
+    pub fn with_key_salt(mut self, salt: KeySalt) -> Self {
+        self.inner.session = self.inner.session.with_key_salt(salt);
+        self
+    }
+
+    /// Frame finalized files into `sink` instead of keeping them under
+    /// `destination`, which should be the sink's staging directory.
+    pub fn with_tar_sink(mut self, sink: Arc<TarSink>) -> Self {
+        self.inner.tar_sink = Some(sink);
+        self
+    }
+
+    /// Set the label reported by `GET /status`.
+    pub fn with_display_name(mut self, name: String) -> Self {
+        self.inner.display_name = name;
+        self
+    }
+
+    /// Require `secret` on every token-gated route.
+    pub fn with_auth_secret(mut self, secret: AuthSecret) -> Self {
+        self.inner.auth_secret = Some(secret);
+        self
+    }
+
+    /// Replace how free space on the destination is measured.
+    pub fn with_space_probe(mut self, probe: SpaceProbe) -> Self {
+        self.inner.space_probe = probe;
+        self
+    }
+
+    /// Write the list of received files to `path` as JSON at completion.
+    pub fn with_received_out(mut self, path: PathBuf) -> Self {
+        self.inner.received_out = Some(path);
+        self
+    }
+
+    /// Re-read and check every file once it is finalized.
+    pub fn with_verify(mut self) -> Self {
+        self.inner.verify = true;
+        self
+    }
+
+    /// Choose how names that already exist at the destination are handled.
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.inner.on_conflict = policy;
+        self
+    }
+
+    /// Finish configuring and share the state.
+    pub fn build(self) -> ReceiveAppState {
+        ReceiveAppState {
+            inner: Arc::new(self.inner),
         }
     }
+}
+
+impl ReceiveAppState {
+    /// Build receive state from session key, destination, and settings.
+    pub fn new(
+        session_key: EncryptionKey,
+        destination: PathBuf,
+        progress: Arc<ProgressTracker>,
+        config: TransferSettings,
+    ) -> Self {
+        Self::builder(session_key, destination, progress, config).build()
+    }
+
+    /// Build receive state around an existing (for example, restored) session.
+    pub fn with_session(
+        session: Session,
+        destination: PathBuf,
+        progress: Arc<ProgressTracker>,
+        config: TransferSettings,
+    ) -> Self {
+        ReceiveAppStateBuilder::new(session, destination, progress, config).build()
+    }
+
+    /// Start building receive state that needs more than the defaults.
+    pub fn builder(
+        session_key: EncryptionKey,
+        destination: PathBuf,
+        progress: Arc<ProgressTracker>,
+        config: TransferSettings,
+    ) -> ReceiveAppStateBuilder {
+        ReceiveAppStateBuilder::new(Session::new(session_key), destination, progress, config)
+    }
 
     /// Return the destination root for received files.
     pub fn destination(&self) -> &PathBuf {
         &self.destination
     }
 
+    /// Issue the upload resume token. Only the first call creates one.
+    pub fn issue_resume_token(&self) -> &str {
+        self.resume_token.get_or_init(|| Uuid::new_v4().to_string())
+    }
+
+    /// True when `candidate` matches the issued resume token.
+    pub fn resume_token_matches(&self, candidate: &str) -> bool {
+        self.resume_token
+            .get()
+            .is_some_and(|issued| !candidate.is_empty() && issued == candidate)
+    }
+
     /// Set expected total chunk count for this transfer.
     pub fn set_total_chunks(&self, total: u64) {
         self.total_chunks.store(total, Ordering::SeqCst);
@@ -155,6 +314,8 @@ mod tests {
             TransferSettings {
                 chunk_size: 1024,
                 concurrency: 1,
+                retry_min_ms: 1,
+                retry_max_ms: 1,
             },
         );
 
@@ -1,30 +1,92 @@
 //! Assembles files from out-of-order chunks with collision-safe naming and RAII cleanup.
+//!
+//! Chunks land in a `<name>.partial` sibling of the final path. Only a
+//! complete, verified file is renamed into place, so the final name never
+//! holds a half-written upload.
 
 use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::io::SeekFrom;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
+use crate::crypto::hash;
+use crate::utils::size::format_size;
+use crate::utils::{log_path, run_blocking};
+
+/// Suffix marking a file that is still being received.
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// `file.txt` -> `file.txt.partial`
+fn partial_path_for(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Reassembled contents differ from the hash the sender declared.
+#[derive(Debug, thiserror::Error)]
+#[error("SHA-256 mismatch: got {actual}, expected {expected}")]
+pub struct HashMismatch {
+    pub expected: String,
+    pub actual: String,
+}
+
+/// What to do when a received file's name is already taken at the destination.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep both: the upload lands as `name (1).ext`
+    #[default]
+    Rename,
+    /// Replace the existing file once the upload is complete and verified
+    Overwrite,
+    /// Keep the existing file and do not accept the upload
+    Skip,
+}
+
 /// Manages file assembly from chunks arriving in any order.
 ///
 /// Collision: `file.txt` → `file (1).txt` (preserves extensions: `a.tar.gz` → `a (1).tar.gz`)
-/// RAII: `disarmed=false` → Drop deletes file. Set `true` after finalization.
+/// RAII: `disarmed=false` → Drop deletes the partial file. Set `true` after finalization.
 pub struct ChunkStorage {
     file: File,
     path: PathBuf,
+    partial_path: PathBuf,
     chunks_received: HashSet<usize>,
     expected_chunks: usize,
     expected_size: u64,
-    disarmed: bool, // false -> delete files on drop
+    disarmed: bool,         // false -> delete files on drop
+    replace_existing: bool, // true -> finalize may replace a file at `path`
     chunk_size: u64,
 }
 
 impl ChunkStorage {
     /// Create storage for one file, resolving name collisions safely.
-    pub async fn new(mut dest_path: PathBuf, file_size: u64, chunk_size: u64) -> Result<Self> {
+    pub async fn new(dest_path: PathBuf, file_size: u64, chunk_size: u64) -> Result<Self> {
+        Self::create(dest_path, file_size, chunk_size, false, false).await
+    }
+
+    /// Create storage that replaces an existing file at `dest_path` when it
+    /// finalizes (`--on-conflict overwrite`). Until then the old file is untouched.
+    pub async fn replacing(dest_path: PathBuf, file_size: u64, chunk_size: u64) -> Result<Self> {
+        Self::create(dest_path, file_size, chunk_size, true, false).await
+    }
+
+    /// Create storage for a file staged before it is framed into a tar
+    /// stream; only the receiving user may read it (0600 on Unix).
+    pub async fn staged(dest_path: PathBuf, file_size: u64, chunk_size: u64) -> Result<Self> {
+        Self::create(dest_path, file_size, chunk_size, false, true).await
+    }
+
+    async fn create(
+        mut dest_path: PathBuf,
+        file_size: u64,
+        chunk_size: u64,
+        replace_existing: bool,
+        owner_only: bool,
+    ) -> Result<Self> {
         if let Some(parent) = dest_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
@@ -74,12 +136,20 @@ impl ChunkStorage {
         };
 
         loop {
-            let result = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create_new(true)
-                .open(&dest_path)
-                .await;
+            // The partial file reserves the name; the final path must be free too
+            let partial_path = partial_path_for(&dest_path);
+            let result =
+                if !replace_existing && tokio::fs::try_exists(&dest_path).await.unwrap_or(false) {
+                    Err(std::io::ErrorKind::AlreadyExists.into())
+                } else {
+                    let mut options = OpenOptions::new();
+                    options.read(true).write(true).create_new(true);
+                    #[cfg(unix)]
+                    if owner_only {
+                        options.mode(0o600);
+                    }
+                    options.open(&partial_path).await
+                };
 
             match result {
                 Ok(file) => {
@@ -94,10 +164,12 @@ impl ChunkStorage {
                     return Ok(Self {
                         file,
                         path: dest_path,
+                        partial_path,
                         chunks_received: HashSet::new(),
                         expected_chunks,
                         expected_size: file_size,
                         disarmed: false,
+                        replace_existing,
                         chunk_size,
                     });
                 }
@@ -110,7 +182,7 @@ impl ChunkStorage {
                 Err(e) => {
                     return Err(anyhow::Error::new(e).context(format!(
                         "Failed to create storage file: {}",
-                        dest_path.display()
+                        log_path(&dest_path)
                     )));
                 }
             };
@@ -127,6 +199,18 @@ impl ChunkStorage {
         &self.path
     }
 
+    /// Return the `.partial` path chunks are written to until finalization.
+    pub fn partial_path(&self) -> &PathBuf {
+        &self.partial_path
+    }
+
+    /// Return the stored chunk indices in ascending order.
+    pub fn received_chunks(&self) -> Vec<usize> {
+        let mut chunks: Vec<usize> = self.chunks_received.iter().copied().collect();
+        chunks.sort_unstable();
+        chunks
+    }
+
     /// Return number of unique chunks written so far.
     pub fn chunk_count(&self) -> usize {
         self.chunks_received.len()
@@ -190,7 +274,7 @@ impl ChunkStorage {
     pub async fn cleanup(&mut self) -> Result<()> {
         if !self.disarmed {
             self.disarmed = true; // prevent Drop
-            tokio::fs::remove_file(&self.path)
+            tokio::fs::remove_file(&self.partial_path)
                 .await
                 .context("Failed to remove incomplete file")?;
         }
@@ -198,7 +282,7 @@ impl ChunkStorage {
         Ok(())
     }
 
-    /// Verify completeness, hash output, and keep finalized file.
+    /// Verify completeness, hash output, and rename the file into place.
     ///
     /// # Returns
     ///
@@ -207,8 +291,18 @@ impl ChunkStorage {
     /// # Errors
     ///
     /// - Missing chunks: Not all expected chunks received
-    /// - I/O errors: Cannot read file for hashing
+    /// - I/O errors: Cannot read file for hashing, sync, or rename
+    /// - Final name taken: Another file appeared under it during the transfer
     pub async fn finalize(&mut self) -> Result<String> {
+        self.finalize_verified(None).await
+    }
+
+    /// [`finalize`](Self::finalize), refusing to move the file into place
+    /// unless its SHA-256 equals `expected_hash` (when given).
+    ///
+    /// A mismatch fails with [`HashMismatch`] and leaves the partial file to
+    /// the drop guard.
+    pub async fn finalize_verified(&mut self, expected_hash: Option<&str>) -> Result<String> {
         // Check for completeness before finalizing
         let received = self.chunk_count();
         if received < self.expected_chunks {
@@ -244,15 +338,108 @@ impl ChunkStorage {
 
             hasher.update(&buffer[..n]);
         }
+        let hash = hex::encode(hasher.finalize());
+
+        if let Some(expected) = expected_hash {
+            if !hash::hashes_match(&hash, expected) {
+                return Err(HashMismatch {
+                    expected: expected.to_string(),
+                    actual: hash,
+                }
+                .into());
+            }
+        }
+
+        // Data must be durable before the rename makes it visible
+        self.file.sync_all().await?;
+
+        // rename() replaces silently, so first claim the final name with
+        // O_EXCL; the rename then only ever replaces our own placeholder
+        if !self.replace_existing {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&self.path)
+                .await
+            {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    return Err(anyhow::anyhow!(
+                        "Cannot finalize: {} already exists",
+                        log_path(&self.path)
+                    ));
+                }
+                Err(e) => {
+                    return Err(anyhow::Error::new(e)
+                        .context(format!("Failed to reserve {}", log_path(&self.path))));
+                }
+            }
+        }
+        if let Err(e) = tokio::fs::rename(&self.partial_path, &self.path).await {
+            if !self.replace_existing {
+                let _ = tokio::fs::remove_file(&self.path).await;
+            }
+            return Err(anyhow::Error::new(e).context(format!(
+                "Failed to move upload into {}",
+                log_path(&self.path)
+            )));
+        }
 
         self.disarmed = true; // mark success
 
-        let hash = hex::encode(hasher.finalize());
         Ok(hash)
     }
 }
 
-/// RAII cleanup guard: Deletes incomplete files unless disarmed by finalization.
+/// Suffix given to a finalized file that failed `--verify`.
+const CORRUPT_SUFFIX: &str = ".corrupt";
+
+/// Re-read a finalized file and confirm its size and SHA-256 (`--verify`).
+///
+/// Catches writes that were acknowledged but did not land intact. On a
+/// mismatch the file is renamed to `<name>.corrupt` so it is never mistaken
+/// for a good copy, and the returned error names both paths.
+pub async fn verify_received(path: &Path, expected_size: u64, expected_hash: &str) -> Result<()> {
+    let actual_size = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("Failed to stat {} for verification", log_path(path)))?
+        .len();
+    let problem = if actual_size != expected_size {
+        Some(format!(
+            "{} bytes on disk, expected {} bytes",
+            actual_size, expected_size
+        ))
+    } else {
+        let hash_path = path.to_path_buf();
+        let actual_hash =
+            run_blocking("verify hash", move || hash::calculate_file_hash(&hash_path)).await?;
+        (!hash::hashes_match(&actual_hash, expected_hash)).then(|| {
+            HashMismatch {
+                expected: expected_hash.to_string(),
+                actual: actual_hash,
+            }
+            .to_string()
+        })
+    };
+
+    let Some(problem) = problem else {
+        return Ok(());
+    };
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(CORRUPT_SUFFIX);
+    let aside = PathBuf::from(aside);
+    tokio::fs::rename(path, &aside)
+        .await
+        .with_context(|| format!("Failed to move {} aside", log_path(path)))?;
+    Err(anyhow::anyhow!(
+        "Verification failed for {} ({}); moved to {}",
+        log_path(path),
+        problem,
+        log_path(&aside)
+    ))
+}
+
+/// RAII cleanup guard: Deletes incomplete partial files unless disarmed by finalization.
 /// # Drop Behavior
 ///
 /// - `disarmed = false`: Delete file (incomplete transfer, error, or Ctrl+C)
@@ -263,9 +450,9 @@ impl ChunkStorage {
 impl Drop for ChunkStorage {
     fn drop(&mut self) {
         if !self.disarmed {
-            if let Err(e) = std::fs::remove_file(&self.path) {
+            if let Err(e) = std::fs::remove_file(&self.partial_path) {
                 tracing::warn!(
-                    path = %self.path.display(),
+                    path = %log_path(&self.partial_path),
                     error = %e,
                     "Failed to clean up temporary file"
                 );
@@ -274,9 +461,14 @@ impl Drop for ChunkStorage {
     }
 }
 
-/// Ensure destination filesystem has enough free space.
-/// Returns Ok if sufficient space available, Err otherwise
-pub fn check_disk_space(destination: &std::path::Path, bytes: u64) -> Result<()> {
+/// Reports the free bytes on the filesystem holding a path, if known.
+pub type SpaceProbe = fn(&Path) -> Option<u64>;
+
+/// Free space kept on top of the declared transfer size
+const DISK_SPACE_MARGIN: u64 = 1024 * 1024 * 1024;
+
+/// Free bytes on the mounted disk that holds `destination`.
+pub fn available_space(destination: &Path) -> Option<u64> {
     use sysinfo::Disks;
 
     let disks = Disks::new_with_refreshed_list();
@@ -288,7 +480,6 @@ pub fn check_disk_space(destination: &std::path::Path, bytes: u64) -> Result<()>
 
     let mut available: Option<u64> = None;
     let mut longest_match_len = 0;
-    let required_bytes = bytes + 1024 * 1024 * 1024; // 1GB buffer
 
     // Find disk with longest matching mount point (most specific)
     for disk in disks.list() {
@@ -299,13 +490,20 @@ pub fn check_disk_space(destination: &std::path::Path, bytes: u64) -> Result<()>
             longest_match_len = mount_len;
         }
     }
+    available
+}
+
+/// Ensure destination filesystem has enough free space for `bytes` plus a margin.
+/// Returns Ok if sufficient space available, Err otherwise
+pub fn check_disk_space(destination: &Path, bytes: u64, probe: SpaceProbe) -> Result<()> {
+    let required_bytes = bytes.saturating_add(DISK_SPACE_MARGIN);
 
-    match available {
+    match probe(destination) {
         Some(avail) if avail >= required_bytes => Ok(()),
         Some(avail) => Err(anyhow::anyhow!(
-            "Insufficient disk space: {} GB available, {} GB required",
-            avail / (1024 * 1024 * 1024),
-            required_bytes / (1024 * 1024 * 1024)
+            "Insufficient disk space: {} available, {} required",
+            format_size(avail),
+            format_size(required_bytes)
         )),
         None => Err(anyhow::anyhow!(
             "Cannot determine available disk space for {:?}.",
@@ -0,0 +1,107 @@
+//! Frame received files as entries of one tar stream (`receive --tar`).
+//!
+//! Chunks still land out of order, so each file is assembled in a private
+//! staging directory first. Once finalized it is copied into the stream
+//! front to back and its staged copy is deleted, so staging holds only the
+//! files still in flight.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+use crate::utils::log_path;
+
+/// `--tar` target that means standard output.
+const STDOUT_TARGET: &str = "-";
+
+type TarBuilder = tar::Builder<Box<dyn Write + Send>>;
+
+/// Tar writer shared by finalize handlers, plus its staging directory.
+pub struct TarSink {
+    builder: Mutex<Option<TarBuilder>>,
+    /// Owner-only (0700) and removed with the sink
+    staging_dir: TempDir,
+}
+
+impl TarSink {
+    /// True when `target` is `-`, i.e. the stream owns stdout.
+    pub fn targets_stdout(target: &Path) -> bool {
+        target == Path::new(STDOUT_TARGET)
+    }
+
+    /// Open `target` (`-` for stdout) and create a fresh staging directory.
+    pub fn create(target: &Path) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = if Self::targets_stdout(target) {
+            Box::new(BufWriter::new(std::io::stdout()))
+        } else {
+            let file = File::create(target)
+                .with_context(|| format!("Cannot create tar output {}", target.display()))?;
+            Box::new(BufWriter::new(file))
+        };
+        Self::new(writer)
+    }
+
+    /// Stream into an arbitrary writer.
+    pub fn new(writer: Box<dyn Write + Send>) -> Result<Self> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("archdrop-tar-");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(std::fs::Permissions::from_mode(0o700));
+        }
+        let staging_dir = builder
+            .tempdir()
+            .context("Cannot create tar staging directory")?;
+
+        Ok(Self {
+            builder: Mutex::new(Some(tar::Builder::new(writer))),
+            staging_dir,
+        })
+    }
+
+    /// Where receive storage should assemble files before they are framed.
+    pub fn staging_dir(&self) -> &Path {
+        self.staging_dir.path()
+    }
+
+    /// Append the finalized file at `staged` as `relative_path`, then delete it.
+    ///
+    /// Blocking; call from `run_blocking`.
+    pub fn append_file(&self, relative_path: &str, staged: &Path) -> Result<()> {
+        let mut file = File::open(staged)
+            .with_context(|| format!("Cannot open staged file {}", log_path(staged)))?;
+
+        let mut guard = self.builder.lock().unwrap_or_else(|e| e.into_inner());
+        let builder = guard
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Tar stream already finished"))?;
+        builder
+            .append_file(relative_path, &mut file)
+            .with_context(|| format!("Failed to write tar entry {}", log_path(relative_path)))?;
+        // Hand each entry to the consumer as soon as it is complete
+        builder
+            .get_mut()
+            .flush()
+            .context("Failed to flush tar stream")?;
+        drop(guard);
+
+        std::fs::remove_file(staged)
+            .with_context(|| format!("Cannot remove staged file {}", log_path(staged)))
+    }
+
+    /// Write the end-of-archive marker and flush. Later calls are no-ops.
+    pub fn finish(&self) -> Result<()> {
+        let mut guard = self.builder.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(builder) = guard.take() {
+            let mut writer = builder
+                .into_inner()
+                .context("Failed to finish tar stream")?;
+            writer.flush().context("Failed to flush tar stream")?;
+        }
+        Ok(())
+    }
+}
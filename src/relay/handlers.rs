@@ -0,0 +1,47 @@
+use super::pipe::PipedBody;
+use super::{PipeOutcome, RelayState};
+use crate::common::AppError;
+use crate::server::auth::BearerToken;
+use axum::{
+    body::Body,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+/// Sender streams ciphertext for its token; answers once the receiver has
+/// read all of it.
+pub async fn upload_handler(
+    State(state): State<RelayState>,
+    BearerToken(token): BearerToken,
+    body: Body,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match state.send(&token, body).await? {
+        PipeOutcome::Delivered(bytes) => {
+            tracing::info!("Relayed {} bytes", bytes);
+            Ok(Json(json!({ "success": true, "bytes": bytes })))
+        }
+        PipeOutcome::Interrupted(bytes) => {
+            tracing::warn!("Relay pipe broke after {} bytes", bytes);
+            Err(AppError::ServiceUnavailable(format!(
+                "transfer interrupted after {} bytes",
+                bytes
+            )))
+        }
+    }
+}
+
+/// Receiver waits for the sender on its token and streams the upload.
+pub async fn download_handler(
+    State(state): State<RelayState>,
+    BearerToken(token): BearerToken,
+) -> Result<Response, AppError> {
+    let pipe = state.receive(&token).await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        Body::new(PipedBody::new(pipe)),
+    )
+        .into_response())
+}
@@ -0,0 +1,51 @@
+//! Rendezvous relay that pipes opaque ciphertext between two peers.
+//!
+//! A sender uploads to `POST /relay` and a receiver downloads from
+//! `GET /relay`, both presenting the same token as `Authorization: Bearer`.
+//! Whichever side arrives first waits for the other; the upload then streams
+//! straight into the download. Nothing is stored, and the relay never sees
+//! the session key, so it cannot decrypt what it forwards.
+
+pub mod handlers;
+mod pipe;
+mod state;
+
+pub use pipe::PipeOutcome;
+pub use state::{RelayState, DEFAULT_MAX_PENDING, DEFAULT_RENDEZVOUS_TIMEOUT};
+
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+
+/// Parse a `--bind` value, accepting `:port` as shorthand for all interfaces.
+pub fn parse_bind_addr(bind: &str) -> Result<SocketAddr> {
+    let normalized = match bind.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => bind.to_string(),
+    };
+
+    normalized
+        .parse()
+        .with_context(|| format!("Invalid relay bind address: {}", bind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_shorthand_binds_all_interfaces() {
+        let addr = parse_bind_addr(":9000").expect("shorthand parses");
+        assert_eq!(addr, SocketAddr::from(([0, 0, 0, 0], 9000)));
+    }
+
+    #[test]
+    fn explicit_host_is_kept() {
+        let addr = parse_bind_addr("127.0.0.1:9000").expect("host:port parses");
+        assert_eq!(addr, SocketAddr::from(([127, 0, 0, 1], 9000)));
+    }
+
+    #[test]
+    fn invalid_bind_is_rejected() {
+        assert!(parse_bind_addr("not-an-addr").is_err());
+    }
+}
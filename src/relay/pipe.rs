@@ -0,0 +1,87 @@
+//! A sender's upload handed to the receiver as its response body.
+
+use axum::body::{Body, Bytes};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::oneshot;
+
+/// How a piped upload ended, reported back to the waiting sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeOutcome {
+    /// Every byte of the upload was handed to the receiver
+    Delivered(u64),
+    /// The receiver or sender went away after this many bytes
+    Interrupted(u64),
+}
+
+/// A sender's upload plus the channel its handler waits on.
+pub(super) struct Pipe {
+    pub body: Body,
+    pub done: oneshot::Sender<PipeOutcome>,
+}
+
+/// Receiver-side body: forwards the upload and reports how far it got.
+pub(super) struct PipedBody {
+    inner: Body,
+    done: Option<oneshot::Sender<PipeOutcome>>,
+    bytes: u64,
+}
+
+impl PipedBody {
+    pub fn new(pipe: Pipe) -> Self {
+        Self {
+            inner: pipe.body,
+            done: Some(pipe.done),
+            bytes: 0,
+        }
+    }
+
+    fn report(&mut self, delivered: bool) {
+        if let Some(done) = self.done.take() {
+            let outcome = if delivered {
+                PipeOutcome::Delivered(self.bytes)
+            } else {
+                PipeOutcome::Interrupted(self.bytes)
+            };
+            let _ = done.send(outcome);
+        }
+    }
+}
+
+impl HttpBody for PipedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(frame))) => {
+                self.bytes += frame.data_ref().map_or(0, |data| data.len() as u64);
+            }
+            Poll::Ready(Some(Err(_))) => self.report(false),
+            Poll::Ready(None) => self.report(true),
+            Poll::Pending => {}
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for PipedBody {
+    fn drop(&mut self) {
+        // Hyper may stop polling once a sized body has no bytes left
+        let delivered = self.inner.is_end_stream();
+        self.report(delivered);
+    }
+}
@@ -0,0 +1,199 @@
+//! Rendezvous bookkeeping: which side is waiting on which token.
+
+use super::pipe::{Pipe, PipeOutcome};
+use crate::common::AppError;
+use axum::body::Body;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How long one side waits for its peer before giving up.
+pub const DEFAULT_RENDEZVOUS_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Most peers that may be waiting at once, across all tokens.
+pub const DEFAULT_MAX_PENDING: usize = 64;
+
+/// Tokens shorter than this are too easy to guess for a public relay.
+const MIN_TOKEN_LEN: usize = 16;
+const MAX_TOKEN_LEN: usize = 128;
+
+/// Slots are keyed by SHA-256 of the token, so the raw token is never kept.
+type SlotKey = [u8; 32];
+
+enum Waiting {
+    /// The upload is only moved out once, but the map needs it to be `Sync`
+    Sender(Mutex<Pipe>),
+    Receiver(oneshot::Sender<Pipe>),
+}
+
+struct Slot {
+    /// Lets a timed-out waiter remove its own slot and nobody else's
+    id: u64,
+    waiting: Waiting,
+}
+
+/// Shared relay state: at most one waiting peer per token.
+#[derive(Clone)]
+pub struct RelayState {
+    slots: Arc<DashMap<SlotKey, Slot>>,
+    next_id: Arc<AtomicU64>,
+    max_pending: usize,
+    rendezvous_timeout: Duration,
+}
+
+impl Default for RelayState {
+    fn default() -> Self {
+        Self {
+            slots: Arc::new(DashMap::new()),
+            next_id: Arc::new(AtomicU64::new(0)),
+            max_pending: DEFAULT_MAX_PENDING,
+            rendezvous_timeout: DEFAULT_RENDEZVOUS_TIMEOUT,
+        }
+    }
+}
+
+impl RelayState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace how many peers may wait for a partner at once.
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Replace how long a peer waits for its partner.
+    pub fn with_rendezvous_timeout(mut self, timeout: Duration) -> Self {
+        self.rendezvous_timeout = timeout;
+        self
+    }
+
+    /// Peers currently waiting for a partner.
+    pub fn pending(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Hand `body` to the receiver holding `token` and wait until it has
+    /// been read through.
+    pub(super) async fn send(&self, token: &str, body: Body) -> Result<PipeOutcome, AppError> {
+        let key = slot_key(token)?;
+        let (done, mut outcome) = oneshot::channel();
+        let pipe = Pipe { body, done };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.check_capacity(&key)?;
+        let parked = match self.slots.entry(key) {
+            Entry::Occupied(entry) => match entry.get().waiting {
+                Waiting::Sender(_) => {
+                    return Err(AppError::Conflict(
+                        "a sender is already waiting on this token".to_string(),
+                    ))
+                }
+                Waiting::Receiver(_) => {
+                    if let Waiting::Receiver(handoff) = entry.remove().waiting {
+                        // A receiver that just timed out drops the pipe; that
+                        // reads as an interrupted transfer below
+                        let _ = handoff.send(pipe);
+                    }
+                    false
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(Slot {
+                    id,
+                    waiting: Waiting::Sender(Mutex::new(pipe)),
+                });
+                true
+            }
+        };
+
+        if parked {
+            tracing::debug!("Relay sender waiting");
+            match tokio::time::timeout(self.rendezvous_timeout, &mut outcome).await {
+                Ok(result) => return Ok(result.unwrap_or(PipeOutcome::Interrupted(0))),
+                Err(_)
+                    if self
+                        .slots
+                        .remove_if(&key, |_, slot| slot.id == id)
+                        .is_some() =>
+                {
+                    return Err(AppError::NotFound(
+                        "no receiver connected for this token".to_string(),
+                    ));
+                }
+                // A receiver took the upload just as the wait ran out
+                Err(_) => {}
+            }
+        }
+
+        Ok(outcome.await.unwrap_or(PipeOutcome::Interrupted(0)))
+    }
+
+    /// Wait for the sender holding `token` and take its upload.
+    pub(super) async fn receive(&self, token: &str) -> Result<Pipe, AppError> {
+        let key = slot_key(token)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.check_capacity(&key)?;
+        let mut handoff = match self.slots.entry(key) {
+            Entry::Occupied(entry) => match entry.get().waiting {
+                Waiting::Receiver(_) => {
+                    return Err(AppError::Conflict(
+                        "a receiver is already waiting on this token".to_string(),
+                    ))
+                }
+                Waiting::Sender(_) => match entry.remove().waiting {
+                    Waiting::Sender(pipe) => {
+                        return Ok(pipe.into_inner().unwrap_or_else(|e| e.into_inner()))
+                    }
+                    Waiting::Receiver(_) => unreachable!("checked above"),
+                },
+            },
+            Entry::Vacant(entry) => {
+                let (handoff, pipe) = oneshot::channel();
+                entry.insert(Slot {
+                    id,
+                    waiting: Waiting::Receiver(handoff),
+                });
+                pipe
+            }
+        };
+
+        tracing::debug!("Relay receiver waiting");
+        if let Ok(Ok(pipe)) = tokio::time::timeout(self.rendezvous_timeout, &mut handoff).await {
+            return Ok(pipe);
+        }
+        self.slots.remove_if(&key, |_, slot| slot.id == id);
+        // A sender may have arrived between the timeout and the removal
+        handoff
+            .try_recv()
+            .map_err(|_| AppError::NotFound("no sender connected for this token".to_string()))
+    }
+
+    /// Refuse a new waiter once `max_pending` peers are already waiting.
+    ///
+    /// A peer whose partner is waiting is always let through.
+    fn check_capacity(&self, key: &SlotKey) -> Result<(), AppError> {
+        if self.slots.len() >= self.max_pending && !self.slots.contains_key(key) {
+            return Err(AppError::ServiceUnavailable(
+                "relay is at capacity, try again shortly".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn slot_key(token: &str) -> Result<SlotKey, AppError> {
+    if !(MIN_TOKEN_LEN..=MAX_TOKEN_LEN).contains(&token.len()) {
+        return Err(AppError::BadRequest(format!(
+            "relay token must be {}-{} characters",
+            MIN_TOKEN_LEN, MAX_TOKEN_LEN
+        )));
+    }
+    Ok(Sha256::digest(token.as_bytes()).into())
+}
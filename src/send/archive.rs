@@ -1,12 +1,18 @@
 use anyhow::{Context, Result};
 use std::collections::HashSet;
 use std::fs::File;
-use std::io;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
-use walkdir::WalkDir;
 use zip::write::FileOptions;
 
+use super::exclude::ExcludeRules;
+use super::inputs::walk_dir_files;
+
+/// Read buffer for copying a source file into its zip entry. Memory use per
+/// entry is this buffer plus the deflater's window, whatever the file size.
+const ENTRY_COPY_BUFFER_BYTES: usize = 64 * 1024;
+
 pub struct TempArchive {
     path: PathBuf,
 }
@@ -25,7 +31,25 @@ impl Drop for TempArchive {
     }
 }
 
-pub fn create_temp_zip_archive(inputs: &[PathBuf]) -> Result<TempArchive> {
+pub fn create_temp_zip_archive(inputs: &[PathBuf], excludes: &ExcludeRules) -> Result<TempArchive> {
+    let entries = archive_entries(inputs, excludes)?;
+    if entries.is_empty() {
+        anyhow::bail!("No files found for zip archive");
+    }
+
+    let archive_path = std::env::temp_dir().join(format!("archdrop-{}.zip", Uuid::new_v4()));
+    write_zip_archive(&archive_path, &entries)?;
+    Ok(TempArchive { path: archive_path })
+}
+
+/// Source files paired with their unique path inside an archive.
+///
+/// A directory input keeps its own name as the top-level folder; a file
+/// input sits at the archive root.
+pub(super) fn archive_entries(
+    inputs: &[PathBuf],
+    excludes: &ExcludeRules,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
     let mut entries = Vec::<(PathBuf, PathBuf)>::new();
     let mut names = HashSet::<PathBuf>::new();
 
@@ -36,12 +60,7 @@ pub fn create_temp_zip_archive(inputs: &[PathBuf]) -> Result<TempArchive> {
                 .and_then(|x| x.to_str())
                 .unwrap_or("dir")
                 .to_string();
-            for entry in WalkDir::new(input)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.path().is_file())
-            {
-                let file_path = entry.path().to_path_buf();
+            for file_path in walk_dir_files(input, &excludes.for_root(input)?) {
                 let rel = file_path
                     .strip_prefix(input)
                     .unwrap_or(file_path.as_path())
@@ -58,13 +77,7 @@ pub fn create_temp_zip_archive(inputs: &[PathBuf]) -> Result<TempArchive> {
         }
     }
 
-    if entries.is_empty() {
-        anyhow::bail!("No files found for zip archive");
-    }
-
-    let archive_path = std::env::temp_dir().join(format!("archdrop-{}.zip", Uuid::new_v4()));
-    write_zip_archive(&archive_path, &entries)?;
-    Ok(TempArchive { path: archive_path })
+    Ok(entries)
 }
 
 fn unique_archive_path(wanted: &Path, names: &mut HashSet<PathBuf>) -> PathBuf {
@@ -106,10 +119,76 @@ fn write_zip_archive(archive_path: &Path, entries: &[(PathBuf, PathBuf)]) -> Res
         writer
             .start_file(entry_name, options)
             .with_context(|| format!("Failed to start zip entry {}", archive_path.display()))?;
-        io::copy(&mut source, &mut writer)
+        copy_in_chunks(&mut source, &mut writer, ENTRY_COPY_BUFFER_BYTES)
             .with_context(|| format!("Failed to add {} to zip", source_path.display()))?;
     }
 
     writer.finish().context("Failed to finalize zip archive")?;
     Ok(())
 }
+
+/// Stream `reader` into `writer` through one fixed-size buffer.
+///
+/// Never holds more than `buf_size` bytes of the source at once, so a
+/// multi-gigabyte file inside a directory cannot exhaust memory.
+fn copy_in_chunks<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buf_size: usize,
+) -> io::Result<u64> {
+    let mut buffer = vec![0u8; buf_size];
+    let mut copied = 0u64;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(copied),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buffer[..read])?;
+        copied += read as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zip_archive_round_trips_directory_entries() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let input = dir.path().join("photos");
+        std::fs::create_dir(&input).unwrap();
+        std::fs::write(
+            input.join("a.txt"),
+            vec![7u8; 3 * ENTRY_COPY_BUFFER_BYTES + 5],
+        )
+        .unwrap();
+
+        let archive =
+            create_temp_zip_archive(std::slice::from_ref(&input), &ExcludeRules::default())
+                .expect("archive");
+        let mut zip = zip::ZipArchive::new(File::open(archive.path()).unwrap()).unwrap();
+        let mut entry = zip.by_name("photos/a.txt").expect("entry");
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, vec![7u8; 3 * ENTRY_COPY_BUFFER_BYTES + 5]);
+    }
+
+    #[test]
+    fn zip_archive_omits_excluded_paths() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let input = dir.path().join("app");
+        std::fs::create_dir_all(input.join("target").join("debug")).unwrap();
+        std::fs::write(input.join("main.rs"), b"fn main() {}").unwrap();
+        std::fs::write(input.join("trace.log"), b"noise").unwrap();
+        std::fs::write(input.join("target").join("debug").join("app"), b"bin").unwrap();
+
+        let excludes = ExcludeRules::new(vec!["target/".to_string(), "*.log".to_string()]).unwrap();
+        let archive =
+            create_temp_zip_archive(std::slice::from_ref(&input), &excludes).expect("archive");
+        let zip = zip::ZipArchive::new(File::open(archive.path()).unwrap()).unwrap();
+        let names: Vec<_> = zip.file_names().collect();
+        assert_eq!(names, vec!["app/main.rs"]);
+    }
+}
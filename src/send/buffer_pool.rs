@@ -1,7 +1,11 @@
 //! Buffer pooling for chunk responses to reduce allocations.
 
 use bytes::Bytes;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Most memory the pool reserves up front; further buffers are allocated on demand.
+const MAX_RESERVED_BYTES: usize = 64 * 1024 * 1024;
 
 /// Pool of reusable byte buffers for send-chunk responses.
 ///
@@ -9,27 +13,43 @@ use std::sync::{Arc, Mutex};
 pub struct BufferPool {
     buffers: Mutex<Vec<Vec<u8>>>,
     buffer_capacity: usize,
+    /// Buffers allocated because the free list was empty
+    misses: AtomicU64,
 }
 
 impl BufferPool {
-    /// Build a pool with `pool_size` buffers of `buffer_capacity`.
+    /// Build a pool holding up to `pool_size` buffers of `buffer_capacity`.
+    ///
+    /// Only as many as fit in `MAX_RESERVED_BYTES` are allocated here, so a large
+    /// chunk size does not reserve `pool_size * buffer_capacity` at startup.
     pub fn new(pool_size: usize, buffer_capacity: usize) -> Arc<Self> {
-        let buffers = (0..pool_size)
+        let reserved = pool_size.min(MAX_RESERVED_BYTES / buffer_capacity.max(1));
+        let buffers = (0..reserved)
             .map(|_| Vec::with_capacity(buffer_capacity))
             .collect();
         Arc::new(Self {
             buffers: Mutex::new(buffers),
             buffer_capacity,
+            misses: AtomicU64::new(0),
         })
     }
 
     /// Take a reusable buffer, allocating only when pool is empty.
     pub fn take(&self) -> Vec<u8> {
-        self.buffers
-            .lock()
-            .unwrap()
-            .pop()
-            .unwrap_or_else(|| Vec::with_capacity(self.buffer_capacity))
+        self.buffers().pop().unwrap_or_else(|| {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            Vec::with_capacity(self.buffer_capacity)
+        })
+    }
+
+    /// Return how many buffers are waiting in the free list.
+    pub fn idle(&self) -> usize {
+        self.buffers().len()
+    }
+
+    /// Return how many buffers were allocated because the pool ran dry.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
     }
 
     /// Wrap a buffer as `Bytes` that returns it to the pool on drop.
@@ -40,11 +60,19 @@ impl BufferPool {
         })
     }
 
+    /// Lock the free list, recovering from poisoning.
+    ///
+    /// The list holds only empty buffers, so a panic elsewhere cannot leave it
+    /// in an inconsistent state.
+    fn buffers(&self) -> MutexGuard<'_, Vec<Vec<u8>>> {
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     fn return_buf(&self, mut buf: Vec<u8>) {
         buf.clear();
         // Only reclaim buffers with full capacity (drop undersized last-chunk fallbacks)
         if buf.capacity() >= self.buffer_capacity {
-            self.buffers.lock().unwrap().push(buf);
+            self.buffers().push(buf);
         }
     }
 }
@@ -71,7 +99,7 @@ impl Drop for PooledVec {
 
 #[cfg(test)]
 mod tests {
-    use super::BufferPool;
+    use super::{BufferPool, MAX_RESERVED_BYTES};
 
     #[test]
     fn returned_buffer_is_cleared_and_reused() {
@@ -92,6 +120,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn empty_pool_counts_fresh_allocations() {
+        let pool = BufferPool::new(1, 8);
+
+        let first = pool.take();
+        assert_eq!(pool.misses(), 0);
+        let second = pool.take();
+        assert_eq!(pool.misses(), 1);
+
+        drop(pool.wrap(first));
+        drop(pool.wrap(second));
+        assert_eq!(pool.idle(), 2);
+    }
+
+    #[test]
+    fn large_buffers_are_not_all_reserved_up_front() {
+        let capacity = 32 * 1024 * 1024;
+        let pool = BufferPool::new(8, capacity);
+        assert_eq!(pool.idle(), MAX_RESERVED_BYTES / capacity);
+
+        let taken: Vec<_> = (0..8).map(|_| pool.take()).collect();
+        assert_eq!(pool.misses(), 6);
+        for buf in taken {
+            drop(pool.wrap(buf));
+        }
+        assert_eq!(pool.idle(), 8, "lazily allocated buffers are reused too");
+    }
+
+    #[test]
+    fn pool_keeps_working_after_lock_is_poisoned() {
+        let pool = BufferPool::new(1, 8);
+
+        let poisoner = pool.clone();
+        let result = std::thread::spawn(move || {
+            let _guard = poisoner.buffers.lock().unwrap();
+            panic!("forced panic while holding pool lock");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(pool.buffers.is_poisoned());
+
+        let buf = pool.take();
+        assert!(buf.capacity() >= 8);
+        drop(pool.wrap(buf));
+        assert!(pool.take().capacity() >= 8);
+    }
+
     #[test]
     fn undersized_buffer_is_not_reclaimed() {
         let pool = BufferPool::new(1, 8);
@@ -0,0 +1,81 @@
+//! Optional zstd compression of chunk plaintext ahead of encryption.
+
+use anyhow::{Context, Result};
+use axum::http::HeaderMap;
+
+/// Request header a client sets to say it can decompress `zstd` chunks.
+pub const ACCEPT_CHUNK_ENCODING_HEADER: &str = "x-accept-chunk-encoding";
+/// Response header marking a chunk whose plaintext was compressed.
+pub const CHUNK_ENCODING_HEADER: &str = "x-chunk-encoding";
+pub const ZSTD_ENCODING: &str = "zstd";
+
+/// Fast enough to keep up with a LAN link while still shrinking text well
+const ZSTD_LEVEL: i32 = 3;
+
+/// Replace `buffer` with its zstd frame when that is smaller.
+///
+/// Returns whether it did; already-compressed data is left untouched.
+pub fn compress_in_place(buffer: &mut Vec<u8>) -> Result<bool> {
+    let packed = zstd::bulk::compress(buffer, ZSTD_LEVEL).context("zstd compression failed")?;
+    if packed.len() >= buffer.len() {
+        return Ok(false);
+    }
+    buffer.clear();
+    buffer.extend_from_slice(&packed);
+    Ok(true)
+}
+
+/// Decompress a chunk, refusing output larger than `max_len`.
+pub fn decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>> {
+    zstd::bulk::decompress(data, max_len).context("zstd decompression failed")
+}
+
+/// True when the request lists `zstd` in [`ACCEPT_CHUNK_ENCODING_HEADER`].
+pub fn client_accepts_zstd(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_CHUNK_ENCODING_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|encoding| encoding.trim().eq_ignore_ascii_case(ZSTD_ENCODING))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressible_data_round_trips() {
+        let original = b"archdrop log line\n".repeat(4096);
+        let mut buffer = original.clone();
+
+        assert!(compress_in_place(&mut buffer).unwrap());
+        assert!(buffer.len() < original.len() / 10);
+        assert_eq!(decompress(&buffer, original.len()).unwrap(), original);
+    }
+
+    #[test]
+    fn incompressible_data_is_left_alone() {
+        let mut original = vec![0u8; 64 * 1024];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut original);
+        let mut buffer = original.clone();
+
+        assert!(!compress_in_place(&mut buffer).unwrap());
+        assert_eq!(buffer, original);
+    }
+
+    #[test]
+    fn decompress_rejects_output_past_limit() {
+        let mut buffer = vec![0u8; 8192];
+        assert!(compress_in_place(&mut buffer).unwrap());
+        assert!(decompress(&buffer, 1024).is_err());
+    }
+
+    #[test]
+    fn accept_header_lists_are_parsed() {
+        let mut headers = HeaderMap::new();
+        assert!(!client_accepts_zstd(&headers));
+        headers.insert(ACCEPT_CHUNK_ENCODING_HEADER, "gzip, ZSTD".parse().unwrap());
+        assert!(client_accepts_zstd(&headers));
+    }
+}
@@ -0,0 +1,78 @@
+//! Manifest preview for `send --dry-run`.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::common::Manifest;
+use crate::utils::size::format_size;
+
+/// One file as it would be served.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunFile {
+    pub name: String,
+    pub relative_path: String,
+    pub size: u64,
+    pub chunks: u64,
+}
+
+/// Everything a send would transfer, built without starting a server.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub files: Vec<DryRunFile>,
+    pub total_bytes: u64,
+    pub total_chunks: u64,
+    pub chunk_size: u64,
+}
+
+impl DryRunReport {
+    pub fn from_manifest(manifest: &Manifest) -> Self {
+        let chunk_size = manifest.config.chunk_size;
+        let files = manifest
+            .files
+            .iter()
+            .map(|file| DryRunFile {
+                name: file.name.clone(),
+                relative_path: file.relative_path.clone(),
+                size: file.size,
+                chunks: file.size.div_ceil(chunk_size),
+            })
+            .collect();
+        Self {
+            files,
+            total_bytes: manifest.files.iter().map(|f| f.size).sum(),
+            total_chunks: manifest.total_chunks(chunk_size),
+            chunk_size,
+        }
+    }
+
+    /// Write one line per file and a total line.
+    pub fn write_text(&self, out: &mut impl Write) -> Result<()> {
+        for file in &self.files {
+            writeln!(
+                out,
+                "{}  {} ({} chunks)",
+                file.relative_path,
+                format_size(file.size),
+                file.chunks
+            )?;
+        }
+        writeln!(
+            out,
+            "Total: {} files, {} ({} bytes) in {} chunks of {}",
+            self.files.len(),
+            format_size(self.total_bytes),
+            self.total_bytes,
+            self.total_chunks,
+            format_size(self.chunk_size)
+        )?;
+        Ok(())
+    }
+
+    /// Write the report as a single JSON document (for `--json`).
+    pub fn write_json(&self, out: &mut impl Write) -> Result<()> {
+        serde_json::to_writer(&mut *out, self).context("Failed to serialize dry run")?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
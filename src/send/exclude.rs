@@ -0,0 +1,179 @@
+//! `--exclude` globs and `.archdropignore` for directory sends.
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+/// Ignore file read from the root of each directory input.
+pub const IGNORE_FILE_NAME: &str = ".archdropignore";
+
+/// Exclusion patterns applied to files found by walking a directory.
+///
+/// Patterns match paths relative to the directory being walked. A pattern
+/// without a leading `/` matches at any depth, and matching a directory
+/// excludes everything beneath it, so `node_modules` and `*.log` behave as
+/// they do in a `.gitignore`. A later `!pattern` brings back files an
+/// earlier pattern excluded, but not inside an excluded directory, which is
+/// never walked. Files named directly on the command line are never excluded.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    patterns: Vec<String>,
+}
+
+impl ExcludeRules {
+    /// Rules from `--exclude`; invalid globs fail here rather than mid-walk.
+    pub fn new(patterns: Vec<String>) -> Result<Self> {
+        build_matcher(&patterns)?;
+        Ok(Self { patterns })
+    }
+
+    /// Matcher for one directory input, adding its `.archdropignore` if present.
+    pub(crate) fn for_root(&self, root: &Path) -> Result<RootExcludes> {
+        let mut patterns = self.patterns.clone();
+        let ignore_file = root.join(IGNORE_FILE_NAME);
+        if ignore_file.is_file() {
+            let contents = std::fs::read_to_string(&ignore_file)
+                .with_context(|| format!("Failed to read {}", ignore_file.display()))?;
+            patterns.extend(parse_ignore_file(&contents));
+        }
+        let (set, rules) = build_matcher(&patterns)
+            .with_context(|| format!("Invalid exclude pattern for {}", root.display()))?;
+        Ok(RootExcludes {
+            root: root.to_path_buf(),
+            set,
+            rules,
+        })
+    }
+}
+
+/// Exclusions resolved for a single walked directory.
+pub(crate) struct RootExcludes {
+    root: PathBuf,
+    set: GlobSet,
+    /// One per glob in `set`
+    rules: Vec<Rule>,
+}
+
+/// Where a glob came from: its pattern's position and kind.
+#[derive(Debug, Clone, Copy)]
+struct Rule {
+    order: usize,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl RootExcludes {
+    /// True when the last pattern matching `path` excludes rather than re-includes it.
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.set
+            .matches(relative)
+            .into_iter()
+            .map(|index| self.rules[index])
+            .filter(|rule| is_dir || !rule.dir_only)
+            .max_by_key(|rule| rule.order)
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Non-empty, non-comment lines of an ignore file.
+fn parse_ignore_file(contents: &str) -> impl Iterator<Item = String> + '_ {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+fn build_matcher(patterns: &[String]) -> Result<(GlobSet, Vec<Rule>)> {
+    let mut builder = GlobSetBuilder::new();
+    let mut rules = Vec::new();
+    for (order, pattern) in patterns.iter().enumerate() {
+        let (negated, body) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern.as_str()),
+        };
+        for (expanded, dir_only) in expand_pattern(body) {
+            let glob = GlobBuilder::new(&expanded)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+            builder.add(glob);
+            rules.push(Rule {
+                order,
+                negated,
+                dir_only,
+            });
+        }
+    }
+    let set = builder
+        .build()
+        .context("Failed to build exclude patterns")?;
+    Ok((set, rules))
+}
+
+/// Gitignore-style anchoring: `/x` only at the root, `x/` only directories.
+///
+/// Each glob comes with whether it may only match a directory.
+fn expand_pattern(pattern: &str) -> Vec<(String, bool)> {
+    let (anchored, pattern) = match pattern.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let base = if anchored {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+
+    vec![(format!("{}/**", base), false), (base, dir_only)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> RootExcludes {
+        let rules = ExcludeRules::new(patterns.iter().map(|p| p.to_string()).collect()).unwrap();
+        rules.for_root(Path::new("/root")).unwrap()
+    }
+
+    #[test]
+    fn anchored_and_directory_only_patterns() {
+        let excludes = matcher(&["/build", "cache/"]);
+
+        assert!(excludes.is_excluded(Path::new("/root/build/out.o"), false));
+        assert!(!excludes.is_excluded(Path::new("/root/src/build/out.o"), false));
+        assert!(excludes.is_excluded(Path::new("/root/a/cache/x"), false));
+        assert!(!excludes.is_excluded(Path::new("/root/a/cache"), false));
+        assert!(excludes.is_excluded(Path::new("/root/a/cache"), true));
+    }
+
+    #[test]
+    fn later_negation_re_includes() {
+        let excludes = matcher(&["*.log", "!keep.log", "!*.tmp"]);
+
+        assert!(excludes.is_excluded(Path::new("/root/a/debug.log"), false));
+        assert!(!excludes.is_excluded(Path::new("/root/a/keep.log"), false));
+        assert!(!excludes.is_excluded(Path::new("/root/a/x.tmp"), false));
+
+        // Order matters: a negation before the pattern it targets is overridden
+        let excludes = matcher(&["!keep.log", "*.log"]);
+        assert!(excludes.is_excluded(Path::new("/root/keep.log"), false));
+    }
+
+    #[test]
+    fn ignore_file_skips_comments_and_blanks() {
+        let lines: Vec<_> = parse_ignore_file("# deps\n\nnode_modules\n  *.tmp  \n").collect();
+        assert_eq!(lines, vec!["node_modules", "*.tmp"]);
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected_up_front() {
+        assert!(ExcludeRules::new(vec!["a[".to_string()]).is_err());
+    }
+}
@@ -3,29 +3,81 @@
 use anyhow::{Context, Result};
 use positioned_io::{RandomAccessFile, ReadAt};
 use std::fs::File;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::tar_stream::TarStream;
+use crate::utils::log_path;
+
+/// The source no longer matches the manifest: it shrank or was removed.
+///
+/// Retrying cannot help; the sender has to start a new session. The message
+/// goes to the receiver, so it never names the local path.
+#[derive(Debug, thiserror::Error)]
+#[error("source file changed during transfer: {0}")]
+pub struct SourceChanged(pub(super) String);
+
+/// Where a handle's bytes come from.
+enum Source {
+    File {
+        file: RandomAccessFile,
+        path: PathBuf,
+    },
+    /// `send --tar`: an archive assembled from its inputs on each read
+    Tar(Arc<TarStream>),
+    /// Any other positioned reader, e.g. data already in memory
+    Reader(Box<dyn ReadAt + Send + Sync>),
+}
 
 /// Thread-safe random-access handle used by send handlers.
 pub struct SendFileHandle {
-    file: RandomAccessFile,
+    source: Source,
     size: u64,
 }
 
 impl SendFileHandle {
     /// Open a file handle for chunked reads with expected file size.
-    #[tracing::instrument(fields(path = %path.display(), size))]
+    #[tracing::instrument(skip(path), fields(path = %log_path(path)))]
     pub fn open(path: &Path, size: u64) -> Result<Self> {
-        let file = File::open(path).context(format!(
-            "Failed to open file for sending: {}",
-            path.display()
-        ))?;
+        let file = match File::open(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(SourceChanged("file no longer exists".to_string()).into());
+            }
+            result => result.context(format!(
+                "Failed to open file for sending: {}",
+                log_path(path)
+            ))?,
+        };
 
         // Wrap in RandomAccessFile for optimized positioned reads
         // On Unix: advises OS with FADV_RANDOM
         // On Windows: orders of magnitude faster than direct FileExt
         let file = RandomAccessFile::try_new(file).context("Failed to create RandomAccessFile")?;
 
-        Ok(Self { file, size })
+        Ok(Self {
+            source: Source::File {
+                file,
+                path: path.to_path_buf(),
+            },
+            size,
+        })
+    }
+
+    /// Handle serving a streamed tar archive as if it were one file.
+    pub fn tar(stream: Arc<TarStream>) -> Self {
+        Self {
+            size: stream.len(),
+            source: Source::Tar(stream),
+        }
+    }
+
+    /// Handle serving `size` bytes from an arbitrary positioned reader.
+    pub fn from_reader(reader: impl ReadAt + Send + Sync + 'static, size: u64) -> Self {
+        Self {
+            source: Source::Reader(Box::new(reader)),
+            size,
+        }
     }
 
     /// File handle using positioned reads for concurrent chunk serving.
@@ -45,16 +97,54 @@ impl SendFileHandle {
             );
         }
 
-        // SAFETY: read_exact_at either fills all `len` bytes or returns Err,
-        // so the buffer is fully initialized on the success path.
+        // read_exact_at loops over pread (Unix) / seek_read (Windows) until
+        // `len` bytes arrive, so there is no platform without a real read.
+        // SAFETY: both read_exact_at and TarStream::read_at either fill all
+        // `len` bytes or return Err, so the buffer is fully initialized on
+        // the success path; on error it is cleared again below.
         // Caller guarantees capacity >= len (pool buffers are pre-sized).
-        unsafe { buffer.set_len(len) };
+        // Outside readers never see uninitialized bytes; their buffer is zeroed.
+        match &self.source {
+            Source::Reader(_) => buffer.resize(len, 0),
+            _ => unsafe { buffer.set_len(len) },
+        }
 
-        self.file
-            .read_exact_at(offset, &mut buffer[..])
-            .context(format!("Failed to read chunk at offset {}", offset))?;
+        let result = match &self.source {
+            Source::File { file, path } => {
+                file.read_exact_at(offset, &mut buffer[..]).or_else(|e| {
+                    match self.source_changed(path, offset + len as u64) {
+                        Some(changed) => Err(changed.into()),
+                        None => {
+                            Err(e).context(format!("Failed to read chunk at offset {}", offset))
+                        }
+                    }
+                })
+            }
+            Source::Tar(stream) => stream.read_at(offset, &mut buffer[..]),
+            Source::Reader(reader) => reader
+                .read_exact_at(offset, &mut buffer[..])
+                .with_context(|| format!("Failed to read chunk at offset {}", offset)),
+        };
+        if result.is_err() {
+            buffer.clear();
+        }
+        result
+    }
 
-        Ok(())
+    /// Re-stat the source after a failed read, naming how it no longer fits.
+    fn source_changed(&self, path: &Path, end: u64) -> Option<SourceChanged> {
+        match std::fs::metadata(path) {
+            Ok(meta) if meta.len() < end => Some(SourceChanged(format!(
+                "file is now {} bytes, expected {}",
+                meta.len(),
+                self.size
+            ))),
+            Ok(_) => None,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Some(SourceChanged("file no longer exists".to_string()))
+            }
+            Err(_) => None,
+        }
     }
 
     /// Return the expected file size for this handle.
@@ -93,6 +183,23 @@ mod tests {
         assert_eq!(&buffer, b"cde");
     }
 
+    #[test]
+    fn read_chunk_from_middle_of_multi_chunk_file() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("sample.bin");
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).expect("write file");
+
+        let handle = SendFileHandle::open(path.as_path(), data.len() as u64).expect("open handle");
+        let (offset, len) = (123_457, 65_537);
+        let mut buffer = Vec::with_capacity(len);
+        handle
+            .read_chunk(offset as u64, len, &mut buffer)
+            .expect("read chunk should succeed");
+
+        assert_eq!(buffer, data[offset..offset + len]);
+    }
+
     #[test]
     fn read_chunk_rejects_offset_beyond_file_size() {
         let dir = tempfile::tempdir().expect("tempdir");
@@ -4,7 +4,8 @@ use anyhow::{Context, Result};
 use axum::{
     body::Body,
     extract::{Path, State},
-    http::Response,
+    http::{HeaderMap, Response, StatusCode},
+    response::IntoResponse,
     Json,
 };
 use bytes::Bytes;
@@ -12,14 +13,26 @@ use reqwest::header;
 use std::collections::HashSet;
 use std::sync::Arc;
 
-use crate::common::AppError;
-use crate::crypto::{self, Nonce};
+use crate::common::{AppError, FileEntry};
+use crate::crypto::{self, ChunkPosition, CipherSuite, Nonce, NonceLedger};
 use crate::send::buffer_pool::BufferPool;
-use crate::send::file_handle::SendFileHandle;
-use crate::server::auth::{self, BearerToken, LockToken};
+use crate::send::compression;
+use crate::send::file_handle::{SendFileHandle, SourceChanged};
+use crate::send::hasher::{HashOutcome, IncrementalHasher};
+use crate::send::range::{self, RangeRequest};
+use crate::server::auth::{self, BearerToken, LockToken, REVOKE_SECRET_HEADER_NAME};
+use crate::server::status::TransferStatus;
+use crate::utils::run_blocking;
+use crate::utils::size::format_size;
 
 use super::SendAppState;
 
+/// Encrypted chunks a raw stream may hold ready ahead of the response body.
+///
+/// The producer works on the following chunk meanwhile, so one buffered
+/// chunk already overlaps the read of chunk N+1 with sending chunk N.
+const RAW_PIPELINE_DEPTH: usize = 1;
+
 /// Manifest payload plus lock token for authenticated chunk requests.
 #[derive(serde::Serialize)]
 pub struct SendManifestResponse {
@@ -47,19 +60,112 @@ struct CompletionAccounting {
     is_premature: bool,
 }
 
+/// Lock token returned by an explicit claim.
+#[derive(serde::Serialize)]
+pub struct ClaimResponse {
+    #[serde(rename = "lockToken")]
+    lock_token: String,
+}
+
+/// Claim the session without reading the manifest.
+///
+/// Required before `GET /send/manifest` when the sender runs with
+/// `--require-claim`; harmless otherwise.
+#[tracing::instrument(name = "session_claim", skip_all)]
+pub async fn claim_handler(
+    BearerToken(token): BearerToken,
+    State(state): State<SendAppState>,
+    headers: HeaderMap,
+) -> Result<Json<ClaimResponse>, AppError> {
+    let lock_token = claim_for_client(&state, &token, &headers)?;
+    Ok(Json(ClaimResponse { lock_token }))
+}
+
+/// Claim the session for the client named in `X-Client-Id`.
+///
+/// A client that claims again (a page reload) gets the claim it still holds
+/// rather than a second slot, and one that already finished cannot count as
+/// another download.
+fn claim_for_client(
+    state: &SendAppState,
+    token: &str,
+    headers: &HeaderMap,
+) -> Result<String, AppError> {
+    let client = auth::client_id(headers).filter(|_| token == state.session.token());
+    if let Some(client) = client {
+        if let Some(lock_token) = state.live_claim_of(client) {
+            tracing::debug!("Returning client reclaimed its session");
+            return Ok(lock_token);
+        }
+        if state.has_client_finished(client) {
+            return Err(AppError::Conflict(
+                "this client already downloaded the files".to_string(),
+            ));
+        }
+    }
+
+    let lock_token = auth::claim_session(&state.session, token)?;
+    if let Some(client) = client {
+        state.bind_client(&lock_token, client);
+    }
+    start_client_progress(state, &lock_token);
+    Ok(lock_token)
+}
+
 /// Claim the session and return the transfer manifest.
+///
+/// A client that already holds the lock (from `POST /send/claim` or an
+/// earlier fetch) sends it in `X-Transfer-Lock` and is served without a new
+/// claim. With `--require-claim` that is the only way to read the manifest.
+///
+/// Responses carry an `ETag`. A reconnecting client that still holds the lock
+/// and sends a matching `If-None-Match` gets `304` instead of the full body.
 pub async fn manifest_handler(
     BearerToken(token): BearerToken,
     State(state): State<SendAppState>,
-) -> Result<Json<SendManifestResponse>, AppError> {
-    // Session claimed when fetching manifest
-    // Manifests holds info about files (sizes, names) only client should see
-    let lock_token = auth::claim_session(&state.session, &token)?;
+    headers: HeaderMap,
+) -> Result<Response<Body>, AppError> {
+    let etag = state.manifest_etag();
+    let held_lock = headers
+        .get(auth::LOCK_HEADER_NAME)
+        .and_then(|v| v.to_str().ok())
+        .filter(|lock| state.session.is_active(&token, lock))
+        .map(str::to_string);
 
-    // Get manifest from session
-    let manifest = state.manifest();
+    if held_lock.is_some() && if_none_match_hits(&headers, etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let lock_token = match held_lock {
+        Some(lock_token) => lock_token,
+        None if state.requires_claim() => {
+            return Err(AppError::Unauthorized(
+                "claim the session first (POST /send/claim)".to_string(),
+            ));
+        }
+        None => {
+            // Session claimed when fetching manifest
+            // Manifests holds info about files (sizes, names) only client should see
+            let _span = tracing::info_span!("session_claim").entered();
+            claim_for_client(&state, &token, &headers)?
+        }
+    };
+
+    let body = Json(SendManifestResponse {
+        manifest: state.manifest().clone(),
+        lock_token,
+    });
+    Ok(([(header::ETAG, etag)], body).into_response())
+}
 
-    // Initialize file tracking for TUI
+/// Register a freshly claimed client and initialize file tracking for the TUI.
+fn start_client_progress(state: &SendAppState, lock_token: &str) {
+    tracing::debug!("Session claimed");
+    // Claims that went idle gave their slot back; their chunk counts go too
+    state.prune_claim_chunks();
+    state.progress.record_client(lock_token);
+
+    let manifest = state.manifest();
     let names: Vec<String> = manifest.files.iter().map(|f| f.name.clone()).collect();
     let totals: Vec<u64> = manifest
         .files
@@ -67,19 +173,120 @@ pub async fn manifest_handler(
         .map(|f| f.size.div_ceil(state.config.chunk_size))
         .collect();
     state.progress.init_files(names, totals);
+    // Chunks already counted (a resumed session) show as done from the start
+    for (file_index, _) in state.sent_chunk_list() {
+        state.progress.increment_file(file_index);
+    }
 
-    Ok(Json(SendManifestResponse {
-        manifest: manifest.clone(),
-        lock_token,
+    // Spare each file's first chunk the open latency
+    let warm = state.clone();
+    tokio::spawn(async move { warm.warm_file_handles().await });
+}
+
+/// Progress for scripts polling without the TUI. Needs no token; the
+/// display name is included only with the session token.
+pub async fn status_handler(
+    bearer: Option<BearerToken>,
+    State(state): State<SendAppState>,
+) -> Json<TransferStatus> {
+    Json(TransferStatus::collect(
+        "send",
+        &state.progress,
+        &state.session,
+        &state.display_name,
+        bearer.as_ref().map(|BearerToken(token)| token.as_str()),
+    ))
+}
+
+/// Remaining link lifetime for the download page countdown.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkTtlResponse {
+    /// Milliseconds left, or null when the link never expires
+    expires_in_ms: Option<u64>,
+    expired: bool,
+}
+
+/// Report how long the link stays claimable. Does not claim the session.
+pub async fn ttl_handler(
+    BearerToken(token): BearerToken,
+    State(state): State<SendAppState>,
+) -> Result<Json<LinkTtlResponse>, AppError> {
+    if token != state.session.token() {
+        return Err(AppError::Unauthorized("invalid session token".to_string()));
+    }
+
+    let remaining = state.session.remaining_ttl();
+    Ok(Json(LinkTtlResponse {
+        expires_in_ms: remaining.map(|left| left.as_millis() as u64),
+        expired: state.session.is_expired(),
     }))
 }
 
+/// Whole-file digest published once a client has downloaded the file.
+#[derive(serde::Serialize)]
+pub struct FileHashResponse {
+    sha256: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+}
+
+/// Publish a file's SHA-256 (and MAC, with `--file-mac`) to a claimed client.
+///
+/// The browser asks after writing the file's last chunk. Files the manifest
+/// did not hash are read here the first time, not before the transfer.
+pub async fn file_hash_handler(
+    BearerToken(token): BearerToken,
+    LockToken(lock_token): LockToken,
+    Path(file_index): Path<usize>,
+    State(state): State<SendAppState>,
+) -> Result<Json<FileHashResponse>, AppError> {
+    auth::require_active_session(&state.session, &token, &lock_token)?;
+    if state.get_file(file_index).is_none() {
+        return Err(AppError::BadRequest(format!(
+            "file_index out of bounds: {}",
+            file_index
+        )));
+    }
+
+    let sha256 = state.file_hash(file_index).await?;
+    let mac = match state.file_macs() {
+        true => Some(crypto::hash::file_mac(
+            state.session.session_key(),
+            &sha256,
+        )?),
+        false => None,
+    };
+    Ok(Json(FileHashResponse { sha256, mac }))
+}
+
+/// True when `If-None-Match` lists `etag` (or `*`). Weak validators compare equal.
+fn if_none_match_hits(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|candidate| candidate.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
 /// Serve one encrypted chunk for a file index/chunk index pair.
+///
+/// With `--compress`, a client that sends `X-Accept-Chunk-Encoding: zstd`
+/// may get a chunk whose plaintext was compressed before encryption; such
+/// responses carry `X-Chunk-Encoding: zstd`.
+#[tracing::instrument(
+    name = "chunk_send",
+    skip_all,
+    fields(file_index = file_index, chunk_index = chunk_index)
+)]
 pub async fn send_handler(
     BearerToken(token): BearerToken,
     LockToken(lock_token): LockToken,
     Path((file_index, chunk_index)): Path<(usize, usize)>,
     State(state): State<SendAppState>,
+    headers: HeaderMap,
 ) -> Result<Response<Body>, AppError> {
     auth::require_active_session(&state.session, &token, &lock_token)?;
 
@@ -88,52 +295,386 @@ pub async fn send_handler(
         .ok_or_else(|| AppError::BadRequest(format!("file_index out of bounds: {}", file_index)))?;
     let chunk_size = state.config.chunk_size;
 
+    // Reject past-the-end requests before they count towards progress
+    let file_chunks = file_entry.size.div_ceil(chunk_size);
+    if chunk_index as u64 >= file_chunks {
+        return Err(AppError::RangeNotSatisfiable(format!(
+            "chunk_index {} out of range for file_index {} ({} chunks)",
+            chunk_index, file_index, file_chunks
+        )));
+    }
+
+    state.rate_limiter.check(&lock_token)?;
+    let chunk_start = chunk_index as u64 * chunk_size;
+    charge_transfer_cap(&state, chunk_size.min(file_entry.size - chunk_start))?;
+    // Held until the encrypted chunk is built
+    let _permit = state.limiter.acquire(&lock_token).await;
+
     // Some browser send multiple retries (safari)
     // Be noted to not count towards total
-    if state.mark_chunk_sent(file_index, chunk_index) {
-        state.progress.increment_file(file_index);
+    if !state.mark_chunk_sent(&lock_token, file_index, chunk_index) {
+        state.progress.record_retry();
     }
 
-    // Get or create file handle (lazy initialization)
-    let file_handle = state
-        .file_handles
-        .entry(file_index)
-        .or_try_insert_with(|| -> Result<Arc<SendFileHandle>> {
-            Ok(Arc::new(SendFileHandle::open(
-                &file_entry.full_path,
-                file_entry.size,
-            )?))
-        })?
-        .value()
-        .clone();
-
-    let encrypted_bytes = process_chunk(
-        &file_handle,
+    let compress =
+        state.manifest().compression.is_some() && compression::client_accepts_zstd(&headers);
+    let chunk = encrypted_chunk(
+        &state,
+        file_index,
+        file_entry,
         chunk_index,
+        state.streaming_hash().cloned(),
+        compress,
+    )
+    .await
+    .inspect_err(|err| record_chunk_failure(&state, file_index, err))
+    .map_err(chunk_error)?;
+
+    let mut response = Response::builder().header(header::CONTENT_TYPE, "application/octet-stream");
+    if chunk.compressed {
+        response = response.header(
+            compression::CHUNK_ENCODING_HEADER,
+            compression::ZSTD_ENCODING,
+        );
+    }
+    tracing::debug!(bytes = chunk.bytes.len(), "Chunk served");
+    Ok(response
+        .body(Body::from(chunk.bytes))
+        .context("build response")?)
+}
+
+/// Serve a file's whole encrypted stream, honoring a single `Range` header.
+///
+/// The stream is every chunk's ciphertext and tag back to back, so ranges map
+/// onto the covering chunks, which are encrypted and trimmed on the fly.
+pub async fn raw_file_handler(
+    BearerToken(token): BearerToken,
+    LockToken(lock_token): LockToken,
+    Path(file_index): Path<usize>,
+    State(state): State<SendAppState>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, AppError> {
+    auth::require_active_session(&state.session, &token, &lock_token)?;
+    // One token per stream; the chunks inside it are not charged again
+    state.rate_limiter.check(&lock_token)?;
+
+    let file_entry = state
+        .get_file(file_index)
+        .ok_or_else(|| AppError::BadRequest(format!("file_index out of bounds: {}", file_index)))?;
+    let chunk_size = state.config.chunk_size;
+    let file_size = file_entry.size;
+    let total = range::encrypted_len(file_size, chunk_size);
+    let disposition = content_disposition(&file_entry.name);
+
+    let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let (status, byte_range) = match range::parse_range(range_header, total) {
+        RangeRequest::Partial(byte_range) => (StatusCode::PARTIAL_CONTENT, Some(byte_range)),
+        RangeRequest::Full => (StatusCode::OK, None),
+        RangeRequest::Unsatisfiable => {
+            return Ok(Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+                .body(Body::empty())
+                .context("build response")?);
+        }
+    };
+
+    let slices = match byte_range {
+        Some(byte_range) => range::covering_chunks(byte_range, file_size, chunk_size),
+        None if total == 0 => Vec::new(),
+        None => range::covering_chunks(
+            range::ByteRange {
+                start: 0,
+                end: total - 1,
+            },
+            file_size,
+            chunk_size,
+        ),
+    };
+
+    let stream = pipelined_chunks(state, file_index, lock_token, slices);
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_DISPOSITION, disposition)
+        .header(header::ACCEPT_RANGES, "bytes");
+    response = match byte_range {
+        Some(byte_range) => response
+            .header(header::CONTENT_LENGTH, byte_range.byte_count())
+            .header(header::CONTENT_RANGE, byte_range.content_range(total)),
+        None => response.header(header::CONTENT_LENGTH, total),
+    };
+
+    Ok(response
+        .body(Body::from_stream(stream))
+        .context("build response")?)
+}
+
+/// `attachment` disposition naming the download as advertised in the manifest.
+///
+/// `filename` is an ASCII fallback; `filename*` (RFC 5987) carries the exact
+/// UTF-8 name for clients that understand it.
+fn content_disposition(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback, encoded
+    )
+}
+
+/// Produce `slices` on a task of their own, running ahead of the response body.
+///
+/// The producer reads and encrypts the next chunk while the body is still
+/// writing the previous one, so disk, CPU and socket stay busy together. It
+/// stops at the first error or once the body is dropped.
+///
+/// Chunks count as delivered only once the body has taken the whole stream;
+/// a body dropped midway (the client hung up) rolls all of them back.
+fn pipelined_chunks(
+    state: SendAppState,
+    file_index: usize,
+    lock_token: String,
+    slices: Vec<range::ChunkSlice>,
+) -> impl futures::Stream<Item = Result<Bytes>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(RAW_PIPELINE_DEPTH);
+    tokio::spawn(async move {
+        for slice in slices {
+            let result = prepare_slice(&state, file_index, &lock_token, slice).await;
+            let failed = result.is_err();
+            if tx.send(result).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    futures::stream::unfold(
+        (rx, Vec::<PendingChunk>::new()),
+        |(mut rx, mut sent)| async move {
+            let Some(item) = rx.recv().await else {
+                for pending in &mut sent {
+                    pending.delivered();
+                }
+                return None;
+            };
+            let item = item.map(|(pending, bytes)| {
+                sent.push(pending);
+                bytes
+            });
+            Some((item, (rx, sent)))
+        },
+    )
+}
+
+/// Encrypt the chunk under `slice` and trim it to the requested bytes.
+async fn prepare_slice(
+    state: &SendAppState,
+    file_index: usize,
+    lock_token: &str,
+    slice: range::ChunkSlice,
+) -> Result<(PendingChunk, Bytes)> {
+    let file_entry = state
+        .get_file(file_index)
+        .context("file disappeared from manifest")?;
+    charge_transfer_cap(state, slice.take as u64)?;
+    let _permit = state.limiter.acquire(lock_token).await;
+    let pending = PendingChunk::mark(state, lock_token, file_index, slice.chunk_index);
+    // Byte ranges assume fixed-size ciphertext, so raw streams stay uncompressed
+    match encrypted_chunk(
+        state,
+        file_index,
+        file_entry,
+        slice.chunk_index,
+        None,
+        false,
+    )
+    .await
+    {
+        Ok(chunk) => Ok((
+            pending,
+            chunk.bytes.slice(slice.skip..slice.skip + slice.take),
+        )),
+        Err(err) => {
+            tracing::error!(file_index, error = ?err, "Raw stream chunk failed");
+            record_chunk_failure(state, file_index, &err);
+            Err(err)
+        }
+    }
+}
+
+/// A source that changed under the session is the sender's problem, not a
+/// server fault; say so instead of returning an opaque 500.
+fn chunk_error(err: anyhow::Error) -> AppError {
+    match source_changed(&err) {
+        Some(changed) => AppError::NotFound(changed.to_string()),
+        None => AppError::Internal(err),
+    }
+}
+
+fn source_changed(err: &anyhow::Error) -> Option<&SourceChanged> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<SourceChanged>())
+}
+
+/// Mark the file failed only when retrying cannot help.
+///
+/// Anything else (a busy disk, a cancelled read) may succeed when the client
+/// asks for the chunk again, so it stays in progress.
+fn record_chunk_failure(state: &SendAppState, file_index: usize, err: &anyhow::Error) {
+    match source_changed(err) {
+        Some(changed) => state.progress.file_failed(file_index, changed.to_string()),
+        None => {
+            tracing::warn!(file_index, error = %format!("{:#}", err), "Chunk failed; client may retry")
+        }
+    }
+}
+
+/// Refuse to serve `bytes` more once the `--max-transfer` cap is passed.
+///
+/// Passing the cap fails the whole transfer, which shuts the session down.
+fn charge_transfer_cap(state: &SendAppState, bytes: u64) -> Result<(), AppError> {
+    if state.charge_served(bytes) {
+        return Ok(());
+    }
+    let cap = format_size(state.max_transfer().unwrap_or_default());
+    tracing::error!(
+        served = state.bytes_served(),
+        "Transfer cap of {} exceeded - aborting",
+        cap
+    );
+    state
+        .progress
+        .fail(format!("Transfer cap of {} exceeded", cap));
+    Err(AppError::PayloadTooLarge(format!(
+        "transfer cap of {} exceeded",
+        cap
+    )))
+}
+
+/// Progress for a streamed chunk that has been counted but not yet handed off.
+///
+/// If the client disconnects, hyper drops the body stream mid-chunk; the drop
+/// rolls the count back so a later request for the same range counts again.
+struct PendingChunk {
+    state: SendAppState,
+    lock_token: String,
+    file_index: usize,
+    chunk_index: usize,
+    counted: bool,
+}
+
+impl PendingChunk {
+    fn mark(state: &SendAppState, lock_token: &str, file_index: usize, chunk_index: usize) -> Self {
+        let counted = state.mark_chunk_sent(lock_token, file_index, chunk_index);
+        Self {
+            state: state.clone(),
+            lock_token: lock_token.to_string(),
+            file_index,
+            chunk_index,
+            counted,
+        }
+    }
+
+    fn delivered(&mut self) {
+        self.counted = false;
+    }
+}
+
+impl Drop for PendingChunk {
+    fn drop(&mut self) {
+        if self.counted
+            && self
+                .state
+                .unmark_chunk_sent(&self.lock_token, self.file_index, self.chunk_index)
+        {
+            tracing::debug!(
+                file_index = self.file_index,
+                chunk_index = self.chunk_index,
+                "Rolled back progress for undelivered chunk"
+            );
+        }
+    }
+}
+
+/// Ciphertext for one chunk, and whether its plaintext was compressed first.
+struct SealedChunk {
+    bytes: Bytes,
+    /// File bytes the chunk carries, before compression
+    plain_len: usize,
+    compressed: bool,
+}
+
+/// Encrypt one chunk of `file_entry`, opening its file handle on first use.
+async fn encrypted_chunk(
+    state: &SendAppState,
+    file_index: usize,
+    file_entry: &FileEntry,
+    chunk_index: usize,
+    hasher: Option<Arc<IncrementalHasher>>,
+    compress: bool,
+) -> Result<SealedChunk> {
+    let started = std::time::Instant::now();
+
+    // Usually warmed at claim time; opened here otherwise
+    let file_handle = state.file_handle(file_index)?;
+
+    let chunk = process_chunk(
+        &file_handle,
+        ChunkPosition::new(file_index, chunk_index, file_entry.size),
         state.session.cipher(),
-        chunk_size,
-        file_entry.size,
+        state.session.cipher_suite(),
+        state.config.chunk_size,
         &file_entry.nonce,
         &state.buffer_pool,
+        &state.nonce_ledger,
+        hasher,
+        compress,
     )
     .await?;
 
-    Ok(Response::builder()
-        .header(header::CONTENT_TYPE, "application/octet-stream")
-        .body(Body::from(encrypted_bytes))
-        .context("build response")?)
+    state
+        .progress
+        .bandwidth()
+        .record_chunk(chunk.plain_len as u64);
+    state.chunk_latency.record(started.elapsed());
+
+    Ok(chunk)
 }
 
 /// Read, encrypt, and return a single chunk payload.
+///
+/// When `hasher` is set, the plaintext is fed to it before encryption.
+/// With `compress`, the plaintext is zstd-compressed first if that shrinks it.
+#[allow(clippy::too_many_arguments)]
 async fn process_chunk(
     file_handle: &Arc<SendFileHandle>,
-    chunk_index: usize,
+    position: ChunkPosition,
     cipher: &Arc<aws_lc_rs::aead::LessSafeKey>,
+    suite: CipherSuite,
     chunk_size: u64,
-    file_size: u64,
     nonce_str: &str,
     pool: &Arc<BufferPool>,
-) -> Result<Bytes> {
+    ledger: &Arc<NonceLedger>,
+    hasher: Option<Arc<IncrementalHasher>>,
+    compress: bool,
+) -> Result<SealedChunk> {
+    let chunk_index = position.chunk_index as usize;
+    let file_size = position.total_len;
     let start = chunk_index as u64 * chunk_size;
 
     // Validate bounds
@@ -152,11 +693,13 @@ async fn process_chunk(
     let cipher = cipher.clone();
     let nonce_str = nonce_str.to_string();
     let pool = pool.clone();
+    let ledger = ledger.clone();
 
     // Read + encrypt in a single blocking task to avoid double thread-pool scheduling
-    tokio::task::spawn_blocking(move || {
+    run_blocking("chunk read", move || {
         let mut buffer = pool.take();
 
+        // Positioned read: chunks of one file share the handle but no cursor
         let read_start = std::time::Instant::now();
         file_handle.read_chunk(start, chunk_len, &mut buffer)?;
         tracing::debug!(
@@ -166,10 +709,22 @@ async fn process_chunk(
             "chunk_read"
         );
 
+        if let Some(hasher) = &hasher {
+            hasher.update(chunk_index as u64, &buffer);
+        }
+
+        let compressed = compress && compression::compress_in_place(&mut buffer)?;
+        if compressed {
+            tracing::debug!(chunk_index, bytes = buffer.len(), "chunk_compress");
+        }
+
+        // Compressed chunks seal under their own counters, bound in the AAD
+        let position = position.with_compression(compressed);
         let file_nonce = Nonce::from_base64(&nonce_str)?;
+        ledger.record(&file_nonce, position.counter(), &buffer);
 
         let encrypt_start = std::time::Instant::now();
-        crypto::encrypt_chunk_in_place(&cipher, &file_nonce, &mut buffer, chunk_index as u32)
+        crypto::encrypt_chunk_at_position(&cipher, &file_nonce, &mut buffer, &position, suite)
             .context("Encryption failed")?;
         tracing::debug!(
             chunk_index,
@@ -179,12 +734,17 @@ async fn process_chunk(
         );
 
         // Wrap in Bytes that returns the buffer to the pool on drop
-        Ok(pool.wrap(buffer))
+        Ok(SealedChunk {
+            bytes: pool.wrap(buffer),
+            plain_len: chunk_len,
+            compressed,
+        })
     })
-    .await?
+    .await
 }
 
 /// Mark the transfer complete (idempotent for client retries).
+#[tracing::instrument(name = "complete", skip_all)]
 pub async fn complete_download(
     BearerToken(token): BearerToken,
     LockToken(lock_token): LockToken,
@@ -201,13 +761,21 @@ pub async fn complete_download(
         })));
     }
 
+    // Retry from a client whose download was already counted
+    if state.is_client_completed(&lock_token) {
+        return Ok(axum::Json(serde_json::json!({
+           "success": true,
+           "message": "Already completed"
+        })));
+    }
+
     // Session must be active and owned to complete
     auth::require_active_session(&state.session, &token, &lock_token)?;
 
     let payload = payload.map_or_else(SendCompleteRequest::default, |Json(value)| value);
     let (skipped_files, skipped_chunks) = apply_skipped_reports(&state, payload.skipped_files);
 
-    let chunks_sent = state.get_chunks_sent();
+    let chunks_sent = state.chunks_sent_to(&lock_token);
     let total_chunks = state.get_total_chunks();
     let accounting = build_completion_accounting(chunks_sent, total_chunks, skipped_chunks);
 
@@ -230,13 +798,91 @@ pub async fn complete_download(
         );
     }
 
+    // Each lock token is one client; stay up until enough distinct clients finish
+    state.mark_client_completed(&lock_token);
+    let downloads = state.progress.record_download();
+    let target = state.progress.download_target();
+    if downloads < target {
+        tracing::info!(
+            "Download {}/{} complete, waiting for next client",
+            downloads,
+            target
+        );
+        state.limiter.forget_client(&lock_token);
+        state.rate_limiter.forget_client(&lock_token);
+        if state.session.remaining_downloads().is_some() {
+            // Multi-use link: use up this client's slot; others may still be mid-download
+            state.session.complete(&token, &lock_token);
+            state.forget_claim_chunks(&lock_token);
+            if state.session.active_claims() == 0 {
+                state.reset_for_next_download();
+            }
+        } else {
+            state.reset_for_next_download();
+            state.session.release(&token, &lock_token);
+        }
+        return Ok(axum::Json(serde_json::json!({
+            "success": true,
+            "message": "Download successful."
+        })));
+    }
+
     state.session.complete(&token, &lock_token);
+    state.limiter.forget_client(&lock_token);
+    state.rate_limiter.forget_client(&lock_token);
     mark_all_files_complete(&state);
+    state.progress.bandwidth().summary().log();
+    if let Some(latency) = state.chunk_latency.percentiles() {
+        latency.log();
+    }
+    tracing::debug!(
+        misses = state.buffer_pool.misses(),
+        idle = state.buffer_pool.idle(),
+        "Chunk buffer pool"
+    );
 
-    Ok(axum::Json(serde_json::json!({
+    let mut body = serde_json::json!({
         "success": true,
         "message": "Download successful. Initiating server shutdown."
-    })))
+    });
+    if let (Some(hasher), Some(file)) = (state.streaming_hash(), state.get_file(0)) {
+        let outcome = hasher.finish();
+        outcome.log(&file.name);
+        body["sha256"] = outcome.digest().into();
+        if let HashOutcome::Mismatch { actual, expected } = &outcome {
+            state.progress.fail(format!(
+                "SHA-256 mismatch for {}: got {}, expected {}",
+                file.name, actual, expected
+            ));
+        }
+    }
+    state.progress.complete();
+
+    Ok(axum::Json(body))
+}
+
+/// Revoke the link (`POST /send/revoke`) and end the session.
+///
+/// Takes the sender's revoke secret from `X-Revoke-Secret`; the link token
+/// alone is not enough, since every recipient holds it. Claims, chunks and
+/// completions are refused from then on, so a download in progress stops at
+/// its next request; the runtime shuts the server down once the cancellation
+/// is published.
+#[tracing::instrument(name = "revoke", skip_all)]
+pub async fn revoke_handler(
+    State(state): State<SendAppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    let secret = headers
+        .get(REVOKE_SECRET_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !state.session.revoke(secret) {
+        return Err(AppError::Unauthorized("invalid revoke secret".to_string()));
+    }
+    tracing::warn!("Link revoked - shutting down");
+    state.progress.cancel();
+    Ok(StatusCode::NO_CONTENT)
 }
 
 fn mark_all_files_complete(state: &SendAppState) {
@@ -296,7 +942,9 @@ fn apply_skipped_reports(state: &SendAppState, reports: Vec<SkippedFileReport>)
         let file_chunks = file.size.div_ceil(state.config.chunk_size);
         skipped_chunks = skipped_chunks.saturating_add(file_chunks);
         skipped_files += 1;
-        state.progress.file_skipped(report.file_index, reason.to_string());
+        state
+            .progress
+            .file_skipped(report.file_index, reason.to_string());
     }
 
     (skipped_files, skipped_chunks)
@@ -304,11 +952,156 @@ fn apply_skipped_reports(state: &SendAppState, reports: Vec<SkippedFileReport>)
 
 #[cfg(test)]
 mod tests {
-    use super::{build_completion_accounting, normalize_skip_reason};
+    use super::{
+        build_completion_accounting, content_disposition, if_none_match_hits,
+        normalize_skip_reason, process_chunk, record_chunk_failure,
+    };
+    use crate::crypto::{self, ChunkPosition, CipherSuite, EncryptionKey, Nonce, NonceLedger};
+    use crate::send::{BufferPool, SendFileHandle};
+    use aws_lc_rs::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn only_source_changes_mark_a_file_failed() {
+        use crate::common::{FileStatus, Manifest};
+        use crate::send::{SendAppState, SourceChanged};
+        use crate::server::progress::ProgressTracker;
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"0123456789abcdef").expect("write file");
+        let config = crate::common::config::AppConfig::default()
+            .transfer_settings(crate::common::config::Transport::Local);
+        let manifest = Manifest::new(vec![path], None, config)
+            .await
+            .expect("manifest");
+        let progress = Arc::new(ProgressTracker::new());
+        let state = SendAppState::new(EncryptionKey::new(), manifest, 1, progress, config);
+        state.progress.init_files(vec!["data.bin".into()], vec![1]);
+
+        // A read that may succeed on retry leaves the file in progress
+        record_chunk_failure(&state, 0, &anyhow::anyhow!("device busy"));
+        assert!(matches!(
+            state.progress.snapshot().files[0].status,
+            FileStatus::Waiting
+        ));
+
+        let changed = anyhow::Error::new(SourceChanged("file no longer exists".to_string()))
+            .context("read chunk");
+        record_chunk_failure(&state, 0, &changed);
+        assert!(matches!(
+            state.progress.snapshot().files[0].status,
+            FileStatus::Failed(ref msg) if msg.contains("no longer exists")
+        ));
+    }
+
+    #[tokio::test]
+    async fn compressed_and_plain_chunks_seal_under_separate_nonces() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("log.txt");
+        let data = b"GET /send/0/chunk/0 200\n".repeat(64);
+        std::fs::write(&path, &data).expect("write file");
+
+        let size = data.len() as u64;
+        let handle = Arc::new(SendFileHandle::open(&path, size).expect("open handle"));
+        let key = EncryptionKey::new();
+        let cipher = Arc::new(LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, key.as_bytes()).expect("valid key"),
+        ));
+        let nonce = Nonce::new();
+        let nonce_str = nonce.to_base64();
+        let pool = BufferPool::new(2, data.len());
+        let ledger = Arc::new(NonceLedger::default());
+        let position = ChunkPosition::new(0, 0, size);
+        let serve = |compress| {
+            process_chunk(
+                &handle,
+                position,
+                &cipher,
+                CipherSuite::PositionBound,
+                size,
+                &nonce_str,
+                &pool,
+                &ledger,
+                None,
+                compress,
+            )
+        };
+
+        let compressed = serve(true).await.expect("compressed chunk");
+        assert!(compressed.compressed);
+        assert_eq!(compressed.plain_len, data.len());
+        let plain = serve(false).await.expect("plain chunk after compressed");
+        assert!(!plain.compressed);
+
+        // Each opens only under its own counter and compression flag
+        let mut sealed = compressed.bytes.to_vec();
+        assert!(crypto::decrypt_chunk_at_position(
+            &cipher,
+            &nonce,
+            &mut sealed.clone(),
+            &position,
+            CipherSuite::PositionBound
+        )
+        .is_err());
+        crypto::decrypt_chunk_at_position(
+            &cipher,
+            &nonce,
+            &mut sealed,
+            &position.with_compression(true),
+            CipherSuite::PositionBound,
+        )
+        .expect("compressed chunk opens with the compressed counter");
+
+        // The ledger holds the bytes actually sealed: raw data at the
+        // compressed counter is a different plaintext
+        #[cfg(debug_assertions)]
+        {
+            let counter = position.with_compression(true).counter();
+            let reuse = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                ledger.record(&nonce, counter, &data)
+            }));
+            assert!(
+                reuse.is_err(),
+                "ledger should fire on a different plaintext"
+            );
+        }
+    }
+
+    #[test]
+    fn content_disposition_quotes_and_encodes_name() {
+        assert_eq!(
+            content_disposition("report 2024.pdf"),
+            "attachment; filename=\"report 2024.pdf\"; filename*=UTF-8''report%202024.pdf"
+        );
+        assert_eq!(
+            content_disposition("r\u{e9}sum\u{e9} \"v2\".txt"),
+            "attachment; filename=\"r_sum_ _v2_.txt\"; \
+             filename*=UTF-8''r%C3%A9sum%C3%A9%20%22v2%22.txt"
+        );
+    }
+
+    #[test]
+    fn if_none_match_accepts_lists_and_weak_validators() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(
+            reqwest::header::IF_NONE_MATCH,
+            "\"other\", W/\"abc\"".parse().unwrap(),
+        );
+        assert!(if_none_match_hits(&headers, "\"abc\""));
+        assert!(!if_none_match_hits(&headers, "\"zzz\""));
+        assert!(!if_none_match_hits(
+            &axum::http::HeaderMap::new(),
+            "\"abc\""
+        ));
+    }
 
     #[test]
     fn normalize_skip_reason_accepts_known_codes_only() {
-        assert_eq!(normalize_skip_reason("browser_limit"), Some("browser_limit"));
+        assert_eq!(
+            normalize_skip_reason("browser_limit"),
+            Some("browser_limit")
+        );
         assert_eq!(normalize_skip_reason("user_skipped"), Some("user_skipped"));
         assert_eq!(normalize_skip_reason("disk_full"), None);
     }
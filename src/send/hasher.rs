@@ -0,0 +1,234 @@
+//! Incremental SHA-256 of a single sent file, fed as chunks are served.
+//!
+//! Chunks arrive out of order and may be retried, so the hasher keeps a small
+//! reorder buffer and only advances over contiguous chunk indices.
+
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::utils::log_path;
+
+/// Max out-of-order chunks held before hashing is abandoned to cap memory.
+const MAX_PENDING_CHUNKS: usize = 64;
+
+/// Result of finalizing an incremental hash at transfer completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HashOutcome {
+    /// Hash computed; no expected value was supplied.
+    Computed(String),
+    /// Hash computed and matched the expected value.
+    Verified(String),
+    /// Hash computed but differs from the expected value.
+    Mismatch { actual: String, expected: String },
+    /// Not every chunk was served in a hashable way (skips, reorder overflow).
+    Incomplete,
+}
+
+struct HasherState {
+    hasher: Sha256,
+    next_chunk: u64,
+    pending: BTreeMap<u64, Vec<u8>>,
+    abandoned: bool,
+}
+
+/// SHA-256 built from served plaintext chunks instead of a pre-pass.
+pub struct IncrementalHasher {
+    total_chunks: u64,
+    expected: Option<String>,
+    state: Mutex<HasherState>,
+}
+
+impl IncrementalHasher {
+    /// Build a hasher for a file split into `total_chunks` chunks.
+    pub fn new(total_chunks: u64, expected: Option<String>) -> Self {
+        Self {
+            total_chunks,
+            expected: expected.map(|hash| hash.trim().to_ascii_lowercase()),
+            state: Mutex::new(HasherState {
+                hasher: Sha256::new(),
+                next_chunk: 0,
+                pending: BTreeMap::new(),
+                abandoned: false,
+            }),
+        }
+    }
+
+    /// Feed one plaintext chunk. Retries of already-hashed chunks are ignored.
+    pub fn update(&self, chunk_index: u64, data: &[u8]) {
+        let mut state = self.lock();
+        if state.abandoned || chunk_index < state.next_chunk {
+            return;
+        }
+
+        if chunk_index > state.next_chunk {
+            if state.pending.len() >= MAX_PENDING_CHUNKS {
+                tracing::warn!("Too many out-of-order chunks; skipping streaming hash");
+                state.abandoned = true;
+                state.pending.clear();
+                return;
+            }
+            state
+                .pending
+                .entry(chunk_index)
+                .or_insert_with(|| data.to_vec());
+            return;
+        }
+
+        state.hasher.update(data);
+        state.next_chunk += 1;
+
+        // Drain any buffered chunks that are now contiguous
+        loop {
+            let next = state.next_chunk;
+            let Some(buffered) = state.pending.remove(&next) else {
+                break;
+            };
+            state.hasher.update(&buffered);
+            state.next_chunk += 1;
+        }
+    }
+
+    /// Finalize the digest (if every chunk was hashed) and compare to expected.
+    pub fn finish(&self) -> HashOutcome {
+        let state = self.lock();
+        if state.abandoned || state.next_chunk < self.total_chunks {
+            return HashOutcome::Incomplete;
+        }
+
+        let actual = hex::encode(state.hasher.clone().finalize());
+        match &self.expected {
+            None => HashOutcome::Computed(actual),
+            Some(expected) if *expected == actual => HashOutcome::Verified(actual),
+            Some(expected) => HashOutcome::Mismatch {
+                actual,
+                expected: expected.clone(),
+            },
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HasherState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl HashOutcome {
+    /// Report the outcome at transfer completion.
+    pub fn log(&self, file_name: &str) {
+        let file_name = log_path(file_name);
+        match self {
+            HashOutcome::Computed(hash) => {
+                tracing::info!("SHA-256 {}: {}", file_name, hash);
+            }
+            HashOutcome::Verified(hash) => {
+                tracing::info!("SHA-256 {}: {} (matches expected)", file_name, hash);
+            }
+            HashOutcome::Mismatch { actual, expected } => {
+                tracing::error!(
+                    "SHA-256 mismatch for {}: got {}, expected {}",
+                    file_name,
+                    actual,
+                    expected
+                );
+            }
+            HashOutcome::Incomplete => {
+                tracing::warn!(
+                    "SHA-256 for {} unavailable: not every chunk was hashed",
+                    file_name
+                );
+            }
+        }
+    }
+
+    /// Hex digest, when one was computed.
+    pub fn digest(&self) -> Option<&str> {
+        match self {
+            HashOutcome::Computed(hash) | HashOutcome::Verified(hash) => Some(hash),
+            HashOutcome::Mismatch { actual, .. } => Some(actual),
+            HashOutcome::Incomplete => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn full_hash(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    #[test]
+    fn out_of_order_chunks_match_full_file_hash() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let chunk_size = 1024;
+        let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+        let hasher = IncrementalHasher::new(chunks.len() as u64, None);
+
+        // Serve in scrambled order with a retry thrown in
+        let order = [2, 0, 1, 1, 5, 3, 4, 9, 7, 8, 6];
+        for index in order {
+            hasher.update(index as u64, chunks[index]);
+        }
+
+        assert_eq!(hasher.finish(), HashOutcome::Computed(full_hash(&data)));
+    }
+
+    #[test]
+    fn expected_hash_is_verified_case_insensitively() {
+        let data = b"verify me";
+        let expected = full_hash(data).to_ascii_uppercase();
+        let hasher = IncrementalHasher::new(1, Some(expected));
+        hasher.update(0, data);
+
+        assert_eq!(hasher.finish(), HashOutcome::Verified(full_hash(data)));
+    }
+
+    #[test]
+    fn mismatch_and_missing_chunks_are_reported() {
+        let hasher = IncrementalHasher::new(2, Some("00".repeat(32)));
+        hasher.update(0, b"first");
+        assert_eq!(hasher.finish(), HashOutcome::Incomplete);
+
+        hasher.update(1, b"second");
+        assert!(matches!(hasher.finish(), HashOutcome::Mismatch { .. }));
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn redacted_logs_show_path_hash_not_name() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        crate::utils::security::set_redact_paths(true);
+        tracing::subscriber::with_default(subscriber, || {
+            HashOutcome::Computed(full_hash(b"x")).log("payroll-2024.xlsx");
+        });
+        crate::utils::security::set_redact_paths(false);
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            logs.contains(&crate::utils::hash_path("payroll-2024.xlsx")),
+            "{logs}"
+        );
+        assert!(!logs.contains("payroll"), "{logs}");
+    }
+}
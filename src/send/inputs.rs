@@ -0,0 +1,346 @@
+//! Send input expansion (glob patterns and directories into concrete paths).
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use super::exclude::{ExcludeRules, RootExcludes};
+use crate::utils::log_path;
+
+/// Fixed names of TLS material older releases wrote next to served files.
+const TOOL_CERT_FILES: [&str; 2] = ["archdrop-cert.pem", "archdrop-key.pem"];
+
+/// True for files ArchDrop itself writes (TLS material, temp zip archives).
+///
+/// Certificates now live only in memory, but a stale key from an older run
+/// must never be swept into a transfer by a directory walk.
+pub(crate) fn is_tool_artifact(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    if TOOL_CERT_FILES.contains(&name) {
+        return true;
+    }
+    name.strip_prefix("archdrop-")
+        .and_then(|rest| rest.strip_suffix(".zip"))
+        .is_some_and(|id| Uuid::parse_str(id).is_ok())
+}
+
+/// Files under `dir` (recursively), skipping ArchDrop's own artifacts and
+/// whatever `excludes` matches. Excluded directories are not descended into.
+pub(crate) fn walk_dir_files(dir: &Path, excludes: &RootExcludes) -> Vec<PathBuf> {
+    let mut excluded = 0usize;
+    let files = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            let skip = e.depth() > 0 && excludes.is_excluded(e.path(), e.file_type().is_dir());
+            if skip {
+                tracing::debug!("Excluded {}", log_path(e.path()));
+                excluded += 1;
+            }
+            !skip
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter(|e| {
+            let artifact = is_tool_artifact(e.path());
+            if artifact {
+                tracing::warn!("Skipping ArchDrop artifact: {}", log_path(e.path()));
+            }
+            !artifact
+        })
+        .map(|e| e.into_path())
+        .collect();
+    if excluded > 0 {
+        tracing::info!(
+            "Excluded {} path(s) under {} (--exclude/.archdropignore)",
+            excluded,
+            log_path(dir)
+        );
+    }
+    files
+}
+
+/// Fail before anything is served if any input is missing, naming all of them.
+pub fn validate_send_inputs(inputs: &[PathBuf]) -> Result<()> {
+    let missing = inputs
+        .iter()
+        .filter(|input| !input.exists())
+        .map(|input| input.display().to_string())
+        .collect::<Vec<_>>();
+    match missing.as_slice() {
+        [] => Ok(()),
+        [one] => anyhow::bail!("File not found: {}", one),
+        many => anyhow::bail!("Files not found: {}", many.join(", ")),
+    }
+}
+
+/// Resolve send inputs into the concrete files to serve.
+///
+/// Directories are walked recursively, dropping files matched by `excludes`;
+/// explicitly named files are kept as-is. A file reached twice (named
+/// directly and inside a listed directory, or listed twice) is served once,
+/// at its first position.
+pub fn collect_send_files(inputs: Vec<PathBuf>, excludes: &ExcludeRules) -> Result<Vec<PathBuf>> {
+    validate_send_inputs(&inputs)?;
+
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for input in inputs {
+        let found: Vec<PathBuf> = if input.is_dir() {
+            walk_dir_files(&input, &excludes.for_root(&input)?)
+        } else {
+            vec![input]
+        };
+        for file in found {
+            let key = std::fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+            if seen.insert(key) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Return true when the argument contains glob metacharacters.
+fn is_glob_pattern(input: &Path) -> bool {
+    input.to_str().is_some_and(|s| s.contains(['*', '?', '[']))
+}
+
+/// Expand one glob pattern into matching paths, sorted for stable manifests.
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut matches = glob::glob(pattern)
+        .with_context(|| format!("Invalid glob pattern: {}", pattern))?
+        .filter_map(|entry| entry.ok())
+        .collect::<Vec<_>>();
+    matches.sort();
+
+    anyhow::ensure!(!matches.is_empty(), "No files matched pattern: {}", pattern);
+    Ok(matches)
+}
+
+/// Expand glob arguments in send inputs, leaving literal paths untouched.
+///
+/// Shells on Windows do not expand globs, so the CLI does it itself. A path
+/// that exists on disk is always treated literally, even if it contains
+/// metacharacters.
+pub fn expand_send_inputs(inputs: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::with_capacity(inputs.len());
+
+    for input in inputs {
+        if input.exists() || !is_glob_pattern(&input) {
+            expanded.push(input);
+            continue;
+        }
+
+        let pattern = input.to_string_lossy();
+        let matches = expand_glob(&pattern)?;
+        eprintln!("Pattern '{}' matched {} file(s)", pattern, matches.len());
+        expanded.extend(matches);
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_pattern_matches_expected_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for name in ["a.log", "b.log", "c.txt"] {
+            std::fs::write(dir.path().join(name), b"data").expect("write file");
+        }
+
+        let pattern = dir.path().join("*.log");
+        let expanded = expand_send_inputs(vec![pattern]).expect("glob should expand");
+
+        assert_eq!(
+            expanded,
+            vec![dir.path().join("a.log"), dir.path().join("b.log")]
+        );
+    }
+
+    #[test]
+    fn glob_pattern_without_matches_fails() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let pattern = dir.path().join("*.missing");
+
+        let err = expand_send_inputs(vec![pattern]).expect_err("empty match should fail");
+        assert!(err.to_string().contains("No files matched"));
+    }
+
+    #[test]
+    fn recognizes_tool_artifacts_only() {
+        assert!(is_tool_artifact(Path::new("/tmp/archdrop-cert.pem")));
+        assert!(is_tool_artifact(Path::new("/tmp/archdrop-key.pem")));
+        let zip = format!("archdrop-{}.zip", Uuid::new_v4());
+        assert!(is_tool_artifact(Path::new(&zip)));
+
+        assert!(!is_tool_artifact(Path::new("archdrop-notes.zip")));
+        assert!(!is_tool_artifact(Path::new("key.pem")));
+    }
+
+    #[test]
+    fn mixed_files_and_dirs_build_one_file_list() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        let photos = dir.path().join("photos");
+        std::fs::create_dir_all(photos.join("2024")).expect("create dirs");
+        for path in [
+            &a,
+            &b,
+            &photos.join("x.jpg"),
+            &photos.join("2024").join("y.jpg"),
+        ] {
+            std::fs::write(path, b"data").expect("write file");
+        }
+
+        // photos/x.jpg is named directly and again through its directory
+        let files = collect_send_files(
+            vec![a.clone(), photos.clone(), b.clone(), photos.join("x.jpg")],
+            &ExcludeRules::default(),
+        )
+        .expect("collect inputs");
+
+        assert_eq!(
+            files,
+            vec![
+                a,
+                photos.join("2024").join("y.jpg"),
+                photos.join("x.jpg"),
+                b,
+            ]
+        );
+    }
+
+    #[test]
+    fn exclude_drops_nested_directory_and_extension_matches() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = dir.path().join("project");
+        let nested_deps = project.join("web").join("node_modules").join("pkg");
+        std::fs::create_dir_all(&nested_deps).expect("create dirs");
+        std::fs::create_dir_all(project.join("src")).expect("create dirs");
+        for path in [
+            project.join("src").join("main.rs"),
+            project.join("src").join("debug.log"),
+            project.join("build.log"),
+            project.join("web").join("index.html"),
+            nested_deps.join("index.js"),
+        ] {
+            std::fs::write(path, b"data").expect("write file");
+        }
+
+        let excludes =
+            ExcludeRules::new(vec!["node_modules".to_string(), "*.log".to_string()]).unwrap();
+        let files = collect_send_files(vec![project.clone()], &excludes).expect("collect inputs");
+
+        assert_eq!(
+            files,
+            vec![
+                project.join("src").join("main.rs"),
+                project.join("web").join("index.html"),
+            ]
+        );
+    }
+
+    #[test]
+    fn excluded_directories_are_pruned_and_negations_apply_to_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("node_modules")).expect("create dirs");
+        for path in [
+            project.join("node_modules").join("keep.js"),
+            project.join("debug.log"),
+            project.join("keep.log"),
+        ] {
+            std::fs::write(path, b"data").expect("write file");
+        }
+
+        // keep.js is never reached: its directory is pruned from the walk
+        let patterns = ["node_modules", "*.log", "!keep.log", "!keep.js"];
+        let excludes = ExcludeRules::new(patterns.map(String::from).to_vec()).unwrap();
+        let files = collect_send_files(vec![project.clone()], &excludes).expect("collect inputs");
+
+        assert_eq!(files, vec![project.join("keep.log")]);
+    }
+
+    #[test]
+    fn archdropignore_applies_and_named_files_are_kept() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join(".git")).expect("create dirs");
+        std::fs::write(project.join(".archdropignore"), "# vcs\n.git\n").unwrap();
+        std::fs::write(project.join(".git").join("HEAD"), b"ref").unwrap();
+        std::fs::write(project.join("notes.tmp"), b"data").unwrap();
+
+        let excludes = ExcludeRules::new(vec!["*.tmp".to_string()]).unwrap();
+        let files = collect_send_files(vec![project.clone(), project.join("notes.tmp")], &excludes)
+            .expect("collect inputs");
+
+        assert_eq!(
+            files,
+            vec![project.join(".archdropignore"), project.join("notes.tmp")]
+        );
+    }
+
+    #[test]
+    fn missing_inputs_are_all_reported() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let present = dir.path().join("present.txt");
+        std::fs::write(&present, b"data").expect("write file");
+
+        let err = collect_send_files(
+            vec![
+                present,
+                dir.path().join("gone.txt"),
+                dir.path().join("also-gone"),
+            ],
+            &ExcludeRules::default(),
+        )
+        .expect_err("missing inputs should fail");
+        let message = err.to_string();
+        assert!(message.starts_with("Files not found"), "{message}");
+        assert!(message.contains("gone.txt") && message.contains("also-gone"));
+    }
+
+    #[test]
+    fn existing_path_with_metacharacters_is_taken_literally() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let bracketed = dir.path().join("report[1].txt");
+        for path in [&bracketed, &dir.path().join("report1.txt")] {
+            std::fs::write(path, b"data").expect("write file");
+        }
+
+        // As a glob, "report[1].txt" would match only report1.txt
+        let expanded = expand_send_inputs(vec![bracketed.clone()]).expect("literal passes");
+        assert_eq!(expanded, vec![bracketed]);
+    }
+
+    #[test]
+    fn glob_matches_feed_the_file_list_and_missing_literals_still_fail() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir(dir.path().join("logs")).expect("create dir");
+        for name in ["a.log", "logs/b.log", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"data").expect("write file");
+        }
+
+        let pattern = dir.path().join("[al]*");
+        let inputs = expand_send_inputs(vec![pattern]).expect("glob should expand");
+        let files = collect_send_files(inputs, &ExcludeRules::default()).expect("collect");
+        assert_eq!(
+            files,
+            vec![dir.path().join("a.log"), dir.path().join("logs/b.log")]
+        );
+
+        let missing = dir.path().join("gone.txt");
+        let inputs = expand_send_inputs(vec![dir.path().join("*.txt"), missing]).unwrap();
+        let err = collect_send_files(inputs, &ExcludeRules::default()).unwrap_err();
+        assert!(err.to_string().contains("gone.txt"), "{err}");
+    }
+}
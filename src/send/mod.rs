@@ -1,10 +1,26 @@
 mod archive;
 mod buffer_pool;
+pub mod compression;
+mod dry_run;
+mod exclude;
 mod file_handle;
 pub mod handlers;
+mod hasher;
+mod inputs;
+pub mod persist;
+pub mod range;
 mod state;
+mod stdin;
+mod tar_stream;
 
 pub use archive::{create_temp_zip_archive, TempArchive};
 pub use buffer_pool::BufferPool;
-pub use file_handle::SendFileHandle;
-pub use state::SendAppState;
+pub use dry_run::{DryRunFile, DryRunReport};
+pub use exclude::{ExcludeRules, IGNORE_FILE_NAME};
+pub use file_handle::{SendFileHandle, SourceChanged};
+pub use hasher::{HashOutcome, IncrementalHasher};
+pub use inputs::{collect_send_files, expand_send_inputs, validate_send_inputs};
+pub use persist::PersistedSend;
+pub use state::{SendAppState, SendAppStateBuilder};
+pub use stdin::{buffer_reader, buffer_stdin, is_stdin_input, StdinPayload, DEFAULT_STDIN_NAME};
+pub use tar_stream::TarStream;
@@ -0,0 +1,286 @@
+//! Send sessions saved to disk (`--resumable`) so a restarted sender can
+//! pick the transfer back up with `--resume <TOKEN>`.
+//!
+//! The record holds everything the old link depends on: token, session key,
+//! link nonce and manifest. It also keeps a bitmap of chunks already
+//! counted. The key is secret, so the record lives in the owner-only data
+//! directory and is deleted once the transfer completes.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::SendAppState;
+use crate::common::{DataDir, Manifest, PersistedSessionStatus, Session, SessionSnapshot};
+use crate::crypto::password::KeySalt;
+use crate::crypto::types::{EncryptionKey, Nonce};
+use crate::crypto::CipherSuite;
+use crate::utils::run_blocking;
+
+/// Data directory subfolder holding one `<token>.json` per session.
+pub const SESSIONS_SUBDIR: &str = "sessions";
+
+/// On-disk form of a resumable send session.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PersistedSend {
+    session: SessionSnapshot,
+    session_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_salt: Option<String>,
+    link_nonce: String,
+    /// Records saved before suites existed were all v1
+    #[serde(default = "legacy_suite")]
+    cipher_suite: CipherSuite,
+    manifest: Manifest,
+    /// Source path per manifest file (not part of the served manifest)
+    paths: Vec<PathBuf>,
+    /// Per-file bitmaps of counted chunks, URL-safe base64
+    sent_chunks: Vec<String>,
+}
+
+// Never print the session key
+impl fmt::Debug for PersistedSend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistedSend")
+            .field("token", &self.session.token)
+            .field("files", &self.paths.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PersistedSend {
+    /// Snapshot the current state of a send session.
+    pub fn capture(state: &SendAppState, link_nonce: &Nonce) -> Self {
+        let manifest = state.manifest().clone();
+        let mut bitmaps: Vec<Vec<u8>> = manifest
+            .files
+            .iter()
+            .map(|file| {
+                let chunks = file.size.div_ceil(manifest.config.chunk_size);
+                vec![0u8; chunks.div_ceil(8) as usize]
+            })
+            .collect();
+        for (file_index, chunk_index) in state.sent_chunk_list() {
+            if let Some(byte) = bitmaps
+                .get_mut(file_index)
+                .and_then(|bitmap| bitmap.get_mut(chunk_index / 8))
+            {
+                *byte |= 1 << (chunk_index % 8);
+            }
+        }
+
+        Self {
+            session: state.session.snapshot(),
+            session_key: state.session.session_key_b64(),
+            key_salt: state.session.key_salt().map(KeySalt::to_base64),
+            link_nonce: link_nonce.to_base64(),
+            cipher_suite: state.session.cipher_suite(),
+            paths: manifest.files.iter().map(|f| f.full_path.clone()).collect(),
+            manifest,
+            sent_chunks: bitmaps
+                .iter()
+                .map(|bitmap| general_purpose::URL_SAFE_NO_PAD.encode(bitmap))
+                .collect(),
+        }
+    }
+
+    pub fn token(&self) -> &str {
+        &self.session.token
+    }
+
+    /// Write the record, replacing any earlier save for the same token.
+    pub fn save(&self, data_dir: &DataDir) -> Result<PathBuf> {
+        data_dir.write_state(SESSIONS_SUBDIR, &state_file(self.token()), self)
+    }
+
+    /// Load the record saved for `token`.
+    pub fn load(data_dir: &DataDir, token: &str) -> Result<Self> {
+        anyhow::ensure!(
+            uuid::Uuid::parse_str(token).is_ok(),
+            "Not a session token: {}",
+            token
+        );
+        let persisted: Self = data_dir
+            .read_state(SESSIONS_SUBDIR, &state_file(token))?
+            .with_context(|| {
+                format!(
+                    "No resumable session {} in {}",
+                    token,
+                    data_dir.path().display()
+                )
+            })?;
+        anyhow::ensure!(
+            persisted.paths.len() == persisted.manifest.files.len()
+                && persisted.sent_chunks.len() == persisted.manifest.files.len(),
+            "Saved session {} is inconsistent",
+            token
+        );
+        anyhow::ensure!(
+            persisted.session.status != PersistedSessionStatus::Completed,
+            "Session {} already completed; nothing to resume",
+            token
+        );
+        Ok(persisted)
+    }
+
+    /// Delete the saved record for `token`, if any.
+    pub fn discard(data_dir: &DataDir, token: &str) {
+        let path = data_dir
+            .path()
+            .join(SESSIONS_SUBDIR)
+            .join(state_file(token));
+        match std::fs::remove_file(&path) {
+            Ok(()) => tracing::debug!("Removed saved session {}", token),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => tracing::warn!("Failed to remove {}: {}", path.display(), err),
+        }
+    }
+
+    /// The saved manifest, pointing at the source files again.
+    ///
+    /// Every file must still have the size and SHA-256 it had when the link
+    /// was made: the old file nonces are reused, which is only safe for
+    /// identical plaintext.
+    pub async fn manifest(&self) -> Result<Manifest> {
+        let mut manifest = self.manifest.clone();
+        for (file, path) in manifest.files.iter_mut().zip(&self.paths) {
+            file.full_path = path.clone();
+            let size = tokio::fs::metadata(path)
+                .await
+                .with_context(|| format!("Cannot resume: {} is gone", path.display()))?
+                .len();
+            let hash_path = path.clone();
+            let hash = run_blocking("file hash", move || {
+                crate::crypto::calculate_file_hash(&hash_path)
+            })
+            .await?;
+            anyhow::ensure!(
+                size == file.size && file.hash.as_deref() == Some(hash.as_str()),
+                "Cannot resume: {} changed since the session was saved",
+                path.display()
+            );
+        }
+        Ok(manifest)
+    }
+
+    /// Rebuild the session with its original token and key.
+    pub fn session(&self) -> Result<Session> {
+        let key = EncryptionKey::from_base64(&self.session_key)
+            .context("Saved session key is invalid")?;
+        let session =
+            Session::restore(key, self.session.clone()).with_cipher_suite(self.cipher_suite);
+        Ok(match &self.key_salt {
+            Some(salt) => session.with_key_salt(KeySalt::from_base64(salt)?),
+            None => session,
+        })
+    }
+
+    /// Nonce carried in the original link.
+    pub fn link_nonce(&self) -> Result<Nonce> {
+        Nonce::from_base64(&self.link_nonce).context("Saved link nonce is invalid")
+    }
+
+    /// File/chunk pairs that had been counted when the record was saved.
+    pub fn sent_chunks(&self) -> Result<Vec<(usize, usize)>> {
+        let mut chunks = Vec::new();
+        for (file_index, encoded) in self.sent_chunks.iter().enumerate() {
+            let bitmap = general_purpose::URL_SAFE_NO_PAD
+                .decode(encoded)
+                .context("Saved chunk bitmap is invalid")?;
+            for (byte_index, byte) in bitmap.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (1 << bit) != 0 {
+                        chunks.push((file_index, byte_index * 8 + bit));
+                    }
+                }
+            }
+        }
+        Ok(chunks)
+    }
+}
+
+fn legacy_suite() -> CipherSuite {
+    CipherSuite::Legacy
+}
+
+fn state_file(token: &str) -> String {
+    format!("{}.json", token)
+}
+
+/// How often a running session re-saves its record when progress moved
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Keeps a session's saved record current while it runs.
+pub struct Autosave {
+    state: SendAppState,
+    link_nonce: Nonce,
+    data_dir: DataDir,
+    task: JoinHandle<()>,
+}
+
+impl Autosave {
+    /// Save once now (failing if that is impossible), then periodically.
+    pub fn start(state: SendAppState, link_nonce: Nonce, data_dir: DataDir) -> Result<Self> {
+        let path = PersistedSend::capture(&state, &link_nonce)
+            .save(&data_dir)
+            .context("Failed to save resumable session")?;
+        let token = state.session.token().to_string();
+        tracing::info!("Resumable session saved to {}", path.display());
+        eprintln!(
+            "If ArchDrop stops before the transfer finishes, run: archdrop send --resume {}",
+            token
+        );
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            let link_nonce = link_nonce.clone();
+            let data_dir = data_dir.clone();
+            async move {
+                let mut saved_chunks = state.get_chunks_sent();
+                let mut ticker = tokio::time::interval(AUTOSAVE_INTERVAL);
+                loop {
+                    ticker.tick().await;
+                    let chunks = state.get_chunks_sent();
+                    if chunks == saved_chunks {
+                        continue;
+                    }
+                    let record = PersistedSend::capture(&state, &link_nonce);
+                    let data_dir = data_dir.clone();
+                    match run_blocking("session autosave", move || record.save(&data_dir)).await {
+                        Ok(_) => saved_chunks = chunks,
+                        Err(err) => tracing::warn!("Failed to save session: {:#}", err),
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            state,
+            link_nonce,
+            data_dir,
+            task,
+        })
+    }
+
+    /// Stop saving. A completed or revoked session's record is deleted;
+    /// anything else is saved one last time so it can be resumed.
+    pub fn finish(self) {
+        self.task.abort();
+        let token = self.state.session.token();
+        if self.state.session.is_completed() || self.state.session.is_revoked() {
+            PersistedSend::discard(&self.data_dir, token);
+            return;
+        }
+        match PersistedSend::capture(&self.state, &self.link_nonce).save(&self.data_dir) {
+            Ok(_) => eprintln!(
+                "Transfer not finished; resume it with: archdrop send --resume {}",
+                token
+            ),
+            Err(err) => tracing::warn!("Failed to save session: {:#}", err),
+        }
+    }
+}
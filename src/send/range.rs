@@ -0,0 +1,185 @@
+//! Byte-range mapping onto the per-chunk encrypted stream.
+//!
+//! A file's encrypted stream is its chunks back to back, each ciphertext
+//! followed by its AEAD tag. A byte range over that stream is served by
+//! encrypting the covering chunks and trimming the first and last.
+
+use crate::server::bandwidth::AEAD_TAG_BYTES;
+
+/// Inclusive byte range within a file's encrypted stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Outcome of parsing a `Range` header against a stream length.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable range (absent, multi-range, or non-byte unit): serve it all.
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// Number of bytes covered (the range is inclusive and never empty).
+    pub fn byte_count(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// `Content-Range` value for this range.
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+/// Length of the encrypted stream for a file of `file_size` bytes.
+pub fn encrypted_len(file_size: u64, chunk_size: u64) -> u64 {
+    file_size + file_size.div_ceil(chunk_size) * AEAD_TAG_BYTES
+}
+
+/// Parse a single `bytes=` range. Multiple ranges are ignored (served in full).
+pub fn parse_range(header: Option<&str>, total: u64) -> RangeRequest {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return RangeRequest::Unsatisfiable;
+    };
+    let (first, last) = (first.trim(), last.trim());
+
+    let range = if first.is_empty() {
+        // Suffix range: the final `last` bytes
+        match last.parse::<u64>() {
+            Ok(0) | Err(_) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => ByteRange {
+                start: total.saturating_sub(suffix),
+                end: total.saturating_sub(1),
+            },
+        }
+    } else {
+        let Ok(start) = first.parse::<u64>() else {
+            return RangeRequest::Unsatisfiable;
+        };
+        let end = if last.is_empty() {
+            total.saturating_sub(1)
+        } else {
+            match last.parse::<u64>() {
+                Ok(end) if end >= start => end.min(total.saturating_sub(1)),
+                _ => return RangeRequest::Unsatisfiable,
+            }
+        };
+        ByteRange { start, end }
+    };
+
+    if total == 0 || range.start >= total {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Partial(range)
+}
+
+/// One covering chunk and the slice of its encrypted bytes to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSlice {
+    pub chunk_index: usize,
+    pub skip: usize,
+    pub take: usize,
+}
+
+/// Map a range of the encrypted stream onto the chunks that cover it.
+pub fn covering_chunks(range: ByteRange, file_size: u64, chunk_size: u64) -> Vec<ChunkSlice> {
+    let stride = chunk_size + AEAD_TAG_BYTES;
+    let chunks = file_size.div_ceil(chunk_size);
+    (range.start / stride..=range.end / stride)
+        .filter(|index| *index < chunks)
+        .map(|index| {
+            let chunk_start = index * stride;
+            let plain = chunk_size.min(file_size - index * chunk_size);
+            let chunk_end = chunk_start + plain + AEAD_TAG_BYTES - 1;
+            let from = range.start.max(chunk_start);
+            let to = range.end.min(chunk_end);
+            ChunkSlice {
+                chunk_index: index as usize,
+                skip: (from - chunk_start) as usize,
+                take: (to - from + 1) as usize,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bounded_open_and_suffix_ranges() {
+        assert_eq!(
+            parse_range(Some("bytes=10-19"), 100),
+            RangeRequest::Partial(ByteRange { start: 10, end: 19 })
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-"), 100),
+            RangeRequest::Partial(ByteRange { start: 90, end: 99 })
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-5"), 100),
+            RangeRequest::Partial(ByteRange { start: 95, end: 99 })
+        );
+        assert_eq!(
+            parse_range(Some("bytes=50-500"), 100),
+            RangeRequest::Partial(ByteRange { start: 50, end: 99 })
+        );
+    }
+
+    #[test]
+    fn rejects_or_ignores_unusable_ranges() {
+        assert_eq!(parse_range(None, 100), RangeRequest::Full);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), RangeRequest::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), RangeRequest::Full);
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            parse_range(Some("bytes=9-3"), 100),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-0"), 100),
+            RangeRequest::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn maps_range_across_chunk_boundaries() {
+        // chunk_size 10 -> stream chunks of 26 bytes, last chunk 5 + 16 = 21
+        let file_size = 25;
+        assert_eq!(encrypted_len(file_size, 10), 25 + 3 * 16);
+
+        let slices = covering_chunks(ByteRange { start: 20, end: 60 }, file_size, 10);
+        assert_eq!(
+            slices,
+            vec![
+                ChunkSlice {
+                    chunk_index: 0,
+                    skip: 20,
+                    take: 6
+                },
+                ChunkSlice {
+                    chunk_index: 1,
+                    skip: 0,
+                    take: 26
+                },
+                ChunkSlice {
+                    chunk_index: 2,
+                    skip: 0,
+                    take: 9
+                },
+            ]
+        );
+    }
+}
@@ -2,14 +2,71 @@
 
 use crate::common::config::TransferSettings;
 use crate::common::{manifest::FileEntry, Manifest, Session, TransferState};
+use crate::crypto::password::KeySalt;
 use crate::crypto::types::EncryptionKey;
+use crate::crypto::{self, NonceLedger};
 use crate::send::buffer_pool::BufferPool;
 use crate::send::file_handle::SendFileHandle;
+use crate::send::hasher::IncrementalHasher;
+use crate::send::tar_stream::TarStream;
+use crate::server::auth::AuthSecret;
+use crate::server::latency::LatencyHistogram;
+use crate::server::limits::{ConcurrencyLimiter, ConcurrencyLimits};
 use crate::server::progress::ProgressTracker;
+use crate::server::rate_limit::{RateLimiter, RateLimits};
+use crate::utils::run_blocking;
+use anyhow::{Context, Result};
 use dashmap::DashMap;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{OnceCell, Semaphore};
+use tokio::task::JoinSet;
+
+/// Files opened at once while warming handles after a claim.
+const WARM_UP_CONCURRENCY: usize = 8;
+
+/// Chunks counted as sent, for retry dedup and the completion check.
+#[derive(Default)]
+struct ChunkLedger {
+    sent: DashMap<(usize, usize), ()>,
+    /// Chunks counted while dedup is off; the map stays empty then
+    counted: AtomicU64,
+}
+
+impl ChunkLedger {
+    fn mark(&self, dedup: bool, chunk: (usize, usize)) -> bool {
+        if !dedup {
+            self.counted.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        self.sent.insert(chunk, ()).is_none()
+    }
+
+    fn unmark(&self, dedup: bool, chunk: (usize, usize)) -> bool {
+        if !dedup {
+            return self
+                .counted
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok();
+        }
+        self.sent.remove(&chunk).is_some()
+    }
+
+    fn count(&self, dedup: bool) -> u64 {
+        if dedup {
+            self.sent.len() as u64
+        } else {
+            self.counted.load(Ordering::Relaxed)
+        }
+    }
+
+    fn clear(&self) {
+        self.sent.clear();
+        self.counted.store(0, Ordering::Relaxed);
+    }
+}
 
 /// Cheaply cloned handle to send state stored behind `Arc`.
 #[derive(Clone)]
@@ -21,11 +78,42 @@ pub struct SendAppState {
 pub struct SendAppStateInner {
     pub session: Session,
     pub manifest: Manifest,
+    manifest_etag: String,
     pub progress: Arc<ProgressTracker>,
     pub file_handles: Arc<DashMap<usize, Arc<SendFileHandle>>>,
     pub buffer_pool: Arc<BufferPool>,
+    /// Debug-build guard against sealing two plaintexts under one nonce
+    pub nonce_ledger: Arc<NonceLedger>,
     pub config: TransferSettings,
-    sent_chunks: Arc<DashMap<(usize, usize), ()>>,
+    pub limiter: Arc<ConcurrencyLimiter>,
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Read+encrypt time per served chunk, summarized at completion
+    pub chunk_latency: LatencyHistogram,
+    /// Label for the files being sent, as shown in the TUI and `GET /status`
+    pub display_name: String,
+    /// Set by `--auth-token`: required alongside the session token
+    pub auth_secret: Option<AuthSecret>,
+    /// Per-chunk dedup for retrying clients (Safari); off trusts the client
+    dedup_chunks: bool,
+    /// Manifest is served only to clients that already claimed the session
+    require_claim: bool,
+    /// Abort once this many bytes have been served across all clients
+    max_transfer: Option<u64>,
+    bytes_served: AtomicU64,
+    /// Chunks sent to anyone; drives progress, and is the only ledger for a
+    /// single-claim session
+    sent_chunks: ChunkLedger,
+    /// Per-claim ledgers on a multi-use link, keyed by lock token
+    claim_chunks: DashMap<String, Arc<ChunkLedger>>,
+    /// Client id (`X-Client-Id`) behind each lock token that sent one
+    claim_clients: DashMap<String, String>,
+    /// Finished clients, by client id, or by lock token for clients without one
+    completed_clients: Arc<DashMap<String, ()>>,
+    streaming_hash: OnceLock<Arc<IncrementalHasher>>,
+    /// SHA-256 of files the manifest left unhashed, filled in on first request
+    file_hashes: DashMap<usize, Arc<OnceCell<String>>>,
+    /// `--file-mac`: published hashes come with a MAC under the session key
+    file_macs: bool,
     total_chunks: Arc<AtomicU64>,
 }
 
@@ -37,10 +125,16 @@ impl Deref for SendAppState {
     }
 }
 
-impl SendAppState {
-    /// Build send state from session data, manifest, and transfer settings.
+/// Send state under construction; options are set here, before any clone
+/// of the finished state can exist.
+pub struct SendAppStateBuilder {
+    inner: SendAppStateInner,
+}
+
+impl SendAppStateBuilder {
+    /// Start from `session` with every option at its default.
     pub fn new(
-        session_key: EncryptionKey,
+        session: Session,
         manifest: Manifest,
         total_chunks: u64,
         progress: Arc<ProgressTracker>,
@@ -51,39 +145,401 @@ impl SendAppState {
         let pool_size = config.concurrency;
 
         Self {
-            inner: Arc::new(SendAppStateInner {
-                session: Session::new(session_key),
+            inner: SendAppStateInner {
+                session,
+                manifest_etag: manifest.etag(),
                 manifest,
                 progress,
                 file_handles: Arc::new(DashMap::new()),
                 buffer_pool: BufferPool::new(pool_size, buf_capacity),
+                nonce_ledger: Arc::new(NonceLedger::default()),
                 config,
-                sent_chunks: Arc::new(DashMap::new()),
+                limiter: Arc::new(ConcurrencyLimiter::new(ConcurrencyLimits::for_transfer(
+                    config,
+                ))),
+                rate_limiter: Arc::new(RateLimiter::new(RateLimits::default())),
+                chunk_latency: LatencyHistogram::default(),
+                display_name: String::new(),
+                auth_secret: None,
+                dedup_chunks: true,
+                require_claim: false,
+                max_transfer: None,
+                bytes_served: AtomicU64::new(0),
+                sent_chunks: ChunkLedger::default(),
+                claim_chunks: DashMap::new(),
+                claim_clients: DashMap::new(),
+                completed_clients: Arc::new(DashMap::new()),
+                streaming_hash: OnceLock::new(),
+                file_hashes: DashMap::new(),
+                file_macs: false,
                 total_chunks: Arc::new(AtomicU64::new(total_chunks)),
-            }),
+            },
+        }
+    }
+
+    /// Replace the default concurrency caps.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.inner.limiter = Arc::new(ConcurrencyLimiter::new(limits));
+        self
+    }
+
+    /// Replace the default per-client request rate.
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.inner.rate_limiter = Arc::new(RateLimiter::new(limits));
+        self
+    }
+
+    /// Expire the link after `ttl`.
+    pub fn with_link_ttl(mut self, ttl: Duration) -> Self {
+        self.inner.session = self.inner.session.with_ttl(ttl);
+        self
+    }
+
+    /// Let up to `downloads` clients hold the link at once, each completion
+    /// using one up.
+    pub fn with_download_capacity(mut self, downloads: u64) -> Self {
+        self.inner.session = self.inner.session.with_download_capacity(downloads);
+        self
+    }
+
+    /// Set the label reported by `GET /status`.
+    pub fn with_display_name(mut self, name: String) -> Self {
+        self.inner.display_name = name;
+        self
+    }
+
+    /// Require `secret` on every token-gated route.
+    pub fn with_auth_secret(mut self, secret: AuthSecret) -> Self {
+        self.inner.auth_secret = Some(secret);
+        self
+    }
+
+    /// Mark the session key as passphrase-derived from `salt`.
+    pub fn with_key_salt(mut self, salt: KeySalt) -> Self {
+        self.inner.session = self.inner.session.with_key_salt(salt);
+        self
+    }
+
+    /// Count every chunk request instead of tracking which chunks were sent.
+    ///
+    /// Saves one map entry per chunk on huge transfers, but a client that
+    /// retries a chunk is counted twice and may reach completion early.
+    pub fn without_chunk_dedup(mut self) -> Self {
+        self.inner.dedup_chunks = false;
+        self
+    }
+
+    /// Serve the manifest only after an explicit `POST /send/claim`.
+    ///
+    /// A passive fetch (link preview, scanner) then cannot burn the claim.
+    pub fn requiring_claim(mut self) -> Self {
+        self.inner.require_claim = true;
+        self
+    }
+
+    /// MAC each published file hash under the session key (`--file-mac`).
+    pub fn with_file_macs(mut self) -> Self {
+        self.inner.file_macs = true;
+        self
+    }
+
+    /// Cap the total bytes served across all clients.
+    pub fn with_max_transfer(mut self, max_bytes: u64) -> Self {
+        self.inner.max_transfer = Some(max_bytes);
+        self
+    }
+
+    /// Finish configuring and share the state.
+    pub fn build(self) -> SendAppState {
+        SendAppState {
+            inner: Arc::new(self.inner),
         }
     }
+}
+
+impl SendAppState {
+    /// Build send state from session data, manifest, and transfer settings.
+    pub fn new(
+        session_key: EncryptionKey,
+        manifest: Manifest,
+        total_chunks: u64,
+        progress: Arc<ProgressTracker>,
+        config: TransferSettings,
+    ) -> Self {
+        Self::builder(session_key, manifest, total_chunks, progress, config).build()
+    }
+
+    /// Build send state around an existing (for example, restored) session.
+    pub fn with_session(
+        session: Session,
+        manifest: Manifest,
+        total_chunks: u64,
+        progress: Arc<ProgressTracker>,
+        config: TransferSettings,
+    ) -> Self {
+        SendAppStateBuilder::new(session, manifest, total_chunks, progress, config).build()
+    }
+
+    /// Start building send state that needs more than the defaults.
+    pub fn builder(
+        session_key: EncryptionKey,
+        manifest: Manifest,
+        total_chunks: u64,
+        progress: Arc<ProgressTracker>,
+        config: TransferSettings,
+    ) -> SendAppStateBuilder {
+        SendAppStateBuilder::new(
+            Session::new(session_key),
+            manifest,
+            total_chunks,
+            progress,
+            config,
+        )
+    }
 
     /// Return the transfer manifest.
     pub fn manifest(&self) -> &Manifest {
         &self.manifest
     }
 
+    /// Return the manifest ETag, computed once at startup.
+    pub fn manifest_etag(&self) -> &str {
+        &self.manifest_etag
+    }
+
+    /// True when published file hashes carry a MAC.
+    pub fn file_macs(&self) -> bool {
+        self.file_macs
+    }
+
+    /// Serve the manifest's only file from `stream` (`send --tar`) instead of
+    /// opening its path.
+    pub fn with_tar_stream(self, stream: Arc<TarStream>) -> Self {
+        self.file_handles
+            .insert(0, Arc::new(SendFileHandle::tar(stream)));
+        self
+    }
+
+    /// True when the manifest needs a prior `POST /send/claim`.
+    pub fn requires_claim(&self) -> bool {
+        self.require_claim
+    }
+
+    /// Configured transfer cap in bytes, if any.
+    pub fn max_transfer(&self) -> Option<u64> {
+        self.max_transfer
+    }
+
+    /// Count `bytes` about to be served. Returns false once the running
+    /// total passes the transfer cap; it keeps counting, so every later
+    /// request is refused too.
+    pub fn charge_served(&self, bytes: u64) -> bool {
+        let served = self.bytes_served.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.max_transfer.is_none_or(|cap| served <= cap)
+    }
+
+    /// Total bytes charged so far, including refused requests.
+    pub fn bytes_served(&self) -> u64 {
+        self.bytes_served.load(Ordering::Relaxed)
+    }
+
+    /// Hash the single manifest file as its chunks are served.
+    ///
+    /// Returns false when the manifest does not hold exactly one file.
+    pub fn enable_streaming_hash(&self, expected: Option<String>) -> bool {
+        let [file] = self.manifest.files.as_slice() else {
+            return false;
+        };
+        let total_chunks = file.size.div_ceil(self.config.chunk_size);
+        let _ = self
+            .streaming_hash
+            .set(Arc::new(IncrementalHasher::new(total_chunks, expected)));
+        true
+    }
+
+    /// Return the streaming hasher when enabled.
+    pub fn streaming_hash(&self) -> Option<&Arc<IncrementalHasher>> {
+        self.streaming_hash.get()
+    }
+
+    /// SHA-256 (hex) of the file at `file_index`.
+    ///
+    /// Taken from the manifest when it was hashed up front; otherwise the file
+    /// is read once, on the first call, and the digest kept for later calls.
+    pub async fn file_hash(&self, file_index: usize) -> Result<String> {
+        let entry = self
+            .get_file(file_index)
+            .with_context(|| format!("File index {} is not in the manifest", file_index))?;
+        if let Some(hash) = &entry.hash {
+            return Ok(hash.clone());
+        }
+        let cell = self
+            .file_hashes
+            .entry(file_index)
+            .or_default()
+            .value()
+            .clone();
+        let path = entry.full_path.clone();
+        cell.get_or_try_init(|| {
+            run_blocking("file hash", move || crypto::calculate_file_hash(&path))
+        })
+        .await
+        .cloned()
+    }
+
     /// Return a file entry by manifest index.
     pub fn get_file(&self, index: usize) -> Option<&FileEntry> {
         self.manifest.files.get(index)
     }
 
-    /// Mark a file/chunk pair as sent; true if newly inserted.
-    pub fn mark_chunk_sent(&self, file_index: usize, chunk_index: usize) -> bool {
+    /// Ledger of the claim holding `lock_token` on a multi-use link.
+    fn claim_ledger(&self, lock_token: &str) -> Option<Arc<ChunkLedger>> {
+        self.session.remaining_downloads()?;
+        Some(
+            self.claim_chunks
+                .entry(lock_token.to_string())
+                .or_default()
+                .clone(),
+        )
+    }
+
+    /// Mark a file/chunk pair as sent to `lock_token`'s claim; true if new
+    /// for that claim. Always true when dedup is off.
+    ///
+    /// Progress moves once per chunk of the transfer, so a second client on a
+    /// multi-use link fetching the same chunk does not push it past 100%.
+    pub fn mark_chunk_sent(&self, lock_token: &str, file_index: usize, chunk_index: usize) -> bool {
+        let chunk = (file_index, chunk_index);
+        let Some(claim) = self.claim_ledger(lock_token) else {
+            let counted = self.sent_chunks.mark(self.dedup_chunks, chunk);
+            if counted {
+                self.progress.increment_file(file_index);
+            }
+            return counted;
+        };
+        let counted = claim.mark(self.dedup_chunks, chunk);
+        if counted && self.sent_chunks.mark(self.dedup_chunks, chunk) {
+            self.progress.increment_file(file_index);
+        }
+        counted
+    }
+
+    /// Forget a file/chunk pair for `lock_token`'s claim so a later request
+    /// counts it again.
+    pub fn unmark_chunk_sent(
+        &self,
+        lock_token: &str,
+        file_index: usize,
+        chunk_index: usize,
+    ) -> bool {
+        let chunk = (file_index, chunk_index);
+        let Some(claim) = self.claim_ledger(lock_token) else {
+            let removed = self.sent_chunks.unmark(self.dedup_chunks, chunk);
+            if removed {
+                self.progress.decrement_file(file_index);
+            }
+            return removed;
+        };
+        if !claim.unmark(self.dedup_chunks, chunk) {
+            return false;
+        }
+        // Progress keeps the chunk while another claim still has it
+        let held_elsewhere = self.dedup_chunks
+            && self
+                .claim_chunks
+                .iter()
+                .any(|entry| entry.value().sent.contains_key(&chunk));
+        if !held_elsewhere && self.sent_chunks.unmark(self.dedup_chunks, chunk) {
+            self.progress.decrement_file(file_index);
+        }
+        true
+    }
+
+    /// File/chunk pairs counted as sent so far (empty when dedup is off).
+    pub fn sent_chunk_list(&self) -> Vec<(usize, usize)> {
         self.sent_chunks
-            .insert((file_index, chunk_index), ())
-            .is_none()
+            .sent
+            .iter()
+            .map(|entry| *entry.key())
+            .collect()
     }
 
-    /// Return count of unique file/chunk pairs sent.
+    /// Count previously sent chunks again, e.g. after reloading a persisted
+    /// session, so a resumed client does not have them counted twice.
+    pub fn restore_sent_chunks(&self, chunks: impl IntoIterator<Item = (usize, usize)>) {
+        for chunk in chunks {
+            self.sent_chunks.mark(self.dedup_chunks, chunk);
+        }
+    }
+
+    /// Drop the chunk ledger of a claim that finished, was released, or went idle.
+    pub fn forget_claim_chunks(&self, lock_token: &str) {
+        self.claim_chunks.remove(lock_token);
+    }
+
+    /// Drop chunk ledgers of claims the session no longer holds.
+    pub fn prune_claim_chunks(&self) {
+        self.claim_chunks
+            .retain(|lock_token, _| self.session.holds_claim(lock_token));
+    }
+
+    /// Return the handle for `file_index`, opening it on first use.
+    ///
+    /// The open runs outside the map's shard lock, so chunk requests for other
+    /// files sharing the shard are not held up behind it.
+    pub fn file_handle(&self, file_index: usize) -> Result<Arc<SendFileHandle>> {
+        if let Some(handle) = self.file_handles.get(&file_index) {
+            return Ok(handle.clone());
+        }
+        let entry = self
+            .get_file(file_index)
+            .with_context(|| format!("File index {} is not in the manifest", file_index))?;
+        let handle = Arc::new(SendFileHandle::open(&entry.full_path, entry.size)?);
+        // A racing request may have opened it too; everyone keeps the first
+        Ok(self
+            .file_handles
+            .entry(file_index)
+            .or_insert(handle)
+            .value()
+            .clone())
+    }
+
+    /// Open every manifest file ahead of its first chunk request.
+    ///
+    /// Opens run on the blocking pool, [`WARM_UP_CONCURRENCY`] at a time. A
+    /// file that fails to open is left for its chunk request to report.
+    pub async fn warm_file_handles(&self) {
+        let permits = Arc::new(Semaphore::new(WARM_UP_CONCURRENCY));
+        let mut opens = JoinSet::new();
+        for file_index in 0..self.manifest.files.len() {
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                break;
+            };
+            let state = self.clone();
+            opens.spawn_blocking(move || {
+                let _permit = permit;
+                if let Err(e) = state.file_handle(file_index) {
+                    tracing::debug!(file_index, "Warm-up open failed: {:#}", e);
+                }
+            });
+        }
+        while opens.join_next().await.is_some() {}
+        tracing::debug!(handles = self.file_handles.len(), "File handles warmed");
+    }
+
+    /// Return count of unique file/chunk pairs sent (every request when dedup is off).
     pub fn unique_chunks_sent(&self) -> usize {
-        self.sent_chunks.len()
+        self.sent_chunks.count(self.dedup_chunks) as usize
+    }
+
+    /// Return how many entries the dedup maps hold.
+    pub fn dedup_entries(&self) -> usize {
+        self.sent_chunks.sent.len()
+            + self
+                .claim_chunks
+                .iter()
+                .map(|entry| entry.value().sent.len())
+                .sum::<usize>()
     }
 
     /// Return count of unique chunks sent.
@@ -91,6 +547,64 @@ impl SendAppState {
         self.unique_chunks_sent() as u64
     }
 
+    /// Chunks counted for `lock_token`'s claim; the whole transfer's count
+    /// on a single-claim session.
+    pub fn chunks_sent_to(&self, lock_token: &str) -> u64 {
+        match self.session.remaining_downloads() {
+            Some(_) => self
+                .claim_chunks
+                .get(lock_token)
+                .map_or(0, |claim| claim.count(self.dedup_chunks)),
+            None => self.get_chunks_sent(),
+        }
+    }
+
+    /// Remember which client id a claim belongs to.
+    pub fn bind_client(&self, lock_token: &str, client_id: &str) {
+        self.claim_clients
+            .insert(lock_token.to_string(), client_id.to_string());
+    }
+
+    /// The claim this client id still holds, if any.
+    pub fn live_claim_of(&self, client_id: &str) -> Option<String> {
+        self.claim_clients
+            .iter()
+            .find(|entry| entry.value() == client_id && self.session.holds_claim(entry.key()))
+            .map(|entry| entry.key().clone())
+    }
+
+    /// Key a client is counted under: its client id, else its lock token.
+    fn client_key(&self, lock_token: &str) -> String {
+        self.claim_clients
+            .get(lock_token)
+            .map_or_else(|| lock_token.to_string(), |client| client.value().clone())
+    }
+
+    /// Record the client behind `lock_token` as finished; true if newly recorded.
+    pub fn mark_client_completed(&self, lock_token: &str) -> bool {
+        self.completed_clients
+            .insert(self.client_key(lock_token), ())
+            .is_none()
+    }
+
+    /// Return true when the client behind `lock_token` was already counted.
+    pub fn is_client_completed(&self, lock_token: &str) -> bool {
+        self.completed_clients
+            .contains_key(&self.client_key(lock_token))
+    }
+
+    /// Return true when this client id already finished a download.
+    pub fn has_client_finished(&self, client_id: &str) -> bool {
+        self.completed_clients.contains_key(client_id)
+    }
+
+    /// Forget per-download chunk accounting before the next client claims.
+    pub fn reset_for_next_download(&self) {
+        self.sent_chunks.clear();
+        self.claim_chunks.clear();
+        self.progress.reset_files();
+    }
+
     /// Return expected total chunk count for this transfer.
     pub fn get_total_chunks(&self) -> u64 {
         self.total_chunks.load(Ordering::SeqCst)
@@ -138,13 +652,19 @@ mod tests {
                 config: TransferSettings {
                     chunk_size: 1024,
                     concurrency: 1,
+                    retry_min_ms: 1,
+                    retry_max_ms: 1,
                 },
+                signature: None,
+                compression: None,
             },
             3,
             Arc::new(ProgressTracker::new()),
             TransferSettings {
                 chunk_size: 1024,
                 concurrency: 1,
+                retry_min_ms: 1,
+                retry_max_ms: 1,
             },
         );
 
@@ -164,19 +684,25 @@ mod tests {
                 config: TransferSettings {
                     chunk_size: 1024,
                     concurrency: 1,
+                    retry_min_ms: 1,
+                    retry_max_ms: 1,
                 },
+                signature: None,
+                compression: None,
             },
             3,
             Arc::new(ProgressTracker::new()),
             TransferSettings {
                 chunk_size: 1024,
                 concurrency: 1,
+                retry_min_ms: 1,
+                retry_max_ms: 1,
             },
         );
 
-        assert!(state.mark_chunk_sent(0, 0));
-        assert!(!state.mark_chunk_sent(0, 0));
-        assert!(state.mark_chunk_sent(0, 1));
+        assert!(state.mark_chunk_sent("lock", 0, 0));
+        assert!(!state.mark_chunk_sent("lock", 0, 0));
+        assert!(state.mark_chunk_sent("lock", 0, 1));
 
         assert_eq!(state.unique_chunks_sent(), 2);
         assert_eq!(state.get_chunks_sent(), 2);
@@ -0,0 +1,130 @@
+//! Sending piped data (`archdrop send -`).
+//!
+//! The stream is buffered to a temp file before anything is served, so the
+//! manifest can report its size and hash like any other file.
+
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::utils::security;
+use crate::utils::size::format_size;
+
+/// Send input that stands for stdin.
+pub const STDIN_ARG: &str = "-";
+/// Download name for piped data when none is given.
+pub const DEFAULT_STDIN_NAME: &str = "stdin";
+
+/// Piped payload buffered on disk; removed again on drop.
+///
+/// The file is owner-only (0600 on Unix) inside an owner-only directory.
+pub struct StdinPayload {
+    path: PathBuf,
+    _dir: TempDir,
+}
+
+impl StdinPayload {
+    /// File holding the payload, named as the download should be.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// True when the send inputs ask for stdin.
+pub fn is_stdin_input(inputs: &[PathBuf]) -> bool {
+    inputs.iter().any(|input| input.as_os_str() == STDIN_ARG)
+}
+
+/// Buffer all of stdin to a temp file named `name`.
+///
+/// Refuses an interactive terminal, which would otherwise wait for EOF.
+pub fn buffer_stdin(inputs: &[PathBuf], name: &str) -> Result<StdinPayload> {
+    anyhow::ensure!(
+        inputs.len() == 1,
+        "'{}' (stdin) cannot be combined with other inputs",
+        STDIN_ARG
+    );
+    let stdin = io::stdin();
+    anyhow::ensure!(
+        !stdin.is_terminal(),
+        "Nothing piped to stdin; usage: <command> | archdrop send -"
+    );
+    let payload = buffer_reader(stdin.lock(), name)?;
+    let size = std::fs::metadata(payload.path())?.len();
+    eprintln!("Read {} from stdin", format_size(size));
+    Ok(payload)
+}
+
+/// Copy `reader` to end of stream into a fresh temp file named `name`.
+pub fn buffer_reader<R: Read>(mut reader: R, name: &str) -> Result<StdinPayload> {
+    security::validate_filename(name)
+        .with_context(|| format!("Invalid download name '{}'", name))?;
+
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("archdrop-stdin-");
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        builder.permissions(std::fs::Permissions::from_mode(0o700));
+        options.mode(0o600);
+    }
+    let dir = builder
+        .tempdir()
+        .context("Failed to create temp dir for stdin")?;
+    // From here on the guard cleans up, including on a failed copy
+    let payload = StdinPayload {
+        path: dir.path().join(name),
+        _dir: dir,
+    };
+
+    let file = options
+        .open(&payload.path)
+        .context("Failed to create stdin buffer file")?;
+    let mut writer = BufWriter::new(file);
+    io::copy(&mut reader, &mut writer).context("Failed to read stdin")?;
+    writer
+        .flush()
+        .context("Failed to write stdin buffer file")?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dash_is_recognised_as_stdin() {
+        assert!(is_stdin_input(&[PathBuf::from("-")]));
+        assert!(!is_stdin_input(&[PathBuf::from("./-x")]));
+    }
+
+    #[test]
+    fn buffered_file_is_named_and_removed_on_drop() {
+        let payload = buffer_reader(&b"piped bytes"[..], "backup.tar.gz").unwrap();
+        let path = payload.path().to_path_buf();
+
+        assert_eq!(path.file_name().unwrap(), "backup.tar.gz");
+        assert_eq!(std::fs::read(&path).unwrap(), b"piped bytes");
+
+        drop(payload);
+        assert!(!path.exists());
+        assert!(!path.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn traversal_names_are_rejected() {
+        assert!(buffer_reader(&b""[..], "../escape").is_err());
+    }
+
+    #[test]
+    fn stdin_cannot_be_mixed_with_files() {
+        let err = buffer_stdin(&[PathBuf::from("-"), PathBuf::from("a.txt")], "stdin")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("cannot be combined"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Tar archive of the send inputs, produced on demand instead of on disk.
+//!
+//! Entry headers are built up front; file contents are read from the sources
+//! only when a chunk covering them is requested. The archive therefore has a
+//! fixed length and supports positioned reads, which is all the chunked send
+//! path needs, and no temp file ever holds a second copy of the inputs.
+
+use anyhow::{Context, Result};
+use positioned_io::ReadAt;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::archive::archive_entries;
+use super::exclude::ExcludeRules;
+use super::file_handle::SourceChanged;
+use crate::utils::log_path;
+
+/// Tar block size; entries are padded and the archive is terminated in blocks.
+const BLOCK: u64 = 512;
+
+/// Bytes hashed per read when computing the archive digest.
+const HASH_READ_BYTES: usize = 64 * 1024;
+
+/// One contiguous run of archive bytes.
+enum Segment {
+    /// Header blocks, including any GNU long-name record
+    Header(Vec<u8>),
+    /// A source file's contents as they were when the archive was planned
+    File { path: PathBuf, size: u64 },
+    /// Entry padding and the end-of-archive marker
+    Zeros(u64),
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        match self {
+            Segment::Header(bytes) => bytes.len() as u64,
+            Segment::File { size, .. } | Segment::Zeros(size) => *size,
+        }
+    }
+}
+
+/// A tar of the send inputs that is never written out.
+pub struct TarStream {
+    segments: Vec<Segment>,
+    /// Archive offset where each segment starts
+    starts: Vec<u64>,
+    len: u64,
+}
+
+impl TarStream {
+    /// Plan the archive for `inputs`, using the same layout as `--zip`.
+    pub fn build(inputs: &[PathBuf], excludes: &ExcludeRules) -> Result<Self> {
+        let entries = archive_entries(inputs, excludes)?;
+        if entries.is_empty() {
+            anyhow::bail!("No files found for tar stream");
+        }
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut segments = Vec::with_capacity(entries.len() * 3 + 1);
+        for (source, name) in &entries {
+            let meta = std::fs::metadata(source)
+                .with_context(|| format!("Failed to read metadata for {}", log_path(source)))?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata_in_mode(&meta, tar::HeaderMode::Complete);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(meta.len());
+            // With no data, the builder emits only the header blocks
+            builder
+                .append_data(&mut header, name, io::empty())
+                .with_context(|| format!("Failed to build tar header for {}", name.display()))?;
+            segments.push(Segment::Header(std::mem::take(builder.get_mut())));
+
+            if meta.len() > 0 {
+                segments.push(Segment::File {
+                    path: source.clone(),
+                    size: meta.len(),
+                });
+                let padding = meta.len().next_multiple_of(BLOCK) - meta.len();
+                if padding > 0 {
+                    segments.push(Segment::Zeros(padding));
+                }
+            }
+        }
+        segments.push(Segment::Zeros(2 * BLOCK));
+
+        let mut starts = Vec::with_capacity(segments.len());
+        let mut len = 0;
+        for segment in &segments {
+            starts.push(len);
+            len += segment.len();
+        }
+        Ok(Self {
+            segments,
+            starts,
+            len,
+        })
+    }
+
+    /// Download name: `<input>.tar` for a single input, else `archdrop.tar`.
+    pub fn default_name(inputs: &[PathBuf]) -> String {
+        match inputs {
+            [input] => input
+                .file_name()
+                .map(|name| format!("{}.tar", name.to_string_lossy()))
+                .unwrap_or_else(|| "archdrop.tar".to_string()),
+            _ => "archdrop.tar".to_string(),
+        }
+    }
+
+    /// Total archive size in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Always false: even an archive of empty files has its end marker.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Fill `buf` with archive bytes starting at `offset`.
+    ///
+    /// Fails with [`SourceChanged`] if a source shrank or vanished since the
+    /// archive was planned.
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let end = offset + buf.len() as u64;
+        if end > self.len {
+            anyhow::bail!("Read past end of tar stream: {} > {}", end, self.len);
+        }
+
+        let mut filled = 0;
+        let mut index = self.starts.partition_point(|&start| start <= offset) - 1;
+        while filled < buf.len() {
+            let pos = offset + filled as u64;
+            let segment = &self.segments[index];
+            let within = pos - self.starts[index];
+            let take = (segment.len() - within).min((buf.len() - filled) as u64) as usize;
+            let out = &mut buf[filled..filled + take];
+            match segment {
+                Segment::Header(bytes) => {
+                    out.copy_from_slice(&bytes[within as usize..within as usize + take])
+                }
+                Segment::Zeros(_) => out.fill(0),
+                Segment::File { path, .. } => read_source(path, within, out)?,
+            }
+            filled += take;
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// SHA-256 (hex) of the whole archive, for the manifest.
+    ///
+    /// Reads every source once; nothing is written.
+    pub fn sha256(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; HASH_READ_BYTES];
+        let mut offset = 0;
+        while offset < self.len {
+            let take = (self.len - offset).min(HASH_READ_BYTES as u64) as usize;
+            self.read_at(offset, &mut buffer[..take])?;
+            hasher.update(&buffer[..take]);
+            offset += take as u64;
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+impl std::fmt::Debug for TarStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TarStream")
+            .field("segments", &self.segments.len())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// Read `out.len()` bytes of `path` at `offset`.
+fn read_source(path: &Path, offset: u64, out: &mut [u8]) -> Result<()> {
+    let file = match File::open(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(SourceChanged(format!("{} no longer exists", file_label(path))).into());
+        }
+        result => result.with_context(|| format!("Failed to open {}", log_path(path)))?,
+    };
+    if let Err(e) = file.read_exact_at(offset, out) {
+        let end = offset + out.len() as u64;
+        if file.metadata().is_ok_and(|meta| meta.len() < end) {
+            return Err(SourceChanged(format!("{} shrank", file_label(path))).into());
+        }
+        return Err(e).with_context(|| format!("Failed to read {}", log_path(path)));
+    }
+    Ok(())
+}
+
+/// File name only; [`SourceChanged`] messages reach the receiver.
+fn file_label(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    /// Read the stream in awkward slices, as chunk requests would.
+    fn read_all(stream: &TarStream, slice: usize) -> Vec<u8> {
+        let mut out = vec![0u8; stream.len() as usize];
+        for (index, piece) in out.chunks_mut(slice).enumerate() {
+            stream.read_at((index * slice) as u64, piece).unwrap();
+        }
+        out
+    }
+
+    fn unpack(bytes: &[u8]) -> BTreeMap<String, Vec<u8>> {
+        let mut archive = tar::Archive::new(bytes);
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let name = entry.path().unwrap().to_string_lossy().replace('\\', "/");
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                (name, data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn streamed_tar_extracts_to_source_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        let deep = root.join("src").join("a".repeat(60)).join("b".repeat(60));
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(root.join("readme.txt"), b"hello").unwrap();
+        std::fs::write(root.join("empty"), b"").unwrap();
+        std::fs::write(root.join("src/data.bin"), vec![9u8; 3 * 512 + 7]).unwrap();
+        // Over 100 bytes, so it needs the GNU long-name record
+        std::fs::write(deep.join("long.txt"), b"deep").unwrap();
+        let single = dir.path().join("extra.txt");
+        std::fs::write(&single, b"extra").unwrap();
+
+        let stream = TarStream::build(&[root.clone(), single], &ExcludeRules::default()).unwrap();
+        assert_eq!(stream.len() % BLOCK, 0);
+        let bytes = read_all(&stream, 1000);
+
+        let long_name = format!("project/src/{}/{}/long.txt", "a".repeat(60), "b".repeat(60));
+        let expected: BTreeMap<String, Vec<u8>> = [
+            ("project/readme.txt".to_string(), b"hello".to_vec()),
+            ("project/empty".to_string(), Vec::new()),
+            ("project/src/data.bin".to_string(), vec![9u8; 3 * 512 + 7]),
+            (long_name, b"deep".to_vec()),
+            ("extra.txt".to_string(), b"extra".to_vec()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(unpack(&bytes), expected);
+
+        let digest = hex::encode(Sha256::digest(&bytes));
+        assert_eq!(stream.sha256().unwrap(), digest);
+    }
+
+    #[test]
+    fn shrunk_source_is_reported_as_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        std::fs::write(&path, vec![1u8; 4096]).unwrap();
+        let stream =
+            TarStream::build(std::slice::from_ref(&path), &ExcludeRules::default()).unwrap();
+
+        std::fs::write(&path, b"short").unwrap();
+        let mut buf = vec![0u8; stream.len() as usize];
+        let err = stream.read_at(0, &mut buf).unwrap_err();
+        assert!(err.is::<SourceChanged>(), "{err:#}");
+        assert!(err.to_string().contains("log.txt shrank"));
+    }
+
+    #[test]
+    fn read_past_end_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, b"a").unwrap();
+        let stream = TarStream::build(&[path], &ExcludeRules::default()).unwrap();
+
+        let mut buf = [0u8; 2];
+        assert!(stream.read_at(stream.len() - 1, &mut buf).is_err());
+    }
+}
@@ -2,16 +2,25 @@
 
 use super::runtime;
 use crate::common::config::{AppConfig, Transport};
-use crate::common::Manifest;
+use crate::common::{ChunkCompression, Manifest, Session};
+use crate::crypto::password::{self, KeySalt};
 use crate::crypto::types::{EncryptionKey, Nonce};
-use crate::receive::ReceiveAppState;
-use crate::send::SendAppState;
+use crate::receive::{ConflictPolicy, ReceiveAppState, TarSink};
+use crate::relay::RelayState;
+use crate::send::persist::Autosave;
+use crate::send::{PersistedSend, SendAppState, SendAppStateBuilder, TarStream};
+use crate::server::auth::AuthSecret;
 use crate::server::progress::ProgressTracker;
 use crate::server::routes;
-use anyhow::Result;
+use crate::server::webhook::Webhook;
+use crate::transport::local::AddressSelector;
+use anyhow::{Context, Result};
 use axum::Router;
+use reqwest::Url;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Router and display metadata for a running transfer server.
 pub struct ServerInstance {
@@ -53,15 +62,185 @@ fn build_send_display_label(manifest: &Manifest) -> (String, Option<usize>) {
     }
 }
 
-/// Build and run a send server for the selected transport.
-pub async fn start_send_server(
-    manifest: Manifest,
+/// Send-only behavior chosen on the command line.
+#[derive(Debug, Clone)]
+pub struct SendOptions {
+    /// Distinct client downloads that end the transfer
+    pub downloads: u64,
+    /// Let all `downloads` clients hold the link at once instead of in turn
+    pub concurrent_downloads: bool,
+    /// Hash the single sent file while serving it
+    pub streaming_hash: bool,
+    /// Expected SHA-256 (hex) to verify the streaming hash against
+    pub expected_hash: Option<String>,
+    /// Write a JSON stats file here when the session ends
+    pub stats_out: Option<PathBuf>,
+    /// How long the link may be claimed before it expires
+    pub link_ttl: Option<Duration>,
+    /// Shut down if nobody claims the link within this long
+    pub idle_timeout: Option<Duration>,
+    /// Track sent chunks so client retries are not double counted
+    pub dedup_chunks: bool,
+    /// Serve the manifest only after an explicit `POST /send/claim`
+    pub require_claim: bool,
+    /// Abort the transfer once this many bytes have been served
+    pub max_transfer: Option<u64>,
+    /// Add a whole-file MAC to each manifest entry for the client to check
+    pub file_mac: bool,
+    /// Offer zstd compression of chunk plaintext to clients that accept it
+    pub compress: bool,
+    /// Print newline-delimited JSON progress on stdout instead of the TUI
+    pub json_progress: bool,
+    /// Print the QR code and link once, then plain progress lines
+    pub plain_output: bool,
+    /// Copy the transfer link to the system clipboard
+    pub clipboard: bool,
+    /// Also write the QR code to this file
+    pub qr_file: Option<PathBuf>,
+    /// Derive the session key from this passphrase instead of at random
+    pub password: Option<String>,
+    /// Require this secret alongside the session token (`--auth-token`)
+    pub auth_token: Option<String>,
+    /// How long in-flight responses may finish after shutdown starts
+    pub shutdown_grace: Option<Duration>,
+    /// Which local address direct HTTPS links use
+    pub address: AddressSelector,
+    /// POST a completion event here when the transfer finishes
+    pub webhook: Option<Url>,
+    /// Advertise local sessions on the LAN over mDNS
+    pub mdns: bool,
+    /// Serve the manifest's single file from this on-the-fly tar (`--tar`)
+    pub tar_stream: Option<Arc<TarStream>>,
+    /// Save the session under the data dir so a restarted sender can resume it
+    pub resumable: bool,
+    /// Continue this saved session (same link) instead of starting a new one
+    pub resume: Option<PersistedSend>,
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        Self {
+            downloads: 1,
+            concurrent_downloads: false,
+            streaming_hash: false,
+            expected_hash: None,
+            stats_out: None,
+            link_ttl: None,
+            idle_timeout: None,
+            dedup_chunks: true,
+            require_claim: false,
+            max_transfer: None,
+            file_mac: false,
+            compress: false,
+            json_progress: false,
+            plain_output: false,
+            clipboard: false,
+            qr_file: None,
+            password: None,
+            auth_token: None,
+            shutdown_grace: None,
+            address: AddressSelector::Auto,
+            webhook: None,
+            mdns: false,
+            tar_stream: None,
+            resumable: false,
+            resume: None,
+        }
+    }
+}
+
+/// Receive-only behavior chosen on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiveOptions {
+    /// Write a JSON stats file here when the session ends
+    pub stats_out: Option<PathBuf>,
+    /// Shut down if nobody claims the link within this long
+    pub idle_timeout: Option<Duration>,
+    /// Write received files as one tar stream here (`-` for stdout)
+    pub tar_output: Option<PathBuf>,
+    /// Write the list of received files as JSON here at completion
+    pub received_out: Option<PathBuf>,
+    /// Re-read each finalized file and check its size and hash
+    pub verify: bool,
+    /// What to do when a received name already exists at the destination
+    pub on_conflict: ConflictPolicy,
+    /// Print newline-delimited JSON progress on stdout instead of the TUI
+    pub json_progress: bool,
+    /// Print the QR code and link once, then plain progress lines
+    pub plain_output: bool,
+    /// Copy the transfer link to the system clipboard
+    pub clipboard: bool,
+    /// Also write the QR code to this file
+    pub qr_file: Option<PathBuf>,
+    /// Derive the session key from this passphrase instead of at random
+    pub password: Option<String>,
+    /// Require this secret alongside the session token (`--auth-token`)
+    pub auth_token: Option<String>,
+    /// How long in-flight responses may finish after shutdown starts
+    pub shutdown_grace: Option<Duration>,
+    /// Which local address direct HTTPS links use
+    pub address: AddressSelector,
+    /// POST a completion event here when the transfer finishes
+    pub webhook: Option<Url>,
+    /// Advertise local sessions on the LAN over mDNS
+    pub mdns: bool,
+}
+
+/// Random session key, or one derived from `password` with a fresh salt.
+async fn session_key(password: Option<String>) -> Result<(EncryptionKey, Option<KeySalt>)> {
+    match password {
+        Some(password) => {
+            let salt = KeySalt::new();
+            let key = password::derive_key_blocking(password, salt.clone()).await?;
+            Ok((key, Some(salt)))
+        }
+        None => Ok((EncryptionKey::new(), None)),
+    }
+}
+
+/// A send session's router and state, ready to be served.
+pub(super) struct PreparedSend {
+    pub(super) server: ServerInstance,
+    pub(super) state: SendAppState,
+    pub(super) nonce: Nonce,
+    pub(super) tracker: Arc<ProgressTracker>,
+}
+
+/// Build the session, state and router that serve `manifest`.
+pub(super) async fn prepare_send(
+    mut manifest: Manifest,
     transport: Transport,
     config: &AppConfig,
-) -> Result<u16> {
-    let session_key = EncryptionKey::new();
-    let nonce = Nonce::new();
+    options: &SendOptions,
+) -> Result<PreparedSend> {
+    // Configs built in code skip the load-time checks; zero chunk size or
+    // concurrency would divide by zero or stall before the first chunk
+    config.validate()?;
+    // Two files sharing a nonce base under one key would break AES-GCM
+    manifest.ensure_unique_nonces()?;
+    let (session, nonce, sent_chunks) = match &options.resume {
+        Some(saved) => (saved.session()?, saved.link_nonce()?, saved.sent_chunks()?),
+        None => {
+            let (session_key, key_salt) = session_key(options.password.clone()).await?;
+            let mut session = Session::new(session_key);
+            if let Some(salt) = key_salt {
+                session = session.with_key_salt(salt);
+            }
+            (session, Nonce::new(), Vec::new())
+        }
+    };
     let transfer_settings = config.transfer_settings(transport);
+    if options.resumable {
+        // A resume checks each file against its saved hash before reusing nonces
+        manifest.hash_files().await?;
+    }
+    if options.file_mac {
+        // Covers files hashed up front; the rest get theirs when published
+        manifest.add_file_macs(session.session_key())?;
+    }
+    if options.compress {
+        manifest.compression = Some(ChunkCompression::Zstd);
+    }
 
     // TUI display
     let (display_name, display_overflow_count) = build_send_display_label(&manifest);
@@ -75,21 +254,102 @@ pub async fn start_send_server(
     // Send specific session
     let total_chunks = manifest.total_chunks(transfer_settings.chunk_size);
     let progress_tracker = Arc::new(ProgressTracker::new());
+    progress_tracker.set_download_target(options.downloads);
 
     // Create typed state for router
-    let send_state = SendAppState::new(
-        session_key,
+    let mut send_state = SendAppStateBuilder::new(
+        session,
         manifest,
         total_chunks,
         progress_tracker.clone(),
         transfer_settings,
+    )
+    .with_concurrency_limits(config.concurrency_limits(transport))
+    .with_rate_limits(config.rate_limits())
+    .with_display_name(display_name.clone());
+    if options.concurrent_downloads {
+        send_state = send_state.with_download_capacity(options.downloads);
+    }
+    if let Some(ttl) = options.link_ttl {
+        send_state = send_state.with_link_ttl(ttl);
+    }
+    if !options.dedup_chunks {
+        send_state = send_state.without_chunk_dedup();
+    }
+    if options.file_mac {
+        send_state = send_state.with_file_macs();
+    }
+    if options.require_claim {
+        send_state = send_state.requiring_claim();
+    }
+    if let Some(max_bytes) = options.max_transfer {
+        send_state = send_state.with_max_transfer(max_bytes);
+    }
+    if let Some(secret) = &options.auth_token {
+        send_state = send_state.with_auth_secret(AuthSecret::new(secret.clone())?);
+    }
+    let mut send_state = send_state.build();
+    if let Some(stream) = &options.tar_stream {
+        send_state = send_state.with_tar_stream(stream.clone());
+    }
+    if options.streaming_hash || options.expected_hash.is_some() {
+        anyhow::ensure!(
+            send_state.enable_streaming_hash(options.expected_hash.clone()),
+            "Streaming hash requires exactly one file (use --zip to bundle inputs)"
+        );
+    }
+    send_state.restore_sent_chunks(sent_chunks);
+    let app = routes::with_request_cap(
+        routes::create_send_router(&send_state),
+        config.max_connections(transport),
     );
-    let app = routes::create_send_router(&send_state);
 
-    let server = ServerInstance::new(app, display_name, display_files, display_overflow_count);
+    Ok(PreparedSend {
+        server: ServerInstance::new(app, display_name, display_files, display_overflow_count),
+        state: send_state,
+        nonce,
+        tracker: progress_tracker,
+    })
+}
+
+/// Build and run a send server for the selected transport.
+pub async fn start_send_server(
+    manifest: Manifest,
+    transport: Transport,
+    config: &AppConfig,
+    options: SendOptions,
+) -> Result<u16> {
+    let PreparedSend {
+        server,
+        state: send_state,
+        nonce,
+        tracker: progress_tracker,
+    } = prepare_send(manifest, transport, config, &options).await?;
+    let autosave = if options.resumable || options.resume.is_some() {
+        Some(Autosave::start(
+            send_state.clone(),
+            nonce.clone(),
+            config.data_dir(),
+        )?)
+    } else {
+        None
+    };
+    let session_options = runtime::SessionOptions {
+        stats_out: options.stats_out,
+        idle_timeout: options.idle_timeout,
+        json_progress: options.json_progress,
+        plain_output: options.plain_output,
+        clipboard: options.clipboard,
+        qr_file: options.qr_file,
+        shutdown_grace: options.shutdown_grace,
+        address: options.address,
+        webhook: options.webhook.map(Webhook::new).transpose()?,
+        mdns: options.mdns,
+        ..Default::default()
+    };
 
     // Call runtime functions directly with typed state
-    match transport {
+    let result = match transport {
         Transport::Local => {
             runtime::start_https(
                 server,
@@ -98,10 +358,11 @@ pub async fn start_send_server(
                 transport,
                 config,
                 progress_tracker,
+                session_options,
             )
             .await
         }
-        Transport::Cloudflare | Transport::Tailscale => {
+        Transport::Cloudflare | Transport::Tailscale | Transport::Ngrok => {
             runtime::start_tunnel(
                 server,
                 send_state,
@@ -109,10 +370,15 @@ pub async fn start_send_server(
                 transport,
                 config,
                 progress_tracker,
+                session_options,
             )
             .await
         }
+    };
+    if let Some(autosave) = autosave {
+        autosave.finish();
     }
+    result
 }
 
 /// Build and run a receive server for the selected transport.
@@ -120,30 +386,87 @@ pub async fn start_receive_server(
     destination: PathBuf,
     transport: Transport,
     config: &AppConfig,
+    options: ReceiveOptions,
 ) -> Result<u16> {
-    let session_key = EncryptionKey::new();
+    // See start_send_server
+    config.validate()?;
+    let (session_key, key_salt) = session_key(options.password).await?;
     let nonce = Nonce::new();
     let transfer_settings = config.transfer_settings(transport);
 
+    // Files are staged until framed into the tar stream
+    let tar_sink = match &options.tar_output {
+        Some(target) => Some(Arc::new(TarSink::create(target)?)),
+        None => None,
+    };
+    let stdout_is_data = options
+        .tar_output
+        .as_deref()
+        .is_some_and(TarSink::targets_stdout);
+
     // TUI display name
-    let display_name = destination
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(".")
-        .to_string();
+    let display_name = match &options.tar_output {
+        Some(_) if stdout_is_data => "tar stream (stdout)".to_string(),
+        Some(target) => target.display().to_string(),
+        None => destination
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(".")
+            .to_string(),
+    };
+    let destination = match &tar_sink {
+        Some(sink) => sink.staging_dir().to_path_buf(),
+        None => destination,
+    };
 
     // Receive specific session
     // Start with 0, will be updated when manifest arrives from client
     let progress_tracker = Arc::new(ProgressTracker::new());
 
     // Create typed state for router
-    let receive_state = ReceiveAppState::new(
+    let mut receive_state = ReceiveAppState::builder(
         session_key,
         destination,
         progress_tracker.clone(),
         transfer_settings,
+    )
+    .with_concurrency_limits(config.concurrency_limits(transport))
+    .with_rate_limits(config.rate_limits())
+    .with_display_name(display_name.clone());
+    if let Some(sink) = tar_sink {
+        receive_state = receive_state.with_tar_sink(sink);
+    }
+    if let Some(salt) = key_salt {
+        receive_state = receive_state.with_key_salt(salt);
+    }
+    if let Some(path) = options.received_out {
+        receive_state = receive_state.with_received_out(path);
+    }
+    if options.verify {
+        receive_state = receive_state.with_verify();
+    }
+    receive_state = receive_state.with_conflict_policy(options.on_conflict);
+    if let Some(secret) = options.auth_token {
+        receive_state = receive_state.with_auth_secret(AuthSecret::new(secret)?);
+    }
+    let receive_state = receive_state.build();
+    let app = routes::with_request_cap(
+        routes::create_receive_router(&receive_state),
+        config.max_connections(transport),
     );
-    let app = routes::create_receive_router(&receive_state);
+    let session_options = runtime::SessionOptions {
+        stats_out: options.stats_out,
+        idle_timeout: options.idle_timeout,
+        stdout_is_data,
+        json_progress: options.json_progress,
+        plain_output: options.plain_output,
+        clipboard: options.clipboard,
+        qr_file: options.qr_file,
+        shutdown_grace: options.shutdown_grace,
+        address: options.address,
+        webhook: options.webhook.map(Webhook::new).transpose()?,
+        mdns: options.mdns,
+    };
 
     let server = ServerInstance::new(app, display_name, Vec::new(), None);
 
@@ -157,10 +480,11 @@ pub async fn start_receive_server(
                 transport,
                 config,
                 progress_tracker,
+                session_options,
             )
             .await
         }
-        Transport::Cloudflare | Transport::Tailscale => {
+        Transport::Cloudflare | Transport::Tailscale | Transport::Ngrok => {
             runtime::start_tunnel(
                 server,
                 receive_state,
@@ -168,12 +492,46 @@ pub async fn start_receive_server(
                 transport,
                 config,
                 progress_tracker,
+                session_options,
             )
             .await
         }
     }
 }
 
+/// Run a relay on `bind` until Ctrl+C, letting open pipes drain for the
+/// default shutdown grace.
+pub async fn start_relay_server(bind: SocketAddr) -> Result<()> {
+    let listener = std::net::TcpListener::bind(bind)
+        .with_context(|| format!("Failed to bind relay to {}", bind))?;
+    listener
+        .set_nonblocking(true)
+        .context("Failed to set listener to non-blocking mode")?;
+    let local_addr = listener.local_addr()?;
+
+    let app = routes::create_relay_router(&RelayState::new());
+    let handle = axum_server::Handle::new();
+    let server_handle = handle.clone();
+    let server = tokio::spawn(async move {
+        axum_server::from_tcp(listener)
+            .handle(server_handle)
+            .serve(app.into_make_service())
+            .await
+    });
+
+    println!("Relay listening on {}", local_addr);
+    tokio::signal::ctrl_c()
+        .await
+        .context("Failed to listen for Ctrl+C")?;
+
+    runtime::stop_server(&handle, runtime::DEFAULT_SHUTDOWN_GRACE).await;
+    server
+        .await
+        .context("Relay server task failed")?
+        .context("Relay server error")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,12 +549,19 @@ mod tests {
                     relative_path: (*name).to_string(),
                     size: 1,
                     nonce: "nonce".to_string(),
+                    parallelism: 1,
+                    hash: None,
+                    mac: None,
                 })
                 .collect(),
             config: TransferSettings {
                 chunk_size: 1024,
                 concurrency: 1,
+                retry_min_ms: 1,
+                retry_max_ms: 1,
             },
+            signature: None,
+            compression: None,
         }
     }
 
@@ -1,14 +1,34 @@
 //! Request extractors and session-gating helpers for server endpoints.
 
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use axum::extract::FromRequestParts;
+use aws_lc_rs::constant_time;
+use axum::extract::{FromRequestParts, Request, State};
 use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
 
 use crate::common::{session_core::ClaimError, session_core::Session, AppError};
 
 /// Header name carrying the transfer lock token.
 pub const LOCK_HEADER_NAME: &str = "x-transfer-lock";
 
+/// Header name carrying a client's own random id, kept across claims.
+pub const CLIENT_ID_HEADER_NAME: &str = "x-client-id";
+
+/// Longest client id accepted; the web client sends a UUID.
+const MAX_CLIENT_ID_LEN: usize = 64;
+
+/// Header name carrying the `--auth-token` secret.
+///
+/// `Authorization` already holds the session token, so the secret gets its own header.
+pub const AUTH_SECRET_HEADER_NAME: &str = "x-auth-token";
+
+/// Header name carrying the sender's revoke secret for `POST /send/revoke`.
+pub const REVOKE_SECRET_HEADER_NAME: &str = "x-revoke-secret";
+
 /// Extracted bearer token from `Authorization: Bearer <token>`.
 pub struct BearerToken(pub String);
 
@@ -61,6 +81,120 @@ impl<S: Send + Sync> FromRequestParts<S> for LockToken {
     }
 }
 
+/// Client id from `X-Client-Id`, if present and of sane length.
+pub fn client_id(headers: &axum::http::HeaderMap) -> Option<&str> {
+    headers
+        .get(CLIENT_ID_HEADER_NAME)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CLIENT_ID_LEN)
+}
+
+/// Shortest `--auth-token` accepted.
+pub const MIN_AUTH_SECRET_LEN: usize = 6;
+
+/// Wrong secrets from link holders tolerated before every route behind it
+/// answers `429`.
+pub const MAX_AUTH_FAILURES: u32 = 5;
+
+/// Extra secret (`--auth-token`) required on top of the session token.
+///
+/// Never part of the link or QR code; it has to be shared separately.
+/// Clones share one failure count, so guesses against any gated route add up
+/// to a single lockout for the session. Only guesses that come with the
+/// session token count (see [`AuthGate`]).
+#[derive(Clone)]
+pub struct AuthSecret {
+    secret: Arc<str>,
+    failures: Arc<AtomicU32>,
+}
+
+impl AuthSecret {
+    pub fn new(secret: impl Into<String>) -> anyhow::Result<Self> {
+        let secret = secret.into();
+        anyhow::ensure!(
+            secret.chars().count() >= MIN_AUTH_SECRET_LEN,
+            "Auth token must be at least {} characters",
+            MIN_AUTH_SECRET_LEN
+        );
+        Ok(Self {
+            secret: Arc::from(secret),
+            failures: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    /// Compare against a presented secret in constant time.
+    pub fn matches(&self, candidate: &str) -> bool {
+        constant_time::verify_slices_are_equal(self.secret.as_bytes(), candidate.as_bytes()).is_ok()
+    }
+
+    /// Whether too many wrong secrets were presented; permanent for the session.
+    pub fn locked_out(&self) -> bool {
+        self.failures.load(Ordering::Acquire) >= MAX_AUTH_FAILURES
+    }
+
+    fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// State for [`require_auth_secret`]: the link's session token and the secret.
+#[derive(Clone)]
+pub struct AuthGate {
+    session_token: Arc<str>,
+    secret: AuthSecret,
+}
+
+impl AuthGate {
+    pub fn new(session_token: &str, secret: AuthSecret) -> Self {
+        Self {
+            session_token: Arc::from(session_token),
+            secret,
+        }
+    }
+}
+
+/// Middleware rejecting requests without the matching `X-Auth-Token` header.
+///
+/// The session bearer token is checked first, so someone who can reach the
+/// port but never saw the link cannot burn through the guesses. After
+/// [`MAX_AUTH_FAILURES`] wrong secrets from link holders every request gets
+/// `429`, the right secret included; the sender has to start a new transfer.
+pub async fn require_auth_secret(
+    State(gate): State<AuthGate>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (mut parts, body) = request.into_parts();
+    let BearerToken(token) = BearerToken::from_request_parts(&mut parts, &()).await?;
+    if constant_time::verify_slices_are_equal(gate.session_token.as_bytes(), token.as_bytes())
+        .is_err()
+    {
+        return Err(AppError::Unauthorized("invalid session token".to_string()));
+    }
+    let request = Request::from_parts(parts, body);
+
+    let secret = &gate.secret;
+    if secret.locked_out() {
+        return Err(AppError::TooManyRequests(
+            "too many wrong auth tokens".to_string(),
+        ));
+    }
+
+    let presented = request
+        .headers()
+        .get(AUTH_SECRET_HEADER_NAME)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| AppError::Unauthorized("missing auth token".to_string()))?;
+
+    if !secret.matches(presented) {
+        secret.record_failure();
+        return Err(AppError::Unauthorized("invalid auth token".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
+
 /// Require a currently active session for `(token, lock_token)`.
 pub fn require_active_session(
     session: &Session,
@@ -84,6 +218,8 @@ pub fn claim_session(session: &Session, token: &str) -> Result<String, AppError>
             Err(AppError::Conflict("session already claimed".to_string()))
         }
         Err(ClaimError::Completed) => Err(AppError::Conflict("session completed".to_string())),
+        Err(ClaimError::Expired) => Err(AppError::Unauthorized("session link expired".to_string())),
+        Err(ClaimError::Revoked) => Err(AppError::Unauthorized("session revoked".to_string())),
     }
 }
 
@@ -157,4 +293,20 @@ mod tests {
         let result = require_active_session(&session, "wrong-token", &lock);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_auth_secret_matches_exact_value_only() {
+        let secret = AuthSecret::new("482193").unwrap();
+        assert!(secret.matches("482193"));
+        assert!(!secret.matches("48219"));
+        assert!(!secret.matches("4821930"));
+        assert!(!secret.matches(""));
+    }
+
+    #[test]
+    fn test_auth_secret_rejects_short_secrets() {
+        assert!(AuthSecret::new("4821").is_err());
+        assert!(AuthSecret::new("abcde").is_err());
+        assert!(AuthSecret::new("abcdef").is_ok());
+    }
 }
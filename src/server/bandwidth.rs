@@ -0,0 +1,115 @@
+//! Payload vs overhead byte accounting for chunk transfers.
+//!
+//! Overhead is derived from known per-chunk costs rather than measured on the
+//! socket: every chunk carries one AES-GCM tag plus one HTTP exchange.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// AES-256-GCM authentication tag appended to every encrypted chunk.
+pub const AEAD_TAG_BYTES: u64 = 16;
+
+/// Approximate HTTP framing per chunk exchange (request line, auth/lock
+/// headers, response status and headers).
+pub const CHUNK_FRAMING_BYTES: u64 = 320;
+
+/// Lock-free counters for bytes moved by chunk requests.
+#[derive(Debug, Default)]
+pub struct BandwidthStats {
+    chunks: AtomicU64,
+    payload_bytes: AtomicU64,
+}
+
+/// Point-in-time view of payload and overhead bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BandwidthSummary {
+    pub chunks: u64,
+    pub payload_bytes: u64,
+    pub tag_bytes: u64,
+    pub framing_bytes: u64,
+}
+
+impl BandwidthStats {
+    /// Record one chunk exchange carrying `payload_len` plaintext bytes.
+    pub fn record_chunk(&self, payload_len: u64) {
+        self.chunks.fetch_add(1, Ordering::Relaxed);
+        self.payload_bytes.fetch_add(payload_len, Ordering::Relaxed);
+    }
+
+    /// Build a summary from the current counters.
+    pub fn summary(&self) -> BandwidthSummary {
+        let chunks = self.chunks.load(Ordering::Relaxed);
+        BandwidthSummary {
+            chunks,
+            payload_bytes: self.payload_bytes.load(Ordering::Relaxed),
+            tag_bytes: chunks.saturating_mul(AEAD_TAG_BYTES),
+            framing_bytes: chunks.saturating_mul(CHUNK_FRAMING_BYTES),
+        }
+    }
+}
+
+impl BandwidthSummary {
+    /// Bytes spent on anything other than file payload.
+    pub fn overhead_bytes(&self) -> u64 {
+        self.tag_bytes.saturating_add(self.framing_bytes)
+    }
+
+    /// Total bytes on the wire (payload plus overhead).
+    pub fn total_bytes(&self) -> u64 {
+        self.payload_bytes.saturating_add(self.overhead_bytes())
+    }
+
+    /// Emit the payload/overhead split at transfer completion.
+    pub fn log(&self) {
+        tracing::info!(
+            chunks = self.chunks,
+            payload_bytes = self.payload_bytes,
+            tag_bytes = self.tag_bytes,
+            framing_bytes = self.framing_bytes,
+            "Bandwidth: {:.2}% overhead",
+            self.overhead_ratio() * 100.0
+        );
+    }
+
+    /// Fraction of wire bytes that were overhead (0.0 when nothing moved).
+    pub fn overhead_ratio(&self) -> f64 {
+        let total = self.total_bytes();
+        if total == 0 {
+            return 0.0;
+        }
+        self.overhead_bytes() as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overhead_matches_tag_and_framing_per_chunk() {
+        let stats = BandwidthStats::default();
+        for _ in 0..4 {
+            stats.record_chunk(1024);
+        }
+        stats.record_chunk(100);
+
+        let summary = stats.summary();
+        assert_eq!(summary.chunks, 5);
+        assert_eq!(summary.payload_bytes, 4 * 1024 + 100);
+        assert_eq!(summary.tag_bytes, 5 * AEAD_TAG_BYTES);
+        assert_eq!(summary.framing_bytes, 5 * CHUNK_FRAMING_BYTES);
+        assert_eq!(
+            summary.overhead_bytes(),
+            5 * (AEAD_TAG_BYTES + CHUNK_FRAMING_BYTES)
+        );
+        assert_eq!(
+            summary.total_bytes(),
+            summary.payload_bytes + summary.overhead_bytes()
+        );
+    }
+
+    #[test]
+    fn empty_summary_has_zero_ratio() {
+        let summary = BandwidthStats::default().summary();
+        assert_eq!(summary.overhead_ratio(), 0.0);
+    }
+}
@@ -0,0 +1,287 @@
+//! Loopback throughput benchmark (`archdrop bench`).
+//!
+//! Serves a scratch file through the real send router over loopback HTTP and
+//! downloads it with an in-process client that decrypts every chunk, so the
+//! numbers cover the same read/encrypt/serve/decrypt path as a real transfer.
+
+use anyhow::{ensure, Context, Result};
+use aws_lc_rs::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
+use futures::{StreamExt, TryStreamExt};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::common::config::{AppConfig, TransferSettings, Transport};
+use crate::common::Manifest;
+use crate::crypto::{self, ChunkPosition, CipherSuite, EncryptionKey, Nonce};
+use crate::send::SendAppState;
+use crate::server::auth::LOCK_HEADER_NAME;
+use crate::server::progress::ProgressTracker;
+use crate::server::routes;
+use crate::transport::local::{start_local_server, BindScope, Protocol};
+use crate::utils::run_blocking;
+
+/// Block written repeatedly to build the scratch payload.
+const FILL_BLOCK_BYTES: usize = 1024 * 1024;
+
+/// Timing for each stage of one benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub settings: TransferSettings,
+    pub bytes: u64,
+    pub chunks: u64,
+    /// Scratch file, manifest, and server startup
+    pub setup: Duration,
+    /// Manifest fetch (session claim)
+    pub manifest: Duration,
+    /// Wall time from first chunk request to last chunk decrypted
+    pub transfer: Duration,
+    /// Client-side decryption, summed across chunks
+    pub decrypt: Duration,
+    /// Completion request
+    pub complete: Duration,
+}
+
+impl BenchReport {
+    /// Payload throughput over the transfer stage, in MB/s (10^6 bytes).
+    pub fn throughput_mb_per_sec(&self) -> f64 {
+        let secs = self.transfer.as_secs_f64();
+        if secs <= 0.0 {
+            return 0.0;
+        }
+        self.bytes as f64 / 1_000_000.0 / secs
+    }
+
+    /// Print a human-readable summary to stdout.
+    pub fn print(&self) {
+        println!(
+            "Bench: {} bytes in {} chunks (chunk_size {}, concurrency {})",
+            self.bytes, self.chunks, self.settings.chunk_size, self.settings.concurrency
+        );
+        println!("  setup     {:>10.2} ms", ms(self.setup));
+        println!("  manifest  {:>10.2} ms", ms(self.manifest));
+        println!("  transfer  {:>10.2} ms", ms(self.transfer));
+        println!("  decrypt   {:>10.2} ms (summed)", ms(self.decrypt));
+        println!("  complete  {:>10.2} ms", ms(self.complete));
+        println!("Throughput: {:.2} MB/s", self.throughput_mb_per_sec());
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Scratch payload removed when the bench finishes.
+struct ScratchFile(PathBuf);
+
+impl ScratchFile {
+    fn create(size: u64) -> Result<Self> {
+        let path =
+            std::env::temp_dir().join(format!("archdrop-bench-{}.bin", uuid::Uuid::new_v4()));
+        let scratch = Self(path);
+
+        let mut block = vec![0u8; FILL_BLOCK_BYTES];
+        rand::Rng::fill(&mut rand::thread_rng(), block.as_mut_slice());
+
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(&scratch.0).context("Failed to create bench payload")?,
+        );
+        let mut remaining = size;
+        while remaining > 0 {
+            let len = remaining.min(FILL_BLOCK_BYTES as u64) as usize;
+            file.write_all(&block[..len])
+                .context("Failed to write bench payload")?;
+            remaining -= len as u64;
+        }
+        file.flush().context("Failed to write bench payload")?;
+
+        Ok(scratch)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Stage timings measured by the in-process client.
+struct ClientTimings {
+    manifest: Duration,
+    transfer: Duration,
+    decrypt: Duration,
+    complete: Duration,
+    bytes: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct ManifestResponse {
+    #[serde(flatten)]
+    manifest: Manifest,
+    #[serde(rename = "lockToken")]
+    lock_token: String,
+}
+
+/// Send `size` bytes over loopback using `transport`'s transfer settings.
+pub async fn run_bench(config: &AppConfig, transport: Transport, size: u64) -> Result<BenchReport> {
+    ensure!(size > 0, "Bench payload size must be greater than zero");
+    let settings = config.transfer_settings(transport);
+
+    let setup_start = Instant::now();
+    let scratch = run_blocking("bench payload", move || ScratchFile::create(size)).await?;
+
+    let manifest = Manifest::new(vec![scratch.path().to_path_buf()], None, settings)
+        .await
+        .context("Failed to create manifest")?;
+    let total_chunks = manifest.total_chunks(settings.chunk_size);
+
+    let key = EncryptionKey::new();
+    let state = SendAppState::builder(
+        key.clone(),
+        manifest,
+        total_chunks,
+        Arc::new(ProgressTracker::new()),
+        settings,
+    )
+    .with_concurrency_limits(config.concurrency_limits(transport))
+    .build();
+    let token = state.session.token().to_string();
+    let app = routes::create_send_router(&state);
+
+    let (port, server) =
+        start_local_server(app, Protocol::Http, BindScope::Loopback, 0, config.network).await?;
+    let setup = setup_start.elapsed();
+
+    let result = download(
+        &format!("http://127.0.0.1:{}", port),
+        &token,
+        &key,
+        state.session.cipher_suite(),
+        settings,
+    )
+    .await;
+    server.shutdown();
+    drop(scratch);
+
+    let timings = result?;
+    ensure!(
+        timings.bytes == size,
+        "Bench received {} bytes, expected {}",
+        timings.bytes,
+        size
+    );
+
+    Ok(BenchReport {
+        settings,
+        bytes: timings.bytes,
+        chunks: total_chunks,
+        setup,
+        manifest: timings.manifest,
+        transfer: timings.transfer,
+        decrypt: timings.decrypt,
+        complete: timings.complete,
+    })
+}
+
+/// Client side: claim, fetch and decrypt every chunk, then complete.
+async fn download(
+    base: &str,
+    token: &str,
+    key: &EncryptionKey,
+    suite: CipherSuite,
+    settings: TransferSettings,
+) -> Result<ClientTimings> {
+    let client = reqwest::Client::new();
+    let cipher = Arc::new(LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid bench key"))?,
+    ));
+
+    let manifest_start = Instant::now();
+    let claimed: ManifestResponse = client
+        .get(format!("{}/send/manifest", base))
+        .bearer_auth(token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to parse bench manifest")?;
+    let manifest_time = manifest_start.elapsed();
+    let lock_token = claimed.lock_token;
+
+    let mut requests = Vec::new();
+    for file in &claimed.manifest.files {
+        let nonce = Nonce::from_base64(&file.nonce)?;
+        for chunk_index in 0..file.size.div_ceil(settings.chunk_size) {
+            let position = ChunkPosition::new(file.index, chunk_index as usize, file.size);
+            requests.push((position, nonce.clone()));
+        }
+    }
+
+    let transfer_start = Instant::now();
+    let per_chunk: Vec<(u64, Duration)> = futures::stream::iter(requests)
+        .map(|(position, nonce)| {
+            let request = client
+                .get(format!(
+                    "{}/send/{}/chunk/{}",
+                    base, position.file_index, position.chunk_index
+                ))
+                .bearer_auth(token)
+                .header(LOCK_HEADER_NAME, &lock_token);
+            let cipher = cipher.clone();
+            async move {
+                let body = request.send().await?.error_for_status()?.bytes().await?;
+                let decrypt_start = Instant::now();
+                let mut buffer = body.to_vec();
+                crypto::decrypt_chunk_at_position(&cipher, &nonce, &mut buffer, &position, suite)?;
+                Ok::<_, anyhow::Error>((buffer.len() as u64, decrypt_start.elapsed()))
+            }
+        })
+        .buffer_unordered(settings.concurrency.max(1))
+        .try_collect()
+        .await?;
+    let transfer = transfer_start.elapsed();
+
+    let complete_start = Instant::now();
+    client
+        .post(format!("{}/send/complete", base))
+        .bearer_auth(token)
+        .header(LOCK_HEADER_NAME, &lock_token)
+        .send()
+        .await?
+        .error_for_status()?;
+    let complete = complete_start.elapsed();
+
+    Ok(ClientTimings {
+        manifest: manifest_time,
+        transfer,
+        decrypt: per_chunk.iter().map(|(_, elapsed)| *elapsed).sum(),
+        complete,
+        bytes: per_chunk.iter().map(|(len, _)| len).sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn small_payload_bench_reports_positive_throughput() {
+        let mut config = AppConfig::default();
+        config.local.transfer.chunk_size = 64 * 1024;
+
+        let report = run_bench(&config, Transport::Local, 300 * 1024)
+            .await
+            .expect("bench should complete");
+
+        assert_eq!(report.bytes, 300 * 1024);
+        assert_eq!(report.chunks, 5);
+        assert!(report.throughput_mb_per_sec() > 0.0);
+    }
+}
@@ -0,0 +1,271 @@
+//! Library API for serving files from another Rust program.
+//!
+//! [`TransferBuilder`] runs the same send session as `archdrop send`, minus
+//! the TUI, signal handling and terminal output. The caller gets the link and
+//! QR code back and decides how to show them.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use archdrop::common::config::Transport;
+//! use archdrop::server::TransferBuilder;
+//!
+//! let transfer = TransferBuilder::new()
+//!     .add_file("report.pdf")
+//!     .transport(Transport::Local)
+//!     .password("correct horse battery staple")
+//!     .build()
+//!     .await?;
+//! println!("{}\n{}", transfer.qr_code(), transfer.url());
+//! let outcome = transfer.wait().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::api::{prepare_send, PreparedSend, SendOptions};
+use super::runtime::{self, Bound, DEFAULT_SHUTDOWN_GRACE};
+use crate::common::config::{AppConfig, Transport};
+use crate::common::{Manifest, TransferEvent, TransferProgress, TransferState};
+use crate::send::{self, ExcludeRules, SendAppState};
+use crate::server::progress::ProgressTracker;
+use crate::transport::local::AddressSelector;
+use crate::transport::tunnel::TunnelHandle;
+use crate::ui::tui::generate_qr;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How often [`TransferHandle::progress_receiver`] gets a fresh snapshot.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Configures a send session.
+///
+/// At least one file or directory is required; everything else has a
+/// default. The transport defaults to [`Transport::Local`] and the tuning to
+/// [`AppConfig::default`], not the user's config file; pass a loaded config
+/// with [`config`](Self::config) to honour it.
+#[derive(Debug, Clone)]
+pub struct TransferBuilder {
+    inputs: Vec<PathBuf>,
+    transport: Transport,
+    config: AppConfig,
+    options: SendOptions,
+}
+
+impl Default for TransferBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransferBuilder {
+    pub fn new() -> Self {
+        Self {
+            inputs: Vec::new(),
+            transport: Transport::Local,
+            config: AppConfig::default(),
+            options: SendOptions::default(),
+        }
+    }
+
+    /// Serve this file, or every file under this directory (required).
+    pub fn add_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inputs.push(path.into());
+        self
+    }
+
+    /// How the receiver reaches the server (default [`Transport::Local`]).
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Ports, chunk sizes and limits (default [`AppConfig::default`]).
+    pub fn config(mut self, config: AppConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Derive the key from a passphrase the receiver must type, so the link
+    /// alone cannot decrypt anything.
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.options.password = Some(password.into());
+        self
+    }
+
+    /// Require this secret alongside the session token.
+    pub fn auth_token(mut self, secret: impl Into<String>) -> Self {
+        self.options.auth_token = Some(secret.into());
+        self
+    }
+
+    /// Distinct client downloads that end the transfer (default 1).
+    pub fn downloads(mut self, downloads: u64) -> Self {
+        self.options.downloads = downloads.max(1);
+        self
+    }
+
+    /// Expire the link if nobody claims it within `ttl`; the transfer then
+    /// fails and [`TransferHandle::wait`] returns.
+    pub fn link_ttl(mut self, ttl: Duration) -> Self {
+        self.options.link_ttl = Some(ttl);
+        self
+    }
+
+    /// Which local address direct HTTPS links use (default: the default route).
+    pub fn address(mut self, address: AddressSelector) -> Self {
+        self.options.address = address;
+        self
+    }
+
+    /// Read the inputs, start the server and return once the link is live.
+    pub async fn build(self) -> Result<TransferHandle> {
+        anyhow::ensure!(
+            !self.inputs.is_empty(),
+            "TransferBuilder needs at least one file (add_file)"
+        );
+        let files = send::collect_send_files(self.inputs, &ExcludeRules::default())?;
+        anyhow::ensure!(!files.is_empty(), "No files to send");
+        let manifest = Manifest::new(files, None, self.config.transfer_settings(self.transport))
+            .await
+            .context("Failed to create manifest")?;
+
+        let PreparedSend {
+            server,
+            state,
+            nonce,
+            tracker,
+        } = prepare_send(manifest, self.transport, &self.config, &self.options).await?;
+        let Bound {
+            port,
+            url,
+            server_handle,
+            tunnel,
+        } = runtime::bind_quietly(
+            server.app,
+            &state,
+            &nonce,
+            self.transport,
+            &self.config,
+            &self.options.address,
+        )
+        .await?;
+        let qr_code = generate_qr(&url)?;
+
+        let (progress_sender, progress) = watch::channel(tracker.snapshot());
+        let sampled = tracker.clone();
+        let sampler = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(PROGRESS_INTERVAL).await;
+                let snapshot = sampled.snapshot();
+                let finished = snapshot.is_finished();
+                progress_sender.send_replace(snapshot);
+                if finished {
+                    break;
+                }
+            }
+        });
+
+        // Same watcher `archdrop send` runs: fail the transfer once the link expires unclaimed
+        let expiry = state.session().remaining_ttl().map(|_| {
+            let session = state.session().clone();
+            let expiry_tracker = tracker.clone();
+            tokio::spawn(async move {
+                if runtime::wait_for_link_expiry(&session).await {
+                    tracing::warn!("Link expired before anyone claimed it");
+                    expiry_tracker.fail("Link expired".to_string());
+                }
+            })
+        });
+
+        Ok(TransferHandle {
+            url,
+            qr_code,
+            port,
+            tracker,
+            progress,
+            sampler,
+            expiry,
+            state,
+            server_handle,
+            tunnel,
+        })
+    }
+}
+
+/// A running send session started by [`TransferBuilder::build`].
+///
+/// Call [`wait`](Self::wait) or [`shutdown`](Self::shutdown) to stop the
+/// server; dropping the handle leaves it running until the runtime exits.
+pub struct TransferHandle {
+    url: String,
+    qr_code: String,
+    port: u16,
+    tracker: Arc<ProgressTracker>,
+    progress: watch::Receiver<TransferProgress>,
+    sampler: JoinHandle<()>,
+    expiry: Option<JoinHandle<()>>,
+    state: SendAppState,
+    server_handle: axum_server::Handle,
+    tunnel: Option<TunnelHandle>,
+}
+
+impl TransferHandle {
+    /// Link for the receiver; the key travels in the `#` fragment.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The link as a terminal-printable QR code.
+    pub fn qr_code(&self) -> &str {
+        &self.qr_code
+    }
+
+    /// Port the server listens on (the local end of the tunnel, if any).
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Progress snapshots, updated a few times a second until the transfer
+    /// finishes.
+    pub fn progress_receiver(&self) -> watch::Receiver<TransferProgress> {
+        self.progress.clone()
+    }
+
+    /// Wait for the transfer to finish, then stop the server.
+    pub async fn wait(mut self) -> TransferEvent {
+        while !self.progress.borrow_and_update().is_finished() {
+            if self.progress.changed().await.is_err() {
+                break;
+            }
+        }
+        self.shutdown().await
+    }
+
+    /// Stop the server now and report how the transfer ended.
+    ///
+    /// An unfinished transfer is recorded as cancelled.
+    pub async fn shutdown(mut self) -> TransferEvent {
+        // No-ops if a handler already published the outcome
+        if self.tracker.snapshot().is_complete() {
+            self.tracker.complete();
+        } else {
+            self.tracker.cancel();
+        }
+        self.sampler.abort();
+        if let Some(expiry) = &self.expiry {
+            expiry.abort();
+        }
+
+        runtime::stop_server(&self.server_handle, DEFAULT_SHUTDOWN_GRACE).await;
+        if let Some(tunnel) = &mut self.tunnel {
+            if let Err(e) = tunnel.shutdown().await {
+                tracing::warn!("Error during tunnel shutdown: {}", e);
+            }
+        }
+        self.state.cleanup().await;
+        self.tracker.event()
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-chunk serve latency histogram.
+//!
+//! Latencies land in log-linear buckets: eight linear sub-buckets per power
+//! of two microseconds, so any reported percentile is within 12.5% of the
+//! true value while recording stays a single atomic increment.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Linear sub-buckets per power of two (must be a power of two).
+const SUB_BUCKETS: u64 = 8;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+/// Largest tracked exponent; slower samples clamp into the last bucket (~19h).
+const MAX_EXPONENT: u32 = 36;
+const BUCKETS: usize = ((MAX_EXPONENT - SUB_BUCKET_BITS + 2) as u64 * SUB_BUCKETS) as usize;
+
+/// Lock-free latency histogram with microsecond resolution.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+}
+
+/// p50/p95/p99 over every recorded sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencyPercentiles {
+    pub samples: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Record one chunk's serve time.
+    pub fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        self.buckets[bucket_index(micros)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Latency at quantile `q` (0.0..=1.0), or None before any samples.
+    ///
+    /// Reports the upper bound of the bucket holding that rank.
+    pub fn percentile(&self, q: f64) -> Option<Duration> {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+
+        let mut seen = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                return Some(Duration::from_micros(bucket_upper_bound(index)));
+            }
+        }
+        // Counts raced ahead of bucket loads; report the slowest bucket seen
+        Some(Duration::from_micros(bucket_upper_bound(BUCKETS - 1)))
+    }
+
+    /// p50/p95/p99 summary; None before any samples.
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        Some(LatencyPercentiles {
+            samples: self.count.load(Ordering::Relaxed),
+            p50: self.percentile(0.50)?,
+            p95: self.percentile(0.95)?,
+            p99: self.percentile(0.99)?,
+        })
+    }
+}
+
+impl LatencyPercentiles {
+    /// Emit the latency spread at transfer completion.
+    pub fn log(&self) {
+        tracing::info!(
+            samples = self.samples,
+            "Chunk latency: p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms",
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.p99)
+        );
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exponent = (63 - micros.leading_zeros()).min(MAX_EXPONENT);
+    let micros = micros.min((1 << (MAX_EXPONENT + 1)) - 1);
+    let sub = (micros >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    ((exponent - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let exponent = (index / SUB_BUCKETS) as u32 + SUB_BUCKET_BITS - 1;
+    let width = 1u64 << (exponent - SUB_BUCKET_BITS);
+    let lower = (SUB_BUCKETS + index % SUB_BUCKETS) * width;
+    lower + width - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        assert!(
+            actual >= expected && actual.as_secs_f64() <= expected.as_secs_f64() * 1.125,
+            "expected ~{expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn known_latencies_give_expected_percentiles() {
+        let histogram = LatencyHistogram::default();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        let summary = histogram.percentiles().expect("samples recorded");
+        assert_eq!(summary.samples, 100);
+        assert_close(summary.p50, Duration::from_millis(50));
+        assert_close(summary.p95, Duration::from_millis(95));
+        assert_close(summary.p99, Duration::from_millis(99));
+    }
+
+    #[test]
+    fn occasional_stall_shows_in_tail_only() {
+        let histogram = LatencyHistogram::default();
+        for _ in 0..98 {
+            histogram.record(Duration::from_millis(2));
+        }
+        histogram.record(Duration::from_secs(3));
+        histogram.record(Duration::from_secs(3));
+
+        let summary = histogram.percentiles().unwrap();
+        assert_close(summary.p50, Duration::from_millis(2));
+        assert_close(summary.p95, Duration::from_millis(2));
+        assert_close(summary.p99, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn buckets_cover_their_samples() {
+        for micros in [0, 7, 8, 9, 15, 16, 1000, 123_456, 1 << 40] {
+            let index = bucket_index(micros);
+            assert!(index < BUCKETS);
+            if micros < 1 << (MAX_EXPONENT + 1) {
+                assert!(bucket_upper_bound(index) >= micros, "{micros}");
+            }
+        }
+        assert!(LatencyHistogram::default().percentiles().is_none());
+    }
+}
@@ -0,0 +1,309 @@
+//! Global and per-client caps on in-flight chunk requests.
+//!
+//! A request first takes a permit from its client's semaphore and only then
+//! from the global one, so a client queued behind its own cap never holds
+//! global capacity other clients could use.
+
+pub use crate::common::config::ConcurrencyLimits;
+use crate::common::AppError;
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use dashmap::DashMap;
+use http_body::{Frame, SizeHint};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Permits held for the lifetime of one chunk request.
+pub struct ChunkPermit {
+    _client: OwnedSemaphorePermit,
+    _global: OwnedSemaphorePermit,
+}
+
+/// Nested semaphores keyed by client (lock token).
+pub struct ConcurrencyLimiter {
+    limits: ConcurrencyLimits,
+    global: Arc<Semaphore>,
+    clients: DashMap<String, Arc<Semaphore>>,
+}
+
+impl ConcurrencyLimiter {
+    /// Build a limiter; zero limits are raised to one.
+    pub fn new(limits: ConcurrencyLimits) -> Self {
+        let limits = ConcurrencyLimits {
+            global: limits.global.max(1),
+            per_client: limits.per_client.max(1),
+        };
+        Self {
+            limits,
+            global: Arc::new(Semaphore::new(limits.global)),
+            clients: DashMap::new(),
+        }
+    }
+
+    pub fn limits(&self) -> ConcurrencyLimits {
+        self.limits
+    }
+
+    /// Wait for a client permit, then a global permit.
+    pub async fn acquire(&self, client: &str) -> ChunkPermit {
+        let client_sem = self.client_semaphore(client);
+        let client_permit = client_sem
+            .acquire_owned()
+            .await
+            .expect("limiter semaphores are never closed");
+        let global_permit = self
+            .global
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("limiter semaphores are never closed");
+        ChunkPermit {
+            _client: client_permit,
+            _global: global_permit,
+        }
+    }
+
+    /// Non-blocking variant of [`acquire`](Self::acquire).
+    pub fn try_acquire(&self, client: &str) -> Option<ChunkPermit> {
+        let client_permit = self.client_semaphore(client).try_acquire_owned().ok()?;
+        let global_permit = self.global.clone().try_acquire_owned().ok()?;
+        Some(ChunkPermit {
+            _client: client_permit,
+            _global: global_permit,
+        })
+    }
+
+    /// Drop bookkeeping for a client that is done.
+    pub fn forget_client(&self, client: &str) {
+        self.clients.remove(client);
+    }
+
+    fn client_semaphore(&self, client: &str) -> Arc<Semaphore> {
+        self.clients
+            .entry(client.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limits.per_client)))
+            .clone()
+    }
+}
+
+/// Server-wide cap on requests being handled at once (`--max-connections`).
+///
+/// Unlike [`ConcurrencyLimiter`], which queues chunk requests, this refuses
+/// outright: past the cap every route answers `503` until a slot frees up.
+#[derive(Clone)]
+pub struct RequestCap {
+    slots: Arc<Semaphore>,
+    max: usize,
+}
+
+impl RequestCap {
+    /// A cap of `max` concurrent requests; zero is raised to one.
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(max)),
+            max,
+        }
+    }
+}
+
+/// Middleware holding one [`RequestCap`] slot until the response body is
+/// done, so a long chunk stream counts against the cap for its whole length.
+pub async fn cap_in_flight(
+    State(cap): State<RequestCap>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Ok(slot) = cap.slots.clone().try_acquire_owned() else {
+        tracing::debug!(max = cap.max, "Request refused: connection cap reached");
+        return Err(AppError::ServiceUnavailable(format!(
+            "server is at its limit of {} concurrent requests",
+            cap.max
+        )));
+    };
+    Ok(next
+        .run(request)
+        .await
+        .map(|inner| Body::new(SlotBody { inner, _slot: slot })))
+}
+
+/// Response body that gives its [`RequestCap`] slot back when dropped.
+struct SlotBody {
+    inner: Body,
+    _slot: OwnedSemaphorePermit,
+}
+
+impl HttpBody for SlotBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_cannot_exceed_its_limit_while_global_has_room() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyLimits {
+            global: 10,
+            per_client: 2,
+        });
+
+        let first = limiter.try_acquire("a").expect("first permit");
+        let _second = limiter.try_acquire("a").expect("second permit");
+        assert!(limiter.try_acquire("a").is_none(), "third must wait");
+
+        // Global capacity is still there for another client
+        assert!(limiter.try_acquire("b").is_some());
+
+        drop(first);
+        assert!(limiter.try_acquire("a").is_some());
+    }
+
+    #[test]
+    fn global_limit_caps_all_clients() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyLimits {
+            global: 2,
+            per_client: 2,
+        });
+
+        let _a = limiter.try_acquire("a").expect("a permit");
+        let _b = limiter.try_acquire("b").expect("b permit");
+        assert!(limiter.try_acquire("c").is_none());
+    }
+
+    #[tokio::test]
+    async fn waiting_client_gets_permit_after_release() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(ConcurrencyLimits {
+            global: 4,
+            per_client: 1,
+        }));
+        let held = limiter.acquire("a").await;
+
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire("a").await;
+            })
+        };
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        drop(held);
+        waiter.await.expect("waiter completes");
+    }
+
+    #[tokio::test]
+    async fn request_past_the_cap_gets_503() {
+        use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+        use tokio::sync::{mpsc, Notify};
+        use tower::ServiceExt;
+
+        let (entered_tx, mut entered) = mpsc::unbounded_channel();
+        let release = Arc::new(Notify::new());
+        let gate = release.clone();
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(move || {
+                    let (entered_tx, gate) = (entered_tx.clone(), gate.clone());
+                    async move {
+                        let _ = entered_tx.send(());
+                        gate.notified().await;
+                        "done"
+                    }
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                RequestCap::new(2),
+                cap_in_flight,
+            ));
+        let request = || Request::get("/slow").body(Body::empty()).unwrap();
+
+        let held: Vec<_> = (0..2)
+            .map(|_| tokio::spawn(app.clone().oneshot(request())))
+            .collect();
+        for _ in 0..2 {
+            entered.recv().await.unwrap();
+        }
+
+        let refused = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(refused.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        release.notify_waiters();
+        for response in held {
+            assert_eq!(response.await.unwrap().unwrap().status(), StatusCode::OK);
+        }
+
+        // Slots come back once the held requests finish
+        let later = tokio::spawn(app.oneshot(request()));
+        entered.recv().await.unwrap();
+        release.notify_waiters();
+        assert_eq!(later.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn slot_is_held_until_the_body_is_done() {
+        use axum::{http::StatusCode, middleware, routing::get, Router};
+        use futures::channel::mpsc;
+        use futures::StreamExt;
+        use tower::ServiceExt;
+
+        let (chunks_tx, chunks_rx) = mpsc::unbounded::<Bytes>();
+        let chunks_rx = Arc::new(std::sync::Mutex::new(Some(chunks_rx)));
+        let app = Router::new()
+            .route(
+                "/stream",
+                get(move || {
+                    let chunks_rx = chunks_rx.clone();
+                    async move {
+                        let rx = chunks_rx.lock().unwrap().take().expect("one stream");
+                        Body::from_stream(rx.map(Ok::<_, std::io::Error>))
+                    }
+                }),
+            )
+            .route("/quick", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                RequestCap::new(1),
+                cap_in_flight,
+            ));
+        let request = |uri| Request::get(uri).body(Body::empty()).unwrap();
+
+        // The handler has returned, but its body is still streaming
+        let streaming = app.clone().oneshot(request("/stream")).await.unwrap();
+        assert_eq!(streaming.status(), StatusCode::OK);
+        let refused = app.clone().oneshot(request("/quick")).await.unwrap();
+        assert_eq!(refused.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        chunks_tx
+            .unbounded_send(Bytes::from_static(b"data"))
+            .unwrap();
+        drop(chunks_tx);
+        let body = axum::body::to_bytes(streaming.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"data");
+
+        let later = app.oneshot(request("/quick")).await.unwrap();
+        assert_eq!(later.status(), StatusCode::OK);
+    }
+}
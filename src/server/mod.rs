@@ -3,9 +3,24 @@
 // Submodules
 mod api;
 pub mod auth;
+pub mod bandwidth;
+pub mod bench;
+mod builder;
+pub mod latency;
+pub mod limits;
 pub mod progress;
+pub mod rate_limit;
 pub mod routes;
 mod runtime;
+pub mod stats;
+pub mod status;
+pub mod webhook;
 
 // Public API (what main.rs imports)
-pub use api::{start_receive_server, start_send_server, ServerInstance};
+pub use crate::transport::local::AddressSelector;
+pub use crate::transport::mdns::{discover, DiscoveredPeer};
+pub use api::{
+    start_receive_server, start_relay_server, start_send_server, ReceiveOptions, SendOptions,
+    ServerInstance,
+};
+pub use builder::{TransferBuilder, TransferHandle};
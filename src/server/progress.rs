@@ -1,10 +1,16 @@
 //! Lock-free transfer progress tracking for TUI snapshots.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 
-use crate::common::{FileProgress, FileStatus, TransferProgress};
+use tokio::sync::watch;
+
+use crate::common::{
+    FileProgress, FileStatus, Throughput, TransferEvent, TransferProgress, TransferStats,
+};
+use crate::server::bandwidth::BandwidthStats;
 
 struct FileState {
     names: Vec<String>,
@@ -15,6 +21,25 @@ struct FileState {
     errors: Mutex<Vec<(usize, String)>>,
 }
 
+/// Lock tokens are secrets; stats only ever keep this many leading chars.
+const CLIENT_ID_PREFIX_LEN: usize = 8;
+
+/// Throughput is measured over this trailing window, so a brief stall dents
+/// the rate instead of zeroing it.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+/// Minimum spacing between stored rate samples; bounds the window's length.
+const RATE_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+/// Spans shorter than this report no rate yet.
+const MIN_RATE_SPAN: Duration = Duration::from_millis(250);
+
+/// Counters at one point in time, for rate calculation.
+#[derive(Clone, Copy)]
+struct RateSample {
+    at: Instant,
+    bytes: u64,
+    chunks: u64,
+}
+
 /// Lock-free progress tracker using atomics.
 /// File metadata is set once via `init_files()` (backed by OnceLock),
 /// The TUI calls `snapshot()` on its render tick to build display data.
@@ -24,6 +49,13 @@ pub struct ProgressTracker {
     files_total: AtomicU64,
     total_chunks: AtomicU64,
     completed_chunks: AtomicU64,
+    download_target: AtomicU64,
+    downloads_completed: AtomicU64,
+    bandwidth: BandwidthStats,
+    events: watch::Sender<TransferEvent>,
+    retries: AtomicU64,
+    client: OnceLock<(String, Instant)>,
+    rate_samples: Mutex<VecDeque<RateSample>>,
 }
 
 impl Default for ProgressTracker {
@@ -41,6 +73,13 @@ impl ProgressTracker {
             files_total: AtomicU64::new(0),
             total_chunks: AtomicU64::new(0),
             completed_chunks: AtomicU64::new(0),
+            download_target: AtomicU64::new(1),
+            downloads_completed: AtomicU64::new(0),
+            bandwidth: BandwidthStats::default(),
+            events: watch::Sender::new(TransferEvent::default()),
+            retries: AtomicU64::new(0),
+            client: OnceLock::new(),
+            rate_samples: Mutex::new(VecDeque::new()),
         }
     }
 
@@ -75,6 +114,19 @@ impl ProgressTracker {
         }
     }
 
+    /// Undo one `increment_file`, e.g. for a chunk the client never received.
+    pub fn decrement_file(&self, file_index: usize) {
+        if let Some(fs) = self.file_state.get() {
+            if file_index < fs.done_chunks.len()
+                && fs.done_chunks[file_index]
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                    .is_ok()
+            {
+                self.completed_chunks.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
     /// Called when a file transfer is fully complete.
     /// Idempotent — repeated calls for the same file_index are no-ops.
     pub fn file_complete(&self, file_index: usize) {
@@ -94,11 +146,15 @@ impl ProgressTracker {
     }
 
     /// Mark a file as failed with an error message.
+    /// Keeps one entry per file; a later failure replaces the message.
     pub fn file_failed(&self, file_index: usize, error: String) {
         if let Some(fs) = self.file_state.get() {
             if file_index < fs.names.len() {
-                let mut errors = fs.errors.lock().unwrap();
-                errors.push((file_index, error));
+                let mut errors = fs.errors.lock().unwrap_or_else(PoisonError::into_inner);
+                match errors.iter_mut().find(|(idx, _)| *idx == file_index) {
+                    Some((_, message)) => *message = error,
+                    None => errors.push((file_index, error)),
+                }
             }
         }
     }
@@ -108,7 +164,7 @@ impl ProgressTracker {
     pub fn file_skipped(&self, file_index: usize, reason: String) {
         if let Some(fs) = self.file_state.get() {
             if file_index < fs.names.len() {
-                let mut skipped = fs.skipped.lock().unwrap();
+                let mut skipped = fs.skipped.lock().unwrap_or_else(PoisonError::into_inner);
                 skipped.entry(file_index).or_insert(reason);
                 if !fs.completed[file_index].swap(true, Ordering::AcqRel) {
                     self.files_completed.fetch_add(1, Ordering::Relaxed);
@@ -123,14 +179,111 @@ impl ProgressTracker {
         }
     }
 
+    /// Set how many distinct client downloads end the transfer (minimum 1).
+    pub fn set_download_target(&self, target: u64) {
+        self.download_target.store(target.max(1), Ordering::Relaxed);
+    }
+
+    /// Return how many distinct client downloads end the transfer.
+    pub fn download_target(&self) -> u64 {
+        self.download_target.load(Ordering::Relaxed)
+    }
+
+    /// Record one finished client download and return the new count.
+    pub fn record_download(&self) -> u64 {
+        self.downloads_completed.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// Clear per-file progress so the next client download starts from zero.
+    pub fn reset_files(&self) {
+        let Some(fs) = self.file_state.get() else {
+            return;
+        };
+
+        for done in &fs.done_chunks {
+            done.store(0, Ordering::Relaxed);
+        }
+        for completed in &fs.completed {
+            completed.store(false, Ordering::Relaxed);
+        }
+        fs.skipped
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        fs.errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.files_completed.store(0, Ordering::Relaxed);
+        self.completed_chunks.store(0, Ordering::Relaxed);
+        self.rate_samples
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Subscribe to lifecycle events. The channel only changes when a terminal
+    /// event is published; progress is sampled through `event()`/`snapshot()`.
+    pub fn subscribe(&self) -> watch::Receiver<TransferEvent> {
+        self.events.subscribe()
+    }
+
+    /// Current lifecycle event: the terminal outcome if one was published,
+    /// otherwise overall chunk progress.
+    pub fn event(&self) -> TransferEvent {
+        let current = self.events.borrow();
+        if current.is_terminal() {
+            return current.clone();
+        }
+        let (completed, total) = self.get_progress();
+        if total == 0 {
+            return TransferEvent::Progress(0.0);
+        }
+        TransferEvent::Progress((completed as f64 / total as f64 * 100.0).min(100.0))
+    }
+
+    /// Publish an explicit successful completion.
+    pub fn complete(&self) {
+        let stats = TransferStats {
+            files: self.files_total.load(Ordering::Relaxed) as usize,
+            bytes: self.bandwidth.summary().payload_bytes,
+        };
+        self.finish(TransferEvent::Completed { stats });
+    }
+
+    /// Publish a transfer failure.
+    pub fn fail(&self, reason: String) {
+        self.finish(TransferEvent::Failed { reason });
+    }
+
+    /// Publish that the transfer was aborted before completing.
+    pub fn cancel(&self) {
+        self.finish(TransferEvent::Cancelled);
+    }
+
+    // The first terminal event wins; later ones are ignored.
+    fn finish(&self, event: TransferEvent) {
+        self.events.send_if_modified(|current| {
+            if current.is_terminal() {
+                return false;
+            }
+            *current = event;
+            true
+        });
+    }
+
     /// Build a snapshot for TUI rendering.
     pub fn snapshot(&self) -> TransferProgress {
+        let event = self.event();
         let Some(fs) = self.file_state.get() else {
-            return TransferProgress::default();
+            return TransferProgress {
+                event,
+                ..TransferProgress::default()
+            };
         };
 
-        let errors = fs.errors.lock().unwrap();
-        let skipped = fs.skipped.lock().unwrap();
+        let errors = fs.errors.lock().unwrap_or_else(PoisonError::into_inner);
+        let skipped = fs.skipped.lock().unwrap_or_else(PoisonError::into_inner);
         let files = fs
             .names
             .iter()
@@ -158,11 +311,99 @@ impl ProgressTracker {
             })
             .collect();
 
+        let target = self.download_target();
+        let downloads = (target > 1).then(|| {
+            (
+                self.downloads_completed.load(Ordering::Relaxed) as usize,
+                target as usize,
+            )
+        });
+
         TransferProgress {
             files,
             completed: self.files_completed.load(Ordering::Relaxed) as usize,
             total: self.files_total.load(Ordering::Relaxed) as usize,
+            downloads,
+            event,
+            throughput: self.throughput(),
+        }
+    }
+
+    /// Record the first client to claim the session; starts the transfer clock.
+    pub fn record_client(&self, lock_token: &str) {
+        let prefix: String = lock_token.chars().take(CLIENT_ID_PREFIX_LEN).collect();
+        let _ = self.client.set((prefix, Instant::now()));
+    }
+
+    /// Short, non-secret prefix of the first client's lock token.
+    pub fn client_id(&self) -> Option<&str> {
+        self.client.get().map(|(id, _)| id.as_str())
+    }
+
+    /// Time since the first client claimed the session (zero before that).
+    pub fn elapsed(&self) -> Duration {
+        self.client
+            .get()
+            .map(|(_, started)| started.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Count a repeated chunk request (client retry).
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of repeated chunk requests seen so far.
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Payload/overhead byte counters for this transfer.
+    pub fn bandwidth(&self) -> &BandwidthStats {
+        &self.bandwidth
+    }
+
+    /// Payload rate over the last few seconds and the time left at that rate.
+    pub fn throughput(&self) -> Throughput {
+        self.throughput_at(Instant::now())
+    }
+
+    fn throughput_at(&self, now: Instant) -> Throughput {
+        let (completed, total) = self.get_progress();
+        let current = RateSample {
+            at: now,
+            bytes: self.bandwidth.summary().payload_bytes,
+            chunks: completed,
+        };
+
+        let mut samples = self
+            .rate_samples
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Keep one sample at or past the window edge so the span covers it
+        while samples.len() >= 2 && now.saturating_duration_since(samples[1].at) >= RATE_WINDOW {
+            samples.pop_front();
+        }
+        if samples
+            .back()
+            .is_none_or(|last| now.saturating_duration_since(last.at) >= RATE_SAMPLE_INTERVAL)
+        {
+            samples.push_back(current);
         }
+
+        let oldest = samples[0];
+        let span = now.saturating_duration_since(oldest.at);
+        if span < MIN_RATE_SPAN {
+            return Throughput::default();
+        }
+        let secs = span.as_secs_f64();
+        let bytes_per_sec = current.bytes.saturating_sub(oldest.bytes) as f64 / secs;
+        let chunks_per_sec = current.chunks.saturating_sub(oldest.chunks) as f64 / secs;
+        let eta = (chunks_per_sec > 0.0).then(|| {
+            Duration::from_secs_f64(total.saturating_sub(completed) as f64 / chunks_per_sec)
+        });
+
+        Throughput { bytes_per_sec, eta }
     }
 
     pub fn get_progress(&self) -> (u64, u64) {
@@ -175,7 +416,8 @@ impl ProgressTracker {
 #[cfg(test)]
 mod tests {
     use super::ProgressTracker;
-    use crate::common::FileStatus;
+    use crate::common::{FileStatus, TransferEvent, TransferStats};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn reports_empty_snapshot_before_init() {
@@ -188,6 +430,21 @@ mod tests {
         assert_eq!(tracker.get_progress(), (0, 0));
     }
 
+    #[test]
+    fn decrement_rolls_back_one_chunk_and_stops_at_zero() {
+        let tracker = ProgressTracker::new();
+        tracker.init_files(vec!["a.bin".into()], vec![4]);
+
+        tracker.increment_file(0);
+        tracker.increment_file(0);
+        tracker.decrement_file(0);
+        assert_eq!(tracker.get_progress(), (1, 4));
+
+        tracker.decrement_file(0);
+        tracker.decrement_file(0);
+        assert_eq!(tracker.get_progress(), (0, 4));
+    }
+
     #[test]
     fn tracks_progress_and_completion_transitions() {
         let tracker = ProgressTracker::new();
@@ -222,6 +479,61 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn per_file_statuses_follow_chunk_increments_across_files() {
+        let tracker = ProgressTracker::new();
+        tracker.init_files(vec!["a.bin".into(), "b.bin".into()], vec![2, 4]);
+
+        tracker.increment_file(0);
+        tracker.increment_file(1);
+        let snapshot = tracker.snapshot();
+        assert!(matches!(snapshot.files[0].status, FileStatus::InProgress(p) if p == 50.0));
+        assert!(matches!(snapshot.files[1].status, FileStatus::InProgress(p) if p == 25.0));
+
+        // The last acknowledged chunk completes a file before the client's
+        // final /complete; the session stays open until then
+        tracker.increment_file(0);
+        let snapshot = tracker.snapshot();
+        assert!(matches!(snapshot.files[0].status, FileStatus::Complete));
+        assert!(matches!(
+            snapshot.files[1].status,
+            FileStatus::InProgress(_)
+        ));
+        assert_eq!(snapshot.completed, 0);
+        assert!(!snapshot.is_finished());
+
+        tracker.file_failed(1, "read error".into());
+        let snapshot = tracker.snapshot();
+        assert!(matches!(snapshot.files[0].status, FileStatus::Complete));
+        assert!(matches!(
+            snapshot.files[1].status,
+            FileStatus::Failed(ref msg) if msg == "read error"
+        ));
+        assert_eq!(tracker.get_progress(), (3, 6));
+    }
+
+    #[test]
+    fn repeated_failures_keep_one_entry_per_file() {
+        let tracker = ProgressTracker::new();
+        tracker.init_files(vec!["a.bin".into(), "b.bin".into()], vec![1, 1]);
+
+        tracker.file_failed(0, "first".into());
+        tracker.file_failed(0, "second".into());
+        tracker.file_failed(1, "other".into());
+
+        let file_state = tracker.file_state.get().unwrap();
+        let errors = file_state.errors.lock().unwrap();
+        assert_eq!(
+            *errors,
+            [(0, "second".to_string()), (1, "other".to_string())]
+        );
+        drop(errors);
+        assert!(matches!(
+            tracker.snapshot().files[0].status,
+            FileStatus::Failed(ref msg) if msg == "second"
+        ));
+    }
+
     #[test]
     fn failed_status_has_precedence_over_complete() {
         let tracker = ProgressTracker::new();
@@ -285,4 +597,83 @@ mod tests {
         assert_eq!(snapshot.completed, 0);
         assert!(matches!(snapshot.files[0].status, FileStatus::Waiting));
     }
+
+    #[test]
+    fn event_reports_progress_until_a_terminal_outcome() {
+        let tracker = ProgressTracker::new();
+        tracker.init_files(vec!["a.bin".into()], vec![4]);
+        tracker.increment_file(0);
+
+        assert_eq!(tracker.event(), TransferEvent::Progress(25.0));
+        assert!(!tracker.snapshot().is_finished());
+
+        tracker.bandwidth().record_chunk(10);
+        tracker.complete();
+
+        let snapshot = tracker.snapshot();
+        assert!(snapshot.is_finished());
+        assert_eq!(
+            snapshot.event,
+            TransferEvent::Completed {
+                stats: TransferStats {
+                    files: 1,
+                    bytes: 10
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn first_terminal_event_wins() {
+        let tracker = ProgressTracker::new();
+        let mut events = tracker.subscribe();
+
+        tracker.cancel();
+        tracker.fail("late".into());
+        tracker.complete();
+
+        assert!(events.has_changed().unwrap());
+        assert_eq!(*events.borrow_and_update(), TransferEvent::Cancelled);
+        assert!(!events.has_changed().unwrap());
+        assert_eq!(tracker.event(), TransferEvent::Cancelled);
+    }
+
+    #[test]
+    fn throughput_tracks_rate_and_survives_brief_stall() {
+        let tracker = ProgressTracker::new();
+        tracker.init_files(vec!["a.bin".into()], vec![100]);
+        let start = Instant::now();
+        assert_eq!(tracker.throughput_at(start).eta, None);
+
+        // One 1 MB chunk every 100 ms: 10 MB/s
+        let mut at = start;
+        for _ in 0..60 {
+            at += Duration::from_millis(100);
+            tracker.increment_file(0);
+            tracker.bandwidth().record_chunk(1_000_000);
+            tracker.throughput_at(at);
+        }
+        let steady = tracker.throughput_at(at);
+        assert!(
+            (steady.bytes_per_sec - 10_000_000.0).abs() < 500_000.0,
+            "rate {}",
+            steady.bytes_per_sec
+        );
+        // 40 chunks left at 10 chunks/s
+        let eta = steady.eta.expect("eta while progressing").as_secs_f64();
+        assert!((eta - 4.0).abs() < 0.3, "eta {eta}");
+
+        // A one-second stall only dents the windowed rate
+        for _ in 0..10 {
+            at += Duration::from_millis(100);
+            tracker.throughput_at(at);
+        }
+        let stalled = tracker.throughput_at(at);
+        assert!(
+            stalled.bytes_per_sec > 7_000_000.0 && stalled.bytes_per_sec < 9_000_000.0,
+            "rate {}",
+            stalled.bytes_per_sec
+        );
+        assert!(stalled.eta.is_some());
+    }
 }
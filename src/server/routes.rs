@@ -1,50 +1,141 @@
 //! Router definitions for send and receive modes
 
 use crate::{
+    common::{config::receive_body_limit, AppError},
     receive::{self, ReceiveAppState},
+    relay::{self, RelayState},
     send::{self, SendAppState},
+    server::auth::{self, AuthGate, AuthSecret},
+    server::limits,
     ui::web,
 };
-use axum::{extract::DefaultBodyLimit, routing::*, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{Method, StatusCode},
+    middleware,
+    routing::*,
+    Router,
+};
+
+/// Reject methods a route does not serve with a JSON 405.
+///
+/// Axum fills in the `Allow` header from the route's registered methods.
+/// Must be applied after every route is added.
+async fn method_not_allowed(method: Method) -> AppError {
+    AppError::MethodNotAllowed(format!("{} is not supported on this route", method))
+}
+
+/// Put the `--auth-token` check in front of `routes`, when a secret is set.
+///
+/// `GET /auth/check` lets the page find out whether it has to ask for the PIN.
+fn gate_with_auth_secret<S>(
+    routes: Router<S>,
+    session_token: &str,
+    secret: Option<&AuthSecret>,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let routes = routes.route("/auth/check", get(|| async { StatusCode::NO_CONTENT }));
+    match secret {
+        Some(secret) => routes.route_layer(middleware::from_fn_with_state(
+            AuthGate::new(session_token, secret.clone()),
+            auth::require_auth_secret,
+        )),
+        None => routes,
+    }
+}
+
+/// Answer `503` once `max` requests are already being handled (`--max-connections`).
+pub fn with_request_cap(router: Router, max: usize) -> Router {
+    router.layer(middleware::from_fn_with_state(
+        limits::RequestCap::new(max),
+        limits::cap_in_flight,
+    ))
+}
 
 /// Build the router for send endpoints and web assets.
 pub fn create_send_router(state: &SendAppState) -> Router {
-    Router::new()
-        .route("/health", get(|| async { "OK" }))
+    let gated = Router::new()
+        .route("/send/claim", post(send::handlers::claim_handler))
         .route("/send/manifest", get(send::handlers::manifest_handler))
+        .route("/send/ttl", get(send::handlers::ttl_handler))
         .route(
             "/send/:file_index/chunk/:chunk_index",
             get(send::handlers::send_handler),
         )
-        .route("/send/complete", post(send::handlers::complete_download))
+        .route(
+            "/send/:file_index/raw",
+            get(send::handlers::raw_file_handler),
+        )
+        .route(
+            "/send/:file_index/hash",
+            get(send::handlers::file_hash_handler),
+        )
+        .route("/send/complete", post(send::handlers::complete_download));
+
+    Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .route("/status", get(send::handlers::status_handler))
+        .merge(gate_with_auth_secret(
+            gated,
+            state.session.token(),
+            state.auth_secret.as_ref(),
+        ))
+        .route("/send/revoke", post(send::handlers::revoke_handler))
         .route("/send", get(|| async { web::serve_download_page() }))
         .route("/download.js", get(|| async { web::serve_download_js() }))
         .route("/styles.css", get(|| async { web::serve_shared_css() }))
         .route("/shared.js", get(|| async { web::serve_shared_js() }))
+        .method_not_allowed_fallback(method_not_allowed)
         .with_state(state.clone())
 }
 
 /// Start a loopback HTTP server plus tunnel and run one session.
 pub fn create_receive_router(state: &ReceiveAppState) -> Router {
-    Router::new()
-        .route("/health", get(|| async { "OK" }))
+    let gated = Router::new()
         .route(
             "/receive/manifest",
             post(receive::handlers::receive_manifest),
         )
+        .route("/receive/resume", post(receive::handlers::resume_upload))
         .route("/receive/chunk", post(receive::handlers::receive_handler))
         .route(
             "/receive/finalize",
             post(receive::handlers::finalize_upload),
         )
-        .route("/receive", get(|| async { web::serve_upload_page() }))
         .route(
             "/receive/complete",
             post(receive::handlers::complete_transfer),
-        )
+        );
+
+    Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .route("/status", get(receive::handlers::status_handler))
+        .merge(gate_with_auth_secret(
+            gated,
+            state.session.token(),
+            state.auth_secret.as_ref(),
+        ))
+        .route("/receive", get(|| async { web::serve_upload_page() }))
         .route("/upload.js", get(|| async { web::serve_upload_js() }))
         .route("/styles.css", get(|| async { web::serve_shared_css() }))
         .route("/shared.js", get(|| async { web::serve_shared_js() }))
+        .method_not_allowed_fallback(method_not_allowed)
+        .with_state(state.clone())
+        .layer(DefaultBodyLimit::max(receive_body_limit(
+            state.config.chunk_size,
+        )))
+}
+
+/// Build the router for `archdrop relay`. Bodies stream through unread.
+pub fn create_relay_router(state: &RelayState) -> Router {
+    Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .route(
+            "/relay",
+            post(relay::handlers::upload_handler).get(relay::handlers::download_handler),
+        )
+        .method_not_allowed_fallback(method_not_allowed)
         .with_state(state.clone())
-        .layer(DefaultBodyLimit::max(25 * 1024 * 1024))
 }
@@ -1,23 +1,93 @@
 //! Runtime lifecycle: start servers, run session UI loop, and shutdown.
 
 use crate::common::config::{AppConfig, Transport};
-use crate::common::TransferState;
+use crate::common::{LinkFragment, Session, TransferEvent, TransferState};
 use crate::crypto::types::Nonce;
 use crate::server::progress::ProgressTracker;
+use crate::server::stats::StatsReport;
+use crate::server::webhook::{CompletionEvent, Webhook};
 use crate::server::ServerInstance;
-use crate::transport::local::{get_local_ip, start_local_server, BindScope, Protocol};
-use crate::transport::tunnel::Tunnel;
-use crate::ui::tui::{generate_qr, spawn_tui, spinner, spinner_error, spinner_success, TuiConfig};
+use crate::transport::local::{
+    other_addresses_hint, resolve_bind_scope, start_local_server, url_host, AddressSelector,
+    BindScope, Protocol,
+};
+use crate::transport::mdns::Advertisement;
+use crate::transport::tunnel::{self, StartRetry, TunnelHandle, TunnelProvider};
+use crate::ui::clipboard;
+use crate::ui::tui::{
+    generate_qr, spawn_tui, spinner, spinner_error, spinner_success, write_qr_file, TuiConfig,
+};
 use anyhow::{Context, Result};
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
+/// How often the idle countdown checks whether the session was claimed.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Time in-flight responses get to finish at shutdown unless overridden.
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+/// How often shutdown checks whether open connections have closed.
+const GRACE_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Per-session behavior shared by send and receive.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// Write a JSON stats file here when the session ends
+    pub stats_out: Option<PathBuf>,
+    /// Shut down if nobody claims the session within this long
+    pub idle_timeout: Option<Duration>,
+    /// Stdout carries transfer data (`receive --tar -`); keep the TUI and
+    /// status text off it
+    pub stdout_is_data: bool,
+    /// Print newline-delimited JSON progress on stdout instead of the TUI;
+    /// the link goes to stderr
+    pub json_progress: bool,
+    /// Print the QR code and link once, then plain progress lines on stderr
+    pub plain_output: bool,
+    /// Copy the link to the system clipboard and report how that went
+    pub clipboard: bool,
+    /// Write the QR code here too (PNG for `.png`, SVG otherwise)
+    pub qr_file: Option<PathBuf>,
+    /// How long in-flight responses may run after shutdown starts
+    /// (default [`DEFAULT_SHUTDOWN_GRACE`])
+    pub shutdown_grace: Option<Duration>,
+    /// Which local address direct HTTPS links use
+    pub address: AddressSelector,
+    /// Notified once the transfer completes
+    pub webhook: Option<Webhook>,
+    /// Advertise the session over mDNS (direct local HTTPS only)
+    pub mdns: bool,
+}
+
 fn no_tui_enabled() -> bool {
     std::env::var("NO_TUI").is_ok()
 }
 
+impl SessionOptions {
+    /// True when only the bare link is printed up front, with no TUI or
+    /// plain-mode header to carry it.
+    fn bare_link_only(&self) -> bool {
+        self.stdout_is_data || self.json_progress || (no_tui_enabled() && !self.plain_output)
+    }
+}
+
+/// Print the link without the TUI, on stderr when stdout carries data.
+fn print_headless_url(url: &str, warning: Option<&str>, stdout_is_data: bool) -> Result<()> {
+    let stderr = std::io::stderr();
+    let mut stderr = stderr.lock();
+    if stdout_is_data {
+        let mut url_out = std::io::stderr();
+        emit_no_tui_output(url, warning, &mut url_out, &mut stderr)
+    } else {
+        let stdout = std::io::stdout();
+        emit_no_tui_output(url, warning, &mut stdout.lock(), &mut stderr)
+    }
+    .context("failed to write NO_TUI output")
+}
+
 fn local_security_warning() -> &'static str {
     "WARNING: Local mode exposes this transfer to your LAN (0.0.0.0).\n\
 On shared/untrusted Wi-Fi, do NOT bypass browser certificate warnings."
@@ -36,6 +106,23 @@ fn emit_no_tui_output(
     Ok(())
 }
 
+/// Build the shareable transfer URL with secrets kept in the fragment.
+fn transfer_url<S: TransferState>(
+    base_url: &str,
+    service: &str,
+    state: &S,
+    nonce: &Nonce,
+    compact: bool,
+) -> String {
+    let session = state.session();
+    let mut fragment = LinkFragment::new(session.token(), session.session_key(), nonce)
+        .with_suite(session.cipher_suite());
+    if let Some(salt) = session.key_salt() {
+        fragment = fragment.with_salt(salt.clone());
+    }
+    format!("{}/{}#{}", base_url, service, fragment.encode(compact))
+}
+
 /// Start a direct HTTPS server and run one transfer session.
 pub async fn start_https<S: TransferState>(
     server: ServerInstance,
@@ -44,6 +131,7 @@ pub async fn start_https<S: TransferState>(
     transport: Transport,
     config: &AppConfig,
     tracker: Arc<ProgressTracker>,
+    options: SessionOptions,
 ) -> Result<u16> {
     let service = app_state.service_path();
     let ServerInstance {
@@ -53,11 +141,13 @@ pub async fn start_https<S: TransferState>(
         display_overflow_count,
     } = server;
 
+    let bind_scope = resolve_bind_scope(&options.address)?;
     let (port, server_handle) = match start_local_server(
         app,
         Protocol::Https,
-        BindScope::AllInterfaces,
+        bind_scope,
         config.port(transport),
+        config.network,
     )
     .await
     {
@@ -66,35 +156,52 @@ pub async fn start_https<S: TransferState>(
     };
 
     // Use local IP instead of localhost for network access
-    let local_ip = get_local_ip().unwrap_or_else(|_| "127.0.0.1".to_string());
-    let base_url = format!("https://{}:{}", local_ip, port);
-    let url = format!(
-        "{}/{}#token={}&key={}&nonce={}",
-        base_url,
+    let local_ip = bind_scope.advertised_ip();
+    let base_url = format!("https://{}:{}", url_host(&local_ip), port);
+    let url = transfer_url(
+        &base_url,
         service,
-        app_state.session().token(),
-        app_state.session().session_key_b64(),
-        nonce.to_base64()
+        &app_state,
+        &nonce,
+        config.tui.compact_url,
     );
 
     let initial_warning = match transport {
         Transport::Local => Some(local_security_warning().to_string()),
-        Transport::Cloudflare | Transport::Tailscale => None,
+        Transport::Cloudflare | Transport::Tailscale | Transport::Ngrok => None,
+    };
+    // A guessed address may sit on a VPN or bridge; show what else exists
+    let initial_warning = match (initial_warning, &options.address) {
+        (Some(warning), AddressSelector::Auto) => match other_addresses_hint(&local_ip) {
+            Some(hint) => Some(format!("{}\n{}", warning, hint)),
+            None => Some(warning),
+        },
+        (warning, _) => warning,
     };
 
-    if no_tui_enabled() {
-        let stdout = std::io::stdout();
-        let stderr = std::io::stderr();
-        let mut stdout = stdout.lock();
-        let mut stderr = stderr.lock();
-        emit_no_tui_output(&url, initial_warning.as_deref(), &mut stdout, &mut stderr)
-            .context("failed to write NO_TUI output")?;
+    if options.bare_link_only() {
+        print_headless_url(
+            &url,
+            initial_warning.as_deref(),
+            options.stdout_is_data || options.json_progress,
+        )?;
     }
 
+    // Loopback-only listeners are unreachable from the LAN, so stay quiet
+    let advertisement = match local_ip.parse::<std::net::IpAddr>() {
+        Ok(ip) if options.mdns && transport == Transport::Local && !ip.is_loopback() => {
+            Advertisement::start(ip, port, service)
+                .inspect_err(|e| tracing::warn!("mDNS advertisement disabled: {:#}", e))
+                .ok()
+        }
+        _ => None,
+    };
+
     run_session(
         server_handle,
         app_state,
         None,
+        advertisement,
         display_name,
         display_files,
         display_overflow_count,
@@ -103,6 +210,7 @@ pub async fn start_https<S: TransferState>(
         initial_warning,
         transport,
         config,
+        options,
     )
     .await?;
     Ok(port)
@@ -116,6 +224,7 @@ pub async fn start_tunnel<S: TransferState>(
     transport: Transport,
     config: &AppConfig,
     tracker: Arc<ProgressTracker>,
+    options: SessionOptions,
 ) -> Result<u16> {
     let service = app_state.service_path();
     let ServerInstance {
@@ -130,6 +239,7 @@ pub async fn start_tunnel<S: TransferState>(
         Protocol::Http,
         BindScope::Loopback,
         config.port(transport),
+        config.network,
     )
     .await
     {
@@ -137,46 +247,28 @@ pub async fn start_tunnel<S: TransferState>(
         Err(err) => return Err(err),
     };
 
-    let tunnel_spinner = spinner(match transport {
-        Transport::Cloudflare => "Starting Cloudflare tunnel...",
-        Transport::Tailscale => "Starting Tailscale tunnel...",
-        Transport::Local => "Starting tunnel...",
-    });
-
-    let tunnel = match Tunnel::start(transport, port).await {
-        Ok(tunnel) => {
-            spinner_success(&tunnel_spinner, "Tunnel established");
-            tunnel
-        }
-        Err(err) => {
-            spinner_error(&tunnel_spinner, "Failed to establish tunnel");
-            return Err(err);
-        }
-    };
+    let provider =
+        tunnel::provider_for(transport).context("Local transport does not use tunneling")?;
+    let tunnel = establish_tunnel(provider.as_ref(), port, tunnel::DEFAULT_START_RETRY).await?;
 
     // Ensure tunnel URL doesn't have trailing slash
     let tunnel_url = tunnel.url().trim_end_matches('/');
-    let url = format!(
-        "{}/{}#token={}&key={}&nonce={}",
+    let url = transfer_url(
         tunnel_url,
         service,
-        app_state.session().token(),
-        app_state.session().session_key_b64(),
-        nonce.to_base64()
+        &app_state,
+        &nonce,
+        config.tui.compact_url,
     );
-    if no_tui_enabled() {
-        let stdout = std::io::stdout();
-        let stderr = std::io::stderr();
-        let mut stdout = stdout.lock();
-        let mut stderr = stderr.lock();
-        emit_no_tui_output(&url, None, &mut stdout, &mut stderr)
-            .context("failed to write NO_TUI output")?;
+    if options.bare_link_only() {
+        print_headless_url(&url, None, options.stdout_is_data || options.json_progress)?;
     }
 
     run_session(
         server_handle,
         app_state,
         Some(tunnel),
+        None,
         display_name,
         display_files,
         display_overflow_count,
@@ -185,47 +277,182 @@ pub async fn start_tunnel<S: TransferState>(
         None,
         transport,
         config,
+        options,
     )
     .await?;
 
     Ok(port)
 }
 
+/// A listening server and its link, for sessions driven without the TUI.
+pub(super) struct Bound {
+    pub(super) port: u16,
+    pub(super) url: String,
+    pub(super) server_handle: axum_server::Handle,
+    pub(super) tunnel: Option<TunnelHandle>,
+}
+
+/// Start serving `app` for `transport` and build its link, printing nothing.
+pub(super) async fn bind_quietly<S: TransferState>(
+    app: axum::Router,
+    state: &S,
+    nonce: &Nonce,
+    transport: Transport,
+    config: &AppConfig,
+    address: &AddressSelector,
+) -> Result<Bound> {
+    let service = state.service_path();
+    match transport {
+        Transport::Local => {
+            let bind_scope = resolve_bind_scope(address)?;
+            let (port, server_handle) = start_local_server(
+                app,
+                Protocol::Https,
+                bind_scope,
+                config.port(transport),
+                config.network,
+            )
+            .await?;
+            let base_url = format!("https://{}:{}", url_host(&bind_scope.advertised_ip()), port);
+            Ok(Bound {
+                port,
+                url: transfer_url(&base_url, service, state, nonce, config.tui.compact_url),
+                server_handle,
+                tunnel: None,
+            })
+        }
+        Transport::Cloudflare | Transport::Tailscale | Transport::Ngrok => {
+            let (port, server_handle) = start_local_server(
+                app,
+                Protocol::Http,
+                BindScope::Loopback,
+                config.port(transport),
+                config.network,
+            )
+            .await?;
+            let provider = tunnel::provider_for(transport)
+                .context("Local transport does not use tunneling")?;
+            let tunnel =
+                match tunnel::open(provider.as_ref(), port, tunnel::DEFAULT_START_RETRY).await {
+                    Ok(tunnel) => tunnel,
+                    Err(err) => {
+                        stop_server(&server_handle, Duration::ZERO).await;
+                        return Err(err);
+                    }
+                };
+            let tunnel_url = tunnel.url().trim_end_matches('/');
+            Ok(Bound {
+                port,
+                url: transfer_url(tunnel_url, service, state, nonce, config.tui.compact_url),
+                server_handle,
+                tunnel: Some(tunnel),
+            })
+        }
+    }
+}
+
+/// Open the provider's tunnel to `port` behind a startup spinner.
+async fn establish_tunnel(
+    provider: &dyn TunnelProvider,
+    port: u16,
+    retry: StartRetry,
+) -> Result<TunnelHandle> {
+    let tunnel_spinner = spinner(&format!("Starting {} tunnel...", provider.display_name()));
+    match tunnel::open(provider, port, retry).await {
+        Ok(tunnel) => {
+            spinner_success(&tunnel_spinner, "Tunnel established");
+            Ok(tunnel)
+        }
+        Err(err) => {
+            spinner_error(&tunnel_spinner, "Failed to establish tunnel");
+            Err(err)
+        }
+    }
+}
+
 /// Run transfer session loop, TUI, signal handling, and cleanup.
 #[allow(clippy::too_many_arguments)]
 async fn run_session<S: TransferState>(
     server_handle: axum_server::Handle,
     state: S,
-    mut tunnel: Option<Tunnel>,
+    mut tunnel: Option<TunnelHandle>,
+    advertisement: Option<Advertisement>,
     display_name: String,
     display_files: Vec<String>,
     display_overflow_count: Option<usize>,
     tracker: Arc<ProgressTracker>,
     url: String,
-    initial_status_message: Option<String>,
+    mut initial_status_message: Option<String>,
     transport: Transport,
     config: &AppConfig,
+    options: SessionOptions,
 ) -> Result<()> {
     // CancellationToken for TUI / main loop
     let root_token = CancellationToken::new();
     let tui_token = root_token.child_token();
 
+    // Held until the session ends; on X11/Wayland the copied link goes with it
+    let _clipboard = options.clipboard.then(|| {
+        let (held, note) = clipboard::copy_link(&url);
+        if options.bare_link_only() {
+            eprintln!("{}", note);
+        }
+        initial_status_message = Some(match initial_status_message.take() {
+            Some(message) => format!("{}\n{}", message, note),
+            None => note,
+        });
+        held
+    });
+
+    if let Some(path) = &options.qr_file {
+        let note = match write_qr_file(&url, path) {
+            Ok(()) => format!("QR code written to {}", path.display()),
+            Err(err) => {
+                tracing::warn!("{:#}", err);
+                format!("Could not write QR code: {:#}", err)
+            }
+        };
+        if options.bare_link_only() {
+            eprintln!("{}", note);
+        }
+        initial_status_message = Some(match initial_status_message.take() {
+            Some(message) => format!("{}\n{}", message, note),
+            None => note,
+        });
+    }
+
+    // Without the TUI's `x` key, the sender revokes over HTTP instead
+    if !state.is_receiving() && (options.bare_link_only() || options.plain_output) {
+        eprintln!(
+            "To revoke the link: POST /send/revoke with header X-Revoke-Secret: {}",
+            state.session().revoke_secret()
+        );
+    }
+
     // TUI msgs
     let (status_sender, status_receiver) = tokio::sync::watch::channel(None);
     if let Some(message) = initial_status_message {
         let _ = status_sender.send(Some(message));
     }
 
+    let outcome_tracker = tracker.clone();
+    // Pressing `x` in the TUI withdraws a send link before shutting down
+    let revoke_token = (!state.is_receiving()).then(CancellationToken::new);
+
     // Spawn TUI (can be disabled with NO_TUI=1 for debugging)
-    let tui_handle = if no_tui_enabled() {
+    let tui_handle = if options.bare_link_only() && !options.json_progress {
         // No TUI mode - poll tracker for completion
-        println!("TUI disabled. Press Ctrl+C to stop.");
+        if options.stdout_is_data {
+            eprintln!("TUI disabled while stdout carries data. Press Ctrl+C to stop.");
+        } else {
+            println!("TUI disabled. Press Ctrl+C to stop.");
+        }
         tokio::spawn(async move {
             loop {
                 tokio::select! {
                     _ = tui_token.cancelled() => break,
                     _ = tokio::time::sleep(Duration::from_millis(500)) => {
-                        if tracker.snapshot().is_complete() {
+                        if tracker.snapshot().is_finished() {
                             break;
                         }
                     }
@@ -234,17 +461,24 @@ async fn run_session<S: TransferState>(
             Ok(())
         })
     } else {
-        let qr_code = generate_qr(&url)?;
+        let qr_code = if options.json_progress {
+            String::new()
+        } else {
+            generate_qr(&url)?
+        };
         let tui_config = TuiConfig {
             is_receiving: state.is_receiving(),
             transport,
             url,
             qr_code,
-            display_name,
+            display_name: display_name.clone(),
             display_files,
             display_overflow_count,
             show_qr: config.tui.show_qr,
             show_url: config.tui.show_url,
+            json_progress: options.json_progress,
+            plain: options.plain_output,
+            revoke: revoke_token.clone(),
         };
         spawn_tui(tui_config, tracker, status_receiver, tui_token)
     };
@@ -260,14 +494,87 @@ async fn run_session<S: TransferState>(
         signal_token.cancel();
     });
 
-    // Wait for transfer completion or Ctrl+C
+    // Give up on a session nobody claims; a claim stops the countdown for good
+    let idle_task = options.idle_timeout.map(|timeout| {
+        let session = state.session().clone();
+        let idle_token = root_token.clone();
+        let idle_tracker = outcome_tracker.clone();
+        let peer = if state.is_receiving() {
+            "sender"
+        } else {
+            "receiver"
+        };
+        tokio::spawn(async move {
+            if wait_for_idle_timeout(&session, timeout).await {
+                tracing::warn!("No claim within {}s - shutting down", timeout.as_secs());
+                idle_tracker.fail(format!("Timed out waiting for {}", peer));
+                idle_token.cancel();
+            }
+        })
+    });
+
+    // Close the session once its link TTL runs out with nobody attached
+    let expiry_task = state.session().remaining_ttl().map(|_| {
+        let session = state.session().clone();
+        let expiry_token = root_token.clone();
+        let expiry_tracker = outcome_tracker.clone();
+        tokio::spawn(async move {
+            if wait_for_link_expiry(&session).await {
+                tracing::warn!("Link expired before anyone claimed it - shutting down");
+                expiry_tracker.fail("Link expired".to_string());
+                expiry_token.cancel();
+            }
+        })
+    });
+
+    // Wait for transfer completion, Ctrl+C, idle timeout, or link expiry
     tokio::select! {
         result = tui_handle => {
-            tracing::info!("Transfer completed successfully");
             let _ = result.context("TUI task failed")?;
         }
         _ = root_token.cancelled() => {}
     };
+    if revoke_token.is_some_and(|token| token.is_cancelled()) {
+        let session = state.session();
+        session.revoke(session.revoke_secret());
+    }
+
+    // No-ops if a handler already published the outcome
+    if outcome_tracker.snapshot().is_complete() {
+        outcome_tracker.complete();
+    } else {
+        outcome_tracker.cancel();
+    }
+    match outcome_tracker.event() {
+        TransferEvent::Completed { stats } => {
+            tracing::info!(
+                "Transfer completed successfully ({} files, {} bytes)",
+                stats.files,
+                stats.bytes
+            );
+        }
+        TransferEvent::Failed { reason } => tracing::error!("Transfer failed: {}", reason),
+        _ => tracing::info!("Transfer cancelled"),
+    }
+
+    // Written for every outcome so cancelled runs still leave a record
+    if let Some(path) = options.stats_out {
+        match StatsReport::from_tracker(&outcome_tracker).write(&path) {
+            Ok(()) => tracing::info!("Transfer stats written to {}", path.display()),
+            Err(e) => tracing::warn!("{:#}", e),
+        }
+    }
+
+    // A webhook that cannot be reached is logged, never fails the transfer
+    if let Some(webhook) = &options.webhook {
+        if matches!(outcome_tracker.event(), TransferEvent::Completed { .. }) {
+            let event = CompletionEvent::collect(state.session(), &display_name, &outcome_tracker);
+            match webhook.deliver(&event).await {
+                Ok(()) => tracing::info!("Completion webhook delivered"),
+                Err(e) => tracing::warn!("{:#}", e),
+            }
+        }
+    }
 
     // Cleanup
 
@@ -282,16 +589,60 @@ async fn run_session<S: TransferState>(
         }
     }
 
+    // Withdraw the mDNS record so browsers stop listing a finished session
+    drop(advertisement);
+
     // Stop the first-stage signal handler before shutdown installs its own
     ctrl_c_task.abort();
     let _ = ctrl_c_task.await;
+    if let Some(idle_task) = idle_task {
+        idle_task.abort();
+    }
+    if let Some(expiry_task) = expiry_task {
+        expiry_task.abort();
+    }
 
     // Shutdown server and drain active transfers
-    shutdown(server_handle, state, status_sender).await?;
+    let grace = options.shutdown_grace.unwrap_or(DEFAULT_SHUTDOWN_GRACE);
+    shutdown(server_handle, state, status_sender, grace).await?;
 
     Ok(())
 }
 
+/// Resolve true once `timeout` passes with the session still unclaimed.
+///
+/// Returns false as soon as a client claims it.
+async fn wait_for_idle_timeout(session: &Session, timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if !session.is_unclaimed() {
+            return false;
+        }
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        tokio::time::sleep(IDLE_POLL_INTERVAL.min(deadline - now)).await;
+    }
+}
+
+/// Resolve true once the session's link TTL runs out while still unclaimed.
+///
+/// Returns false as soon as a client claims it, or right away when the
+/// link never expires.
+pub(super) async fn wait_for_link_expiry(session: &Session) -> bool {
+    loop {
+        if !session.is_unclaimed() {
+            return false;
+        }
+        match session.remaining_ttl() {
+            None => return false,
+            Some(left) if left.is_zero() => return true,
+            Some(left) => tokio::time::sleep(IDLE_POLL_INTERVAL.min(left)).await,
+        }
+    }
+}
+
 //==========
 // SHUTDOWN
 //==========
@@ -306,9 +657,10 @@ async fn shutdown<S: TransferState>(
     server_handle: axum_server::Handle,
     state: S,
     status_sender: tokio::sync::watch::Sender<Option<String>>,
+    grace: Duration,
 ) -> Result<()> {
-    // Stop accepting new connections
-    server_handle.shutdown();
+    // Stop accepting new connections; let responses already on the wire finish
+    stop_server(&server_handle, grace).await;
     tracing::info!("Server stopped accepting new connections");
 
     // Wait for in-flight transfers to finish (Ctrl+C to force quit)
@@ -338,6 +690,23 @@ async fn shutdown<S: TransferState>(
     Ok(())
 }
 
+/// Stop accepting connections and give in-flight responses up to `grace`
+/// to complete, so a chunk is never cut off halfway through its body.
+///
+/// Open connections are force-closed once `grace` runs out.
+pub(super) async fn stop_server(server_handle: &axum_server::Handle, grace: Duration) {
+    server_handle.graceful_shutdown(Some(grace));
+
+    let deadline = tokio::time::Instant::now() + grace;
+    while server_handle.connection_count() > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(GRACE_POLL_INTERVAL).await;
+    }
+    let remaining = server_handle.connection_count();
+    if remaining > 0 {
+        tracing::warn!("Closing {} connection(s) still open after grace", remaining);
+    }
+}
+
 /// Wait for active transfers to finish, or force quit on Ctrl+C.
 async fn wait_for_transfers<S: TransferState>(
     state: &S,
@@ -447,7 +816,7 @@ mod tests {
 
         tokio::time::timeout(
             Duration::from_millis(100),
-            shutdown(handle, state.clone(), status_sender),
+            shutdown(handle, state.clone(), status_sender, DEFAULT_SHUTDOWN_GRACE),
         )
         .await
         .expect("shutdown should not wait for drain")
@@ -467,7 +836,7 @@ mod tests {
         let (status_sender, _status_receiver) = tokio::sync::watch::channel(None);
         let handle = axum_server::Handle::new();
 
-        shutdown(handle, state.clone(), status_sender)
+        shutdown(handle, state.clone(), status_sender, DEFAULT_SHUTDOWN_GRACE)
             .await
             .expect("shutdown should succeed");
 
@@ -479,6 +848,209 @@ mod tests {
         assert_eq!(state.transfer_count(), 0);
     }
 
+    /// Serve `respond` at `/`, request it, and call `stop_server` once the
+    /// handler has started. Returns the response body and how long the
+    /// shutdown took.
+    async fn stop_server_mid_request<F, Fut, R>(respond: F) -> (String, Duration)
+    where
+        F: Fn() -> Fut + Clone + Send + Sync + 'static,
+        Fut: std::future::Future<Output = R> + Send,
+        R: axum::response::IntoResponse,
+    {
+        use crate::common::config::NetworkSettings;
+        use axum::routing::get;
+
+        let (entered_tx, entered_rx) = tokio::sync::oneshot::channel::<()>();
+        let entered_tx = Arc::new(std::sync::Mutex::new(Some(entered_tx)));
+        let app = axum::Router::new().route(
+            "/",
+            get(move || {
+                let entered_tx = entered_tx.clone();
+                let respond = respond.clone();
+                async move {
+                    if let Some(tx) = entered_tx.lock().unwrap().take() {
+                        let _ = tx.send(());
+                    }
+                    respond().await
+                }
+            }),
+        );
+        let (port, handle) = start_local_server(
+            app,
+            Protocol::Http,
+            BindScope::Loopback,
+            0,
+            NetworkSettings::default(),
+        )
+        .await
+        .expect("start server");
+
+        let request = tokio::spawn(async move {
+            reqwest::get(format!("http://127.0.0.1:{}/", port))
+                .await?
+                .text()
+                .await
+        });
+        entered_rx.await.expect("handler should start");
+
+        let started = tokio::time::Instant::now();
+        stop_server(&handle, Duration::from_secs(5)).await;
+        let elapsed = started.elapsed();
+
+        let body = request
+            .await
+            .unwrap()
+            .expect("in-flight response should complete");
+        assert_eq!(handle.connection_count(), 0);
+        (body, elapsed)
+    }
+
+    #[tokio::test]
+    async fn in_flight_request_completes_during_graceful_shutdown() {
+        let (body, _) = stop_server_mid_request(|| async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            "chunk body"
+        })
+        .await;
+
+        assert_eq!(body, "chunk body");
+    }
+
+    #[tokio::test]
+    async fn streaming_response_is_drained_before_shutdown_returns() {
+        use axum::body::Body;
+        use futures::StreamExt;
+
+        // Body parts trickle out well after the handler has returned
+        let (body, elapsed) = stop_server_mid_request(|| async {
+            let parts = futures::stream::iter(0..3).then(|part| async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok::<_, std::convert::Infallible>(format!("part{};", part))
+            });
+            Body::from_stream(parts)
+        })
+        .await;
+
+        assert!(
+            elapsed >= Duration::from_millis(200),
+            "shutdown returned before the stream finished"
+        );
+        assert_eq!(body, "part0;part1;part2;");
+    }
+
+    struct MockTunnel {
+        shutdowns: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl tunnel::ActiveTunnel for MockTunnel {
+        async fn shutdown(&mut self) -> Result<()> {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockProvider {
+        started_on: std::sync::Mutex<Option<u16>>,
+        shutdowns: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl TunnelProvider for MockProvider {
+        fn display_name(&self) -> &'static str {
+            "Mock"
+        }
+
+        async fn start(&self, local_port: u16) -> Result<TunnelHandle> {
+            if self.fail {
+                anyhow::bail!("mock tunnel refused");
+            }
+            *self.started_on.lock().unwrap() = Some(local_port);
+            Ok(TunnelHandle::new(
+                format!("https://mock.example/{}/", local_port),
+                MockTunnel {
+                    shutdowns: self.shutdowns.clone(),
+                },
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn establish_tunnel_starts_provider_on_local_port() {
+        let provider = MockProvider::default();
+        let mut handle = establish_tunnel(&provider, 8443, tunnel::DEFAULT_START_RETRY)
+            .await
+            .expect("mock tunnel should start");
+
+        assert_eq!(*provider.started_on.lock().unwrap(), Some(8443));
+        assert_eq!(handle.url(), "https://mock.example/8443/");
+
+        handle.shutdown().await.expect("shutdown");
+        assert_eq!(provider.shutdowns.load(Ordering::SeqCst), 1);
+    }
+
+    const NO_RETRY: StartRetry = StartRetry {
+        attempts: 1,
+        base_delay: Duration::ZERO,
+    };
+
+    #[tokio::test]
+    async fn establish_tunnel_surfaces_provider_errors() {
+        let provider = MockProvider {
+            fail: true,
+            ..Default::default()
+        };
+        let err = establish_tunnel(&provider, 8443, NO_RETRY)
+            .await
+            .err()
+            .expect("failing provider should error");
+        assert!(err.to_string().contains("mock tunnel refused"));
+    }
+
+    #[tokio::test]
+    async fn idle_timeout_fires_when_nobody_claims() {
+        let session = Session::new(EncryptionKey::new());
+        let fired = tokio::time::timeout(
+            Duration::from_secs(2),
+            wait_for_idle_timeout(&session, Duration::from_millis(50)),
+        )
+        .await
+        .expect("idle timeout should resolve within the window");
+        assert!(fired);
+    }
+
+    #[tokio::test]
+    async fn claim_cancels_idle_timeout() {
+        let session = Session::new(EncryptionKey::new());
+        let claimer = session.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            claimer.claim(claimer.token()).expect("claim session");
+        });
+
+        assert!(!wait_for_idle_timeout(&session, Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn link_expiry_fires_for_unclaimed_session() {
+        let session = Session::new(EncryptionKey::new()).with_ttl(Duration::from_millis(50));
+        let fired = tokio::time::timeout(Duration::from_secs(2), wait_for_link_expiry(&session))
+            .await
+            .expect("link expiry should resolve within the window");
+        assert!(fired);
+    }
+
+    #[tokio::test]
+    async fn link_expiry_skips_claimed_and_ttl_free_sessions() {
+        let session = Session::new(EncryptionKey::new()).with_ttl(Duration::from_secs(5));
+        session.claim(session.token()).expect("claim session");
+        assert!(!wait_for_link_expiry(&session).await);
+
+        assert!(!wait_for_link_expiry(&Session::new(EncryptionKey::new())).await);
+    }
+
     #[test]
     fn local_security_warning_mentions_shared_network_risk() {
         let warning = local_security_warning();
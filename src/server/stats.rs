@@ -0,0 +1,109 @@
+//! Transfer statistics export (`--stats-out`).
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::common::TransferEvent;
+use crate::server::progress::ProgressTracker;
+
+/// JSON record written once a session ends, whatever the outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    /// `completed`, `failed`, `cancelled`, or `incomplete`
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub files: usize,
+    pub files_completed: usize,
+    pub bytes: u64,
+    pub duration_secs: f64,
+    pub throughput_bytes_per_sec: f64,
+    pub retries: u64,
+    pub client_id: Option<String>,
+}
+
+impl StatsReport {
+    /// Collect the current (possibly partial) statistics from a tracker.
+    pub fn from_tracker(tracker: &ProgressTracker) -> Self {
+        let (status, reason) = match tracker.event() {
+            TransferEvent::Completed { .. } => ("completed", None),
+            TransferEvent::Failed { reason } => ("failed", Some(reason)),
+            TransferEvent::Cancelled => ("cancelled", None),
+            TransferEvent::Progress(_) => ("incomplete", None),
+        };
+        let snapshot = tracker.snapshot();
+        let bytes = tracker.bandwidth().summary().payload_bytes;
+        let duration_secs = tracker.elapsed().as_secs_f64();
+        let throughput_bytes_per_sec = if duration_secs > 0.0 {
+            bytes as f64 / duration_secs
+        } else {
+            0.0
+        };
+
+        Self {
+            status,
+            reason,
+            files: snapshot.total,
+            files_completed: snapshot.completed,
+            bytes,
+            duration_secs,
+            throughput_bytes_per_sec,
+            retries: tracker.retries(),
+            client_id: tracker.client_id().map(str::to_string),
+        }
+    }
+
+    /// Write the report as pretty JSON, replacing any existing file.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self).context("Failed to serialize stats")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write stats to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_transfer_writes_partial_stats() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("stats.json");
+
+        let tracker = ProgressTracker::new();
+        tracker.init_files(vec!["a.bin".into(), "b.bin".into()], vec![1, 1]);
+        tracker.record_client("0123456789abcdef-lock");
+        tracker.bandwidth().record_chunk(512);
+        tracker.file_complete(0);
+        tracker.record_retry();
+        tracker.cancel();
+
+        StatsReport::from_tracker(&tracker)
+            .write(&path)
+            .expect("write stats");
+
+        let parsed: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).expect("valid JSON");
+        assert_eq!(parsed["status"], "cancelled");
+        assert_eq!(parsed["files"], 2);
+        assert_eq!(parsed["files_completed"], 1);
+        assert_eq!(parsed["bytes"], 512);
+        assert_eq!(parsed["retries"], 1);
+        assert_eq!(parsed["client_id"], "01234567");
+        assert!(parsed["duration_secs"].as_f64().is_some());
+        assert!(parsed.get("reason").is_none());
+    }
+
+    #[test]
+    fn failed_transfer_records_reason() {
+        let tracker = ProgressTracker::new();
+        tracker.fail("disk full".into());
+
+        let report = StatsReport::from_tracker(&tracker);
+        assert_eq!(report.status, "failed");
+        assert_eq!(report.reason.as_deref(), Some("disk full"));
+        assert_eq!(report.throughput_bytes_per_sec, 0.0);
+        assert!(report.client_id.is_none());
+    }
+}
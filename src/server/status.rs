@@ -0,0 +1,43 @@
+//! Unauthenticated `GET /status` for wrapper scripts and monitors.
+//!
+//! Only progress counters are reported to anyone. The display label names
+//! local files, so it is added only for callers presenting the session
+//! token; the token, key and lock tokens never appear here.
+
+use serde::Serialize;
+
+use crate::common::Session;
+use crate::server::progress::ProgressTracker;
+
+/// JSON body of `GET /status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferStatus {
+    /// `send` or `receive`
+    pub mode: &'static str,
+    pub chunks_completed: u64,
+    pub chunks_total: u64,
+    /// A client holds or has finished the session
+    pub claimed: bool,
+    /// Only when the request carried the session token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+impl TransferStatus {
+    pub fn collect(
+        mode: &'static str,
+        progress: &ProgressTracker,
+        session: &Session,
+        display_name: &str,
+        bearer: Option<&str>,
+    ) -> Self {
+        let (chunks_completed, chunks_total) = progress.get_progress();
+        Self {
+            mode,
+            chunks_completed,
+            chunks_total,
+            claimed: !session.is_unclaimed(),
+            display_name: (bearer == Some(session.token())).then(|| display_name.to_string()),
+        }
+    }
+}
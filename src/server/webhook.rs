@@ -0,0 +1,162 @@
+//! Completion webhook (`--webhook`).
+
+use anyhow::{ensure, Context, Result};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::common::Session;
+use crate::server::progress::ProgressTracker;
+
+/// How long one delivery attempt may take.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause before the single retry.
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// JSON body POSTed once a transfer completes. Carries no key material.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletionEvent {
+    pub token: String,
+    pub display_name: String,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    pub client_id: Option<String>,
+}
+
+impl CompletionEvent {
+    /// Describe the finished session from its tracker.
+    pub fn collect(session: &Session, display_name: &str, tracker: &ProgressTracker) -> Self {
+        Self {
+            token: session.token().to_string(),
+            display_name: display_name.to_string(),
+            bytes: tracker.bandwidth().summary().payload_bytes,
+            duration_ms: tracker.elapsed().as_millis() as u64,
+            client_id: tracker.client_id().map(str::to_string),
+        }
+    }
+}
+
+/// Endpoint told about completed transfers.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: Url,
+    client: Client,
+}
+
+impl Webhook {
+    pub fn new(url: Url) -> Result<Self> {
+        ensure!(
+            matches!(url.scheme(), "http" | "https"),
+            "Webhook URL must use http or https: {}",
+            url
+        );
+        let client = Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .context("Failed to build webhook client")?;
+        Ok(Self { url, client })
+    }
+
+    /// POST `event`, retrying once if the first attempt fails.
+    pub async fn deliver(&self, event: &CompletionEvent) -> Result<()> {
+        if let Err(e) = self.post(event).await {
+            tracing::debug!("Webhook attempt failed, retrying: {:#}", e);
+            tokio::time::sleep(WEBHOOK_RETRY_DELAY).await;
+            self.post(event).await?;
+        }
+        Ok(())
+    }
+
+    async fn post(&self, event: &CompletionEvent) -> Result<()> {
+        self.client
+            .post(self.url.clone())
+            .json(event)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Webhook {} failed", self.url))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::types::EncryptionKey;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    /// Local endpoint recording each body; the first `failures` requests get a 500.
+    async fn mock_endpoint(failures: usize) -> (Url, Received) {
+        async fn record(
+            State((received, failures)): State<(Received, usize)>,
+            Json(body): Json<serde_json::Value>,
+        ) -> StatusCode {
+            let mut received = received.lock().unwrap();
+            received.push(body);
+            if received.len() <= failures {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::NO_CONTENT
+            }
+        }
+
+        let received = Received::default();
+        let app = Router::new()
+            .route("/hook", post(record))
+            .with_state((received.clone(), failures));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url.parse().unwrap(), received)
+    }
+
+    #[tokio::test]
+    async fn delivers_completion_body_without_key() {
+        let (url, received) = mock_endpoint(0).await;
+        let session = Session::new(EncryptionKey::new());
+        let tracker = ProgressTracker::new();
+        tracker.record_client("0123456789abcdef-lock");
+        tracker.bandwidth().record_chunk(2048);
+
+        let event = CompletionEvent::collect(&session, "report.pdf", &tracker);
+        Webhook::new(url).unwrap().deliver(&event).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let body = &received[0];
+        assert_eq!(body["token"], session.token());
+        assert_eq!(body["display_name"], "report.pdf");
+        assert_eq!(body["bytes"], 2048);
+        assert_eq!(body["client_id"], "01234567");
+        assert!(body["duration_ms"].as_u64().is_some());
+        assert!(!body.to_string().contains(&session.session_key_b64()));
+    }
+
+    #[tokio::test]
+    async fn retries_once_after_server_error() {
+        let (url, received) = mock_endpoint(1).await;
+        let session = Session::new(EncryptionKey::new());
+        let event = CompletionEvent::collect(&session, "a.bin", &ProgressTracker::new());
+
+        Webhook::new(url).unwrap().deliver(&event).await.unwrap();
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_second_failure() {
+        let (url, received) = mock_endpoint(2).await;
+        let session = Session::new(EncryptionKey::new());
+        let event = CompletionEvent::collect(&session, "a.bin", &ProgressTracker::new());
+
+        assert!(Webhook::new(url).unwrap().deliver(&event).await.is_err());
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_non_http_url() {
+        assert!(Webhook::new("ftp://example.com/hook".parse().unwrap()).is_err());
+    }
+}
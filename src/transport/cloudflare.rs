@@ -7,13 +7,17 @@ use std::time::Duration;
 use thiserror::Error;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::watch;
 use tracing::{info, warn};
 
+use crate::transport::tunnel::{permanent, ActiveTunnel};
 use crate::transport::with_startup_timeout;
 
 const TUNNEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
-const START_MAX_ATTEMPTS: u8 = 3;
-const START_RETRY_BACKOFF: Duration = Duration::from_millis(300);
+/// Quick tunnel hostnames live under this zone; `api.` is cloudflared's own
+/// endpoint and shows up in its error lines.
+const QUICK_TUNNEL_ZONE: &str = ".trycloudflare.com";
+const QUICK_TUNNEL_API_HOST: &str = "api.trycloudflare.com";
 
 #[derive(Deserialize)]
 struct QuickTunnelResponse {
@@ -40,8 +44,19 @@ enum CloudflareError {
     StartupFailed(String),
 }
 
-/// Cloudflare startup errors
+/// Cloudflare startup errors; [`tunnel::open`](crate::transport::tunnel::open)
+/// retries only the ones another start can fix.
 fn map_start_error(err: CloudflareError) -> anyhow::Error {
+    let retryable = is_retryable_start_error(&err);
+    let err = describe_start_error(err);
+    if retryable {
+        err
+    } else {
+        permanent(err)
+    }
+}
+
+fn describe_start_error(err: CloudflareError) -> anyhow::Error {
     match err {
         CloudflareError::BinaryMissing => anyhow::anyhow!(
             "Failed to start Cloudflare tunnel.\n\n\
@@ -76,39 +91,7 @@ fn map_start_error(err: CloudflareError) -> anyhow::Error {
 impl CloudflareTunnel {
     #[tracing::instrument(fields(local_port))]
     pub async fn start(local_port: u16) -> Result<Self> {
-        Self::start_with_retry(local_port)
-            .await
-            .map_err(map_start_error)
-    }
-
-    async fn start_with_retry(local_port: u16) -> std::result::Result<Self, CloudflareError> {
-        let mut last_error = None;
-
-        for attempt in 1..=START_MAX_ATTEMPTS {
-            match Self::start_once(local_port).await {
-                Ok(tunnel) => return Ok(tunnel),
-                Err(err) => {
-                    if attempt < START_MAX_ATTEMPTS && is_retryable_start_error(&err) {
-                        warn!(
-                            "Cloudflare tunnel startup attempt {}/{} failed: {}. Retrying...",
-                            attempt, START_MAX_ATTEMPTS, err
-                        );
-                        last_error = Some(err);
-                        tokio::time::sleep(START_RETRY_BACKOFF * u32::from(attempt)).await;
-                        continue;
-                    }
-                    return Err(err);
-                }
-            }
-        }
-
-        if let Some(err) = last_error {
-            return Err(err);
-        }
-
-        Err(CloudflareError::StartupFailed(
-            "unknown startup failure".to_string(),
-        ))
+        Self::start_once(local_port).await.map_err(map_start_error)
     }
 
     async fn start_once(local_port: u16) -> std::result::Result<Self, CloudflareError> {
@@ -137,14 +120,15 @@ impl CloudflareTunnel {
                 }
             })?;
 
-        // log stderr for debugging
+        // log stderr for debugging, and watch it for the tunnel URL
+        let (url_tx, url_rx) = watch::channel(None);
         if let Some(stderr) = child.stderr.take() {
-            tokio::spawn(log_stderr(stderr));
+            tokio::spawn(log_stderr(stderr, url_tx));
         }
 
         // Parse stream with timeout
         // reader keeps stream alive after url
-        let url = match with_startup_timeout(wait_for_url(metrics_port, &mut child)).await {
+        let url = match with_startup_timeout(wait_for_url(metrics_port, &mut child, url_rx)).await {
             Ok(Ok(u)) => u,
             Ok(Err(e)) => {
                 if let Err(kill_err) = child.kill().await {
@@ -200,14 +184,28 @@ impl CloudflareTunnel {
     }
 }
 
+#[async_trait::async_trait]
+impl ActiveTunnel for CloudflareTunnel {
+    async fn shutdown(&mut self) -> Result<()> {
+        CloudflareTunnel::shutdown(self).await
+    }
+}
+
+/// Wait for the tunnel URL from whichever source has it first: the
+/// metrics endpoint or the banner cloudflared prints on stderr.
 async fn wait_for_url(
     metrics_port: u16,
     child: &mut Child,
+    stderr_url: watch::Receiver<Option<String>>,
 ) -> std::result::Result<String, CloudflareError> {
     let client = reqwest::Client::new();
     let api_url = format!("http://localhost:{}/quicktunnel", metrics_port);
 
     loop {
+        if let Some(url) = stderr_url.borrow().clone() {
+            return Ok(url);
+        }
+
         if let Some(status) = child
             .try_wait()
             .map_err(|err| CloudflareError::StartupFailed(err.to_string()))?
@@ -254,14 +252,39 @@ fn get_available_port() -> Option<u16> {
         .map(|a| a.port())
 }
 
+/// Public quick tunnel URL in a cloudflared log line, if it has one.
+///
+/// The URL sits inside an ASCII box (`|  https://x.trycloudflare.com  |`),
+/// so surrounding punctuation is trimmed.
+fn parse_tunnel_url(line: &str) -> Option<String> {
+    line.split_whitespace()
+        .filter_map(|word| {
+            word.trim_matches(|c: char| "|\"'<>(),".contains(c))
+                .strip_prefix("https://")
+        })
+        .map(|host| host.trim_end_matches('/'))
+        .find(|host| {
+            host.ends_with(QUICK_TUNNEL_ZONE)
+                && host.len() > QUICK_TUNNEL_ZONE.len()
+                && *host != QUICK_TUNNEL_API_HOST
+                && !host.contains('/')
+        })
+        .map(|host| format!("https://{}", host))
+}
+
 // Cloudflare only uses stderr for logging
-async fn log_stderr(stderr: ChildStderr) {
+async fn log_stderr(stderr: ChildStderr, url_tx: watch::Sender<Option<String>>) {
     let reader = BufReader::new(stderr);
     let mut lines = reader.lines();
 
     // Cloudflare uses stderr for both logs and errors
     // errors will contain error/fatal
     while let Some(line) = lines.next_line().await.ok().flatten() {
+        if url_tx.borrow().is_none() {
+            if let Some(url) = parse_tunnel_url(&line) {
+                url_tx.send_replace(Some(url));
+            }
+        }
         let lowercase_line = line.to_lowercase();
         if lowercase_line.contains("error") || lowercase_line.contains("fatal") {
             tracing::error!("cloudflared stderr: {}", line);
@@ -289,4 +312,45 @@ mod tests {
     fn non_retryable_errors_are_not_retried() {
         assert!(!is_retryable_start_error(&CloudflareError::BinaryMissing));
     }
+
+    #[test]
+    fn only_retryable_errors_reach_open_as_transient() {
+        use crate::transport::tunnel::PermanentError;
+
+        let missing = map_start_error(CloudflareError::BinaryMissing);
+        assert!(missing.downcast_ref::<PermanentError>().is_some());
+        let timeout = map_start_error(CloudflareError::UrlTimeout);
+        assert!(timeout.downcast_ref::<PermanentError>().is_none());
+    }
+
+    // Captured from `cloudflared tunnel --url http://localhost:8080`
+    const CLOUDFLARED_LOG: &str = "\
+2024-05-02T10:14:03Z INF Thank you for trying Cloudflare Tunnel. Doing so, without a Cloudflare account, is a quick way to experiment and try it out.
+2024-05-02T10:14:03Z INF Requesting new quick Tunnel on trycloudflare.com...
+2024-05-02T10:14:04Z INF +--------------------------------------------------------------------------------------------+
+2024-05-02T10:14:04Z INF |  Your quick Tunnel has been created! Visit it at (it may take some time to be reachable):  |
+2024-05-02T10:14:04Z INF |  https://seasonal-deck-organisms-sf.trycloudflare.com                                      |
+2024-05-02T10:14:04Z INF +--------------------------------------------------------------------------------------------+
+2024-05-02T10:14:04Z INF Starting metrics server on 127.0.0.1:40123/metrics
+2024-05-02T10:14:05Z INF Registered tunnel connection connIndex=0 location=fra08 protocol=http2";
+
+    #[test]
+    fn tunnel_url_is_extracted_from_cloudflared_banner() {
+        let urls: Vec<String> = CLOUDFLARED_LOG
+            .lines()
+            .filter_map(parse_tunnel_url)
+            .collect();
+        assert_eq!(
+            urls,
+            vec!["https://seasonal-deck-organisms-sf.trycloudflare.com"]
+        );
+    }
+
+    #[test]
+    fn api_endpoint_in_error_lines_is_not_a_tunnel_url() {
+        let line = "2024-05-02T10:14:03Z ERR Error unmarshaling QuickTunnel response: \
+                    error=\"failed to request quick Tunnel: Post \\\"https://api.trycloudflare.com/tunnel\\\"\"";
+        assert_eq!(parse_tunnel_url(line), None);
+        assert_eq!(parse_tunnel_url("https://trycloudflare.com"), None);
+    }
 }
@@ -3,10 +3,17 @@
 //! - Tunnel mode should bind loopback only.
 //! - Local HTTPS mode may bind all interfaces for LAN access.
 
+use crate::common::config::{NetworkSettings, TlsVersion};
 use anyhow::{Context, Result};
-use axum_server::tls_rustls::RustlsConfig;
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
 use rcgen::generate_simple_self_signed;
-use std::net::{SocketAddr, UdpSocket};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::SupportedProtocolVersion;
+use socket2::{Domain, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::Arc;
+use tokio::net::TcpStream;
 
 /// HTTP/TLS mode used for local server startup.
 pub enum Protocol {
@@ -19,32 +26,114 @@ pub enum Protocol {
 pub enum BindScope {
     Loopback,
     AllInterfaces,
+    /// Only this locally assigned address, which the URL also uses
+    Address(IpAddr),
+}
+
+impl BindScope {
+    /// Address put in the link and certificate for this scope.
+    pub fn advertised_ip(self) -> String {
+        match self {
+            BindScope::Address(ip) => ip.to_string(),
+            BindScope::Loopback | BindScope::AllInterfaces => {
+                get_local_ip().unwrap_or_else(|_| "127.0.0.1".to_string())
+            }
+        }
+    }
 }
 
 fn bind_addr(scope: BindScope, port: u16) -> SocketAddr {
     match scope {
         BindScope::Loopback => SocketAddr::from(([127, 0, 0, 1], port)),
         BindScope::AllInterfaces => SocketAddr::from(([0, 0, 0, 0], port)),
+        BindScope::Address(ip) => SocketAddr::new(ip, port),
+    }
+}
+
+/// Bind address for a URL pointing at `local_ip`.
+///
+/// An IPv6 link needs an IPv6 listener; `[::]` is bound dual-stack so IPv4
+/// clients still get through.
+fn bind_addr_for(scope: BindScope, port: u16, local_ip: &str) -> SocketAddr {
+    let is_v6 = split_zone(local_ip).0.parse::<Ipv6Addr>().is_ok();
+    match scope {
+        BindScope::AllInterfaces if is_v6 => SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)),
+        _ => bind_addr(scope, port),
+    }
+}
+
+/// Acceptor applying per-connection TCP options before TLS/HTTP handling.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpTuningAcceptor {
+    nodelay: bool,
+}
+
+impl TcpTuningAcceptor {
+    pub fn new(network: NetworkSettings) -> Self {
+        Self {
+            nodelay: network.nodelay,
+        }
+    }
+}
+
+impl<S> Accept<TcpStream, S> for TcpTuningAcceptor {
+    type Stream = TcpStream;
+    type Service = S;
+    type Future = std::future::Ready<std::io::Result<(TcpStream, S)>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        let result = if self.nodelay {
+            stream.set_nodelay(true)
+        } else {
+            Ok(())
+        };
+        std::future::ready(result.map(|()| (stream, service)))
     }
 }
 
+/// Bind a non-blocking listener with the configured backlog.
+fn bind_listener(addr: SocketAddr, network: NetworkSettings) -> Result<std::net::TcpListener> {
+    // A pinned port that is taken is an error; never fall back to another one
+    let bind_context = || {
+        format!(
+            "Failed to bind to port {} - port already in use.\n\n\
+             Is another archdrop instance running?\n\
+             Or is another service using this port?",
+            addr.port()
+        )
+    };
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)
+        .context("Failed to create listening socket")?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket
+            .set_only_v6(false)
+            .context("Failed to enable dual-stack listener")?;
+    }
+    socket.bind(&addr.into()).with_context(bind_context)?;
+
+    // listen(2) takes a C int; clamp oversized config values instead of wrapping
+    let backlog = i32::try_from(network.backlog).unwrap_or(i32::MAX);
+    socket.listen(backlog).with_context(bind_context)?;
+    socket
+        .set_nonblocking(true)
+        .context("Failed to set listener to non-blocking mode")?;
+
+    Ok(socket.into())
+}
+
 /// Starts a local Axum server and returns `(bound_port, handle)`.
 pub async fn start_local_server(
     app: axum::Router,
     protocol: Protocol,
     bind_scope: BindScope,
     port: u16,
+    network: NetworkSettings,
 ) -> Result<(u16, axum_server::Handle)> {
-    let addr = bind_addr(bind_scope, port);
-    let listener = std::net::TcpListener::bind(addr).context(
-        "Failed to bind to port - port already in use.\n\n\
-         Is another archdrop instance running?\n\
-         Or is another service using this port?",
-    )?;
-
-    listener
-        .set_nonblocking(true)
-        .context("Failed to set listener to non-blocking mode")?;
+    let local_ip = bind_scope.advertised_ip();
+    let addr = bind_addr_for(bind_scope, port, &local_ip);
+    let listener = bind_listener(addr, network)?;
+    let acceptor = TcpTuningAcceptor::new(network);
 
     let port = listener.local_addr()?.port();
 
@@ -55,28 +144,29 @@ pub async fn start_local_server(
     // HTTPS uses self signed certs
     match protocol {
         Protocol::Https => {
-            let local_ip = get_local_ip().unwrap_or_else(|_| "127.0.0.1".to_string());
-            let tls_config = generate_cert(&local_ip)
+            let tls_config = generate_cert(&local_ip, network.min_tls)
                 .await
                 .context("Failed to generate TLS certificate")?;
             tokio::spawn(async move {
-                if let Err(e) = axum_server::from_tcp_rustls(listener, tls_config)
+                if let Err(e) = axum_server::from_tcp(listener)
+                    .acceptor(RustlsAcceptor::new(tls_config).acceptor(acceptor))
                     .handle(server_handle_clone)
                     .serve(app.into_make_service())
                     .await
                 {
-                    eprintln!("Server error: {}", e);
+                    tracing::error!("Server error: {}", e);
                 }
             });
         }
         Protocol::Http => {
             tokio::spawn(async move {
                 if let Err(e) = axum_server::from_tcp(listener)
+                    .acceptor(acceptor)
                     .handle(server_handle_clone)
                     .serve(app.into_make_service())
                     .await
                 {
-                    eprintln!("Server error: {}", e);
+                    tracing::error!("Server error: {}", e);
                 }
             });
         }
@@ -85,34 +175,240 @@ pub async fn start_local_server(
     Ok((port, server_handle))
 }
 
+/// Route-lookup targets. Connecting a UDP socket sends nothing; it only asks
+/// the routing table which local address would be used.
+const IPV4_PROBE: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 80);
+// 2001:db8::/32 is the documentation prefix, so the lookup follows the default route
+const IPV6_PROBE: SocketAddr = SocketAddr::new(
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+    80,
+);
+
+/// Local address the OS would use to reach `dest`.
+fn route_to(dest: SocketAddr) -> std::io::Result<SocketAddr> {
+    let unspecified: IpAddr = match dest {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let socket = UdpSocket::bind((unspecified, 0))?;
+    socket.connect(dest)?;
+    socket.local_addr()
+}
+
 /// Best-effort local non-loopback IP discovery for URL/certificate use.
+///
+/// IPv4 is preferred to match the default `0.0.0.0` bind; IPv6-only hosts
+/// fall back to their IPv6 route.
 pub fn get_local_ip() -> Result<String> {
-    let socket = UdpSocket::bind("0.0.0.0:0").context("Failed to bind socket for IP detection")?;
+    local_ip_via(route_to)
+}
 
-    socket
-        .connect("8.8.8.8:80")
-        .context("Failed to connect socket for IP detection")?;
+fn local_ip_via(probe: impl Fn(SocketAddr) -> std::io::Result<SocketAddr>) -> Result<String> {
+    let mut last_err = None;
+    for dest in [IPV4_PROBE, IPV6_PROBE] {
+        match probe(dest) {
+            Ok(local) if !local.ip().is_unspecified() => return Ok(format_scoped_ip(local)),
+            Ok(_) => {}
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(match last_err {
+        Some(e) => anyhow::Error::new(e).context("Failed to find a route for IP detection"),
+        None => anyhow::anyhow!("No usable local address for IP detection"),
+    })
+}
 
-    let local_addr = socket.local_addr().context("Failed to get local address")?;
+/// How the address for local HTTPS links is chosen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AddressSelector {
+    /// Follow the default route
+    #[default]
+    Auto,
+    /// An address on this network interface, IPv4 first
+    Interface(String),
+    /// This exact address, which must be assigned to this machine
+    Ip(IpAddr),
+}
 
-    Ok(local_addr.ip().to_string())
+/// One address assigned to a local network interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAddress {
+    pub interface: String,
+    pub ip: IpAddr,
+}
+
+/// Non-loopback addresses a receiver could reach, in OS order.
+///
+/// Link-local IPv6 is skipped; it is unusable in a link without a zone.
+pub fn local_addresses() -> Result<Vec<LocalAddress>> {
+    let interfaces = if_addrs::get_if_addrs().context("Failed to list network interfaces")?;
+    Ok(interfaces
+        .into_iter()
+        .filter(|iface| !iface.is_loopback())
+        .filter(|iface| !matches!(iface.ip(), IpAddr::V6(ip) if is_ipv6_link_local(&ip)))
+        .map(|iface| LocalAddress {
+            ip: iface.ip(),
+            interface: iface.name,
+        })
+        .collect())
+}
+
+/// Resolve an explicit selection to a listening scope; `Auto` keeps the
+/// all-interfaces default.
+pub fn resolve_bind_scope(selector: &AddressSelector) -> Result<BindScope> {
+    match selector {
+        AddressSelector::Auto => Ok(BindScope::AllInterfaces),
+        _ => select_address(&local_addresses()?, selector).map(BindScope::Address),
+    }
+}
+
+fn select_address(addresses: &[LocalAddress], selector: &AddressSelector) -> Result<IpAddr> {
+    let found = match selector {
+        AddressSelector::Auto => anyhow::bail!("No address selected"),
+        AddressSelector::Interface(name) => {
+            let on_interface = || addresses.iter().filter(|a| &a.interface == name);
+            on_interface()
+                .find(|a| a.ip.is_ipv4())
+                .or_else(|| on_interface().next())
+                .map(|a| a.ip)
+                .ok_or_else(|| format!("No usable address on interface '{}'", name))
+        }
+        AddressSelector::Ip(ip) => addresses
+            .iter()
+            .any(|a| a.ip == *ip)
+            .then_some(*ip)
+            .ok_or_else(|| format!("{} is not assigned to this machine", ip)),
+    };
+    found.map_err(|message| {
+        anyhow::anyhow!(
+            "{}. Available: {}",
+            message,
+            describe_addresses(addresses.iter())
+        )
+    })
+}
+
+fn describe_addresses<'a>(addresses: impl Iterator<Item = &'a LocalAddress>) -> String {
+    let listed: Vec<String> = addresses
+        .map(|a| format!("{} ({})", a.interface, a.ip))
+        .collect();
+    if listed.is_empty() {
+        "none".to_string()
+    } else {
+        listed.join(", ")
+    }
+}
+
+/// Hint listing addresses other than `chosen`, for when the default pick
+/// is unreachable (VPN, docker bridge). None when there is no alternative.
+pub fn other_addresses_hint(chosen: &str) -> Option<String> {
+    let addresses = local_addresses().ok()?;
+    alternatives_hint(&addresses, chosen)
+}
+
+fn alternatives_hint(addresses: &[LocalAddress], chosen: &str) -> Option<String> {
+    let chosen = split_zone(chosen).0;
+    let mut others = addresses
+        .iter()
+        .filter(|a| a.ip.to_string() != chosen)
+        .peekable();
+    others.peek()?;
+    Some(format!(
+        "Other addresses: {}. Use --interface or --bind if this link is unreachable.",
+        describe_addresses(others)
+    ))
+}
+
+fn is_ipv6_link_local(ip: &Ipv6Addr) -> bool {
+    (ip.segments()[0] & 0xffc0) == 0xfe80
+}
+
+/// Render an address as text, keeping the zone (`fe80::1%3`) for link-local IPv6.
+///
+/// Link-local addresses are ambiguous without the interface they belong to.
+fn format_scoped_ip(addr: SocketAddr) -> String {
+    match addr {
+        SocketAddr::V6(v6) if is_ipv6_link_local(v6.ip()) && v6.scope_id() != 0 => {
+            format!("{}%{}", v6.ip(), v6.scope_id())
+        }
+        other => other.ip().to_string(),
+    }
+}
+
+/// Split `addr%zone` into address and optional zone id.
+fn split_zone(ip: &str) -> (&str, Option<&str>) {
+    match ip.split_once('%') {
+        Some((addr, zone)) => (addr, Some(zone)),
+        None => (ip, None),
+    }
+}
+
+/// Format an address for the host part of a URL.
+///
+/// IPv6 literals are bracketed, and a zone id is percent-encoded as `%25`
+/// (RFC 6874), e.g. `[fe80::1%25eth0]`.
+pub fn url_host(ip: &str) -> String {
+    let (addr, zone) = split_zone(ip);
+    match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => match zone {
+            Some(zone) => format!("[{}%25{}]", addr, zone),
+            None => format!("[{}]", addr),
+        },
+        _ => ip.to_string(),
+    }
+}
+
+/// Certificate SAN for an address. Zone ids are not part of an IP SAN.
+fn cert_san(ip: &str) -> String {
+    split_zone(ip).0.to_string()
+}
+
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+static TLS13_AND_TLS12: &[&SupportedProtocolVersion] =
+    &[&rustls::version::TLS13, &rustls::version::TLS12];
+
+/// Protocol versions offered for a given minimum, newest first.
+fn protocol_versions(min: TlsVersion) -> &'static [&'static SupportedProtocolVersion] {
+    match min {
+        TlsVersion::Tls13 => TLS13_ONLY,
+        TlsVersion::Tls12 => TLS13_AND_TLS12,
+    }
+}
+
+/// Builds the rustls server config for a certificate, restricted to `min_tls`.
+fn server_config(
+    cert_der: Vec<u8>,
+    key_der: Vec<u8>,
+    min_tls: TlsVersion,
+) -> Result<rustls::ServerConfig> {
+    let mut config =
+        rustls::ServerConfig::builder_with_protocol_versions(protocol_versions(min_tls))
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(cert_der)],
+                PrivateKeyDer::Pkcs8(key_der.into()),
+            )
+            .context("Failed to create TLS configuration")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
 }
 
 /// Builds an in-memory self-signed TLS config for local HTTPS serving.
-pub async fn generate_cert(ip: &str) -> Result<RustlsConfig> {
-    let subject_alt_names = vec![ip.to_string(), "localhost".to_string()];
+///
+/// The certificate and key stay DER bytes in memory; nothing is written to
+/// disk, so there are no temp files to clean up or race on.
+pub async fn generate_cert(ip: &str, min_tls: TlsVersion) -> Result<RustlsConfig> {
+    let subject_alt_names = vec![cert_san(ip), "localhost".to_string()];
     let cert = generate_simple_self_signed(subject_alt_names)
         .context("Failed to generate self-signed certificate")?;
 
-    let cert_pem = cert
-        .serialize_pem()
-        .context("Failed to serialize certificate to PEM")?
-        .into_bytes();
-    let key_pem = cert.serialize_private_key_pem().into_bytes();
+    let cert_der = cert
+        .serialize_der()
+        .context("Failed to serialize certificate to DER")?;
+    let key_der = cert.serialize_private_key_der();
 
-    RustlsConfig::from_pem(cert_pem, key_pem)
-        .await
-        .context("Failed to create TLS configuration")
+    let config = server_config(cert_der, key_der, min_tls)?;
+    Ok(RustlsConfig::from_config(Arc::new(config)))
 }
 
 #[cfg(test)]
@@ -130,4 +426,366 @@ mod tests {
         let addr = bind_addr(BindScope::AllInterfaces, 8080);
         assert_eq!(addr.ip().to_string(), "0.0.0.0");
     }
+
+    #[test]
+    fn link_local_url_host_encodes_zone_id() {
+        assert_eq!(url_host("fe80::1%eth0"), "[fe80::1%25eth0]");
+        assert_eq!(
+            format!(
+                "https://{}:8443/send",
+                url_host("fe80::1ff:fe23:4567:890a%3")
+            ),
+            "https://[fe80::1ff:fe23:4567:890a%253]:8443/send"
+        );
+    }
+
+    fn probe_with(
+        v4: Option<&'static str>,
+        v6: Option<&'static str>,
+    ) -> impl Fn(SocketAddr) -> std::io::Result<SocketAddr> {
+        move |dest| {
+            let route = if dest.is_ipv4() { v4 } else { v6 };
+            route
+                .map(|addr| addr.parse().expect("socket addr"))
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NetworkUnreachable))
+        }
+    }
+
+    #[test]
+    fn local_ip_prefers_ipv4_route() {
+        let probe = probe_with(Some("192.168.1.10:50000"), Some("[2001:db8::10]:50000"));
+        assert_eq!(local_ip_via(probe).unwrap(), "192.168.1.10");
+    }
+
+    #[test]
+    fn local_ip_falls_back_to_ipv6_route() {
+        let probe = probe_with(None, Some("[2001:db8::10]:50000"));
+        let ip = local_ip_via(probe).unwrap();
+        assert_eq!(ip, "2001:db8::10");
+        assert_eq!(
+            format!("https://{}:8443/send", url_host(&ip)),
+            "https://[2001:db8::10]:8443/send"
+        );
+        assert_eq!(cert_san(&ip), "2001:db8::10");
+        assert_eq!(
+            bind_addr_for(BindScope::AllInterfaces, 8443, &ip).to_string(),
+            "[::]:8443"
+        );
+        assert_eq!(
+            bind_addr_for(BindScope::Loopback, 8443, &ip).to_string(),
+            "127.0.0.1:8443"
+        );
+    }
+
+    fn mock_interfaces() -> Vec<LocalAddress> {
+        let entry = |interface: &str, ip: &str| LocalAddress {
+            interface: interface.to_string(),
+            ip: ip.parse().unwrap(),
+        };
+        vec![
+            entry("tun0", "10.8.0.2"),
+            entry("eth0", "2001:db8::5"),
+            entry("eth0", "192.168.1.20"),
+            entry("docker0", "172.17.0.1"),
+        ]
+    }
+
+    #[test]
+    fn interface_selection_prefers_ipv4_on_that_interface() {
+        let addresses = mock_interfaces();
+        let pick =
+            |name: &str| select_address(&addresses, &AddressSelector::Interface(name.into()));
+        assert_eq!(pick("eth0").unwrap().to_string(), "192.168.1.20");
+        assert_eq!(pick("docker0").unwrap().to_string(), "172.17.0.1");
+
+        let err = pick("wlan0").unwrap_err().to_string();
+        assert!(
+            err.contains("No usable address on interface 'wlan0'"),
+            "{err}"
+        );
+        assert!(err.contains("eth0 (192.168.1.20)"), "{err}");
+    }
+
+    #[test]
+    fn bind_selection_requires_a_locally_assigned_address() {
+        let addresses = mock_interfaces();
+        let ip = |s: &str| AddressSelector::Ip(s.parse().unwrap());
+        assert_eq!(
+            select_address(&addresses, &ip("2001:db8::5"))
+                .unwrap()
+                .to_string(),
+            "2001:db8::5"
+        );
+        let err = select_address(&addresses, &ip("192.168.1.99")).unwrap_err();
+        assert!(err.to_string().contains("192.168.1.99 is not assigned"));
+    }
+
+    #[test]
+    fn alternatives_hint_lists_every_other_address() {
+        let addresses = mock_interfaces();
+        let hint = alternatives_hint(&addresses, "10.8.0.2").expect("alternatives");
+        assert!(!hint.contains("tun0"), "{hint}");
+        assert!(hint.contains("eth0 (192.168.1.20)"), "{hint}");
+        assert!(hint.contains("docker0 (172.17.0.1)"), "{hint}");
+        assert_eq!(alternatives_hint(&addresses[..1], "10.8.0.2"), None);
+    }
+
+    #[test]
+    fn address_scope_binds_and_advertises_the_chosen_ip() {
+        let scope = BindScope::Address("192.168.1.20".parse().unwrap());
+        assert_eq!(bind_addr(scope, 8443).to_string(), "192.168.1.20:8443");
+        assert_eq!(scope.advertised_ip(), "192.168.1.20");
+    }
+
+    #[test]
+    fn local_ip_errors_without_any_route() {
+        assert!(local_ip_via(probe_with(None, None)).is_err());
+        assert!(local_ip_via(probe_with(Some("0.0.0.0:0"), None)).is_err());
+    }
+
+    #[test]
+    fn url_host_brackets_ipv6_and_leaves_ipv4() {
+        assert_eq!(url_host("2001:db8::1"), "[2001:db8::1]");
+        assert_eq!(url_host("192.168.1.10"), "192.168.1.10");
+    }
+
+    #[test]
+    fn scoped_format_keeps_zone_only_for_link_local() {
+        let link_local = SocketAddr::V6(std::net::SocketAddrV6::new(
+            "fe80::1".parse().unwrap(),
+            0,
+            0,
+            3,
+        ));
+        assert_eq!(format_scoped_ip(link_local), "fe80::1%3");
+
+        let global = SocketAddr::V6(std::net::SocketAddrV6::new(
+            "2001:db8::1".parse().unwrap(),
+            0,
+            0,
+            3,
+        ));
+        assert_eq!(format_scoped_ip(global), "2001:db8::1");
+        assert_eq!(cert_san("fe80::1%3"), "fe80::1");
+    }
+
+    #[test]
+    fn listener_binds_with_custom_backlog() {
+        let network = NetworkSettings {
+            backlog: 16,
+            ..NetworkSettings::default()
+        };
+        let listener =
+            bind_listener(bind_addr(BindScope::Loopback, 0), network).expect("bind listener");
+        assert_ne!(listener.local_addr().expect("local addr").port(), 0);
+    }
+
+    #[tokio::test]
+    async fn second_server_on_pinned_port_fails_to_bind() {
+        let app = || axum::Router::new().route("/health", axum::routing::get(|| async { "OK" }));
+        let network = NetworkSettings::default();
+
+        let (port, first) =
+            start_local_server(app(), Protocol::Http, BindScope::Loopback, 0, network)
+                .await
+                .expect("first server");
+
+        let err = start_local_server(app(), Protocol::Http, BindScope::Loopback, port, network)
+            .await
+            .expect_err("second bind on the same port should fail");
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains(&format!("port {} - port already in use", port)),
+            "{message}"
+        );
+
+        first.shutdown();
+    }
+
+    #[tokio::test]
+    async fn acceptor_sets_nodelay_when_enabled() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        let _client = TcpStream::connect(addr).await.expect("connect");
+        let (stream, _) = listener.accept().await.expect("accept");
+        assert!(!stream.nodelay().expect("read nodelay"));
+
+        let acceptor = TcpTuningAcceptor::new(NetworkSettings::default());
+        let (stream, ()) = acceptor.accept(stream, ()).await.expect("accept hook");
+        assert!(stream.nodelay().expect("read nodelay"));
+    }
+
+    /// Handshake an in-memory client offering `client_versions` against a
+    /// server built for `min_tls`; returns the negotiated version.
+    fn negotiate(
+        min_tls: TlsVersion,
+        client_versions: &[&'static SupportedProtocolVersion],
+    ) -> Result<rustls::ProtocolVersion, rustls::Error> {
+        let cert = generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_der = cert.serialize_der().unwrap();
+        let server_config =
+            server_config(cert_der.clone(), cert.serialize_private_key_der(), min_tls).unwrap();
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(CertificateDer::from(cert_der)).unwrap();
+        let client_config = rustls::ClientConfig::builder_with_protocol_versions(client_versions)
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let mut server = rustls::ServerConnection::new(Arc::new(server_config)).unwrap();
+        let mut client =
+            rustls::ClientConnection::new(Arc::new(client_config), "localhost".try_into().unwrap())
+                .unwrap();
+
+        for _ in 0..10 {
+            let mut flight = Vec::new();
+            while client.wants_write() {
+                client.write_tls(&mut flight).unwrap();
+            }
+            server.read_tls(&mut flight.as_slice()).unwrap();
+            server.process_new_packets()?;
+
+            let mut flight = Vec::new();
+            while server.wants_write() {
+                server.write_tls(&mut flight).unwrap();
+            }
+            client.read_tls(&mut flight.as_slice()).unwrap();
+            client.process_new_packets()?;
+
+            if !client.is_handshaking() && !server.is_handshaking() {
+                return Ok(server.protocol_version().expect("negotiated version"));
+            }
+        }
+        panic!("handshake did not finish");
+    }
+
+    #[test]
+    fn tls12_client_is_refused_unless_minimum_allows_it() {
+        let tls12_only = &[&rustls::version::TLS12];
+
+        assert_eq!(
+            negotiate(TlsVersion::Tls13, tls12_only),
+            Err(rustls::Error::PeerIncompatible(
+                rustls::PeerIncompatible::Tls12NotOfferedOrEnabled
+            ))
+        );
+        assert_eq!(
+            negotiate(TlsVersion::Tls12, tls12_only).unwrap(),
+            rustls::ProtocolVersion::TLSv1_2
+        );
+        assert_eq!(
+            negotiate(TlsVersion::Tls12, rustls::DEFAULT_VERSIONS).unwrap(),
+            rustls::ProtocolVersion::TLSv1_3
+        );
+        assert_eq!(
+            negotiate(TlsVersion::Tls13, rustls::DEFAULT_VERSIONS).unwrap(),
+            rustls::ProtocolVersion::TLSv1_3
+        );
+    }
+
+    #[tokio::test]
+    async fn generates_tls_config_for_each_minimum() {
+        for min in [TlsVersion::Tls12, TlsVersion::Tls13] {
+            let config = generate_cert("127.0.0.1", min).await.expect("tls config");
+            assert_eq!(
+                config.get_inner().alpn_protocols,
+                vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+            );
+        }
+    }
+
+    fn pem_files_in_temp_dir() -> Vec<std::path::PathBuf> {
+        std::fs::read_dir(std::env::temp_dir())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("archdrop") && name.ends_with(".pem"))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn https_server_serves_tls_without_writing_cert_files() {
+        let before = pem_files_in_temp_dir();
+
+        let app = axum::Router::new().route("/health", axum::routing::get(|| async { "OK" }));
+        let (port, handle) = start_local_server(
+            app,
+            Protocol::Https,
+            BindScope::Loopback,
+            0,
+            NetworkSettings::default(),
+        )
+        .await
+        .expect("start https server");
+
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .expect("client");
+        let body = client
+            .get(format!("https://127.0.0.1:{}/health", port))
+            .send()
+            .await
+            .expect("tls request")
+            .text()
+            .await
+            .expect("body");
+        handle.shutdown();
+
+        assert_eq!(body, "OK");
+        assert_eq!(pem_files_in_temp_dir(), before);
+    }
+
+    /// DER certificate the HTTPS server on `port` presents.
+    async fn served_cert(port: u16) -> Vec<u8> {
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .tls_info(true)
+            .build()
+            .expect("client");
+        let response = client
+            .get(format!("https://127.0.0.1:{}/health", port))
+            .send()
+            .await
+            .expect("tls request");
+        let cert = response
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(|info| info.peer_certificate())
+            .expect("peer certificate")
+            .to_vec();
+        assert_eq!(response.text().await.expect("body"), "OK");
+        cert
+    }
+
+    #[tokio::test]
+    async fn concurrent_https_servers_get_distinct_certs() {
+        let start = || {
+            let app = axum::Router::new().route("/health", axum::routing::get(|| async { "OK" }));
+            start_local_server(
+                app,
+                Protocol::Https,
+                BindScope::Loopback,
+                0,
+                NetworkSettings::default(),
+            )
+        };
+        let (first, second) = tokio::join!(start(), start());
+        let (first_port, first_handle) = first.expect("start first server");
+        let (second_port, second_handle) = second.expect("start second server");
+
+        let (first_cert, second_cert) =
+            tokio::join!(served_cert(first_port), served_cert(second_port));
+        first_handle.shutdown();
+        second_handle.shutdown();
+
+        assert!(!first_cert.is_empty());
+        assert_ne!(first_cert, second_cert);
+    }
 }
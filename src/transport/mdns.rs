@@ -0,0 +1,181 @@
+//! mDNS/Bonjour advertisement for zero-config LAN discovery
+//!
+//! Only direct local HTTPS sessions are advertised. The record carries the
+//! port and service path, never the token or key, so a discovered URL still
+//! needs the shared link fragment before anything can be transferred.
+
+use anyhow::{Context, Result};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use crate::transport::local::url_host;
+
+/// DNS-SD service type every ArchDrop session registers under.
+pub const SERVICE_TYPE: &str = "_archdrop._tcp.local.";
+
+/// TXT key holding the listening port.
+const TXT_PORT: &str = "port";
+/// TXT key holding the service path (`send` or `receive`).
+const TXT_SERVICE: &str = "service";
+
+/// A registered service, withdrawn when dropped.
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertisement {
+    /// Announce `service` on `ip:port` until dropped.
+    pub fn start(ip: IpAddr, port: u16, service: &str) -> Result<Self> {
+        let info = service_info(&instance_name(port), ip, port, service)?;
+        let fullname = info.get_fullname().to_string();
+        let daemon = ServiceDaemon::new().context("Failed to start mDNS responder")?;
+        daemon
+            .register(info)
+            .context("Failed to register mDNS service")?;
+        tracing::info!("Advertising {} on the LAN via mDNS", fullname);
+        Ok(Self { daemon, fullname })
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // Goodbye packets go out from the daemon thread; no need to wait for them
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            tracing::debug!("mDNS unregister failed: {}", e);
+        }
+        if let Err(e) = self.daemon.shutdown() {
+            tracing::debug!("mDNS shutdown failed: {}", e);
+        }
+    }
+}
+
+/// Instance label unique per host and port, e.g. `archdrop-laptop-51234`.
+fn instance_name(port: u16) -> String {
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "archdrop".to_string());
+    let host: String = host
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("archdrop-{}-{}", host, port)
+}
+
+/// Record for one session; the TXT entries hold no secrets.
+fn service_info(instance: &str, ip: IpAddr, port: u16, service: &str) -> Result<ServiceInfo> {
+    let host_name = format!("{}.local.", instance);
+    let properties = [
+        (TXT_PORT, port.to_string()),
+        (TXT_SERVICE, service.to_string()),
+    ];
+    ServiceInfo::new(
+        SERVICE_TYPE,
+        instance,
+        &host_name,
+        ip,
+        port,
+        &properties[..],
+    )
+    .context("Failed to build mDNS service record")
+}
+
+/// A sender or receiver found on the LAN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPeer {
+    pub name: String,
+    pub url: String,
+}
+
+impl DiscoveredPeer {
+    fn from_info(info: &ServiceInfo) -> Option<Self> {
+        let ip = info
+            .get_addresses()
+            .iter()
+            .min_by_key(|ip| ip.is_ipv6())
+            .copied()?;
+        let service = info.get_property_val_str(TXT_SERVICE).unwrap_or("send");
+        Some(Self {
+            name: instance_of(info.get_fullname()).to_string(),
+            url: format!(
+                "https://{}:{}/{}",
+                url_host(&ip.to_string()),
+                info.get_port(),
+                service
+            ),
+        })
+    }
+}
+
+/// Instance label of a full service name.
+fn instance_of(fullname: &str) -> &str {
+    fullname
+        .strip_suffix(SERVICE_TYPE)
+        .map_or(fullname, |name| name.trim_end_matches('.'))
+}
+
+/// Browse for advertised sessions for `window`, sorted by name.
+pub async fn discover(window: Duration) -> Result<Vec<DiscoveredPeer>> {
+    let daemon = ServiceDaemon::new().context("Failed to start mDNS browser")?;
+    let events = daemon
+        .browse(SERVICE_TYPE)
+        .context("Failed to browse for mDNS services")?;
+
+    let mut found = BTreeMap::new();
+    let deadline = Instant::now() + window;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match tokio::time::timeout(left, events.recv_async()).await {
+            Ok(Ok(ServiceEvent::ServiceResolved(info))) => {
+                if let Some(peer) = DiscoveredPeer::from_info(&info) {
+                    found.insert(peer.name.clone(), peer);
+                }
+            }
+            Ok(Ok(ServiceEvent::ServiceRemoved(_, fullname))) => {
+                found.remove(instance_of(&fullname));
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) | Err(_) => break,
+        }
+    }
+
+    let _ = daemon.shutdown();
+    Ok(found.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txt_records_carry_port_and_service_only() {
+        let ip: IpAddr = "192.168.1.20".parse().unwrap();
+        let info = service_info("archdrop-test-51234", ip, 51234, "send").unwrap();
+
+        assert_eq!(info.get_property_val_str("port"), Some("51234"));
+        assert_eq!(info.get_property_val_str("service"), Some("send"));
+        assert_eq!(info.get_properties().len(), 2);
+        assert_eq!(info.get_port(), 51234);
+        assert_eq!(
+            info.get_fullname(),
+            "archdrop-test-51234._archdrop._tcp.local."
+        );
+    }
+
+    #[test]
+    fn discovered_peer_url_has_no_fragment() {
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        let info = service_info("archdrop-box-8443", ip, 8443, "receive").unwrap();
+
+        let peer = DiscoveredPeer::from_info(&info).unwrap();
+        assert_eq!(peer.name, "archdrop-box-8443");
+        assert_eq!(peer.url, "https://10.0.0.7:8443/receive");
+    }
+
+    #[test]
+    fn instance_name_is_dns_label_safe() {
+        let name = instance_name(9000);
+        assert!(name.starts_with("archdrop-"));
+        assert!(name.ends_with("-9000"));
+        assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    }
+}
@@ -1,5 +1,7 @@
 pub(crate) mod cloudflare;
 pub(crate) mod local;
+pub(crate) mod mdns;
+pub(crate) mod ngrok;
 pub(crate) mod tailscale;
 pub(crate) mod tunnel;
 
@@ -0,0 +1,442 @@
+//! ngrok tunnel management via the agent's local inspection API.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::transport::tunnel::{permanent, ActiveTunnel};
+use crate::transport::with_startup_timeout;
+
+/// The agent serves its API here unless configured otherwise
+const AGENT_API: &str = "http://127.0.0.1:4040/api/tunnels";
+const AUTHTOKEN_ENV: &str = "NGROK_AUTHTOKEN";
+const TUNNEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const AGENT_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Deserialize)]
+struct TunnelList {
+    tunnels: Vec<AgentTunnel>,
+}
+
+#[derive(Deserialize)]
+struct AgentTunnel {
+    public_url: String,
+    config: AgentTunnelConfig,
+}
+
+#[derive(Deserialize)]
+struct AgentTunnelConfig {
+    addr: String,
+}
+
+#[derive(Serialize)]
+struct CreateTunnel<'a> {
+    name: &'a str,
+    proto: &'a str,
+    addr: String,
+}
+
+/// How the tunnel was opened, which decides how it is torn down.
+enum Backing {
+    /// `ngrok http` child we spawned
+    Process(Child),
+    /// Named tunnel added to an agent that was already running
+    Agent { name: String },
+}
+
+/// Active ngrok tunnel and its public URL.
+pub struct NgrokTunnel {
+    backing: Backing,
+    url: String,
+}
+
+#[derive(Debug, Error)]
+enum NgrokError {
+    #[error("ngrok binary not found")]
+    BinaryMissing,
+    #[error("ngrok exited before URL became available (status: {status})")]
+    ProcessExited {
+        status: String,
+        last_error: Option<String>,
+    },
+    #[error("timed out waiting for ngrok tunnel URL")]
+    UrlTimeout,
+    #[error("ngrok agent rejected the tunnel: {0}")]
+    AgentRejected(String),
+    #[error("ngrok startup failed: {0}")]
+    StartupFailed(String),
+}
+
+/// ngrok startup errors
+fn map_start_error(err: NgrokError) -> anyhow::Error {
+    let retryable = !matches!(
+        err,
+        NgrokError::BinaryMissing | NgrokError::AgentRejected(_)
+    );
+    let err = describe_start_error(err);
+    if retryable {
+        err
+    } else {
+        permanent(err)
+    }
+}
+
+fn describe_start_error(err: NgrokError) -> anyhow::Error {
+    match err {
+        NgrokError::BinaryMissing => anyhow::anyhow!(
+            "Failed to start ngrok tunnel.\n\n\
+             Install ngrok:\n\
+             https://ngrok.com/download\n\n\
+             Or use a different tunnel provider."
+        ),
+        NgrokError::ProcessExited { status, last_error } => {
+            let detail = last_error
+                .map(|line| format!("\n\nngrok said: {}", line))
+                .unwrap_or_default();
+            let hint = if std::env::var_os(AUTHTOKEN_ENV).is_none() {
+                "\n\nngrok needs an authtoken. Set NGROK_AUTHTOKEN or run:\n  \
+                 ngrok config add-authtoken <token>"
+            } else {
+                "\n\nTry again, or use a different tunnel provider."
+            };
+            anyhow::anyhow!(
+                "ngrok exited before tunnel URL became available (status: {}).{}{}",
+                status,
+                detail,
+                hint
+            )
+        }
+        NgrokError::UrlTimeout => anyhow::anyhow!(
+            "Timed out waiting for ngrok tunnel URL.\n\n\
+             Check your internet connection and firewall settings, then try again.\n\n\
+             Or use a different tunnel provider."
+        ),
+        NgrokError::AgentRejected(msg) => anyhow::anyhow!(
+            "The running ngrok agent refused to open a tunnel: {}\n\n\
+             Stop the agent to let ArchDrop start its own, or use a different tunnel provider.",
+            msg
+        ),
+        NgrokError::StartupFailed(msg) => anyhow::anyhow!(
+            "Failed to start ngrok tunnel: {}\n\n\
+             Or use a different tunnel provider.",
+            msg
+        ),
+    }
+}
+
+impl NgrokTunnel {
+    /// Open a tunnel to `local_port`, reusing a running agent when one
+    /// answers on its API port and spawning `ngrok http` otherwise.
+    #[tracing::instrument(fields(local_port))]
+    pub async fn start(local_port: u16) -> Result<Self> {
+        let client = reqwest::Client::new();
+        let result = if agent_running(&client).await {
+            info!("Using running ngrok agent");
+            Self::start_via_agent(&client, local_port).await
+        } else {
+            Self::start_process(&client, local_port).await
+        };
+        result.map_err(map_start_error)
+    }
+
+    async fn start_via_agent(
+        client: &reqwest::Client,
+        local_port: u16,
+    ) -> std::result::Result<Self, NgrokError> {
+        let name = format!("archdrop-{}", local_port);
+        let request = CreateTunnel {
+            name: &name,
+            proto: "http",
+            addr: local_port.to_string(),
+        };
+        let response = client
+            .post(AGENT_API)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| NgrokError::StartupFailed(err.to_string()))?;
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(NgrokError::AgentRejected(body.trim().to_string()));
+        }
+
+        let url = match with_startup_timeout(wait_for_url(client, local_port, None)).await {
+            Ok(result) => result,
+            Err(_) => Err(NgrokError::UrlTimeout),
+        };
+        let mut tunnel = Self {
+            backing: Backing::Agent { name },
+            url: String::new(),
+        };
+        match url {
+            Ok(url) => {
+                tunnel.url = url;
+                Ok(tunnel)
+            }
+            Err(err) => {
+                if let Err(close_err) = tunnel.shutdown().await {
+                    warn!(
+                        "Failed to close ngrok tunnel after startup failure: {}",
+                        close_err
+                    );
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn start_process(
+        client: &reqwest::Client,
+        local_port: u16,
+    ) -> std::result::Result<Self, NgrokError> {
+        let mut command = Command::new("ngrok");
+        command
+            .args([
+                "http",
+                &format!("http://localhost:{}", local_port),
+                "--log",
+                "stderr",
+                "--log-format",
+                "logfmt",
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Re-exported trimmed; tokens pasted into shell profiles often
+        // carry a trailing newline ngrok rejects
+        if let Some(token) = authtoken() {
+            command.env(AUTHTOKEN_ENV, token);
+        }
+
+        let mut child = command.spawn().map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                NgrokError::BinaryMissing
+            } else {
+                NgrokError::StartupFailed(err.to_string())
+            }
+        })?;
+
+        let (error_tx, error_rx) = watch::channel(None);
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(log_stderr(stderr, error_tx));
+        }
+
+        let url = match with_startup_timeout(wait_for_url(
+            client,
+            local_port,
+            Some((&mut child, error_rx)),
+        ))
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(NgrokError::UrlTimeout),
+        };
+        match url {
+            Ok(url) => Ok(Self {
+                backing: Backing::Process(child),
+                url,
+            }),
+            Err(err) => {
+                if let Err(kill_err) = child.kill().await {
+                    warn!("Failed to kill ngrok after startup failure: {}", kill_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Stops the spawned agent, or removes our tunnel from a shared one.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        match &mut self.backing {
+            Backing::Process(child) => {
+                if let Err(e) = child.kill().await {
+                    warn!("Failed to stop ngrok process: {}", e);
+                    return Ok(());
+                }
+                match tokio::time::timeout(Duration::from_secs(5), child.wait()).await {
+                    Ok(Ok(status)) => {
+                        info!("ngrok exited with status: {}", status);
+                        Ok(())
+                    }
+                    Ok(Err(e)) => Err(e).context("Failed to wait for ngrok process"),
+                    Err(_) => {
+                        warn!("ngrok did not exit after 5 seconds, may be stuck");
+                        Ok(())
+                    }
+                }
+            }
+            Backing::Agent { name } => {
+                reqwest::Client::new()
+                    .delete(format!("{}/{}", AGENT_API, name))
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status())
+                    .context("Failed to close tunnel on ngrok agent")?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Return the public forwarding URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveTunnel for NgrokTunnel {
+    async fn shutdown(&mut self) -> Result<()> {
+        NgrokTunnel::shutdown(self).await
+    }
+}
+
+fn authtoken() -> Option<String> {
+    std::env::var(AUTHTOKEN_ENV)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+async fn agent_running(client: &reqwest::Client) -> bool {
+    matches!(
+        tokio::time::timeout(AGENT_PROBE_TIMEOUT, client.get(AGENT_API).send()).await,
+        Ok(Ok(res)) if res.status().is_success()
+    )
+}
+
+/// Poll the agent API until it lists an https tunnel for `local_port`.
+///
+/// With a spawned child, also stop early if it exits.
+async fn wait_for_url(
+    client: &reqwest::Client,
+    local_port: u16,
+    mut process: Option<(&mut Child, watch::Receiver<Option<String>>)>,
+) -> std::result::Result<String, NgrokError> {
+    loop {
+        if let Some((child, last_error)) = process.as_mut() {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|err| NgrokError::StartupFailed(err.to_string()))?
+            {
+                return Err(NgrokError::ProcessExited {
+                    status: status.to_string(),
+                    last_error: last_error.borrow().clone(),
+                });
+            }
+        }
+
+        // silence errors here because the API is not up until ngrok is
+        if let Ok(res) = client.get(AGENT_API).send().await {
+            if let Ok(body) = res.text().await {
+                if let Some(url) = public_url_for(&body, local_port) {
+                    return Ok(url);
+                }
+            }
+        }
+
+        tokio::time::sleep(TUNNEL_POLL_INTERVAL).await;
+    }
+}
+
+/// HTTPS forwarding URL for `local_port` in an `/api/tunnels` response.
+///
+/// The agent may host unrelated tunnels, so only ones whose upstream
+/// address ends in our port count.
+fn public_url_for(json: &str, local_port: u16) -> Option<String> {
+    let list: TunnelList = serde_json::from_str(json).ok()?;
+    let port = local_port.to_string();
+    list.tunnels
+        .into_iter()
+        .filter(|tunnel| {
+            let addr = tunnel.config.addr.trim_end_matches('/');
+            addr == port || addr.rsplit_once(':').is_some_and(|(_, p)| p == port)
+        })
+        .map(|tunnel| tunnel.public_url)
+        .find(|url| url.starts_with("https://"))
+}
+
+// ngrok logs in logfmt; `lvl=eror` lines explain early exits
+async fn log_stderr(stderr: ChildStderr, error_tx: watch::Sender<Option<String>>) {
+    let reader = BufReader::new(stderr);
+    let mut lines = reader.lines();
+
+    while let Some(line) = lines.next_line().await.ok().flatten() {
+        if line.contains("lvl=eror") || line.contains("lvl=crit") || line.contains("ERROR:") {
+            tracing::error!("ngrok stderr: {}", line);
+            error_tx.send_replace(Some(line));
+        } else {
+            tracing::debug!("ngrok stderr: {}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from `curl localhost:4040/api/tunnels` with a second agent tunnel
+    const TUNNELS_JSON: &str = r#"{
+        "tunnels": [
+            {
+                "name": "webapp",
+                "ID": "2f3d1a",
+                "uri": "/api/tunnels/webapp",
+                "public_url": "https://1a2b-203-0-113-7.ngrok-free.app",
+                "proto": "https",
+                "config": { "addr": "http://localhost:3000", "inspect": true },
+                "metrics": {}
+            },
+            {
+                "name": "command_line",
+                "ID": "9c8e7f",
+                "uri": "/api/tunnels/command_line",
+                "public_url": "https://5e6f-203-0-113-7.ngrok-free.app",
+                "proto": "https",
+                "config": { "addr": "http://localhost:8443", "inspect": true },
+                "metrics": {}
+            }
+        ],
+        "uri": "/api/tunnels"
+    }"#;
+
+    #[test]
+    fn picks_forwarding_url_for_our_port() {
+        assert_eq!(
+            public_url_for(TUNNELS_JSON, 8443).as_deref(),
+            Some("https://5e6f-203-0-113-7.ngrok-free.app")
+        );
+        assert_eq!(
+            public_url_for(TUNNELS_JSON, 3000).as_deref(),
+            Some("https://1a2b-203-0-113-7.ngrok-free.app")
+        );
+    }
+
+    #[test]
+    fn no_url_until_our_tunnel_is_listed() {
+        assert_eq!(public_url_for(TUNNELS_JSON, 9999), None);
+        assert_eq!(
+            public_url_for(r#"{"tunnels":[],"uri":"/api/tunnels"}"#, 8443),
+            None
+        );
+        assert_eq!(public_url_for("not json", 8443), None);
+    }
+
+    #[test]
+    fn plain_http_endpoints_are_skipped() {
+        let json = r#"{"tunnels":[
+            {"public_url":"http://5e6f.ngrok-free.app","config":{"addr":"8443"}},
+            {"public_url":"https://5e6f.ngrok-free.app","config":{"addr":"8443"}}
+        ]}"#;
+        assert_eq!(
+            public_url_for(json, 8443).as_deref(),
+            Some("https://5e6f.ngrok-free.app")
+        );
+    }
+}
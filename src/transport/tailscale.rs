@@ -2,10 +2,13 @@
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use serde::Deserialize;
+use std::net::IpAddr;
 use tailscale_localapi::LocalApi;
 use thiserror::Error;
 use tokio::process::Command;
 
+use crate::transport::tunnel::{permanent, ActiveTunnel};
 use crate::transport::with_startup_timeout;
 
 /// Active Tailscale tunnel context.
@@ -25,6 +28,10 @@ enum TailscaleError {
     BinaryMissing,
     #[error("tailscale daemon not available")]
     DaemonUnavailable,
+    #[error("tailscale is not running (state {0})")]
+    NotRunning(String),
+    #[error("tailscale node has no MagicDNS name")]
+    NoMagicDns(Option<IpAddr>),
     #[error("tailscale startup timed out")]
     StartupTimeout,
     #[error("unknown tailscale error: {0}")]
@@ -33,26 +40,99 @@ enum TailscaleError {
 
 struct CommandOutput {
     success: bool,
+    stdout: String,
     stderr: String,
 }
 
+/// The parts of `tailscale status --json` (same shape as the local API's
+/// `/status`) needed to advertise this node.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NodeStatus {
+    backend_state: String,
+    #[serde(rename = "Self", default)]
+    self_status: Option<SelfStatus>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SelfStatus {
+    #[serde(rename = "DNSName", default)]
+    dns_name: String,
+    #[serde(rename = "TailscaleIPs", default)]
+    tailscale_ips: Option<Vec<IpAddr>>,
+}
+
+impl NodeStatus {
+    fn from_json(json: &str) -> Result<Self, TailscaleError> {
+        serde_json::from_str(json)
+            .map_err(|e| TailscaleError::Unknown(format!("unreadable tailscale status: {}", e)))
+    }
+
+    /// MagicDNS name the funnel serves on, e.g. `host.tailnet.ts.net`.
+    ///
+    /// Funnel certificates are issued for the MagicDNS name only, so the
+    /// 100.x address is reported for diagnosis but never advertised.
+    fn advertised_host(&self) -> Result<String, TailscaleError> {
+        if self.backend_state != "Running" {
+            return Err(TailscaleError::NotRunning(self.backend_state.clone()));
+        }
+        let node = self.self_status.as_ref();
+        let host = node
+            .map(|node| node.dns_name.trim_end_matches('.'))
+            .unwrap_or_default();
+        if host.is_empty() {
+            return Err(TailscaleError::NoMagicDns(self.tailnet_ipv4()));
+        }
+        Ok(host.to_string())
+    }
+
+    fn tailnet_ipv4(&self) -> Option<IpAddr> {
+        self.self_status
+            .as_ref()?
+            .tailscale_ips
+            .as_ref()?
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .copied()
+    }
+}
+
 #[async_trait]
 trait TailscaleBackend {
-    async fn hostname(&self) -> Result<String, TailscaleError>;
+    async fn status(&self) -> Result<NodeStatus, TailscaleError>;
     async fn run(&self, args: &[&str]) -> Result<CommandOutput, TailscaleError>;
 }
 
 struct SystemTailscaleBackend;
 
+/// tailscaled's API socket on Linux; other platforms go through the CLI.
+const TAILSCALED_SOCKET: &str = "/var/run/tailscale/tailscaled.sock";
+
 #[async_trait]
 impl TailscaleBackend for SystemTailscaleBackend {
-    async fn hostname(&self) -> Result<String, TailscaleError> {
-        let client = LocalApi::new_with_socket_path("/var/run/tailscale/tailscaled.sock");
-        let status = with_startup_timeout(client.status())
-            .await
-            .map_err(|_| TailscaleError::StartupTimeout)?
-            .map_err(|_| TailscaleError::DaemonUnavailable)?;
-        Ok(status.self_status.dnsname.trim_end_matches('.').to_string())
+    async fn status(&self) -> Result<NodeStatus, TailscaleError> {
+        let client = LocalApi::new_with_socket_path(TAILSCALED_SOCKET);
+        match with_startup_timeout(client.status()).await {
+            Ok(Ok(status)) => {
+                return Ok(NodeStatus {
+                    backend_state: format!("{:?}", status.backend_state),
+                    self_status: Some(SelfStatus {
+                        dns_name: status.self_status.dnsname,
+                        tailscale_ips: Some(status.self_status.tailscale_ips),
+                    }),
+                })
+            }
+            Ok(Err(e)) => tracing::debug!("Tailscale local API unavailable, using CLI: {}", e),
+            Err(_) => return Err(TailscaleError::StartupTimeout),
+        }
+
+        let output = self.run(&["status", "--json"]).await?;
+        // A logged-out node still prints its status, so parse before
+        // looking at the exit code
+        match NodeStatus::from_json(&output.stdout) {
+            Err(_) if !output.success => Err(TailscaleError::DaemonUnavailable),
+            parsed => parsed,
+        }
     }
 
     async fn run(&self, args: &[&str]) -> Result<CommandOutput, TailscaleError> {
@@ -69,6 +149,7 @@ impl TailscaleBackend for SystemTailscaleBackend {
 
         Ok(CommandOutput {
             success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
         })
     }
@@ -104,6 +185,22 @@ fn is_benign_off_failure(stderr: &str) -> bool {
 }
 
 fn map_start_error(err: TailscaleError) -> anyhow::Error {
+    // Only a busy or slow daemon can come good on the next attempt
+    let retryable = matches!(
+        err,
+        TailscaleError::DaemonUnavailable
+            | TailscaleError::StartupTimeout
+            | TailscaleError::Unknown(_)
+    );
+    let err = describe_start_error(err);
+    if retryable {
+        err
+    } else {
+        permanent(err)
+    }
+}
+
+fn describe_start_error(err: TailscaleError) -> anyhow::Error {
     match err {
         TailscaleError::PermissionDenied => anyhow!(
             "Tailscale funnel requires operator permissions.\n\n\
@@ -129,6 +226,19 @@ fn map_start_error(err: TailscaleError) -> anyhow::Error {
              Then run: sudo tailscale up\n\n\
              Or use a different tunnel provider."
         ),
+        TailscaleError::NotRunning(state) => anyhow!(
+            "Tailscale is installed but not connected (state: {}).\n\n\
+             Log in and connect with: tailscale up\n\n\
+             Or use a different tunnel provider.",
+            state
+        ),
+        TailscaleError::NoMagicDns(ip) => anyhow!(
+            "This Tailscale node{} has no MagicDNS name.\n\n\
+             Funnel needs MagicDNS and HTTPS certificates enabled in the\n\
+             admin console: https://login.tailscale.com/admin/dns\n\n\
+             Or use a different tunnel provider.",
+            ip.map(|ip| format!(" ({})", ip)).unwrap_or_default()
+        ),
         TailscaleError::StartupTimeout => anyhow!(
             "Timed out starting Tailscale funnel.\n\n\
              Check that Tailscale is running and responsive, then try again.\n\n\
@@ -158,7 +268,7 @@ impl TailscaleTunnel {
         backend: &B,
         port: u16,
     ) -> std::result::Result<Self, TailscaleError> {
-        let hostname = backend.hostname().await?;
+        let hostname = backend.status().await?.advertised_host()?;
 
         let port_arg = port.to_string();
         let output = backend.run(&["funnel", "--bg", &port_arg]).await?;
@@ -239,6 +349,13 @@ impl TailscaleTunnel {
     }
 }
 
+#[async_trait]
+impl ActiveTunnel for TailscaleTunnel {
+    async fn shutdown(&mut self) -> Result<()> {
+        TailscaleTunnel::shutdown(self).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,8 +385,14 @@ mod tests {
 
     #[async_trait]
     impl TailscaleBackend for MockBackend {
-        async fn hostname(&self) -> Result<String, TailscaleError> {
-            Ok(self.hostname.clone())
+        async fn status(&self) -> Result<NodeStatus, TailscaleError> {
+            Ok(NodeStatus {
+                backend_state: "Running".to_string(),
+                self_status: Some(SelfStatus {
+                    dns_name: format!("{}.", self.hostname),
+                    tailscale_ips: None,
+                }),
+            })
         }
 
         async fn run(&self, args: &[&str]) -> Result<CommandOutput, TailscaleError> {
@@ -291,6 +414,7 @@ mod tests {
             "host.test.ts.net",
             vec![Ok(CommandOutput {
                 success: false,
+                stdout: String::new(),
                 stderr: "Access denied: serve config denied".to_string(),
             })],
         );
@@ -304,6 +428,7 @@ mod tests {
             "host.test.ts.net",
             vec![Ok(CommandOutput {
                 success: false,
+                stdout: String::new(),
                 stderr: "already listening on 8443".to_string(),
             })],
         );
@@ -322,10 +447,12 @@ mod tests {
             vec![
                 Ok(CommandOutput {
                     success: true,
+                    stdout: String::new(),
                     stderr: String::new(),
                 }),
                 Ok(CommandOutput {
                     success: true,
+                    stdout: String::new(),
                     stderr: String::new(),
                 }),
             ],
@@ -351,6 +478,7 @@ mod tests {
             "host.test.ts.net",
             vec![Ok(CommandOutput {
                 success: false,
+                stdout: String::new(),
                 stderr: "address already in use".to_string(),
             })],
         );
@@ -369,6 +497,70 @@ mod tests {
         assert_eq!(calls[0], vec!["funnel", "--bg", "8443"]);
     }
 
+    const STATUS_FIXTURE: &str = r#"{
+        "Version": "1.76.1-t1234abcd",
+        "BackendState": "Running",
+        "AuthURL": "",
+        "TailscaleIPs": ["100.101.102.103", "fd7a:115c:a1e0::1"],
+        "Self": {
+            "ID": "nAbCdEf",
+            "HostName": "laptop",
+            "DNSName": "laptop.tail1234.ts.net.",
+            "OS": "linux",
+            "TailscaleIPs": ["100.101.102.103", "fd7a:115c:a1e0::1"],
+            "Online": true
+        },
+        "MagicDNSSuffix": "tail1234.ts.net",
+        "Peer": null
+    }"#;
+
+    #[test]
+    fn status_fixture_advertises_magic_dns_name() {
+        let status = NodeStatus::from_json(STATUS_FIXTURE).expect("parse fixture");
+        assert_eq!(status.advertised_host().unwrap(), "laptop.tail1234.ts.net");
+        assert_eq!(
+            status.tailnet_ipv4(),
+            Some("100.101.102.103".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn logged_out_or_dnsless_status_is_a_clear_error() {
+        let logged_out = STATUS_FIXTURE.replace("\"Running\"", "\"NeedsLogin\"");
+        let status = NodeStatus::from_json(&logged_out).unwrap();
+        assert_eq!(
+            status.advertised_host(),
+            Err(TailscaleError::NotRunning("NeedsLogin".to_string()))
+        );
+        let message = map_start_error(status.advertised_host().unwrap_err()).to_string();
+        assert!(message.contains("tailscale up"), "{message}");
+
+        let no_dns = STATUS_FIXTURE.replace("laptop.tail1234.ts.net.", "");
+        let status = NodeStatus::from_json(&no_dns).unwrap();
+        let err = status.advertised_host().unwrap_err();
+        assert_eq!(
+            err,
+            TailscaleError::NoMagicDns(Some("100.101.102.103".parse().unwrap()))
+        );
+        assert!(map_start_error(err).to_string().contains("100.101.102.103"));
+    }
+
+    #[tokio::test]
+    async fn start_uses_status_host_in_url() {
+        let backend = MockBackend::new(
+            "host.test.ts.net",
+            vec![Ok(CommandOutput {
+                success: true,
+                stdout: String::new(),
+                stderr: String::new(),
+            })],
+        );
+        let tunnel = TailscaleTunnel::start_with_backend(&backend, 8443)
+            .await
+            .expect("start should succeed");
+        assert_eq!(tunnel.url(), "https://host.test.ts.net");
+    }
+
     #[test]
     fn binary_missing_is_typed() {
         let err = TailscaleError::BinaryMissing;
@@ -1,43 +1,283 @@
 //! Provider agnostic tunnel behavior
 
 use super::cloudflare::CloudflareTunnel;
+use super::ngrok::NgrokTunnel;
 use super::tailscale::TailscaleTunnel;
 use super::with_startup_timeout;
 use crate::common::config::Transport;
 
 use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::warn;
 
-pub enum Tunnel {
-    Cloudflare(CloudflareTunnel),
-    Tailscale(TailscaleTunnel),
+/// How often [`open`] tries a provider, and the wait before the first
+/// retry; the wait doubles after each failure.
+#[derive(Debug, Clone, Copy)]
+pub struct StartRetry {
+    pub attempts: u32,
+    pub base_delay: Duration,
 }
 
-impl Tunnel {
-    #[tracing::instrument(fields(transport = ?transport, port))]
-    pub async fn start(transport: Transport, port: u16) -> Result<Self> {
-        with_startup_timeout(async {
-            match transport {
-                Transport::Local => anyhow::bail!("Local transport does not use tunneling"),
-                Transport::Cloudflare => Ok(Self::Cloudflare(CloudflareTunnel::start(port).await?)),
-                Transport::Tailscale => Ok(Self::Tailscale(TailscaleTunnel::start(port).await?)),
-            }
-        })
-        .await
-        .map_err(|_| anyhow::anyhow!("Timed out establishing tunnel"))?
+/// Covers a cold `cloudflared`/`ngrok` start without stalling on real outages
+pub const DEFAULT_START_RETRY: StartRetry = StartRetry {
+    attempts: 3,
+    base_delay: Duration::from_secs(2),
+};
+
+/// Startup failure another attempt cannot fix (missing binary, no
+/// permissions), so [`open`] reports it straight away.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct PermanentError(#[from] anyhow::Error);
+
+/// Mark a provider startup error as not worth retrying.
+pub fn permanent(err: anyhow::Error) -> anyhow::Error {
+    PermanentError(err).into()
+}
+
+/// A way of exposing a loopback port on a public URL.
+///
+/// Adding a provider means implementing this and [`ActiveTunnel`], then
+/// returning it from [`provider_for`].
+#[async_trait]
+pub trait TunnelProvider: Send + Sync {
+    /// Name shown while the tunnel starts, e.g. "Cloudflare"
+    fn display_name(&self) -> &'static str;
+
+    /// Expose `local_port` and return the running tunnel.
+    async fn start(&self, local_port: u16) -> Result<TunnelHandle>;
+}
+
+/// Whatever a running tunnel owns (a child process, a funnel mapping) and
+/// must release at shutdown.
+#[async_trait]
+pub trait ActiveTunnel: Send {
+    async fn shutdown(&mut self) -> Result<()>;
+}
+
+/// A running tunnel: its public URL plus the resources behind it.
+pub struct TunnelHandle {
+    url: String,
+    active: Box<dyn ActiveTunnel>,
+}
+
+impl TunnelHandle {
+    pub fn new(url: impl Into<String>, active: impl ActiveTunnel + 'static) -> Self {
+        Self {
+            url: url.into(),
+            active: Box::new(active),
+        }
     }
 
     pub fn url(&self) -> &str {
-        match self {
-            Self::Cloudflare(t) => t.url(),
-            Self::Tailscale(t) => t.url(),
-        }
+        &self.url
     }
 
     #[tracing::instrument(skip(self))]
     pub async fn shutdown(&mut self) -> Result<()> {
-        match self {
-            Self::Cloudflare(t) => t.shutdown().await,
-            Self::Tailscale(t) => t.shutdown().await,
+        self.active.shutdown().await
+    }
+}
+
+struct CloudflareProvider;
+
+#[async_trait]
+impl TunnelProvider for CloudflareProvider {
+    fn display_name(&self) -> &'static str {
+        "Cloudflare"
+    }
+
+    async fn start(&self, local_port: u16) -> Result<TunnelHandle> {
+        let tunnel = CloudflareTunnel::start(local_port).await?;
+        let url = tunnel.url().to_string();
+        Ok(TunnelHandle::new(url, tunnel))
+    }
+}
+
+struct TailscaleProvider;
+
+#[async_trait]
+impl TunnelProvider for TailscaleProvider {
+    fn display_name(&self) -> &'static str {
+        "Tailscale"
+    }
+
+    async fn start(&self, local_port: u16) -> Result<TunnelHandle> {
+        let tunnel = TailscaleTunnel::start(local_port).await?;
+        let url = tunnel.url().to_string();
+        Ok(TunnelHandle::new(url, tunnel))
+    }
+}
+
+struct NgrokProvider;
+
+#[async_trait]
+impl TunnelProvider for NgrokProvider {
+    fn display_name(&self) -> &'static str {
+        "ngrok"
+    }
+
+    async fn start(&self, local_port: u16) -> Result<TunnelHandle> {
+        let tunnel = NgrokTunnel::start(local_port).await?;
+        let url = tunnel.url().to_string();
+        Ok(TunnelHandle::new(url, tunnel))
+    }
+}
+
+/// Tunnel provider behind a transport; None for direct local serving.
+pub fn provider_for(transport: Transport) -> Option<Box<dyn TunnelProvider>> {
+    match transport {
+        Transport::Local => None,
+        Transport::Cloudflare => Some(Box::new(CloudflareProvider)),
+        Transport::Tailscale => Some(Box::new(TailscaleProvider)),
+        Transport::Ngrok => Some(Box::new(NgrokProvider)),
+    }
+}
+
+/// Start `provider`, giving each attempt the shared transport startup
+/// timeout and backing off between failed ones.
+#[tracing::instrument(skip(provider, retry), fields(provider = provider.display_name(), port))]
+pub async fn open(
+    provider: &dyn TunnelProvider,
+    port: u16,
+    retry: StartRetry,
+) -> Result<TunnelHandle> {
+    let attempts = retry.attempts.max(1);
+    let mut delay = retry.base_delay;
+    let mut attempt = 1;
+
+    loop {
+        let err = match with_startup_timeout(provider.start(port)).await {
+            Ok(Ok(tunnel)) => return Ok(tunnel),
+            Ok(Err(err)) => err,
+            Err(_) => anyhow::anyhow!("Timed out establishing tunnel"),
+        };
+        if attempt >= attempts || err.downcast_ref::<PermanentError>().is_some() {
+            return Err(err);
+        }
+
+        warn!(
+            "{} tunnel attempt {}/{} failed: {:#}. Retrying in {:?}...",
+            provider.display_name(),
+            attempt,
+            attempts,
+            err,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+        delay = delay.saturating_mul(2);
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const FAST_RETRY: StartRetry = StartRetry {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+    };
+
+    struct NoopTunnel;
+
+    #[async_trait]
+    impl ActiveTunnel for NoopTunnel {
+        async fn shutdown(&mut self) -> Result<()> {
+            Ok(())
         }
     }
+
+    /// Fails its first `failures` starts, then hands out a tunnel.
+    struct FlakyProvider {
+        failures: u32,
+        starts: AtomicU32,
+        permanent: bool,
+    }
+
+    impl FlakyProvider {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures,
+                starts: AtomicU32::new(0),
+                permanent: false,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TunnelProvider for FlakyProvider {
+        fn display_name(&self) -> &'static str {
+            "Flaky"
+        }
+
+        async fn start(&self, local_port: u16) -> Result<TunnelHandle> {
+            let start = self.starts.fetch_add(1, Ordering::SeqCst) + 1;
+            if start <= self.failures {
+                let err = anyhow::anyhow!("cold start {}", start);
+                return Err(if self.permanent { permanent(err) } else { err });
+            }
+            Ok(TunnelHandle::new(
+                format!("https://flaky.example:{}", local_port),
+                NoopTunnel,
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_provider_comes_up() {
+        let provider = FlakyProvider::new(2);
+        let tunnel = open(&provider, 9000, FAST_RETRY)
+            .await
+            .expect("third attempt should succeed");
+
+        assert_eq!(tunnel.url(), "https://flaky.example:9000");
+        assert_eq!(provider.starts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_last_attempt() {
+        let provider = FlakyProvider::new(5);
+        let err = open(&provider, 9000, FAST_RETRY)
+            .await
+            .err()
+            .expect("all attempts fail");
+
+        assert_eq!(err.to_string(), "cold start 3");
+        assert_eq!(provider.starts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let provider = FlakyProvider {
+            permanent: true,
+            ..FlakyProvider::new(5)
+        };
+        let err = open(&provider, 9000, FAST_RETRY)
+            .await
+            .err()
+            .expect("permanent failure");
+
+        assert_eq!(err.to_string(), "cold start 1");
+        assert_eq!(provider.starts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn backoff_doubles_between_attempts() {
+        let provider = FlakyProvider::new(2);
+        let retry = StartRetry {
+            attempts: 3,
+            base_delay: Duration::from_millis(40),
+        };
+        let started = tokio::time::Instant::now();
+        open(&provider, 9000, retry)
+            .await
+            .expect("third attempt should succeed");
+
+        // 40ms then 80ms
+        assert!(started.elapsed() >= Duration::from_millis(120));
+    }
 }
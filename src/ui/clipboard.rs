@@ -0,0 +1,116 @@
+//! Copying the transfer link to the system clipboard (`--clipboard`).
+//!
+//! arboard picks the platform backend (Wayland data-control, X11, macOS,
+//! Windows). Where none is reachable, such as over SSH, copying is skipped
+//! and the reason is shown instead.
+
+/// Anything that can take the link as clipboard text.
+pub trait ClipboardBackend: Send {
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+}
+
+impl ClipboardBackend for arboard::Clipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        arboard::Clipboard::set_text(self, text).map_err(|err| err.to_string())
+    }
+}
+
+/// Owner of the copied text.
+///
+/// On X11 and Wayland the process serves the selection itself, so the text
+/// vanishes once this drops; keep it for the whole session.
+#[must_use = "dropping the holder can clear the copied link"]
+pub struct HeldClipboard {
+    _owner: Option<Box<dyn ClipboardBackend>>,
+}
+
+impl HeldClipboard {
+    /// True when the link actually made it onto the clipboard.
+    pub fn is_held(&self) -> bool {
+        self._owner.is_some()
+    }
+}
+
+/// Copy `url` to the system clipboard and return a status line for the user.
+pub fn copy_link(url: &str) -> (HeldClipboard, String) {
+    match arboard::Clipboard::new() {
+        Ok(clipboard) => copy_with(Box::new(clipboard), url),
+        Err(err) => {
+            tracing::debug!("No clipboard available: {}", err);
+            (
+                HeldClipboard { _owner: None },
+                format!("Clipboard unavailable ({}); copy the link manually", err),
+            )
+        }
+    }
+}
+
+fn copy_with(mut backend: Box<dyn ClipboardBackend>, url: &str) -> (HeldClipboard, String) {
+    match backend.set_text(url) {
+        Ok(()) => (
+            HeldClipboard {
+                _owner: Some(backend),
+            },
+            "Link copied to clipboard".to_string(),
+        ),
+        Err(err) => {
+            tracing::warn!("Failed to copy link to clipboard: {}", err);
+            (
+                HeldClipboard { _owner: None },
+                format!("Could not copy link to clipboard: {}", err),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct StubClipboard {
+        contents: Arc<Mutex<Option<String>>>,
+        fail: bool,
+    }
+
+    impl ClipboardBackend for StubClipboard {
+        fn set_text(&mut self, text: &str) -> Result<(), String> {
+            if self.fail {
+                return Err("selection owner refused".to_string());
+            }
+            *self.contents.lock().unwrap() = Some(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn link_is_written_and_backend_kept_alive() {
+        let contents = Arc::new(Mutex::new(None));
+        let stub = StubClipboard {
+            contents: contents.clone(),
+            fail: false,
+        };
+
+        let (held, message) = copy_with(Box::new(stub), "https://192.0.2.7:8443/send#k=abc");
+
+        assert_eq!(
+            contents.lock().unwrap().as_deref(),
+            Some("https://192.0.2.7:8443/send#k=abc")
+        );
+        assert_eq!(message, "Link copied to clipboard");
+        assert!(held._owner.is_some());
+    }
+
+    #[test]
+    fn backend_errors_become_a_status_message() {
+        let stub = StubClipboard {
+            contents: Arc::new(Mutex::new(None)),
+            fail: true,
+        };
+
+        let (held, message) = copy_with(Box::new(stub), "https://example.test");
+
+        assert!(message.contains("selection owner refused"));
+        assert!(held._owner.is_none());
+    }
+}
@@ -0,0 +1,90 @@
+//! Color policy shared by the TUI, spinners, and log output.
+//!
+//! Colors are off when `--no-color` is passed, when `NO_COLOR` is set to a
+//! non-empty value (<https://no-color.org>), or when stdout is not a terminal.
+
+use std::ffi::OsStr;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ratatui::{buffer::Buffer, style::Color};
+
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Decide whether to emit colors from the flag, `NO_COLOR`, and TTY state.
+pub fn resolve(no_color_flag: bool, no_color_env: Option<&OsStr>, is_tty: bool) -> bool {
+    let env_disables = no_color_env.is_some_and(|value| !value.is_empty());
+    !no_color_flag && !env_disables && is_tty
+}
+
+/// Resolve the policy for this process and apply it to `console` styling.
+///
+/// Returns whether colors are enabled, for configuring the log formatter.
+pub fn init(no_color_flag: bool) -> bool {
+    let enabled = resolve(
+        no_color_flag,
+        std::env::var_os("NO_COLOR").as_deref(),
+        std::io::stdout().is_terminal(),
+    );
+    set_enabled(enabled);
+    enabled
+}
+
+/// Override the process-wide color policy.
+pub fn set_enabled(enabled: bool) {
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
+
+/// Whether colored output is currently allowed.
+pub fn enabled() -> bool {
+    COLORS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Reset every cell's colors in a rendered TUI frame, keeping bold/dim etc.
+pub fn strip_colors(buf: &mut Buffer) {
+    for cell in buf.content.iter_mut() {
+        cell.set_fg(Color::Reset);
+        cell.set_bg(Color::Reset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{
+        layout::Rect,
+        style::{Style, Stylize},
+        widgets::{Paragraph, Widget},
+    };
+
+    #[test]
+    fn no_color_env_flag_and_pipes_disable_colors() {
+        assert!(resolve(false, None, true));
+        assert!(!resolve(true, None, true));
+        assert!(!resolve(false, Some(OsStr::new("1")), true));
+        assert!(!resolve(false, None, false));
+        // An empty NO_COLOR does not count as set
+        assert!(resolve(false, Some(OsStr::new("")), true));
+    }
+
+    #[test]
+    fn no_color_output_contains_no_ansi_escapes() {
+        set_enabled(resolve(false, Some(OsStr::new("1")), true));
+
+        let line = crate::ui::tui::status_line(true, "sent");
+        assert!(!line.contains('\x1b'), "{line:?}");
+        assert!(line.contains("sent"));
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 1));
+        Paragraph::new("hi".red())
+            .style(Style::default().on_blue())
+            .render(buf.area, &mut buf);
+        strip_colors(&mut buf);
+        assert!(buf
+            .content
+            .iter()
+            .all(|cell| cell.fg == Color::Reset && cell.bg == Color::Reset));
+    }
+}
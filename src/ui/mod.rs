@@ -1,2 +1,5 @@
+pub mod clipboard;
+pub mod color;
+pub mod prompt;
 pub mod tui;
 pub mod web;
@@ -0,0 +1,80 @@
+//! Interactive prompts outside the TUI.
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::io::{self, BufRead, IsTerminal, Write};
+use zeroize::Zeroizing;
+
+/// Restores cooked mode even if reading fails.
+struct RawModeGuard;
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Read a secret from the terminal without echoing it.
+///
+/// When stdin is not a terminal the first line of stdin is used, so scripts
+/// can pipe the secret in.
+pub fn read_secret(prompt: &str) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        let mut line = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .context("Failed to read from stdin")?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    eprint!("{}", prompt);
+    io::stderr().flush().ok();
+
+    enable_raw_mode().context("Failed to enable raw terminal mode")?;
+    let guard = RawModeGuard;
+    let mut secret = String::new();
+    loop {
+        let Event::Key(key) = event::read().context("Failed to read key")? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => break,
+            KeyCode::Backspace => {
+                secret.pop();
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                drop(guard);
+                eprintln!();
+                anyhow::bail!("Cancelled");
+            }
+            KeyCode::Esc => {
+                drop(guard);
+                eprintln!();
+                anyhow::bail!("Cancelled");
+            }
+            KeyCode::Char(c) => secret.push(c),
+            _ => {}
+        }
+    }
+    drop(guard);
+    eprintln!();
+    Ok(secret)
+}
+
+/// Ask for a new passphrase, twice on a terminal so typos are caught.
+pub fn read_new_passphrase() -> Result<String> {
+    let passphrase = read_secret("Passphrase: ")?;
+    anyhow::ensure!(!passphrase.is_empty(), "Passphrase must not be empty");
+    if io::stdin().is_terminal() {
+        let confirm = Zeroizing::new(read_secret("Repeat passphrase: ")?);
+        anyhow::ensure!(*confirm == passphrase, "Passphrases do not match");
+    }
+    Ok(passphrase)
+}
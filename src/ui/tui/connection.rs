@@ -21,6 +21,7 @@ pub(crate) fn render_connection_panel(
     area: Rect,
     config: &TuiConfig,
     compact_qr_code: Option<&str>,
+    link_hidden: bool,
     feedback_text: &str,
     feedback_style: Style,
 ) {
@@ -91,6 +92,11 @@ pub(crate) fn render_connection_panel(
         );
     } else if config.show_qr {
         render_qr_section(frame, content, &config.qr_code, compact_qr_code, ACCENT);
+    } else if link_hidden {
+        let note = Paragraph::new("Link hidden - press k to show it")
+            .alignment(Alignment::Center)
+            .style(muted_style());
+        frame.render_widget(note, content);
     } else if config.show_url {
         let url_only = Layout::default()
             .direction(Direction::Vertical)
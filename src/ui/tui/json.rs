@@ -0,0 +1,166 @@
+//! Newline-delimited JSON progress events (`--json`), in place of the TUI.
+//!
+//! One object per line on stdout: `claimed` once a client claims the
+//! session, `progress` whenever the chunk count moves, then exactly one
+//! terminal event (`complete`, `failed`, or `cancelled`).
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+
+use crate::common::TransferEvent;
+use crate::server::progress::ProgressTracker;
+
+/// How often progress is sampled between lifecycle events.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Write events for `tracker` to `out` until the transfer finishes or
+/// `cancel` fires. Returns the writer so callers can inspect buffered output.
+pub async fn emit_events<W: Write>(
+    tracker: Arc<ProgressTracker>,
+    cancel: CancellationToken,
+    mut out: W,
+) -> io::Result<W> {
+    let mut events = tracker.subscribe();
+    let mut claimed = false;
+    let mut last_progress = None;
+
+    loop {
+        if !claimed && tracker.client_id().is_some() {
+            claimed = true;
+            write_event(&mut out, json!({"event": "claimed"}))?;
+        }
+
+        let (completed, total) = tracker.get_progress();
+        if total > 0 && last_progress != Some((completed, total)) {
+            last_progress = Some((completed, total));
+            let pct = match tracker.event() {
+                TransferEvent::Progress(pct) => pct,
+                _ => completed as f64 / total as f64 * 100.0,
+            };
+            write_event(
+                &mut out,
+                json!({
+                    "event": "progress",
+                    "completed": completed,
+                    "total": total,
+                    "pct": (pct * 10.0).round() / 10.0,
+                }),
+            )?;
+        }
+
+        let snapshot = tracker.snapshot();
+        if snapshot.event.is_terminal() {
+            write_event(&mut out, terminal_event(&snapshot.event))?;
+            return Ok(out);
+        }
+        // Same end condition as the TUI; the runtime publishes the outcome after
+        if snapshot.is_complete() {
+            let bytes = tracker.bandwidth().summary().payload_bytes;
+            write_event(
+                &mut out,
+                json!({"event": "complete", "files": snapshot.total, "bytes": bytes}),
+            )?;
+            return Ok(out);
+        }
+        if cancel.is_cancelled() {
+            write_event(&mut out, terminal_event(&TransferEvent::Cancelled))?;
+            return Ok(out);
+        }
+
+        tokio::select! {
+            _ = cancel.cancelled() => {}
+            _ = events.changed() => {}
+            _ = tokio::time::sleep(SAMPLE_INTERVAL) => {}
+        }
+    }
+}
+
+/// Spawn [`emit_events`] writing to stdout.
+pub fn spawn_json_progress(
+    tracker: Arc<ProgressTracker>,
+    cancel: CancellationToken,
+) -> tokio::task::JoinHandle<io::Result<()>> {
+    tokio::spawn(async move {
+        emit_events(tracker, cancel, io::stdout()).await?;
+        Ok(())
+    })
+}
+
+fn terminal_event(event: &TransferEvent) -> Value {
+    match event {
+        TransferEvent::Completed { stats } => {
+            json!({"event": "complete", "files": stats.files, "bytes": stats.bytes})
+        }
+        TransferEvent::Failed { reason } => json!({"event": "failed", "reason": reason}),
+        TransferEvent::Cancelled | TransferEvent::Progress(_) => json!({"event": "cancelled"}),
+    }
+}
+
+fn write_event<W: Write>(out: &mut W, event: Value) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, &event)?;
+    out.write_all(b"\n")?;
+    // Consumers read line by line; don't sit on a buffered event
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn small_transfer_emits_claim_progress_and_complete() {
+        let tracker = Arc::new(ProgressTracker::new());
+        let cancel = CancellationToken::new();
+        let emitter = tokio::spawn(emit_events(tracker.clone(), cancel, Vec::new()));
+
+        tokio::time::sleep(SAMPLE_INTERVAL / 2).await;
+        tracker.record_client("0123456789abcdef-lock");
+        tracker.init_files(vec!["a.bin".into()], vec![2]);
+        for _ in 0..2 {
+            tokio::time::sleep(SAMPLE_INTERVAL * 2).await;
+            tracker.increment_file(0);
+            tracker.bandwidth().record_chunk(1024);
+        }
+        tokio::time::sleep(SAMPLE_INTERVAL * 2).await;
+        tracker.file_complete(0);
+        tracker.complete();
+
+        let out = emitter.await.unwrap().unwrap();
+        let events: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("one JSON object per line"))
+            .collect();
+
+        assert_eq!(events.first().unwrap(), &json!({"event": "claimed"}));
+        assert_eq!(
+            events.last().unwrap(),
+            &json!({"event": "complete", "files": 1, "bytes": 2048})
+        );
+        let progress: Vec<(u64, f64)> = events
+            .iter()
+            .filter(|e| e["event"] == "progress")
+            .map(|e| (e["completed"].as_u64().unwrap(), e["pct"].as_f64().unwrap()))
+            .collect();
+        assert_eq!(progress.first(), Some(&(0, 0.0)));
+        assert!(progress.contains(&(1, 50.0)), "{progress:?}");
+        assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[tokio::test]
+    async fn cancellation_ends_stream_with_cancelled() {
+        let tracker = Arc::new(ProgressTracker::new());
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let out = emit_events(tracker, cancel, Vec::new()).await.unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"event\":\"cancelled\"}\n"
+        );
+    }
+}
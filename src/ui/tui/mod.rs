@@ -2,13 +2,16 @@
 
 mod connection;
 mod hyperlink;
+mod json;
 mod output;
+mod plain;
 mod render;
+mod speed_history;
 mod transfer_panel;
 mod types;
 mod ui;
 
-pub use output::{spinner, spinner_error, spinner_success};
+pub use output::{spinner, spinner_error, spinner_success, status_line};
 pub use render::{spawn_tui, TransferUI};
 pub use types::{FileProgress, FileStatus, TransferProgress, TuiConfig};
-pub use ui::generate_qr;
+pub use ui::{generate_qr, generate_qr_png, generate_qr_svg, write_qr_file};
@@ -15,10 +15,20 @@ pub fn spinner(msg: &str) -> ProgressBar {
     pb
 }
 
+/// `✓ msg` / `✗ msg`, colored unless colors are disabled.
+pub fn status_line(ok: bool, msg: &str) -> String {
+    let mark = if ok {
+        style("✓").green().bold()
+    } else {
+        style("✗").red().bold()
+    };
+    format!("{} {}", mark, msg)
+}
+
 pub fn spinner_success(spinner: &ProgressBar, msg: &str) {
-    spinner.finish_with_message(format!("{} {}", style("✓").green().bold(), msg));
+    spinner.finish_with_message(status_line(true, msg));
 }
 
 pub fn spinner_error(spinner: &ProgressBar, msg: &str) {
-    spinner.finish_with_message(format!("{} {}", style("✗").red().bold(), msg));
+    spinner.finish_with_message(status_line(false, msg));
 }
@@ -0,0 +1,259 @@
+//! Line-oriented output (`--no-tui`) for terminals where the TUI renders
+//! poorly, such as over SSH.
+//!
+//! The QR code and link are printed once on stdout; after that one progress
+//! line goes to stderr whenever the chunk count moves, then a final outcome.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use super::types::TuiConfig;
+use crate::common::{TransferEvent, TransferProgress};
+use crate::server::progress::ProgressTracker;
+use crate::utils::size::format_size;
+
+/// Progress lines are at most this frequent; fine for a log, easy on SSH
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Print the link block once, then progress for `tracker` until the
+/// transfer finishes or `cancel` fires. Returns the writers for inspection.
+pub async fn emit_plain<O: Write, E: Write>(
+    config: TuiConfig,
+    tracker: Arc<ProgressTracker>,
+    mut status_rx: watch::Receiver<Option<String>>,
+    cancel: CancellationToken,
+    mut out: O,
+    mut err: E,
+) -> io::Result<(O, E)> {
+    write_header(&config, &mut out)?;
+    if let Some(status) = status_rx.borrow_and_update().as_deref() {
+        writeln!(err, "{}", status)?;
+    }
+    writeln!(err, "Press Ctrl+C to stop.")?;
+
+    let mut last_progress = None;
+    loop {
+        let (completed, total) = tracker.get_progress();
+        if total > 0 && last_progress != Some((completed, total)) {
+            last_progress = Some((completed, total));
+            writeln!(
+                err,
+                "{}",
+                progress_line(completed, total, &tracker.snapshot())
+            )?;
+        }
+
+        let snapshot = tracker.snapshot();
+        if snapshot.is_finished() {
+            writeln!(err, "{}", outcome_line(&snapshot))?;
+            return Ok((out, err));
+        }
+        if cancel.is_cancelled() {
+            writeln!(err, "Transfer cancelled")?;
+            return Ok((out, err));
+        }
+
+        tokio::select! {
+            _ = cancel.cancelled() => {}
+            changed = status_rx.changed() => {
+                if changed.is_ok() {
+                    if let Some(status) = status_rx.borrow().as_deref() {
+                        writeln!(err, "{}", status)?;
+                    }
+                }
+            }
+            _ = tokio::time::sleep(SAMPLE_INTERVAL) => {}
+        }
+    }
+}
+
+/// Spawn [`emit_plain`] on stdout/stderr.
+pub fn spawn_plain_progress(
+    config: TuiConfig,
+    tracker: Arc<ProgressTracker>,
+    status_rx: watch::Receiver<Option<String>>,
+    cancel: CancellationToken,
+) -> tokio::task::JoinHandle<io::Result<()>> {
+    tokio::spawn(async move {
+        emit_plain(
+            config,
+            tracker,
+            status_rx,
+            cancel,
+            io::stdout(),
+            io::stderr(),
+        )
+        .await?;
+        Ok(())
+    })
+}
+
+fn write_header<O: Write>(config: &TuiConfig, out: &mut O) -> io::Result<()> {
+    let action = if config.is_receiving {
+        "send files here"
+    } else {
+        "download"
+    };
+    if config.show_qr && !config.qr_code.is_empty() {
+        writeln!(out, "{}", config.qr_code)?;
+    }
+    // The link is the whole point of this mode, so `show_url` does not hide it
+    writeln!(out, "Open this link to {}:", action)?;
+    writeln!(out, "{}", config.url)?;
+    out.flush()
+}
+
+fn progress_line(completed: u64, total: u64, snapshot: &TransferProgress) -> String {
+    let pct = completed as f64 / total as f64 * 100.0;
+    let mut line = format!("{:5.1}%  {}/{} chunks", pct, completed, total);
+    let rate = snapshot.throughput.bytes_per_sec;
+    if rate > 0.0 {
+        line.push_str(&format!("  {}/s", format_size(rate as u64)));
+    }
+    if let Some(eta) = snapshot.throughput.eta {
+        line.push_str(&format!("  ~{}s left", eta.as_secs()));
+    }
+    if let Some((done, target)) = snapshot.downloads {
+        line.push_str(&format!("  download {}/{}", done + 1, target));
+    }
+    line
+}
+
+fn outcome_line(snapshot: &TransferProgress) -> String {
+    match &snapshot.event {
+        TransferEvent::Completed { stats } => format!(
+            "Transfer complete: {} file(s), {}",
+            stats.files,
+            format_size(stats.bytes)
+        ),
+        TransferEvent::Failed { reason } => format!("Transfer failed: {}", reason),
+        TransferEvent::Cancelled => "Transfer cancelled".to_string(),
+        // Complete by count; the runtime publishes the final event after
+        TransferEvent::Progress(_) => "Transfer complete".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::config::Transport;
+
+    fn config() -> TuiConfig {
+        TuiConfig {
+            is_receiving: false,
+            transport: Transport::Local,
+            url: "https://192.0.2.7:8443/send#k=abc".to_string(),
+            qr_code: "[qr]".to_string(),
+            display_name: "a.bin".to_string(),
+            display_files: vec!["a.bin".to_string()],
+            display_overflow_count: None,
+            show_qr: true,
+            show_url: true,
+            json_progress: false,
+            plain: true,
+            revoke: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn prints_link_once_then_progress_lines() {
+        let tracker = Arc::new(ProgressTracker::new());
+        let (_status_tx, status_rx) = watch::channel(Some("LAN warning".to_string()));
+        let emitter = tokio::spawn(emit_plain(
+            config(),
+            tracker.clone(),
+            status_rx,
+            CancellationToken::new(),
+            Vec::new(),
+            Vec::new(),
+        ));
+
+        tracker.record_client("0123456789abcdef-lock");
+        tracker.init_files(vec!["a.bin".into()], vec![2]);
+        tracker.increment_file(0);
+        tokio::time::sleep(SAMPLE_INTERVAL * 2).await;
+        tracker.increment_file(0);
+        tracker.file_complete(0);
+
+        let (out, err) = emitter.await.unwrap().unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let err = String::from_utf8(err).unwrap();
+
+        assert_eq!(
+            out,
+            "[qr]\nOpen this link to download:\nhttps://192.0.2.7:8443/send#k=abc\n"
+        );
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines[..2], ["LAN warning", "Press Ctrl+C to stop."]);
+        assert_eq!(lines.last(), Some(&"Transfer complete"));
+        let progress = &lines[2..lines.len() - 1];
+        assert_eq!(progress.len(), 2, "one line per chunk count:\n{err}");
+        for (line, expected) in progress
+            .iter()
+            .zip([" 50.0%  1/2 chunks", "100.0%  2/2 chunks"])
+        {
+            let extra = line.strip_prefix(expected).expect(line);
+            // Rate and ETA are appended only once measured
+            assert!(extra.is_empty() || extra.starts_with("  "), "{line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn output_is_plain_text_with_a_real_qr_code() {
+        let tracker = Arc::new(ProgressTracker::new());
+        tracker.init_files(vec!["a.bin".into()], vec![1]);
+        tracker.increment_file(0);
+        tracker.file_complete(0);
+        let (_status_tx, status_rx) = watch::channel(None);
+        let mut config = config();
+        config.qr_code = super::super::generate_qr(&config.url).unwrap();
+
+        let (out, err) = emit_plain(
+            config,
+            tracker,
+            status_rx,
+            CancellationToken::new(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .await
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let err = String::from_utf8(err).unwrap();
+
+        // Safe to pipe into a log: no colors, cursor moves or screen switches
+        assert!(!out.contains('\x1b') && !err.contains('\x1b'));
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines.len() > 20, "QR code missing:\n{out}");
+        assert_eq!(
+            lines[lines.len() - 2..],
+            [
+                "Open this link to download:",
+                "https://192.0.2.7:8443/send#k=abc"
+            ]
+        );
+        assert!(lines[..lines.len() - 2].iter().all(|line| line
+            .chars()
+            .all(|c| " \u{2580}\u{2584}\u{2588}".contains(c))));
+        assert!(err.contains("100.0%  1/1 chunks"));
+    }
+
+    #[tokio::test]
+    async fn cancel_ends_output_with_cancelled_line() {
+        let tracker = Arc::new(ProgressTracker::new());
+        let (_status_tx, status_rx) = watch::channel(None);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let (_, err) = emit_plain(config(), tracker, status_rx, cancel, Vec::new(), Vec::new())
+            .await
+            .unwrap();
+        assert!(String::from_utf8(err)
+            .unwrap()
+            .ends_with("Transfer cancelled\n"));
+    }
+}
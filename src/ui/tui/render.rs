@@ -1,14 +1,16 @@
 //! TUI runtime loop and top-level orchestration.
 
 use std::io::{self, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use futures::StreamExt;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     prelude::{CrosstermBackend, Terminal},
@@ -21,13 +23,18 @@ use tokio_util::sync::CancellationToken;
 use tui_big_text::{BigText, PixelSize};
 
 use super::connection;
+use super::speed_history::SpeedHistory;
 use super::transfer_panel;
 use super::types::{TransferProgress, TuiConfig};
 use super::ui::generate_compact_qr;
 use crate::server::progress::ProgressTracker;
+use crate::ui::clipboard::{self, HeldClipboard};
+use crate::ui::color;
 
 /// Render and poll interval
 const RENDER_INTERVAL: Duration = Duration::from_millis(50);
+/// Throughput sampling interval for the speed sparkline
+const SPEED_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
 const COPY_FEEDBACK_DURATION: Duration = Duration::from_millis(1200);
 
 const ACCENT: Color = Color::Rgb(248, 190, 117);
@@ -36,6 +43,10 @@ const PANEL_SIDE_INSET_WIDE: u16 = 6;
 const PANEL_SIDE_INSET_MEDIUM: u16 = 4;
 const STATUS_PANEL_MAX_HEIGHT: u16 = 6;
 
+/// Set while raw mode and the alternate screen are on, so the panic hook
+/// only restores a terminal that was actually taken over
+static TERMINAL_ACTIVE: AtomicBool = AtomicBool::new(false);
+
 struct LayoutAreas {
     logo: Rect,
     connection: Rect,
@@ -86,7 +97,9 @@ fn calculate_layout(
     };
 
     let status_height = status_message
-        .map(|message| ((message.lines().count() as u16).max(1) + 2).clamp(3, STATUS_PANEL_MAX_HEIGHT))
+        .map(|message| {
+            ((message.lines().count() as u16).max(1) + 2).clamp(3, STATUS_PANEL_MAX_HEIGHT)
+        })
         .unwrap_or(0);
 
     let main_chunks = Layout::default()
@@ -127,6 +140,9 @@ pub struct TuiState {
     pub transfer: TransferProgress,
     pub status_message: Option<String>,
     copy_feedback_expires_at: Option<Instant>,
+    /// Whether the last `c` press reached the clipboard
+    copy_succeeded: bool,
+    speed_history: SpeedHistory,
 }
 
 /// Owns TUI runtime, state updates, and frame rendering.
@@ -136,6 +152,10 @@ pub struct TransferUI {
     tracker: Arc<ProgressTracker>,
     status_rx: watch::Receiver<Option<String>>,
     compact_qr_code: Option<String>,
+    /// Link copied with `c`; on X11/Wayland it stays pasteable while held
+    clipboard: Option<HeldClipboard>,
+    /// `(show_url, show_qr)` to restore while `k` has the link hidden
+    hidden_link: Option<(bool, bool)>,
 }
 
 impl TransferUI {
@@ -150,6 +170,8 @@ impl TransferUI {
             state: TuiState::default(),
             tracker,
             status_rx,
+            clipboard: None,
+            hidden_link: None,
         }
     }
 
@@ -159,6 +181,7 @@ impl TransferUI {
 
         // Setup terminal
         enable_raw_mode()?;
+        TERMINAL_ACTIVE.store(true, Ordering::SeqCst);
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
         let backend = CrosstermBackend::new(stdout);
@@ -167,6 +190,12 @@ impl TransferUI {
         // Initial render
         terminal.draw(|f| self.render(f))?;
 
+        let mut events = EventStream::new();
+        let mut render_tick = tokio::time::interval(RENDER_INTERVAL);
+        // A late sample is skipped rather than fired in a burst, so catching
+        // up never crowds out status updates or input
+        let mut sample_tick = tokio::time::interval(SPEED_SAMPLE_INTERVAL);
+        sample_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         loop {
             let should_quit = tokio::select! {
                 // Check for cancellation
@@ -182,9 +211,21 @@ impl TransferUI {
                     false
                 }
 
-                // Input polling and render tick
-                _ = tokio::time::sleep(RENDER_INTERVAL) => {
-                    self.handle_input()?
+                // Keyboard input, as soon as it arrives
+                event = events.next() => match event {
+                    Some(Ok(Event::Key(key))) => self.handle_key(key),
+                    Some(Ok(_)) => false,
+                    Some(Err(e)) => return Err(e),
+                    None => true,
+                },
+
+                // Render tick
+                _ = render_tick.tick() => false,
+
+                // Sparkline sample
+                _ = sample_tick.tick() => {
+                    self.sample_speed();
+                    false
                 }
             };
 
@@ -195,8 +236,8 @@ impl TransferUI {
             // Read latest state from tracker
             self.state.transfer = self.tracker.snapshot();
 
-            // Check if transfer is complete
-            if self.state.transfer.is_complete() {
+            // Check if transfer completed, failed, or was cancelled
+            if self.state.transfer.is_finished() {
                 // Final render to show completed state
                 terminal.draw(|f| self.render(f))?;
                 // Give a moment to see final state
@@ -218,42 +259,93 @@ impl TransferUI {
             .copy_feedback_expires_at
             .is_some_and(|expires_at| Instant::now() <= expires_at)
         {
-            return ("copied", Style::default().fg(Color::Green));
+            return if self.state.copy_succeeded {
+                ("copied", Style::default().fg(Color::Green))
+            } else {
+                ("clipboard unavailable", Style::default().fg(Color::Red))
+            };
         }
 
+        let hint = if self.config.revoke.is_some() {
+            "c copy  k hide  x revoke  q quit"
+        } else {
+            "c copy  k hide  q quit"
+        };
         (
-            "c copy",
+            hint,
             Style::default()
                 .fg(Color::DarkGray)
                 .add_modifier(Modifier::DIM),
         )
     }
 
-    fn set_copy_feedback(&mut self) {
+    fn set_copy_feedback(&mut self, succeeded: bool) {
+        self.state.copy_succeeded = succeeded;
         self.state.copy_feedback_expires_at = Some(Instant::now() + COPY_FEEDBACK_DURATION);
     }
 
-    /// Check for keyboard input
-    fn handle_input(&mut self) -> io::Result<bool> {
-        if event::poll(Duration::from_millis(0))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            return Ok(true);
-                        }
-                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                            return Ok(true);
-                        }
-                        KeyCode::Char('c') => {
-                            self.set_copy_feedback();
-                        }
-                        _ => {}
-                    }
-                }
+    /// Add the current byte rate to the sparkline once data is moving.
+    fn sample_speed(&mut self) {
+        let transfer = &self.state.transfer;
+        if transfer.total == 0 || transfer.is_finished() {
+            return;
+        }
+        let rate = self.tracker.throughput().bytes_per_sec;
+        self.state.speed_history.push(rate);
+    }
+
+    /// Copy the link to the system clipboard and flash the result.
+    fn copy_url(&mut self) {
+        let (held, note) = clipboard::copy_link(&self.config.url);
+        tracing::debug!("{}", note);
+        self.set_copy_feedback(held.is_held());
+        if held.is_held() {
+            self.clipboard = Some(held);
+        }
+    }
+
+    /// Hide both the link and its QR code, or bring back whichever were shown.
+    fn toggle_link(&mut self) {
+        match self.hidden_link.take() {
+            Some((show_url, show_qr)) => {
+                self.config.show_url = show_url;
+                self.config.show_qr = show_qr;
+            }
+            None => {
+                self.hidden_link = Some((self.config.show_url, self.config.show_qr));
+                self.config.show_url = false;
+                self.config.show_qr = false;
             }
         }
-        Ok(false)
+    }
+
+    /// Apply one key press. Returns true when the TUI should quit, which
+    /// ends the session and shuts the server down.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => true,
+            KeyCode::Char('c') => {
+                self.copy_url();
+                false
+            }
+            // Hide the link while screen sharing; the QR code encodes it too
+            KeyCode::Char('k') => {
+                self.toggle_link();
+                false
+            }
+            KeyCode::Char('x') => match &self.config.revoke {
+                Some(revoke) => {
+                    revoke.cancel();
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
     }
 
     /// Render the logo at the top of the screen
@@ -310,6 +402,7 @@ impl TransferUI {
             connection_area,
             &self.config,
             self.compact_qr_code.as_deref(),
+            self.hidden_link.is_some(),
             feedback_text,
             feedback_style,
         );
@@ -317,6 +410,7 @@ impl TransferUI {
             frame,
             transfer_area,
             &self.state.transfer,
+            &self.state.speed_history.ordered(),
             &self.config.display_files,
             self.config.display_overflow_count,
             &self.config.display_name,
@@ -326,6 +420,10 @@ impl TransferUI {
         if let Some(status_area) = areas.status {
             self.render_status(frame, inset_horizontal(status_area, panel_inset));
         }
+
+        if !color::enabled() {
+            color::strip_colors(frame.buffer_mut());
+        }
     }
 }
 
@@ -334,27 +432,37 @@ fn install_panic_hook() {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
         // Attempt to restore terminal
-        let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        if TERMINAL_ACTIVE.swap(false, Ordering::SeqCst) {
+            let _ = disable_raw_mode();
+            let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        }
         original_hook(panic_info);
     }));
 }
 
 /// Cleanup terminal state
 fn cleanup_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    TERMINAL_ACTIVE.store(false, Ordering::SeqCst);
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
     Ok(())
 }
 
-/// Spawns the TUI task on Tokio.
+/// Spawns the TUI task on Tokio, or the JSON event writer for `--json`, or
+/// plain line output for `--no-tui`.
 pub fn spawn_tui(
     config: TuiConfig,
     tracker: Arc<ProgressTracker>,
     status_rx: watch::Receiver<Option<String>>,
     cancel: CancellationToken,
 ) -> tokio::task::JoinHandle<io::Result<()>> {
+    if config.json_progress {
+        return super::json::spawn_json_progress(tracker, cancel);
+    }
+    if config.plain {
+        return super::plain::spawn_plain_progress(config, tracker, status_rx, cancel);
+    }
     tokio::spawn(async move {
         let ui = TransferUI::new(config, tracker, status_rx);
         ui.run(cancel).await
@@ -364,6 +472,118 @@ pub fn spawn_tui(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::config::Transport;
+
+    #[tokio::test]
+    async fn plain_mode_never_takes_over_the_terminal() {
+        let tracker = Arc::new(ProgressTracker::new());
+        tracker.cancel();
+        let (_status_tx, status_rx) = watch::channel(None);
+        let config = TuiConfig {
+            is_receiving: false,
+            transport: Transport::Local,
+            url: "https://192.0.2.7:8443/send".to_string(),
+            qr_code: String::new(),
+            display_name: String::new(),
+            display_files: Vec::new(),
+            display_overflow_count: None,
+            show_qr: false,
+            show_url: true,
+            json_progress: false,
+            plain: true,
+            revoke: None,
+        };
+
+        spawn_tui(config, tracker, status_rx, CancellationToken::new())
+            .await
+            .unwrap()
+            .expect("plain output should finish");
+        assert!(!TERMINAL_ACTIVE.load(Ordering::SeqCst));
+    }
+
+    fn key_test_ui(revoke: Option<CancellationToken>) -> TransferUI {
+        let (_status_tx, status_rx) = watch::channel(None);
+        let config = TuiConfig {
+            is_receiving: false,
+            transport: Transport::Local,
+            url: "https://192.0.2.7:8443/send".to_string(),
+            qr_code: String::new(),
+            display_name: String::new(),
+            display_files: Vec::new(),
+            display_overflow_count: None,
+            show_qr: true,
+            show_url: true,
+            json_progress: false,
+            plain: false,
+            revoke,
+        };
+        TransferUI::new(config, Arc::new(ProgressTracker::new()), status_rx)
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn q_esc_and_ctrl_c_signal_quit() {
+        let mut ui = key_test_ui(None);
+        assert!(ui.handle_key(press(KeyCode::Char('q'))));
+        assert!(ui.handle_key(press(KeyCode::Esc)));
+        assert!(ui.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(!ui.handle_key(press(KeyCode::Char('z'))));
+
+        // Key releases (reported on Windows) do nothing
+        let mut release = press(KeyCode::Char('q'));
+        release.kind = KeyEventKind::Release;
+        assert!(!ui.handle_key(release));
+    }
+
+    fn rendered_text(ui: &TransferUI) -> String {
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 60)).unwrap();
+        terminal.draw(|frame| ui.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn k_hides_the_link_and_qr_code_without_quitting() {
+        let mut ui = key_test_ui(None);
+        ui.config.qr_code = super::super::generate_qr(&ui.config.url).unwrap();
+        let shown = rendered_text(&ui);
+        assert!(shown.contains("Scan"));
+        assert!(shown.contains("Open"));
+        assert!(shown.contains("192.0.2.7"));
+
+        assert!(!ui.handle_key(press(KeyCode::Char('k'))));
+        let hidden = rendered_text(&ui);
+        assert!(
+            !hidden.contains("Scan"),
+            "QR code still on screen:\n{hidden}"
+        );
+        assert!(!hidden.contains("Open"));
+        assert!(!hidden.contains("192.0.2.7"));
+        assert!(hidden.contains("Link hidden"));
+
+        assert!(!ui.handle_key(press(KeyCode::Char('k'))));
+        assert_eq!(rendered_text(&ui), shown);
+    }
+
+    #[test]
+    fn x_revokes_only_when_the_session_allows_it() {
+        let mut receiving = key_test_ui(None);
+        assert!(!receiving.handle_key(press(KeyCode::Char('x'))));
+
+        let revoke = CancellationToken::new();
+        let mut sending = key_test_ui(Some(revoke.clone()));
+        assert!(sending.handle_key(press(KeyCode::Char('x'))));
+        assert!(revoke.is_cancelled());
+    }
 
     #[test]
     fn calculate_layout_caps_status_height_for_long_messages() {
@@ -0,0 +1,88 @@
+//! Recent throughput samples for the transfer panel's sparkline.
+
+/// Samples kept; one a second gives the last half minute.
+pub(crate) const SPEED_HISTORY_LEN: usize = 30;
+
+/// Fixed-size ring of byte-rate samples, overwriting the oldest when full.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpeedHistory {
+    samples: Vec<u64>,
+    /// Slot the next sample goes into once the ring is full
+    next: usize,
+}
+
+impl SpeedHistory {
+    /// Record one byte-per-second sample.
+    pub(crate) fn push(&mut self, bytes_per_sec: f64) {
+        push_sample(
+            &mut self.samples,
+            &mut self.next,
+            SPEED_HISTORY_LEN,
+            bytes_per_sec.max(0.0) as u64,
+        );
+    }
+
+    /// Samples oldest first, as the sparkline draws them left to right.
+    pub(crate) fn ordered(&self) -> Vec<u64> {
+        ordered_samples(&self.samples, self.next)
+    }
+}
+
+/// Append `value`, or overwrite the oldest sample once `capacity` is reached.
+fn push_sample(samples: &mut Vec<u64>, next: &mut usize, capacity: usize, value: u64) {
+    if capacity == 0 {
+        return;
+    }
+    if samples.len() < capacity {
+        samples.push(value);
+        return;
+    }
+    samples[*next] = value;
+    *next = (*next + 1) % capacity;
+}
+
+/// Unroll the ring so the oldest sample comes first.
+fn ordered_samples(samples: &[u64], next: usize) -> Vec<u64> {
+    let (newer, older) = samples.split_at(next.min(samples.len()));
+    older.iter().chain(newer).copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(capacity: usize, values: impl IntoIterator<Item = u64>) -> Vec<u64> {
+        let (mut samples, mut next) = (Vec::new(), 0);
+        for value in values {
+            push_sample(&mut samples, &mut next, capacity, value);
+        }
+        ordered_samples(&samples, next)
+    }
+
+    #[test]
+    fn fills_up_in_order() {
+        assert_eq!(ring(4, [1, 2, 3]), vec![1, 2, 3]);
+        assert_eq!(ring(4, []), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn wraparound_drops_the_oldest() {
+        assert_eq!(ring(3, [1, 2, 3, 4]), vec![2, 3, 4]);
+        assert_eq!(ring(3, [1, 2, 3, 4, 5, 6]), vec![4, 5, 6]);
+        assert_eq!(ring(3, 1..=8), vec![6, 7, 8]);
+    }
+
+    #[test]
+    fn history_is_bounded_and_clamps_negative_rates() {
+        let mut history = SpeedHistory::default();
+        for rate in 0..(SPEED_HISTORY_LEN as u64 + 5) {
+            history.push(rate as f64);
+        }
+        history.push(-1.0);
+
+        let samples = history.ordered();
+        assert_eq!(samples.len(), SPEED_HISTORY_LEN);
+        assert_eq!(samples[0], 6);
+        assert_eq!(samples.last(), Some(&0));
+    }
+}
@@ -2,15 +2,19 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::Span,
-    widgets::{Block, BorderType, Borders, Gauge, Paragraph},
+    widgets::{Block, BorderType, Borders, Gauge, Paragraph, Sparkline},
     Frame,
 };
 
-use super::types::{FileProgress, FileStatus, TransferProgress};
+use super::types::{FileProgress, FileStatus, Throughput, TransferEvent, TransferProgress};
 
 const MAX_VISIBLE_FILE_ROWS: usize = 5;
 const MAX_VISIBLE_FILE_ROWS_COMPACT: usize = 3;
 const MAX_VISIBLE_FILE_ROWS_TIGHT: usize = 2;
+/// Rows given to the speed sparkline under the file list
+const SPARKLINE_HEIGHT: u16 = 2;
+/// Smallest panel interior that still fits file rows above the sparkline
+const SPARKLINE_MIN_INNER_HEIGHT: u16 = 5;
 
 #[derive(Debug, Clone, PartialEq)]
 struct FileListRow {
@@ -21,24 +25,42 @@ struct FileListRow {
     progress_percent: Option<u16>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn render_transfer_panel(
     frame: &mut Frame,
     area: Rect,
     transfer: &TransferProgress,
+    speed_history: &[u64],
     display_files: &[String],
     display_overflow_count: Option<usize>,
     display_name: &str,
     accent: Color,
 ) {
     let title = transfer_title(transfer, display_files, display_overflow_count);
+    let title_color = match transfer.event {
+        TransferEvent::Failed { .. } => Color::Red,
+        _ => accent,
+    };
 
     let block = Block::default()
-        .title(Span::styled(title, Style::default().fg(accent)))
+        .title(Span::styled(title, Style::default().fg(title_color)))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
-    let inner = block.inner(area);
+    let mut inner = block.inner(area);
     frame.render_widget(block, area);
 
+    if !speed_history.is_empty() && inner.height >= SPARKLINE_MIN_INNER_HEIGHT {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(SPARKLINE_HEIGHT)])
+            .split(inner);
+        inner = split[0];
+        let sparkline = Sparkline::default()
+            .data(speed_history)
+            .style(Style::default().fg(accent));
+        frame.render_widget(sparkline, split[1]);
+    }
+
     let max_visible_rows = max_visible_rows_for_area(inner);
     if let Some((rows_data, overflow)) = build_panel_rows(
         transfer,
@@ -58,10 +80,25 @@ fn transfer_title(
     display_files: &[String],
     display_overflow_count: Option<usize>,
 ) -> String {
+    match &transfer.event {
+        TransferEvent::Completed { stats } => {
+            return format!(" Transfer • complete • {} file(s) ", stats.files);
+        }
+        TransferEvent::Failed { reason } => return format!(" Transfer • failed: {} ", reason),
+        TransferEvent::Cancelled => return " Transfer • cancelled ".to_string(),
+        TransferEvent::Progress(_) => {}
+    }
+
+    if let Some((completed, target)) = transfer.downloads {
+        return format!(" Transfer • {}/{} downloads complete ", completed, target);
+    }
+
     if transfer.total > 0 {
         return format!(
-            " Transfer • {}/{} complete ",
-            transfer.completed, transfer.total
+            " Transfer • {}/{} complete{} ",
+            transfer.completed,
+            transfer.total,
+            throughput_suffix(&transfer.throughput)
         );
     }
 
@@ -73,6 +110,28 @@ fn transfer_title(
     " Transfer ".to_string()
 }
 
+/// ` • 12.3 MB/s • 0:42 left`, or empty before any rate is known.
+fn throughput_suffix(throughput: &Throughput) -> String {
+    if throughput.bytes_per_sec <= 0.0 {
+        return String::new();
+    }
+    let rate = format!(" • {:.1} MB/s", throughput.bytes_per_sec / 1_000_000.0);
+    match throughput.eta {
+        Some(eta) => format!("{} • {} left", rate, format_eta(eta.as_secs())),
+        None => rate,
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up.
+fn format_eta(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
 fn max_visible_rows_for_area(area: Rect) -> usize {
     if area.height <= 4 {
         MAX_VISIBLE_FILE_ROWS_TIGHT
@@ -218,8 +277,12 @@ fn muted_style() -> Style {
 
 #[cfg(test)]
 mod tests {
-    use super::build_visible_file_rows;
-    use crate::ui::tui::types::{FileProgress, FileStatus};
+    use super::{build_visible_file_rows, transfer_title};
+    use crate::common::TransferStats;
+    use crate::ui::tui::types::{
+        FileProgress, FileStatus, Throughput, TransferEvent, TransferProgress,
+    };
+    use std::time::Duration;
 
     fn waiting_file(name: &str) -> FileProgress {
         FileProgress {
@@ -235,6 +298,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn title_shows_download_count_when_serving_multiple_clients() {
+        let transfer = TransferProgress {
+            files: vec![waiting_file("a.txt")],
+            completed: 0,
+            total: 1,
+            downloads: Some((3, 5)),
+            ..TransferProgress::default()
+        };
+        assert_eq!(
+            transfer_title(&transfer, &[], None),
+            " Transfer • 3/5 downloads complete "
+        );
+    }
+
+    fn finished_with(event: TransferEvent) -> TransferProgress {
+        TransferProgress {
+            files: vec![waiting_file("a.txt")],
+            completed: 0,
+            total: 1,
+            event,
+            ..TransferProgress::default()
+        }
+    }
+
+    #[test]
+    fn title_renders_terminal_event_states() {
+        let completed = finished_with(TransferEvent::Completed {
+            stats: TransferStats {
+                files: 1,
+                bytes: 42,
+            },
+        });
+        assert_eq!(
+            transfer_title(&completed, &[], None),
+            " Transfer • complete • 1 file(s) "
+        );
+
+        let failed = finished_with(TransferEvent::Failed {
+            reason: "hash mismatch".to_string(),
+        });
+        assert_eq!(
+            transfer_title(&failed, &[], None),
+            " Transfer • failed: hash mismatch "
+        );
+
+        let cancelled = finished_with(TransferEvent::Cancelled);
+        assert_eq!(
+            transfer_title(&cancelled, &[], None),
+            " Transfer • cancelled "
+        );
+
+        for transfer in [completed, failed, cancelled] {
+            assert!(transfer.is_finished());
+        }
+    }
+
+    #[test]
+    fn title_shows_file_count_while_in_progress() {
+        let transfer = finished_with(TransferEvent::Progress(50.0));
+        assert!(!transfer.is_finished());
+        assert_eq!(
+            transfer_title(&transfer, &[], None),
+            " Transfer • 0/1 complete "
+        );
+    }
+
+    #[test]
+    fn title_shows_rate_and_eta_once_known() {
+        let mut transfer = finished_with(TransferEvent::Progress(50.0));
+        transfer.throughput = Throughput {
+            bytes_per_sec: 12_345_678.0,
+            eta: Some(Duration::from_secs(3725)),
+        };
+        assert_eq!(
+            transfer_title(&transfer, &[], None),
+            " Transfer • 0/1 complete • 12.3 MB/s • 1:02:05 left "
+        );
+
+        transfer.throughput.eta = None;
+        assert_eq!(
+            transfer_title(&transfer, &[], None),
+            " Transfer • 0/1 complete • 12.3 MB/s "
+        );
+    }
+
     #[test]
     fn builds_vertical_rows_with_waiting_status_text() {
         let files = vec![waiting_file("text1.txt"), waiting_file("test2.txt")];
@@ -1,5 +1,8 @@
 use crate::common::config::Transport;
-pub use crate::common::progress::{FileProgress, FileStatus, TransferProgress};
+pub use crate::common::progress::{
+    FileProgress, FileStatus, Throughput, TransferEvent, TransferProgress,
+};
+use tokio_util::sync::CancellationToken;
 
 /// Static configuration passed to TUI at startup
 #[derive(Clone, Debug)]
@@ -13,4 +16,10 @@ pub struct TuiConfig {
     pub display_overflow_count: Option<usize>,
     pub show_qr: bool,
     pub show_url: bool,
+    /// Print newline-delimited JSON events to stdout instead of drawing
+    pub json_progress: bool,
+    /// Print the QR code and link once, then plain progress lines
+    pub plain: bool,
+    /// Cancelled when the sender presses `x` to revoke the link (send only)
+    pub revoke: Option<CancellationToken>,
 }
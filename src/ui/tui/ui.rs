@@ -1,7 +1,14 @@
 use anyhow::{Context, Result};
-use qrcode::render::unicode;
+use image::{ImageOutputFormat, Luma};
+use qrcode::render::{svg, unicode};
 use qrcode::QrCode;
+use std::io::Cursor;
+use std::path::Path;
 
+/// Pixels per QR module in file output, large enough to print or project
+const FILE_MODULE_PX: u32 = 8;
+
+// Terminal variant is inverted: most terminals draw light text on dark
 pub fn generate_qr(url: &str) -> Result<String> {
     let code = QrCode::new(url.as_bytes()).context("Failed to generate QR code")?;
 
@@ -23,3 +30,134 @@ pub(crate) fn generate_compact_qr(url: &str) -> Option<String> {
             .build(),
     )
 }
+
+/// Standalone SVG of the QR code, dark modules on white.
+pub fn generate_qr_svg(url: &str) -> Result<String> {
+    let code = QrCode::new(url.as_bytes()).context("Failed to generate QR code")?;
+
+    Ok(code
+        .render::<svg::Color>()
+        .module_dimensions(FILE_MODULE_PX, FILE_MODULE_PX)
+        .quiet_zone(true)
+        .build())
+}
+
+/// PNG-encoded QR code, dark modules on white.
+pub fn generate_qr_png(url: &str) -> Result<Vec<u8>> {
+    let code = QrCode::new(url.as_bytes()).context("Failed to generate QR code")?;
+    let image = code
+        .render::<Luma<u8>>()
+        .module_dimensions(FILE_MODULE_PX, FILE_MODULE_PX)
+        .quiet_zone(true)
+        .build();
+
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .context("Failed to encode QR code as PNG")?;
+    Ok(png.into_inner())
+}
+
+/// Write the QR code for `url` to `path`: PNG for a `.png` extension,
+/// SVG otherwise.
+///
+/// The code carries the whole link, key included, so the file is owner-only
+/// (0600 on Unix), even when it replaces an existing one.
+pub fn write_qr_file(url: &str, path: &Path) -> Result<()> {
+    let is_png = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let bytes = if is_png {
+        generate_qr_png(url)?
+    } else {
+        generate_qr_svg(url)?.into_bytes()
+    };
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write QR code to {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        // `mode` only applies to newly created files
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict {}", path.display()))?;
+    }
+    std::io::Write::write_all(&mut file, &bytes)
+        .with_context(|| format!("Failed to write QR code to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 19 bytes fits version 2 at the default error correction: 25x25 modules
+    const URL: &str = "https://example.com";
+    const MODULES: u32 = 25;
+    const QUIET_ZONE: u32 = 4;
+
+    #[test]
+    fn svg_has_one_square_per_dark_module() {
+        let svg = generate_qr_svg(URL).unwrap();
+        let side = (MODULES + 2 * QUIET_ZONE) * FILE_MODULE_PX;
+
+        assert!(svg.contains(&format!("viewBox=\"0 0 {} {}\"", side, side)));
+        let dark = QrCode::new(URL.as_bytes())
+            .unwrap()
+            .to_colors()
+            .into_iter()
+            .filter(|color| *color == qrcode::Color::Dark)
+            .count();
+        assert_eq!(svg.matches('M').count(), dark);
+        assert!(
+            svg.contains("fill=\"#000\""),
+            "dark modules stay dark on file"
+        );
+    }
+
+    #[test]
+    fn png_output_is_scaled_to_module_grid() {
+        let png = generate_qr_png(URL).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        let side = (MODULES + 2 * QUIET_ZONE) * FILE_MODULE_PX;
+
+        assert_eq!((image.width(), image.height()), (side, side));
+    }
+
+    #[test]
+    fn file_format_follows_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let svg_path = dir.path().join("link.svg");
+        let png_path = dir.path().join("link.PNG");
+
+        write_qr_file(URL, &svg_path).unwrap();
+        write_qr_file(URL, &png_path).unwrap();
+
+        assert!(std::fs::read_to_string(&svg_path).unwrap().contains("<svg"));
+        assert!(std::fs::read(&png_path).unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn qr_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let fresh = dir.path().join("fresh.svg");
+        let existing = dir.path().join("existing.png");
+        std::fs::write(&existing, b"old").unwrap();
+        std::fs::set_permissions(&existing, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_qr_file(URL, &fresh).unwrap();
+        write_qr_file(URL, &existing).unwrap();
+
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&fresh), 0o600);
+        assert_eq!(mode(&existing), 0o600);
+    }
+}
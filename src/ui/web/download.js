@@ -196,6 +196,7 @@ const keyStore = new KeyStore();
 //=============
 let cachedManifest = null
 let cachedToken = null
+let cachedMacKey = null
 
 // Download button
 document.addEventListener('DOMContentLoaded', async () => {
@@ -207,20 +208,41 @@ document.addEventListener('DOMContentLoaded', async () => {
     // Load manifest and display files
     try {
         cachedToken = getTokenFromUrl()
+        await ensureAuthPin()
+
+        if (await startLinkCountdown()) {
+            return
+        }
+
+        // Claim first; senders started with --require-claim only serve the
+        // manifest to a client that already holds the lock
+        const claimResponse = await fetch('/send/claim', {
+            method: 'POST',
+            headers: { ...authHeaders(), [CLIENT_ID_HEADER_NAME]: clientId() }
+        })
+        if (!claimResponse.ok) {
+            throw new Error(`Failed to claim transfer: HTTP ${claimResponse.status}`);
+        }
+        setLockToken((await claimResponse.json()).lockToken)
 
         const manifestResponse = await fetch('/send/manifest', {
-            headers: authHeaders()
+            headers: transferHeaders()
         })
         if (!manifestResponse.ok) {
             throw new Error(`Failed to fetch manifest: HTTP ${manifestResponse.status}`);
 
         }
 
-        cachedManifest = await manifestResponse.json()
-        setLockToken(cachedManifest.lockToken)
+        const manifest = await manifestResponse.json()
+        if (!(await checkSender(manifest))) {
+            return
+        }
+        cachedManifest = manifest
+        setRetryHints(manifest.config)
 
         // Extract and store keys
-        const { key } = await getEncryptionKeyFromUrl(['decrypt'])
+        const { key, macKey } = await getEncryptionKeyFromUrl(['decrypt'])
+        cachedMacKey = macKey
 
         for (const file of cachedManifest.files) {
             await keyStore.put(
@@ -239,6 +261,193 @@ document.addEventListener('DOMContentLoaded', async () => {
     }
 })
 
+//==============
+// Chunk compression
+//==============
+const ACCEPT_CHUNK_ENCODING_HEADER = 'X-Accept-Chunk-Encoding'
+const CHUNK_ENCODING_HEADER = 'X-Chunk-Encoding'
+
+let zstdSupported = null
+
+// Only Compression Streams with zstd can undo a compressed chunk
+function supportsZstd() {
+    if (zstdSupported === null) {
+        try {
+            new DecompressionStream('zstd')
+            zstdSupported = true
+        } catch {
+            zstdSupported = false
+        }
+    }
+    return zstdSupported
+}
+
+// Ask for compressed chunks only when the sender offers them and we can decode
+function chunkHeaders() {
+    const headers = transferHeaders()
+    if (cachedManifest?.compression === 'zstd' && supportsZstd()) {
+        headers[ACCEPT_CHUNK_ENCODING_HEADER] = 'zstd'
+    }
+    return headers
+}
+
+async function decompressZstd(data) {
+    const stream = new Blob([data]).stream().pipeThrough(new DecompressionStream('zstd'))
+    return new Uint8Array(await new Response(stream).arrayBuffer())
+}
+
+//==============
+// Sender identity
+//==============
+const PINNED_SIGN_KEY = 'archdrop.pinnedSignKey'
+
+// Sender key the receiver trusts. Only the pin form sets it: a key read from
+// the link would be chosen by whoever controls the link being verified.
+function getPinnedSignKey() {
+    return localStorage.getItem(PINNED_SIGN_KEY)
+}
+
+// Offer to pin a key the sender shared some other way, then check against it
+function showPinForm(manifest) {
+    const form = document.getElementById('pinForm')
+    form.hidden = false
+    form.onsubmit = async (event) => {
+        event.preventDefault()
+        const key = document.getElementById('pinKey').value.trim()
+        if (!key) return
+        localStorage.setItem(PINNED_SIGN_KEY, key)
+        form.hidden = true
+        await checkSender(manifest)
+    }
+}
+
+// Must match Manifest::signing_payload in manifest.rs
+function manifestSigningPayload(manifest) {
+    return JSON.stringify([
+        'archdrop-manifest-v2',
+        manifest.config.chunk_size,
+        manifest.files.map(f => [f.index, f.relative_path, f.size, f.nonce, f.hash ?? null]),
+    ])
+}
+
+async function verifyManifestSignature(manifest, pinnedKey) {
+    const signed = manifest.signature
+    if (!signed) throw new Error('manifest is not signed')
+    if (signed.public_key !== pinnedKey) throw new Error('signed by a different key')
+
+    const key = await crypto.subtle.importKey(
+        'raw', urlSafeBase64ToUint8Array(pinnedKey), { name: 'Ed25519' }, false, ['verify']
+    )
+    const valid = await crypto.subtle.verify(
+        { name: 'Ed25519' },
+        key,
+        urlSafeBase64ToUint8Array(signed.signature),
+        new TextEncoder().encode(manifestSigningPayload(manifest))
+    )
+    if (!valid) throw new Error('signature does not match')
+}
+
+// Verify the manifest when a sender key is pinned. Returns false to block the download.
+async function checkSender(manifest) {
+    const pinnedKey = getPinnedSignKey()
+    if (!pinnedKey) {
+        if (manifest.signature) showPinForm(manifest)
+        return true
+    }
+
+    const label = document.getElementById('signer')
+    try {
+        await verifyManifestSignature(manifest, pinnedKey)
+        label.textContent = 'Signed by your pinned sender key'
+        label.hidden = false
+        return true
+    } catch (e) {
+        console.error('Sender verification failed:', e)
+        label.textContent = `Sender verification failed: ${e.message}`
+        label.classList.add('failed')
+        label.hidden = false
+        document.getElementById('downloadBtn').disabled = true
+        return false
+    }
+}
+
+//==============
+// Link expiry
+//==============
+const TTL_RESYNC_MS = 15000
+let linkTtlTimer = null
+
+function formatRemaining(ms) {
+    const totalSecs = Math.ceil(ms / 1000)
+    const mins = Math.floor(totalSecs / 60)
+    const secs = totalSecs % 60
+    return `${mins}:${String(secs).padStart(2, '0')}`
+}
+
+function showLinkExpired(label) {
+    label.textContent = 'Link expired'
+    label.classList.add('expired')
+    label.hidden = false
+    stopLinkCountdown()
+}
+
+function stopLinkCountdown() {
+    if (linkTtlTimer) {
+        clearInterval(linkTtlTimer)
+        linkTtlTimer = null
+    }
+}
+
+async function fetchLinkTtl() {
+    const response = await fetch('/send/ttl', { headers: authHeaders() })
+    if (!response.ok) return null
+    return response.json()
+}
+
+// Show a countdown when the sender set a link TTL. Returns true if already expired.
+async function startLinkCountdown() {
+    const label = document.getElementById('linkTtl')
+    if (!label) return false
+
+    let ttl
+    try {
+        ttl = await fetchLinkTtl()
+    } catch (e) {
+        console.warn('Could not read link TTL:', e)
+        return false
+    }
+    if (!ttl || ttl.expiresInMs === null) return false
+    if (ttl.expired) {
+        showLinkExpired(label)
+        return true
+    }
+
+    let deadline = Date.now() + ttl.expiresInMs
+    let lastSync = Date.now()
+
+    const tick = async () => {
+        if (Date.now() - lastSync >= TTL_RESYNC_MS) {
+            lastSync = Date.now()
+            const fresh = await fetchLinkTtl().catch(() => null)
+            if (fresh && fresh.expiresInMs !== null) {
+                deadline = Date.now() + fresh.expiresInMs
+            }
+        }
+
+        const remaining = deadline - Date.now()
+        if (remaining <= 0) {
+            showLinkExpired(label)
+            return
+        }
+        label.textContent = `Link expires in ${formatRemaining(remaining)}`
+        label.hidden = false
+    }
+
+    await tick()
+    linkTtlTimer = setInterval(tick, 1000)
+    return false
+}
+
 // List of files to download
 function displayFileList(files) {
     const fileList = document.getElementById('fileList')
@@ -280,6 +489,7 @@ async function handleDownloadAction() {
         // ACTION: Finish and Close
         try {
             await fetch('/send/complete', { method: 'POST', headers: transferHeaders() });
+            stopLinkCountdown();
             downloadBtn.textContent = 'Connection Closed';
             window.close(); // Try to close tab
         } catch (e) {
@@ -403,6 +613,11 @@ class DownloadManager {
         this.transferConfig = config
     }
 
+    // Server weights parallelism by file size; older manifests lack the hint
+    fileConcurrency(fileEntry) {
+        return fileEntry.parallelism || this.transferConfig.concurrency
+    }
+
     async download(fileEntry, fileItem) {
         const keyData = await keyStore.get(this.token, fileEntry.index)
         if (!keyData) {
@@ -428,15 +643,18 @@ class DownloadManager {
                 fileEntry,
                 keyData,
                 fileItem,
-                this.transferConfig.concurrency,
+                this.fileConcurrency(fileEntry),
                 async (data, _) => {
                     // enforcing order, so cannot stream direct
                     await writable.write(data)
                 }
             )
-        } finally {
-            await writable.close()
+        } catch (err) {
+            // close() would commit bytes that failed the SHA-256 or MAC check
+            await writable.abort().catch(() => {})
+            throw err
         }
+        await writable.close()
     }
     async downloadToBlob(fileEntry, keyData, fileItem) {
         const totalChunks = Math.ceil(fileEntry.size / this.transferConfig.chunk_size);
@@ -446,7 +664,7 @@ class DownloadManager {
             fileEntry,
             keyData,
             fileItem,
-            this.transferConfig.concurrency,
+            this.fileConcurrency(fileEntry),
             async (data, index) => {
                 chunks[index] = data
             }
@@ -463,6 +681,8 @@ class DownloadManager {
         const activeFetches = new Map()
         let nextFetch = 0
         let nextChunkNeeded = 0
+        // Chunks are written in order, so the whole file can be hashed on the way
+        const hasher = new Sha256()
 
         // Files arrive in correct order, but we still use concurrecy
         // nextFetch sends out up to max limit of chunks or room in buffer
@@ -492,6 +712,7 @@ class DownloadManager {
                 // wait for needed chunk to arrive in map
                 const chunkData = await activeFetches.get(nextChunkNeeded)
 
+                hasher.update(chunkData)
                 await writeCallback(chunkData, nextChunkNeeded);
 
                 activeFetches.delete(nextChunkNeeded)
@@ -503,6 +724,28 @@ class DownloadManager {
                 throw err
             }
         }
+
+        const { sha256, mac } = await this.expectedDigest(fileEntry)
+        const actual = hasher.hexDigest()
+        if (actual !== sha256.toLowerCase()) {
+            throw new Error(`SHA-256 mismatch for ${fileEntry.name}: got ${actual}, expected ${sha256}`)
+        }
+        if (mac && !(await verifyFileMac(cachedMacKey, actual, mac))) {
+            throw new Error(`Whole-file MAC mismatch for ${fileEntry.name}`)
+        }
+    }
+
+    // Hash from the manifest, or else the one the sender publishes after download
+    async expectedDigest(fileEntry) {
+        if (fileEntry.hash) {
+            return { sha256: fileEntry.hash, mac: fileEntry.mac }
+        }
+        const response = await retryWithExponentialBackoff(async () => {
+            const res = await fetch(`/send/${fileEntry.index}/hash`, { headers: transferHeaders() })
+            if (!res.ok) throw new Error(`Failed to fetch hash: HTTP ${res.status}`)
+            return res
+        }, 3, `hash for ${fileEntry.name}`)
+        return response.json()
     }
 
     async fetchAndDecrypt(fileEntry, chunkIndex, keyData) {
@@ -513,11 +756,14 @@ class DownloadManager {
             try {
                 const res = await fetch(
                     `/send/${fileEntry.index}/chunk/${chunkIndex}`,
-                    { signal: controller.signal, headers: transferHeaders() }
+                    { signal: controller.signal, headers: chunkHeaders() }
                 )
 
                 clearTimeout(timeout)
 
+                if (res.status === 401) {
+                    throw new Error('The sender revoked this link or it is no longer active')
+                }
                 if (!res.ok) {
                     throw new Error(`HTTP ${res.status}`)
                 }
@@ -534,13 +780,17 @@ class DownloadManager {
 
         const encrypted = await response.arrayBuffer()
         const nonceBase = urlSafeBase64ToUint8Array(keyData.nonceBase64)
-        const nonce = generateNonce(nonceBase, chunkIndex)
+        // The header only picks the counter; a forged one fails to decrypt
+        const compressed = response.headers.get(CHUNK_ENCODING_HEADER) === 'zstd'
         const decrypted = await crypto.subtle.decrypt(
-            { name: 'AES-GCM', iv: nonce },
+            chunkCipherParams(nonceBase, fileEntry.index, chunkIndex, fileEntry.size, compressed),
             keyData.key,
             encrypted
         )
 
+        if (compressed) {
+            return decompressZstd(decrypted)
+        }
         return new Uint8Array(decrypted)
     }
 
@@ -7,9 +7,14 @@ const DEFAULT_CONCURRENT = 4
 // URL Helpers
 //============
 function urlSafeBase64ToUint8Array(str) {
-    // Convert URL-safe base64 to standard base64
-    let base64 = str.replace(/-/g, '+').replace(/_/g, '/')
-    
+    // Convert URL-safe base64 to standard base64. Links from older releases
+    // used the standard alphabet with padding, and some clients turn '+'
+    // into a space, so accept those forms too.
+    let base64 = str
+        .replace(/=+$/, '')
+        .replace(/[- ]/g, '+')
+        .replace(/_/g, '/')
+
     // Add padding if needed (Rust uses URL_SAFE_NO_PAD, so padding may be missing)
     // Base64 padding: length mod 4 determines padding
     const padLength = (4 - (base64.length % 4)) % 4
@@ -28,29 +33,77 @@ function urlSafeBase64ToUint8Array(str) {
 // Cache for values extracted from the URL fragment (cleared after first read)
 let _fragmentToken = null
 let _fragmentKey = null
+let _fragmentSalt = ''
+// Cipher suite: 1 = no associated data, 2 = chunks bound to their position
+let _fragmentSuite = 1
 
 function _parseFragment() {
     if (_fragmentToken !== null) return // already parsed
     const fragment = window.location.hash.substring(1)
     const params = new URLSearchParams(fragment)
-    _fragmentToken = params.get('token') || ''
-    _fragmentKey = params.get('key') || ''
+    const packed = params.get('p')
+    if (packed) {
+        _parsePackedFragment(packed)
+    } else {
+        _fragmentToken = params.get('token') || ''
+        _fragmentKey = params.get('key') || ''
+        // `--password` links carry a salt; the key comes from the passphrase
+        _fragmentSalt = params.get('salt') || ''
+        const suite = Number(params.get('v') || '1')
+        if (!SUPPORTED_SUITES.includes(suite)) {
+            // Unknown (or garbled) version: refuse rather than guess the nonce scheme
+            _fragmentToken = ''
+            _fragmentKey = ''
+            _fragmentSalt = ''
+        } else {
+            _fragmentSuite = suite
+        }
+    }
 
     // Clear URL fragment immediately to prevent it from persisting in browser history
     history.replaceState(null, document.title, location.pathname + location.search)
 }
 
+// Packed fragment: base64url of token (16) || key (32) || nonce (8) || flags (1)
+// The flags byte is the cipher suite version
+const PACKED_FRAGMENT_LEN = 57
+const SUPPORTED_SUITES = [1, 2]
+
+function _parsePackedFragment(packed) {
+    const bytes = urlSafeBase64ToUint8Array(packed)
+    const suite = bytes[PACKED_FRAGMENT_LEN - 1]
+    if (bytes.length !== PACKED_FRAGMENT_LEN || !SUPPORTED_SUITES.includes(suite)) {
+        _fragmentToken = ''
+        _fragmentKey = ''
+        return
+    }
+    _fragmentSuite = suite
+
+    const hex = Array.from(bytes.slice(0, 16), b => b.toString(16).padStart(2, '0')).join('')
+    _fragmentToken = [
+        hex.slice(0, 8), hex.slice(8, 12), hex.slice(12, 16), hex.slice(16, 20), hex.slice(20)
+    ].join('-')
+    _fragmentKey = arrayBufferToBase64(bytes.slice(16, 48))
+}
+
 async function getEncryptionKeyFromUrl(usages = ['encrypt', 'decrypt']) {
     _parseFragment()
     const keyBase64 = _fragmentKey
 
-    if (!keyBase64) {
+    let keyData
+    if (keyBase64) {
+        // base64 -> string -> byte array
+        keyData = urlSafeBase64ToUint8Array(keyBase64);
+    } else if (_fragmentSalt) {
+        const passphrase = window.prompt('Enter the passphrase the sender gave you')
+        if (!passphrase) {
+            throw new Error('Passphrase required')
+        }
+        keyData = await deriveKeyInWorker(passphrase, _fragmentSalt)
+    } else {
         throw new Error('Missing encryption key')
     }
 
-    // base64 -> string -> byte array
-    const keyData = urlSafeBase64ToUint8Array(keyBase64);
-
     const key = await crypto.subtle.importKey(
         'raw',
         keyData,
@@ -58,8 +111,29 @@ async function getEncryptionKeyFromUrl(usages = ['encrypt', 'decrypt']) {
         false,
         usages
     )
+    const macKey = await deriveFileMacKey(keyData, ['verify'])
+
+    return { key, macKey }
+}
 
-    return { key }
+// HKDF label for the whole-file MAC key; must match FILE_MAC_INFO in hash.rs
+const FILE_MAC_INFO = 'archdrop-file-mac-v1'
+
+// Whole-file MACs use an HKDF-derived key, never the AES key bytes themselves
+async function deriveFileMacKey(keyData, usages) {
+    const ikm = await crypto.subtle.importKey('raw', keyData, 'HKDF', false, ['deriveKey'])
+    return crypto.subtle.deriveKey(
+        {
+            name: 'HKDF',
+            hash: 'SHA-256',
+            salt: new Uint8Array(0),
+            info: new TextEncoder().encode(FILE_MAC_INFO),
+        },
+        ikm,
+        { name: 'HMAC', hash: 'SHA-256', length: 256 },
+        false,
+        usages
+    )
 }
 
 function getTokenFromUrl() {
@@ -67,13 +141,66 @@ function getTokenFromUrl() {
     return _fragmentToken
 }
 
+// Servers started with --auth-token also want a PIN that is never in the link
+const AUTH_SECRET_HEADER_NAME = 'X-Auth-Token'
+let _authPin = ''
+
 function authHeaders() {
-    return { 'Authorization': 'Bearer ' + getTokenFromUrl() }
+    const headers = { 'Authorization': 'Bearer ' + getTokenFromUrl() }
+    if (_authPin) {
+        headers[AUTH_SECRET_HEADER_NAME] = _authPin
+    }
+    return headers
+}
+
+// Ask for the PIN until the server accepts it; a no-op when none is required
+async function ensureAuthPin() {
+    for (;;) {
+        const response = await fetch('/auth/check', { headers: authHeaders() })
+        if (response.ok) {
+            return
+        }
+        if (response.status === 429) {
+            throw new Error('Too many wrong PINs; ask the sender to start a new transfer')
+        }
+        if (response.status !== 401) {
+            throw new Error(`Auth check failed: HTTP ${response.status}`)
+        }
+        const pin = window.prompt(_authPin
+            ? 'Wrong PIN, try again:'
+            : 'Enter the PIN shared with you separately:')
+        if (pin === null) {
+            throw new Error('A PIN is required for this transfer')
+        }
+        _authPin = pin.trim()
+    }
 }
 
 const LOCK_HEADER_NAME = 'X-Transfer-Lock'
 let _lockToken = ''
 
+// A random id this browser keeps, so a reload reuses its claim instead of
+// counting as another download
+const CLIENT_ID_HEADER_NAME = 'X-Client-Id'
+const CLIENT_ID_STORAGE_KEY = 'archdrop-client-id'
+let _clientId = ''
+
+function clientId() {
+    if (!_clientId) {
+        try {
+            _clientId = localStorage.getItem(CLIENT_ID_STORAGE_KEY) || ''
+            if (!_clientId) {
+                _clientId = crypto.randomUUID()
+                localStorage.setItem(CLIENT_ID_STORAGE_KEY, _clientId)
+            }
+        } catch {
+            // Storage blocked: the id still covers retries from this page
+            _clientId = _clientId || crypto.randomUUID()
+        }
+    }
+    return _clientId
+}
+
 function setLockToken(lockToken) {
     _lockToken = lockToken || ''
 }
@@ -120,6 +247,542 @@ function generateNonce(nonceBase64, counter) {
     return nonce
 }
 
+// Counter bit for chunks whose plaintext was zstd-compressed, so the same
+// chunk sealed compressed and plain never shares a nonce
+const COMPRESSED_COUNTER_FLAG = 0x80000000
+
+// AES-GCM params for one chunk. Suite 2 links also authenticate where the
+// chunk belongs, matching Rust's ChunkPosition::to_aad:
+// [4 byte file index][4 byte chunk index][8 byte file size][1 byte flags],
+// all big-endian; flag bit 0 marks a compressed chunk
+function chunkCipherParams(nonceBase, fileIndex, chunkIndex, fileSize, compressed = false) {
+    _parseFragment()
+    const counter = compressed ? (chunkIndex | COMPRESSED_COUNTER_FLAG) >>> 0 : chunkIndex
+    const iv = generateNonce(nonceBase, counter)
+    if (_fragmentSuite < 2) {
+        return { name: 'AES-GCM', iv }
+    }
+
+    const additionalData = new Uint8Array(17)
+    const view = new DataView(additionalData.buffer)
+    view.setUint32(0, fileIndex, false)
+    view.setUint32(4, chunkIndex, false)
+    view.setBigUint64(8, BigInt(fileSize), false)
+    additionalData[16] = compressed ? 1 : 0
+
+    return { name: 'AES-GCM', iv, additionalData }
+}
+
+//=======================
+// Passphrase-derived keys
+//=======================
+// Links made with `--password` carry a salt instead of the key. The key is
+// Argon2id(passphrase, salt); these parameters must match src/crypto/password.rs
+const ARGON2_MEMORY_KIB = 19456
+const ARGON2_ITERATIONS = 2
+const ARGON2_PARALLELISM = 1
+const ARGON2_KEY_LEN = 32
+
+// 64-bit words are stored as [lo, hi] pairs of 32-bit words
+const _B2B_IV32 = new Uint32Array([
+    0xF3BCC908, 0x6A09E667, 0x84CAA73B, 0xBB67AE85, 0xFE94F82B, 0x3C6EF372, 0x5F1D36F1, 0xA54FF53A,
+    0xADE682D1, 0x510E527F, 0x2B3E6C1F, 0x9B05688C, 0xFB41BD6B, 0x1F83D9AB, 0x137E2179, 0x5BE0CD19
+])
+
+const _B2B_SIGMA = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3]
+]
+
+// v[a] += v[b] (+ m[c] when given); a, b, c index 64-bit words
+function _add64(v, a, b, m, c) {
+    let lo = v[2 * a] + v[2 * b]
+    let hi = v[2 * a + 1] + v[2 * b + 1]
+    if (m) {
+        lo += m[2 * c]
+        hi += m[2 * c + 1]
+    }
+    v[2 * a] = lo
+    v[2 * a + 1] = hi + Math.floor(lo / 0x100000000)
+}
+
+// v[a] = rotr64(v[a] ^ v[b], n) for n in 16, 24, 32, 63
+function _xorRotr64(v, a, b, n) {
+    const lo = v[2 * a] ^ v[2 * b]
+    const hi = v[2 * a + 1] ^ v[2 * b + 1]
+    if (n === 32) {
+        v[2 * a] = hi
+        v[2 * a + 1] = lo
+    } else if (n === 63) {
+        v[2 * a] = (lo << 1) | (hi >>> 31)
+        v[2 * a + 1] = (hi << 1) | (lo >>> 31)
+    } else {
+        v[2 * a] = (lo >>> n) | (hi << (32 - n))
+        v[2 * a + 1] = (hi >>> n) | (lo << (32 - n))
+    }
+}
+
+function _blake2bG(v, m, a, b, c, d, x, y) {
+    _add64(v, a, b, m, x)
+    _xorRotr64(v, d, a, 32)
+    _add64(v, c, d)
+    _xorRotr64(v, b, c, 24)
+    _add64(v, a, b, m, y)
+    _xorRotr64(v, d, a, 16)
+    _add64(v, c, d)
+    _xorRotr64(v, b, c, 63)
+}
+
+// One-shot BLAKE2b (unkeyed) with `outLen` bytes of output (1..64)
+function blake2b(input, outLen) {
+    const h = new Uint32Array(_B2B_IV32)
+    h[0] ^= 0x01010000 ^ outLen
+    const v = new Uint32Array(32)
+    const m = new Uint32Array(32)
+    const block = new Uint8Array(128)
+    const blockWords = new DataView(block.buffer)
+
+    let offset = 0
+    do {
+        const len = Math.min(128, input.length - offset)
+        block.fill(0)
+        block.set(input.subarray(offset, offset + len))
+        offset += len
+        const last = offset >= input.length
+
+        for (let i = 0; i < 32; i++) m[i] = blockWords.getUint32(4 * i, true)
+        v.set(h)
+        v.set(_B2B_IV32, 16)
+        v[24] ^= offset % 0x100000000
+        v[25] ^= Math.floor(offset / 0x100000000)
+        if (last) {
+            v[28] = ~v[28]
+            v[29] = ~v[29]
+        }
+        for (const s of _B2B_SIGMA) {
+            _blake2bG(v, m, 0, 4, 8, 12, s[0], s[1])
+            _blake2bG(v, m, 1, 5, 9, 13, s[2], s[3])
+            _blake2bG(v, m, 2, 6, 10, 14, s[4], s[5])
+            _blake2bG(v, m, 3, 7, 11, 15, s[6], s[7])
+            _blake2bG(v, m, 0, 5, 10, 15, s[8], s[9])
+            _blake2bG(v, m, 1, 6, 11, 12, s[10], s[11])
+            _blake2bG(v, m, 2, 7, 8, 13, s[12], s[13])
+            _blake2bG(v, m, 3, 4, 9, 14, s[14], s[15])
+        }
+        for (let i = 0; i < 16; i++) h[i] ^= v[i] ^ v[i + 16]
+    } while (offset < input.length)
+
+    const out = new Uint8Array(64)
+    const outWords = new DataView(out.buffer)
+    for (let i = 0; i < 16; i++) outWords.setUint32(4 * i, h[i], true)
+    return out.slice(0, outLen)
+}
+
+function _le32(n) {
+    const bytes = new Uint8Array(4)
+    new DataView(bytes.buffer).setUint32(0, n, true)
+    return bytes
+}
+
+function _concatBytes(...parts) {
+    const out = new Uint8Array(parts.reduce((n, p) => n + p.length, 0))
+    let offset = 0
+    for (const part of parts) {
+        out.set(part, offset)
+        offset += part.length
+    }
+    return out
+}
+
+// Argon2 variable-length hash H'
+function _argon2Hash(outLen, input) {
+    const prefixed = _concatBytes(_le32(outLen), input)
+    if (outLen <= 64) return blake2b(prefixed, outLen)
+
+    const out = new Uint8Array(outLen)
+    let v = blake2b(prefixed, 64)
+    out.set(v.subarray(0, 32), 0)
+    let pos = 32
+    while (outLen - pos > 64) {
+        v = blake2b(v, 64)
+        out.set(v.subarray(0, 32), pos)
+        pos += 32
+    }
+    out.set(blake2b(v, outLen - pos), pos)
+    return out
+}
+
+// Argon2's G on four 64-bit words at u32 offsets a, b, c, d of v, using the
+// BlaMka multiply-add x + y + 2 * lo32(x) * lo32(y) in place of plain adds
+function _argon2G(v, a, b, c, d) {
+    let al = v[a], ah = v[a + 1], bl = v[b], bh = v[b + 1]
+    let cl = v[c], ch = v[c + 1], dl = v[d], dh = v[d + 1]
+    let pl, ph, t, m
+
+    // a = a + b + 2ab; d = rotr64(d ^ a, 32)
+    m = (al >>> 16) * (bl & 0xffff) + (al & 0xffff) * (bl >>> 16)
+    pl = (al & 0xffff) * (bl & 0xffff) + (m % 0x10000) * 0x10000
+    ph = (al >>> 16) * (bl >>> 16) + ((m / 0x10000) | 0) + ((pl / 0x100000000) | 0)
+    pl = pl >>> 0
+    t = al + bl + 2 * pl
+    ah = (ah + bh + 2 * ph + ((t / 0x100000000) | 0)) >>> 0
+    al = t >>> 0
+    t = (dl ^ al) >>> 0; dl = (dh ^ ah) >>> 0; dh = t
+
+    // c = c + d + 2cd; b = rotr64(b ^ c, 24)
+    m = (cl >>> 16) * (dl & 0xffff) + (cl & 0xffff) * (dl >>> 16)
+    pl = (cl & 0xffff) * (dl & 0xffff) + (m % 0x10000) * 0x10000
+    ph = (cl >>> 16) * (dl >>> 16) + ((m / 0x10000) | 0) + ((pl / 0x100000000) | 0)
+    pl = pl >>> 0
+    t = cl + dl + 2 * pl
+    ch = (ch + dh + 2 * ph + ((t / 0x100000000) | 0)) >>> 0
+    cl = t >>> 0
+    bl ^= cl; bh ^= ch
+    t = ((bl >>> 24) | (bh << 8)) >>> 0; bh = ((bh >>> 24) | (bl << 8)) >>> 0; bl = t
+
+    // a = a + b + 2ab; d = rotr64(d ^ a, 16)
+    m = (al >>> 16) * (bl & 0xffff) + (al & 0xffff) * (bl >>> 16)
+    pl = (al & 0xffff) * (bl & 0xffff) + (m % 0x10000) * 0x10000
+    ph = (al >>> 16) * (bl >>> 16) + ((m / 0x10000) | 0) + ((pl / 0x100000000) | 0)
+    pl = pl >>> 0
+    t = al + bl + 2 * pl
+    ah = (ah + bh + 2 * ph + ((t / 0x100000000) | 0)) >>> 0
+    al = t >>> 0
+    dl ^= al; dh ^= ah
+    t = ((dl >>> 16) | (dh << 16)) >>> 0; dh = ((dh >>> 16) | (dl << 16)) >>> 0; dl = t
+
+    // c = c + d + 2cd; b = rotr64(b ^ c, 63)
+    m = (cl >>> 16) * (dl & 0xffff) + (cl & 0xffff) * (dl >>> 16)
+    pl = (cl & 0xffff) * (dl & 0xffff) + (m % 0x10000) * 0x10000
+    ph = (cl >>> 16) * (dl >>> 16) + ((m / 0x10000) | 0) + ((pl / 0x100000000) | 0)
+    pl = pl >>> 0
+    t = cl + dl + 2 * pl
+    ch = (ch + dh + 2 * ph + ((t / 0x100000000) | 0)) >>> 0
+    cl = t >>> 0
+    bl ^= cl; bh ^= ch
+    t = (bl << 1) | (bh >>> 31); bh = (bh << 1) | (bl >>> 31); bl = t
+
+    v[a] = al; v[a + 1] = ah; v[b] = bl; v[b + 1] = bh
+    v[c] = cl; v[c + 1] = ch; v[d] = dl; v[d + 1] = dh
+}
+
+// BLAKE2 round without message over 16 of the block's 64-bit words,
+// given as u32 offsets w[o..o+16]
+function _argon2Round(v, w, o) {
+    _argon2G(v, w[o], w[o + 4], w[o + 8], w[o + 12])
+    _argon2G(v, w[o + 1], w[o + 5], w[o + 9], w[o + 13])
+    _argon2G(v, w[o + 2], w[o + 6], w[o + 10], w[o + 14])
+    _argon2G(v, w[o + 3], w[o + 7], w[o + 11], w[o + 15])
+    _argon2G(v, w[o], w[o + 5], w[o + 10], w[o + 15])
+    _argon2G(v, w[o + 1], w[o + 6], w[o + 11], w[o + 12])
+    _argon2G(v, w[o + 2], w[o + 7], w[o + 8], w[o + 13])
+    _argon2G(v, w[o + 3], w[o + 4], w[o + 9], w[o + 14])
+}
+
+// u32 offsets of the 64-bit words in each row, then each column, of a block
+const _ARGON2_ROWS = Int32Array.from({ length: 128 }, (_, k) => 2 * k)
+const _ARGON2_COLUMNS = Int32Array.from({ length: 128 }, (_, k) => {
+    const i = k >> 4, j = k & 15
+    return 2 * (2 * i + (j & 1) + 16 * (j >> 1))
+})
+
+// Compression G: out = P(x ^ y) ^ x ^ y (^ old out when xorOut)
+// Blocks are 256-word views into memory (or scratch arrays)
+const _argon2R = new Uint32Array(256)
+const _argon2Tmp = new Uint32Array(256)
+function _argon2Compress(out, x, y, xorOut) {
+    const r = _argon2R, tmp = _argon2Tmp
+    for (let i = 0; i < 256; i++) r[i] = x[i] ^ y[i]
+    tmp.set(r)
+    if (xorOut) {
+        for (let i = 0; i < 256; i++) tmp[i] ^= out[i]
+    }
+    for (let o = 0; o < 128; o += 16) _argon2Round(r, _ARGON2_ROWS, o)
+    for (let o = 0; o < 128; o += 16) _argon2Round(r, _ARGON2_COLUMNS, o)
+    for (let i = 0; i < 256; i++) out[i] = tmp[i] ^ r[i]
+}
+
+function _bytesToWords(bytes) {
+    const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength)
+    const words = new Uint32Array(bytes.length / 4)
+    for (let i = 0; i < words.length; i++) words[i] = view.getUint32(4 * i, true)
+    return words
+}
+
+// Argon2id (version 0x13); links use no secret or associated data
+function argon2id(password, salt, {
+    memoryKib, iterations, parallelism, outLen,
+    secret = new Uint8Array(0), associatedData = new Uint8Array(0)
+}) {
+    const h0 = blake2b(_concatBytes(
+        _le32(parallelism), _le32(outLen), _le32(memoryKib), _le32(iterations),
+        _le32(0x13), _le32(2),
+        _le32(password.length), password,
+        _le32(salt.length), salt,
+        _le32(secret.length), secret,
+        _le32(associatedData.length), associatedData
+    ), 64)
+
+    const lanes = parallelism
+    const segmentLength = Math.floor(memoryKib / (4 * lanes))
+    const laneLength = 4 * segmentLength
+    const memory = new Uint32Array(lanes * laneLength * 256)
+    const block = index => memory.subarray(index * 256, index * 256 + 256)
+
+    for (let lane = 0; lane < lanes; lane++) {
+        for (let i = 0; i < 2; i++) {
+            const bytes = _argon2Hash(1024, _concatBytes(h0, _le32(i), _le32(lane)))
+            memory.set(_bytesToWords(bytes), (lane * laneLength + i) * 256)
+        }
+    }
+
+    const zero = new Uint32Array(256)
+    const input = new Uint32Array(256)
+    const addresses = new Uint32Array(256)
+    const nextAddresses = () => {
+        input[12]++
+        _argon2Compress(addresses, zero, input, false)
+        _argon2Compress(addresses, zero, addresses, false)
+    }
+
+    for (let pass = 0; pass < iterations; pass++) {
+        for (let slice = 0; slice < 4; slice++) {
+            for (let lane = 0; lane < lanes; lane++) {
+                const independent = pass === 0 && slice < 2
+                if (independent) {
+                    input.fill(0)
+                    input[0] = pass
+                    input[2] = lane
+                    input[4] = slice
+                    input[6] = lanes * laneLength
+                    input[8] = iterations
+                    input[10] = 2
+                }
+
+                let start = 0
+                if (pass === 0 && slice === 0) {
+                    start = 2
+                    if (independent) nextAddresses()
+                }
+
+                for (let index = start; index < segmentLength; index++) {
+                    const column = slice * segmentLength + index
+                    const current = lane * laneLength + column
+                    const previous = column === 0 ? current + laneLength - 1 : current - 1
+
+                    let j1, j2
+                    if (independent) {
+                        if (index % 128 === 0) nextAddresses()
+                        j1 = addresses[2 * (index % 128)]
+                        j2 = addresses[2 * (index % 128) + 1]
+                    } else {
+                        j1 = memory[previous * 256]
+                        j2 = memory[previous * 256 + 1]
+                    }
+
+                    const refLane = pass === 0 && slice === 0 ? lane : j2 % lanes
+                    const sameLane = refLane === lane
+                    let areaSize
+                    if (pass === 0) {
+                        areaSize = slice === 0
+                            ? index - 1
+                            : sameLane
+                                ? slice * segmentLength + index - 1
+                                : slice * segmentLength - (index === 0 ? 1 : 0)
+                    } else {
+                        areaSize = sameLane
+                            ? laneLength - segmentLength + index - 1
+                            : laneLength - segmentLength - (index === 0 ? 1 : 0)
+                    }
+
+                    // relative = areaSize - 1 - (areaSize * (j1^2 >> 32) >> 32)
+                    const x = Number((BigInt(j1) * BigInt(j1)) >> 32n)
+                    const y = Number((BigInt(areaSize) * BigInt(x)) >> 32n)
+                    const relative = areaSize - 1 - y
+                    const startPosition = pass !== 0 && slice !== 3 ? (slice + 1) * segmentLength : 0
+                    const refIndex = (startPosition + relative) % laneLength
+
+                    _argon2Compress(
+                        block(current),
+                        block(previous),
+                        block(refLane * laneLength + refIndex),
+                        pass !== 0
+                    )
+                }
+            }
+        }
+    }
+
+    const final = new Uint32Array(block(laneLength - 1))
+    for (let lane = 1; lane < lanes; lane++) {
+        const last = block(lane * laneLength + laneLength - 1)
+        for (let i = 0; i < 256; i++) final[i] ^= last[i]
+    }
+    const finalBytes = new Uint8Array(1024)
+    const view = new DataView(finalBytes.buffer)
+    for (let i = 0; i < 256; i++) view.setUint32(4 * i, final[i], true)
+    return _argon2Hash(outLen, finalBytes)
+}
+
+// Derive the raw AES-256 key for a `--password` link
+function deriveKeyFromPassphrase(passphrase, saltBase64) {
+    return argon2id(new TextEncoder().encode(passphrase), urlSafeBase64ToUint8Array(saltBase64), {
+        memoryKib: ARGON2_MEMORY_KIB,
+        iterations: ARGON2_ITERATIONS,
+        parallelism: ARGON2_PARALLELISM,
+        outLen: ARGON2_KEY_LEN
+    })
+}
+
+// Argon2id takes seconds and 19 MiB; run it in a Worker on this same script
+// so the page stays responsive
+function deriveKeyInWorker(passphrase, saltBase64) {
+    return new Promise((resolve, reject) => {
+        const worker = new Worker('/shared.js')
+        worker.onmessage = ({ data }) => {
+            worker.terminate()
+            if (data.error) {
+                reject(new Error(data.error))
+            } else {
+                resolve(data.key)
+            }
+        }
+        worker.onerror = event => {
+            worker.terminate()
+            reject(new Error(event.message || 'Key derivation failed'))
+        }
+        worker.postMessage({ passphrase, salt: saltBase64 })
+    })
+}
+
+// Loaded as a Worker by deriveKeyInWorker: answer one derivation request
+if (typeof WorkerGlobalScope !== 'undefined' && self instanceof WorkerGlobalScope) {
+    self.onmessage = ({ data }) => {
+        try {
+            const key = deriveKeyFromPassphrase(data.passphrase, data.salt)
+            self.postMessage({ key }, [key.buffer])
+        } catch (err) {
+            self.postMessage({ error: err.message })
+        }
+    }
+}
+
+//==================
+// File integrity
+//==================
+// WebCrypto only hashes whole buffers, so chunks are fed to this
+// incremental SHA-256 in file order and checked against the manifest
+const _SHA256_K = new Int32Array([
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+])
+
+class Sha256 {
+    constructor() {
+        this.h = new Int32Array([
+            0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+            0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+        ])
+        this.w = new Int32Array(64)
+        this.block = new Uint8Array(64)
+        this.blockLen = 0
+        this.length = 0
+    }
+
+    update(bytes) {
+        let offset = 0
+        this.length += bytes.length
+        if (this.blockLen > 0) {
+            const take = Math.min(64 - this.blockLen, bytes.length)
+            this.block.set(bytes.subarray(0, take), this.blockLen)
+            this.blockLen += take
+            offset = take
+            if (this.blockLen < 64) return this
+            this._compress(this.block, 0)
+            this.blockLen = 0
+        }
+        for (; offset + 64 <= bytes.length; offset += 64) {
+            this._compress(bytes, offset)
+        }
+        this.block.set(bytes.subarray(offset), 0)
+        this.blockLen = bytes.length - offset
+        return this
+    }
+
+    hexDigest() {
+        const bitLength = this.length * 8
+        const tail = new Uint8Array(this.blockLen < 56 ? 64 : 128)
+        tail.set(this.block.subarray(0, this.blockLen))
+        tail[this.blockLen] = 0x80
+        const view = new DataView(tail.buffer)
+        view.setUint32(tail.length - 8, Math.floor(bitLength / 0x100000000))
+        view.setUint32(tail.length - 4, bitLength >>> 0)
+        for (let offset = 0; offset < tail.length; offset += 64) {
+            this._compress(tail, offset)
+        }
+        return Array.from(this.h, word => (word >>> 0).toString(16).padStart(8, '0')).join('')
+    }
+
+    _compress(bytes, offset) {
+        const w = this.w
+        for (let i = 0; i < 16; i++) {
+            const j = offset + i * 4
+            w[i] = (bytes[j] << 24) | (bytes[j + 1] << 16) | (bytes[j + 2] << 8) | bytes[j + 3]
+        }
+        for (let i = 16; i < 64; i++) {
+            const x = w[i - 15], y = w[i - 2]
+            const s0 = ((x >>> 7) | (x << 25)) ^ ((x >>> 18) | (x << 14)) ^ (x >>> 3)
+            const s1 = ((y >>> 17) | (y << 15)) ^ ((y >>> 19) | (y << 13)) ^ (y >>> 10)
+            w[i] = (w[i - 16] + s0 + w[i - 7] + s1) | 0
+        }
+        const state = this.h
+        let a = state[0], b = state[1], c = state[2], d = state[3]
+        let e = state[4], f = state[5], g = state[6], h = state[7]
+        for (let i = 0; i < 64; i++) {
+            const S1 = ((e >>> 6) | (e << 26)) ^ ((e >>> 11) | (e << 21)) ^ ((e >>> 25) | (e << 7))
+            const t1 = (h + S1 + ((e & f) ^ (~e & g)) + _SHA256_K[i] + w[i]) | 0
+            const S0 = ((a >>> 2) | (a << 30)) ^ ((a >>> 13) | (a << 19)) ^ ((a >>> 22) | (a << 10))
+            const t2 = (S0 + ((a & b) ^ (a & c) ^ (b & c))) | 0
+            h = g; g = f; f = e; e = (d + t1) | 0
+            d = c; c = b; b = a; a = (t1 + t2) | 0
+        }
+        state[0] += a; state[1] += b; state[2] += c; state[3] += d
+        state[4] += e; state[5] += f; state[6] += g; state[7] += h
+    }
+}
+
+// Check a manifest `mac` (hex HMAC-SHA256 over the raw digest bytes)
+async function verifyFileMac(macKey, sha256Hex, macHex) {
+    const hexToBytes = hex => new Uint8Array(hex.match(/../g).map(byte => parseInt(byte, 16)))
+    return crypto.subtle.verify('HMAC', macKey, hexToBytes(macHex), hexToBytes(sha256Hex))
+}
+
+// SHA-256 of a local File, read a slice at a time
+async function hashFile(file, chunkSize = 4 * 1024 * 1024) {
+    const hasher = new Sha256()
+    for (let start = 0; start < file.size; start += chunkSize) {
+        const chunk = await file.slice(start, start + chunkSize).arrayBuffer()
+        hasher.update(new Uint8Array(chunk))
+    }
+    return hasher.hexDigest()
+}
+
 //=============
 // UI
 //=============
@@ -261,6 +924,17 @@ function formatFileSize(bytes) {
 // Retry Helper
 //================
 
+// Backoff bounds advertised by the server in its transfer config
+let _retryMinMs = 1000
+let _retryMaxMs = 4000
+
+function setRetryHints(config) {
+    if (config && config.retry_min_ms > 0) {
+        _retryMinMs = config.retry_min_ms
+        _retryMaxMs = Math.max(config.retry_max_ms || 0, _retryMinMs)
+    }
+}
+
 // Retry an async function with exponential backoff
 async function retryWithExponentialBackoff(asyncFn, maxRetries = 3, context = '') {
     for (let attempt = 0; attempt < maxRetries; attempt++) {
@@ -270,8 +944,8 @@ async function retryWithExponentialBackoff(asyncFn, maxRetries = 3, context = ''
             if (attempt === maxRetries - 1) {
                 throw e
             }
-            // Exponential backoff: 1s, 2s, 4s
-            const delay = 1000 * Math.pow(2, attempt)
+            // Exponential backoff from the min hint, capped at the max hint
+            const delay = Math.min(_retryMinMs * Math.pow(2, attempt), _retryMaxMs)
             await new Promise(r => setTimeout(r, delay))
             console.log(`Retrying ${context} (attempt ${attempt + 2}/${maxRetries})...`)
         }
@@ -101,12 +101,16 @@ function removeFile(index) {
 // LOGIC
 //==========
 async function sendManifest(files) {
-    const manifest = {
-        files: files.map(file => ({
+    // The receiver checks each reassembled file against this hash
+    const entries = []
+    for (const file of files) {
+        entries.push({
             relative_path: file.webkitRelativePath || file.name,
-            size: file.size
-        }))
-    };
+            size: file.size,
+            hash: await hashFile(file)
+        })
+    }
+    const manifest = { files: entries };
 
     const response = await fetch('/receive/manifest', {
         method: 'POST',
@@ -131,6 +135,42 @@ async function sendManifest(files) {
     return await response.json();
 }
 
+// Resume token is kept per session token so a reloaded page can re-attach
+function resumeStorageKey() {
+    return 'archdrop-resume:' + getTokenFromUrl()
+}
+
+async function resumeUpload(resumeToken) {
+    const response = await fetch('/receive/resume', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json', ...authHeaders() },
+        body: JSON.stringify({ resumeToken })
+    });
+    if (!response.ok) return null
+    return await response.json();
+}
+
+async function startUploadSession(selectedFiles) {
+    await ensureAuthPin()
+
+    const storedToken = localStorage.getItem(resumeStorageKey())
+    if (storedToken) {
+        const resumed = await resumeUpload(storedToken)
+        if (resumed) {
+            // Files missing from the listing were already finalized
+            const files = new Map(resumed.files.map(f => [f.relative_path, f]))
+            return { ...resumed, resumed: true, files }
+        }
+        localStorage.removeItem(resumeStorageKey())
+    }
+
+    const manifestResponse = await sendManifest(selectedFiles)
+    if (manifestResponse.resumeToken) {
+        localStorage.setItem(resumeStorageKey(), manifestResponse.resumeToken)
+    }
+    return { ...manifestResponse, resumed: false, files: new Map() }
+}
+
 async function uploadFiles(selectedFiles) {
     if (selectedFiles.length === 0) {
         alert('Please select files')
@@ -151,20 +191,42 @@ async function uploadFiles(selectedFiles) {
         const { key } = await getEncryptionKeyFromUrl(['encrypt'])
 
         // Send manifest first so server knows total chunks
+        uploadBtn.textContent = 'Hashing files...'
         console.time('Manifest upload');
-        const manifestResponse = await sendManifest(selectedFiles);
-        setLockToken(manifestResponse.lockToken)
-        const transferConfig = manifestResponse.config
+        const uploadSession = await startUploadSession(selectedFiles);
+        setLockToken(uploadSession.lockToken)
+        const transferConfig = uploadSession.config
+        setRetryHints(transferConfig)
         console.timeEnd('Manifest upload');
+        uploadBtn.textContent = 'Uploading...'
+
+        // The receiver keeps its own copy of these (--on-conflict skip)
+        const skipped = new Set(uploadSession.skipped || [])
 
         await runWithConcurrency(
             selectedFiles.map((file, index) => ({ file, index, fileItem: fileItems[index] })),
-            async ({ file, fileItem }) => {
+            async ({ file, index, fileItem }) => {
                 const relativePath = file.webkitRelativePath || file.name
+                if (skipped.has(relativePath)) {
+                    fileItem.classList.add('skipped')
+                    const progressText = fileItem.querySelector('.progress-text')
+                    if (progressText) {
+                        progressText.textContent = 'Skipped - already on the receiver'
+                    }
+                    return
+                }
+                const resumeState = uploadSession.files.get(relativePath)
+                if (uploadSession.resumed && !resumeState) {
+                    fileItem.classList.add('completed')
+                    return
+                }
 
                 fileItem.classList.add('uploading')
                 try {
-                    await uploadFile(file, relativePath, key, fileItem, transferConfig)
+                    // The receiver numbers files in manifest order; a resumed
+                    // session reports the number it gave each file
+                    const fileIndex = resumeState ? resumeState.index : index
+                    await uploadFile(file, fileIndex, relativePath, key, fileItem, transferConfig, resumeState)
                     fileItem.classList.remove('uploading')
                     fileItem.classList.add('completed')
                 } catch (error) {
@@ -177,6 +239,7 @@ async function uploadFiles(selectedFiles) {
         )
 
         await fetch('/receive/complete', { method: 'POST', headers: transferHeaders() })
+        localStorage.removeItem(resumeStorageKey())
 
         uploadBtn.textContent = 'Upload Complete!'
 
@@ -188,7 +251,7 @@ async function uploadFiles(selectedFiles) {
     }
 }
 
-async function uploadFile(file, relativePath, key, fileItem, config) {
+async function uploadFile(file, fileIndex, relativePath, key, fileItem, config, resumeState) {
     // each file gets its own nonce
     const chunkSize = config.chunk_size
     const totalChunks = Math.ceil(file.size / chunkSize)
@@ -197,10 +260,14 @@ async function uploadFile(file, relativePath, key, fileItem, config) {
     console.time(`${relativePath} - chunk 0`);
     console.time(`${relativePath} - total`);
 
-    const fileNonce = crypto.getRandomValues(new Uint8Array(8));
+    // Keep the nonce the server already saw for a resumed file
+    const fileNonce = resumeState && resumeState.nonce
+        ? urlSafeBase64ToUint8Array(resumeState.nonce)
+        : crypto.getRandomValues(new Uint8Array(8));
+    const alreadyReceived = new Set(resumeState ? resumeState.received : [])
 
     // Track completed chunks for progress
-    let completedChunks = 0
+    let completedChunks = alreadyReceived.size
 
     // Helper function to prepare and upload a single chunk
     const prepareAndUploadChunk = async (chunkIndex) => {
@@ -210,10 +277,9 @@ async function uploadFile(file, relativePath, key, fileItem, config) {
         const chunkData = await chunkBlob.arrayBuffer()
 
         // Encrypt chunk
-        const nonce = generateNonce(fileNonce, chunkIndex)
         const nonceBase64 = arrayBufferToBase64(fileNonce)
         const encrypted = await crypto.subtle.encrypt(
-            { name: 'AES-GCM', iv: nonce },
+            chunkCipherParams(fileNonce, fileIndex, chunkIndex, file.size),
             key,
             chunkData
         )
@@ -233,7 +299,8 @@ async function uploadFile(file, relativePath, key, fileItem, config) {
     }
 
     if (totalChunks > 0) {
-        const chunkIndices = Array.from({ length: totalChunks }, (_, i) => i);
+        const chunkIndices = Array.from({ length: totalChunks }, (_, i) => i)
+            .filter(i => !alreadyReceived.has(i));
         await runWithConcurrency(
             chunkIndices,
             prepareAndUploadChunk,
@@ -0,0 +1,50 @@
+//! Blocking-task execution that turns panics into ordinary errors.
+
+use anyhow::Result;
+
+/// Run `task` on the blocking pool and flatten its result.
+///
+/// A panic inside the task is logged and returned as an error instead of
+/// propagating, so one bad chunk cannot take down the request loop. Shared
+/// locks touched by these tasks recover from poisoning on their side.
+pub async fn run_blocking<T, F>(label: &'static str, task: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(task).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => {
+            let payload = err.into_panic();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            tracing::error!(task = label, panic = %message, "Blocking task panicked");
+            Err(anyhow::anyhow!("{} task panicked", label))
+        }
+        Err(err) => Err(anyhow::anyhow!("{} task cancelled: {}", label, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn panic_becomes_error() {
+        let err = run_blocking("probe", || -> Result<()> { panic!("boom") })
+            .await
+            .expect_err("panic should surface as error");
+        assert_eq!(err.to_string(), "probe task panicked");
+    }
+
+    #[tokio::test]
+    async fn ok_result_passes_through() {
+        let value = run_blocking("probe", || Ok(7))
+            .await
+            .expect("task succeeds");
+        assert_eq!(value, 7);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod blocking;
 pub mod security;
+pub mod size;
 
-pub use security::{hash_path, validate_filename, validate_path, ValidationError};
+pub use blocking::run_blocking;
+pub use security::{hash_path, log_path, validate_filename, validate_path, ValidationError};
@@ -1,5 +1,6 @@
 use sha2::{Digest, Sha256};
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -46,6 +47,33 @@ pub fn hash_path(path: &str) -> String {
     format!("{:x}", hasher.finalize())[..16].to_string()
 }
 
+// =============
+// Log redaction
+// =============
+
+static REDACT_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Log [`hash_path`] digests instead of file paths (`--redact-paths`).
+pub fn set_redact_paths(enabled: bool) {
+    REDACT_PATHS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether file paths are currently kept out of logs.
+pub fn redact_paths() -> bool {
+    REDACT_PATHS.load(Ordering::Relaxed)
+}
+
+/// A file path as it may appear in logs: as given, or its [`hash_path`]
+/// digest when redaction is on.
+pub fn log_path(path: impl AsRef<Path>) -> String {
+    let path = path.as_ref().to_string_lossy();
+    if redact_paths() {
+        hash_path(&path)
+    } else {
+        path.into_owned()
+    }
+}
+
 // ==================
 // Lexical validation
 // ==================
@@ -97,6 +125,13 @@ pub fn validate_filename(filename: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// Clap `value_parser` for a user-chosen download filename.
+pub fn parse_download_name(input: &str) -> Result<String, String> {
+    validate_filename(input)
+        .map(|()| input.to_string())
+        .map_err(|err| err.to_string())
+}
+
 // =========================
 // Receive path confinement
 // =========================
@@ -0,0 +1,88 @@
+//! Human-readable byte sizes for CLI flags and messages.
+
+const UNITS: [(&str, u64); 5] = [
+    ("", 1),
+    ("K", 1 << 10),
+    ("M", 1 << 20),
+    ("G", 1 << 30),
+    ("T", 1 << 40),
+];
+
+/// Parse `1048576`, `512K`, `10MB`, `1.5GiB` and the like into bytes.
+///
+/// Units are binary (`1K` = 1024) and case-insensitive; a trailing `B` or
+/// `iB` is optional. Usable directly as a clap `value_parser`.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let unit = unit.trim().to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let multiplier = UNITS
+        .iter()
+        .find(|(suffix, _)| *suffix == unit)
+        .map(|(_, multiplier)| *multiplier)
+        .ok_or_else(|| format!("unknown size unit in '{}' (use K, M, G or T)", input))?;
+
+    let bytes = match number.parse::<u64>() {
+        Ok(whole) => whole.checked_mul(multiplier),
+        Err(_) => number
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite() && *value >= 0.0)
+            .map(|value| value * multiplier as f64)
+            .filter(|bytes| *bytes < u64::MAX as f64)
+            .map(|bytes| bytes as u64),
+    };
+    bytes.ok_or_else(|| format!("invalid size '{}'", input))
+}
+
+/// Format bytes with the largest binary unit that keeps the value >= 1.
+pub fn format_size(bytes: u64) -> String {
+    let (suffix, multiplier) = UNITS
+        .iter()
+        .rev()
+        .find(|(_, multiplier)| bytes >= *multiplier)
+        .copied()
+        .unwrap_or(UNITS[0]);
+    if multiplier == 1 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}iB", bytes as f64 / multiplier as f64, suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_and_suffixed_sizes() {
+        assert_eq!(parse_size("1048576"), Ok(1_048_576));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("10MB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("2 gib"), Ok(2 << 30));
+        assert_eq!(parse_size("1.5G"), Ok(3 << 29));
+    }
+
+    #[test]
+    fn rejects_unknown_units_and_garbage() {
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn formats_with_largest_unit() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(10 * 1024 * 1024), "10.0 MiB");
+    }
+}
@@ -0,0 +1,84 @@
+//! Peak heap use while archiving a directory that holds one large file.
+//!
+//! Kept in its own test binary with a single test: the counting allocator is
+//! process-wide, so concurrent tests would inflate the peak.
+
+use archdrop::send::{create_temp_zip_archive, ExcludeRules, TarStream};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::File;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAlloc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Reset the peak to the current live size and return that baseline.
+fn reset_peak() -> usize {
+    let live = LIVE.load(Ordering::Relaxed);
+    PEAK.store(live, Ordering::Relaxed);
+    live
+}
+
+const FILE_SIZE: u64 = 64 * 1024 * 1024;
+const HEAP_BUDGET: usize = 8 * 1024 * 1024;
+
+#[test]
+fn archiving_a_large_file_keeps_heap_use_bounded() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("photos");
+    std::fs::create_dir(&input).unwrap();
+    File::create(input.join("huge.bin"))
+        .and_then(|file| file.set_len(FILE_SIZE))
+        .unwrap();
+    std::fs::write(input.join("small.txt"), b"small").unwrap();
+    let inputs = vec![input];
+    let excludes = ExcludeRules::default();
+
+    let baseline = reset_peak();
+    let archive = create_temp_zip_archive(&inputs, &excludes).unwrap();
+    let zip_peak = PEAK.load(Ordering::Relaxed) - baseline;
+    assert!(
+        zip_peak < HEAP_BUDGET,
+        "zip used {zip_peak} bytes of heap for a {FILE_SIZE}-byte file"
+    );
+    let mut zip = zip::ZipArchive::new(File::open(archive.path()).unwrap()).unwrap();
+    assert_eq!(zip.by_name("photos/huge.bin").unwrap().size(), FILE_SIZE);
+    drop(zip);
+    drop(archive);
+
+    let stream = TarStream::build(&inputs, &excludes).unwrap();
+    let baseline = reset_peak();
+    stream.sha256().unwrap();
+    let mut chunk = vec![0u8; 1024 * 1024];
+    let mut offset = 0;
+    while offset < stream.len() {
+        let take = chunk.len().min((stream.len() - offset) as usize);
+        stream.read_at(offset, &mut chunk[..take]).unwrap();
+        offset += take as u64;
+    }
+    let tar_peak = PEAK.load(Ordering::Relaxed) - baseline;
+    assert!(
+        tar_peak < HEAP_BUDGET,
+        "tar stream used {tar_peak} bytes of heap for a {FILE_SIZE}-byte file"
+    );
+}
@@ -3,7 +3,10 @@
 pub mod config_test_utils;
 
 use archdrop::common::TransferSettings;
-use archdrop::crypto::types::EncryptionKey;
+use archdrop::crypto::types::{EncryptionKey, Nonce};
+use archdrop::crypto::{
+    decrypt_chunk_at_position, encrypt_chunk_at_position, ChunkPosition, CipherSuite,
+};
 use aws_lc_rs::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
 use tempfile::TempDir;
 
@@ -13,6 +16,8 @@ pub fn default_config() -> TransferSettings {
     TransferSettings {
         chunk_size: CHUNK_SIZE as u64,
         concurrency: 8,
+        retry_min_ms: 1,
+        retry_max_ms: 1,
     }
 }
 
@@ -24,3 +29,29 @@ pub fn create_cipher(key: &EncryptionKey) -> LessSafeKey {
     let unbound = UnboundKey::new(&AES_256_GCM, key.as_bytes()).expect("valid 32-byte AES-256 key");
     LessSafeKey::new(unbound)
 }
+
+/// Seal a chunk the way the web client does for a v2 (position-bound) link.
+pub fn encrypt_chunk(
+    cipher: &LessSafeKey,
+    nonce: &Nonce,
+    buffer: &mut Vec<u8>,
+    file_index: usize,
+    chunk_index: usize,
+    file_size: u64,
+) -> anyhow::Result<()> {
+    let position = ChunkPosition::new(file_index, chunk_index, file_size);
+    encrypt_chunk_at_position(cipher, nonce, buffer, &position, CipherSuite::PositionBound)
+}
+
+/// Open a chunk served to a v2 (position-bound) link.
+pub fn decrypt_chunk(
+    cipher: &LessSafeKey,
+    nonce: &Nonce,
+    buffer: &mut Vec<u8>,
+    file_index: usize,
+    chunk_index: usize,
+    file_size: u64,
+) -> anyhow::Result<()> {
+    let position = ChunkPosition::new(file_index, chunk_index, file_size);
+    decrypt_chunk_at_position(cipher, nonce, buffer, &position, CipherSuite::PositionBound)
+}
@@ -24,7 +24,7 @@ use axum::{
     http::{Method, Request, StatusCode},
     Router,
 };
-use common::{create_cipher, default_config, setup_temp_dir, CHUNK_SIZE};
+use common::{create_cipher, default_config, encrypt_chunk, setup_temp_dir, CHUNK_SIZE};
 use http_body_util::BodyExt;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -207,7 +207,7 @@ async fn test_concurrent_different_files_same_directory() {
 
             let cipher = create_cipher(&key);
             let mut encrypted = chunk_data.clone();
-            archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted, 0)
+            encrypt_chunk(&cipher, &nonce, &mut encrypted, file_idx, 0, file_size)
                 .expect("Failed to encrypt chunk");
 
             let request = with_lock_token(
@@ -332,11 +332,13 @@ async fn test_concurrent_chunks_different_files() {
 
                 let cipher = create_cipher(&key);
                 let mut encrypted = chunk_data.clone();
-                archdrop::crypto::encrypt_chunk_in_place(
+                encrypt_chunk(
                     &cipher,
                     &nonce,
                     &mut encrypted,
-                    chunk_idx as u32,
+                    file_idx,
+                    chunk_idx,
+                    file_size,
                 )
                 .expect("Failed to encrypt chunk");
 
@@ -565,13 +567,8 @@ async fn test_concurrent_chunk_uploads_mutex_contention() {
 
             let cipher = create_cipher(&key);
             let mut encrypted = chunk_data.clone();
-            archdrop::crypto::encrypt_chunk_in_place(
-                &cipher,
-                &nonce,
-                &mut encrypted,
-                chunk_idx as u32,
-            )
-            .expect("Failed to encrypt chunk");
+            encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, chunk_idx, file_size)
+                .expect("Failed to encrypt chunk");
 
             let request = with_lock_token(
                 build_multipart_request(
@@ -650,6 +647,8 @@ async fn test_concurrent_upload_smoke() {
     let small_config = TransferSettings {
         chunk_size: SMALL_CHUNK as u64,
         concurrency: 4,
+        retry_min_ms: 1,
+        retry_max_ms: 1,
     };
     let (app, state) =
         create_test_app_with_config(temp_dir.path().to_path_buf(), key.clone(), small_config);
@@ -691,8 +690,15 @@ async fn test_concurrent_upload_smoke() {
 
             let cipher = create_cipher(&key);
             let mut encrypted = chunk_data.clone();
-            archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted, 0)
-                .expect("Failed to encrypt chunk");
+            encrypt_chunk(
+                &cipher,
+                &nonce,
+                &mut encrypted,
+                file_idx,
+                0,
+                SMALL_CHUNK as u64,
+            )
+            .expect("Failed to encrypt chunk");
 
             let request = with_lock_token(
                 build_multipart_request(
@@ -718,9 +724,9 @@ async fn test_concurrent_upload_smoke() {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
-    // Verify all files exist
+    // Verify all files exist (still partial: nothing was finalized)
     for file_idx in 0..3 {
-        let filename = format!("file{}.bin", file_idx);
+        let filename = format!("file{}.bin.partial", file_idx);
         let path = temp_dir.path().join(&filename);
         assert!(path.exists(), "File {} should exist", filename);
     }
@@ -736,6 +742,8 @@ async fn test_concurrent_chunks_smoke() {
     let small_config = TransferSettings {
         chunk_size: SMALL_CHUNK as u64,
         concurrency: 4,
+        retry_min_ms: 1,
+        retry_max_ms: 1,
     };
     let (app, state) =
         create_test_app_with_config(temp_dir.path().to_path_buf(), key.clone(), small_config);
@@ -776,13 +784,8 @@ async fn test_concurrent_chunks_smoke() {
 
             let cipher = create_cipher(&key);
             let mut encrypted = chunk_data.clone();
-            archdrop::crypto::encrypt_chunk_in_place(
-                &cipher,
-                &nonce,
-                &mut encrypted,
-                chunk_idx as u32,
-            )
-            .expect("Failed to encrypt chunk");
+            encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, chunk_idx, file_size)
+                .expect("Failed to encrypt chunk");
 
             let request = with_lock_token(
                 build_multipart_request(
@@ -808,8 +811,8 @@ async fn test_concurrent_chunks_smoke() {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
-    // Verify file exists with correct size
-    let path = temp_dir.path().join("test.bin");
+    // Verify file exists with correct size (still partial: nothing was finalized)
+    let path = temp_dir.path().join("test.bin.partial");
     let metadata = tokio::fs::metadata(&path)
         .await
         .expect("Failed to get metadata");
@@ -1,7 +1,13 @@
 mod common;
 
-use archdrop::common::config::{apply_overrides, load_config, ConfigOverrides, Transport};
+use archdrop::common::config::{
+    apply_overrides, load_config, load_config_from, ConfigOverrides, TlsVersion, Transport,
+};
+use archdrop::crypto::types::EncryptionKey;
+use archdrop::receive::ReceiveAppState;
+use archdrop::server::progress::ProgressTracker;
 use common::config_test_utils::with_config_env;
+use std::sync::Arc;
 
 #[test]
 fn precedence_defaults_file_env_cli() {
@@ -16,6 +22,10 @@ fn precedence_defaults_file_env_cli() {
             let overrides = ConfigOverrides {
                 transport: Some(Transport::Local),
                 port: Some(3333),
+                min_tls: None,
+                concurrency: None,
+                chunk_size: None,
+                max_connections: None,
             };
 
             let config = load_config().expect("load config");
@@ -75,3 +85,164 @@ fn zip_env_overrides_config_file() {
         },
     );
 }
+
+#[test]
+fn min_tls_defaults_to_1_3_and_file_and_cli_override() {
+    with_config_env("", || {
+        let config = load_config().expect("load config");
+        assert_eq!(config.network.min_tls, TlsVersion::Tls13);
+    });
+
+    with_config_env(
+        r#"
+        [network]
+        min_tls = "1.2"
+        "#,
+        || {
+            let config = load_config().expect("load config");
+            assert_eq!(config.network.min_tls, TlsVersion::Tls12);
+
+            let overrides = ConfigOverrides {
+                transport: None,
+                port: None,
+                min_tls: Some(TlsVersion::Tls13),
+                concurrency: None,
+                chunk_size: None,
+                max_connections: None,
+            };
+            let config = apply_overrides(config, &overrides);
+            assert_eq!(config.network.min_tls, TlsVersion::Tls13);
+        },
+    );
+}
+
+#[test]
+fn cli_concurrency_overrides_effective_transport_and_reaches_state() {
+    with_config_env("default_transport = \"tailscale\"", || {
+        let overrides = ConfigOverrides {
+            concurrency: Some(3),
+            ..ConfigOverrides::default()
+        };
+        let config = apply_overrides(load_config().expect("load config"), &overrides);
+        let settings = config.transfer_settings(Transport::Tailscale);
+        assert_eq!(settings.concurrency, 3);
+        assert_eq!(config.transfer_settings(Transport::Local).concurrency, 8);
+        assert_eq!(
+            config.concurrency_limits(Transport::Tailscale).per_client,
+            3
+        );
+
+        // The router state (and from it the manifest) carries the override
+        let state = ReceiveAppState::new(
+            EncryptionKey::new(),
+            std::env::temp_dir(),
+            Arc::new(ProgressTracker::new()),
+            settings,
+        );
+        assert_eq!(state.config.concurrency, 3);
+
+        for (requested, expected) in [(0, 1), (500, 64)] {
+            let overrides = ConfigOverrides {
+                transport: Some(Transport::Local),
+                concurrency: Some(requested),
+                ..ConfigOverrides::default()
+            };
+            let config = apply_overrides(load_config().expect("load config"), &overrides);
+            assert_eq!(
+                config.transfer_settings(Transport::Local).concurrency,
+                expected
+            );
+        }
+    });
+}
+
+#[test]
+fn max_connections_follows_concurrency_unless_set() {
+    with_config_env("", || {
+        let config = load_config().expect("load config");
+        assert_eq!(config.max_connections(Transport::Local), 8 * 8 + 16);
+
+        let overrides = ConfigOverrides {
+            concurrency: Some(2),
+            ..ConfigOverrides::default()
+        };
+        let config = apply_overrides(load_config().expect("load config"), &overrides);
+        assert_eq!(config.max_connections(Transport::Local), 2 * 8 + 16);
+
+        let overrides = ConfigOverrides {
+            max_connections: Some(5),
+            ..ConfigOverrides::default()
+        };
+        let config = apply_overrides(load_config().expect("load config"), &overrides);
+        assert_eq!(config.max_connections(Transport::Local), 5);
+    });
+}
+
+#[test]
+fn data_dir_defaults_to_platform_dir_and_reads_from_config_file() {
+    with_config_env("", || {
+        let config = load_config().expect("load config");
+        assert_eq!(
+            config.data_dir().path(),
+            archdrop::common::data_dir::default_data_dir()
+        );
+    });
+
+    with_config_env(
+        r#"
+        data_dir = "/srv/archdrop-state"
+        "#,
+        || {
+            let config = load_config().expect("load config");
+            assert_eq!(
+                config.data_dir().path(),
+                std::path::Path::new("/srv/archdrop-state")
+            );
+        },
+    );
+}
+
+#[test]
+fn explicit_json_config_sets_chunk_size_and_cli_overrides_port() {
+    with_config_env(
+        r#"
+        [local]
+        chunk_size = 4096
+        "#,
+        || {
+            let dir = tempfile::tempdir().expect("tempdir");
+            let path = dir.path().join("archdrop.json");
+            std::fs::write(
+                &path,
+                r#"{ "local": { "chunk_size": 65536, "port": 1111 } }"#,
+            )
+            .expect("write json config");
+
+            // The explicit file replaces the default one rather than layering on it
+            let config = load_config_from(&path).expect("load json config");
+            assert_eq!(config.transfer_settings(Transport::Local).chunk_size, 65536);
+            assert_eq!(config.port(Transport::Local), 1111);
+
+            let overrides = ConfigOverrides {
+                transport: Some(Transport::Local),
+                port: Some(3333),
+                min_tls: None,
+                concurrency: None,
+                chunk_size: None,
+                max_connections: None,
+            };
+            let config = apply_overrides(config, &overrides);
+            assert_eq!(config.port(Transport::Local), 3333);
+            assert_eq!(config.transfer_settings(Transport::Local).chunk_size, 65536);
+        },
+    );
+}
+
+#[test]
+fn missing_explicit_config_file_is_an_error() {
+    with_config_env("", || {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let err = load_config_from(&dir.path().join("absent.toml")).unwrap_err();
+        assert!(err.to_string().contains("Config file not found"), "{err}");
+    });
+}
@@ -1,6 +1,9 @@
 mod common;
 
-use archdrop::common::config::{load_config, MAX_TRANSFER_CHUNK_SIZE_BYTES};
+use archdrop::common::config::{
+    apply_overrides, load_config, parse_chunk_size, AppConfig, ConfigOverrides, Transport,
+    MAX_TRANSFER_CHUNK_SIZE_BYTES,
+};
 use common::config_test_utils::with_config_env;
 
 #[test]
@@ -68,6 +71,47 @@ fn allows_chunk_size_at_conservative_runtime_limit() {
     });
 }
 
+#[test]
+fn chunk_size_override_parses_human_sizes() {
+    assert_eq!(parse_chunk_size("8M"), Ok(8 * 1024 * 1024));
+    assert_eq!(parse_chunk_size("512K"), Ok(512 * 1024));
+    assert_eq!(parse_chunk_size("65536"), Ok(64 * 1024));
+    assert_eq!(parse_chunk_size("1.5MiB"), Ok(1536 * 1024));
+}
+
+#[test]
+fn chunk_size_override_rejects_out_of_range_values() {
+    for input in ["0", "63K", "129M", "1G"] {
+        let err = parse_chunk_size(input).expect_err(input);
+        assert!(err.contains("between"), "{input}: {err}");
+    }
+    assert_eq!(parse_chunk_size("128M"), Ok(MAX_TRANSFER_CHUNK_SIZE_BYTES));
+    assert!(parse_chunk_size("8X").is_err());
+}
+
+#[test]
+fn chunk_size_override_applies_to_effective_transport() {
+    with_config_env("", || {
+        let overrides = ConfigOverrides {
+            transport: Some(Transport::Cloudflare),
+            chunk_size: Some(256 * 1024),
+            ..ConfigOverrides::default()
+        };
+        let config = apply_overrides(load_config().unwrap(), &overrides);
+        config.validate().expect("override stays valid");
+        assert_eq!(
+            config.transfer_settings(Transport::Cloudflare).chunk_size,
+            256 * 1024
+        );
+        assert_eq!(
+            config.transfer_settings(Transport::Local).chunk_size,
+            AppConfig::default()
+                .transfer_settings(Transport::Local)
+                .chunk_size
+        );
+    });
+}
+
 #[test]
 fn rejects_over_max_concurrency() {
     with_config_env(
@@ -81,3 +125,139 @@ fn rejects_over_max_concurrency() {
         },
     );
 }
+
+#[test]
+fn rejects_zero_listen_backlog() {
+    with_config_env(
+        r#"
+        [network]
+        backlog = 0
+        "#,
+        || {
+            let err = load_config().expect_err("expected validation failure");
+            assert!(err.to_string().contains("network.backlog"));
+        },
+    );
+}
+
+#[test]
+fn network_settings_load_from_file() {
+    with_config_env(
+        r#"
+        [network]
+        backlog = 64
+        nodelay = false
+        "#,
+        || {
+            let config = load_config().expect("config should load");
+            assert_eq!(config.network.backlog, 64);
+            assert!(!config.network.nodelay);
+        },
+    );
+}
+
+#[test]
+fn client_concurrency_defaults_to_transport_concurrency() {
+    use archdrop::common::config::Transport;
+
+    with_config_env(
+        r#"
+        [local]
+        concurrency = 6
+
+        [network]
+        global_concurrency = 32
+        "#,
+        || {
+            let config = load_config().expect("config should load");
+            let limits = config.concurrency_limits(Transport::Local);
+            assert_eq!(limits.global, 32);
+            assert_eq!(limits.per_client, 6);
+        },
+    );
+}
+
+#[test]
+fn rejects_zero_global_concurrency() {
+    with_config_env(
+        r#"
+        [network]
+        global_concurrency = 0
+        "#,
+        || {
+            let err = load_config().expect_err("expected validation failure");
+            assert!(err.to_string().contains("global_concurrency"));
+        },
+    );
+}
+
+#[test]
+fn programmatic_config_names_zero_transfer_setting() {
+    let mut config = AppConfig::default();
+    config.cloudflare.transfer.chunk_size = 0;
+    assert_eq!(
+        config.validate().unwrap_err().to_string(),
+        "Invalid config: cloudflare.chunk_size must be > 0"
+    );
+
+    let mut config = AppConfig::default();
+    config.tailscale.transfer.concurrency = 0;
+    assert_eq!(
+        config.validate().unwrap_err().to_string(),
+        "Invalid config: tailscale.concurrency must be >= 1"
+    );
+}
+
+#[tokio::test]
+async fn receive_server_rejects_zero_concurrency_before_startup() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut config = AppConfig::default();
+    config.local.transfer.concurrency = 0;
+
+    let err = archdrop::server::start_receive_server(
+        dir.path().to_path_buf(),
+        Transport::Local,
+        &config,
+        archdrop::server::ReceiveOptions::default(),
+    )
+    .await
+    .expect_err("zero concurrency must not start a server");
+    assert_eq!(
+        err.to_string(),
+        "Invalid config: local.concurrency must be >= 1"
+    );
+}
+
+#[test]
+fn tunnel_transports_advertise_longer_retry_hints_than_local() {
+    let config = AppConfig::default();
+    let local = config.transfer_settings(Transport::Local);
+
+    for transport in [
+        Transport::Cloudflare,
+        Transport::Tailscale,
+        Transport::Ngrok,
+    ] {
+        let tunnel = config.transfer_settings(transport);
+        assert!(tunnel.retry_min_ms > local.retry_min_ms);
+        assert!(tunnel.retry_max_ms > local.retry_max_ms);
+    }
+}
+
+#[test]
+fn rejects_retry_max_below_retry_min() {
+    with_config_env(
+        r#"
+        [local]
+        retry_min_ms = 2000
+        retry_max_ms = 500
+        "#,
+        || {
+            let err = load_config().expect_err("expected validation failure");
+            assert_eq!(
+                err.to_string(),
+                "Invalid config: local.retry_max_ms must be >= local.retry_min_ms"
+            );
+        },
+    );
+}
@@ -1,5 +1,8 @@
 use archdrop::crypto::types::{EncryptionKey, Nonce};
-use archdrop::crypto::{decrypt_chunk_in_place, encrypt_chunk_in_place};
+use archdrop::crypto::{
+    decrypt_chunk_at_position, decrypt_chunk_in_place, encrypt_chunk_at_position,
+    encrypt_chunk_in_place, ChunkPosition, CipherSuite, NonceLedger,
+};
 use aws_lc_rs::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
 
 fn make_key(key: &EncryptionKey) -> LessSafeKey {
@@ -95,6 +98,108 @@ fn test_wrong_counter_fails_decryption() {
     assert!(result.is_err(), "Decryption with wrong counter should fail");
 }
 
+#[test]
+fn test_altered_position_fails_decryption() {
+    let key = EncryptionKey::new();
+    let nonce = Nonce::new();
+    let cipher = make_key(&key);
+
+    let position = ChunkPosition::new(1, 3, 4096);
+    let mut sealed = b"position bound".to_vec();
+    encrypt_chunk_at_position(
+        &cipher,
+        &nonce,
+        &mut sealed,
+        &position,
+        CipherSuite::PositionBound,
+    )
+    .expect("Encryption should succeed");
+
+    let mut buffer = sealed.clone();
+    decrypt_chunk_at_position(
+        &cipher,
+        &nonce,
+        &mut buffer,
+        &position,
+        CipherSuite::PositionBound,
+    )
+    .expect("Decryption at the sealed position should succeed");
+    assert_eq!(buffer, b"position bound");
+
+    // Same nonce counter, different file or length: only the AAD differs
+    for moved in [
+        ChunkPosition::new(0, 3, 4096),
+        ChunkPosition::new(1, 3, 4097),
+    ] {
+        let mut buffer = sealed.clone();
+        let result = decrypt_chunk_at_position(
+            &cipher,
+            &nonce,
+            &mut buffer,
+            &moved,
+            CipherSuite::PositionBound,
+        );
+        assert!(result.is_err(), "{moved:?} should not open");
+    }
+
+    // A v2 chunk does not open as v1 either
+    let mut buffer = sealed;
+    let result =
+        decrypt_chunk_at_position(&cipher, &nonce, &mut buffer, &position, CipherSuite::Legacy);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_legacy_suite_matches_unbound_chunks() {
+    let key = EncryptionKey::new();
+    let nonce = Nonce::new();
+    let cipher = make_key(&key);
+
+    let mut buffer = b"old link".to_vec();
+    encrypt_chunk_in_place(&cipher, &nonce, &mut buffer, 2).expect("Encryption should succeed");
+
+    let position = ChunkPosition::new(5, 2, 1);
+    decrypt_chunk_at_position(&cipher, &nonce, &mut buffer, &position, CipherSuite::Legacy)
+        .expect("v1 chunks ignore everything but the chunk index");
+    assert_eq!(buffer, b"old link");
+}
+
+#[test]
+fn test_position_aad_layout() {
+    let position = ChunkPosition::new(1, 2, 0x0102_0304_0506);
+    assert_eq!(
+        position.to_aad(),
+        [0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0]
+    );
+    assert_eq!(position.counter(), 2);
+
+    let compressed = position.with_compression(true);
+    assert_eq!(compressed.to_aad()[16], 1);
+    assert_eq!(compressed.counter(), 0x8000_0002);
+}
+
+#[test]
+fn test_compression_flag_is_authenticated() {
+    let key = EncryptionKey::new();
+    let nonce = Nonce::new();
+    let cipher = make_key(&key);
+
+    let position = ChunkPosition::new(0, 4, 1024).with_compression(true);
+    let mut sealed = b"zstd frame".to_vec();
+    encrypt_chunk_at_position(&cipher, &nonce, &mut sealed, &position, CipherSuite::Legacy)
+        .expect("Encryption should succeed");
+
+    // A stripped or forged X-Chunk-Encoding header changes the counter
+    let mut buffer = sealed.clone();
+    let plain = position.with_compression(false);
+    assert!(
+        decrypt_chunk_at_position(&cipher, &nonce, &mut buffer, &plain, CipherSuite::Legacy)
+            .is_err()
+    );
+    decrypt_chunk_at_position(&cipher, &nonce, &mut sealed, &position, CipherSuite::Legacy)
+        .expect("Decryption with the sealed flag should succeed");
+}
+
 #[test]
 fn test_key_base64_roundtrip() {
     let key = EncryptionKey::new();
@@ -132,3 +237,24 @@ fn test_invalid_nonce_base64() {
     let result = Nonce::from_base64("YQ");
     assert!(result.is_err(), "Wrong length should fail");
 }
+
+#[test]
+fn nonce_ledger_allows_resealing_the_same_chunk() {
+    let ledger = NonceLedger::default();
+    let nonce = Nonce::new();
+
+    ledger.record(&nonce, 0, b"chunk zero");
+    ledger.record(&nonce, 0, b"chunk zero");
+    ledger.record(&nonce, 1, b"chunk one");
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "nonce reuse")]
+fn nonce_ledger_panics_when_a_nonce_seals_different_data() {
+    let ledger = NonceLedger::default();
+    let nonce = Nonce::new();
+
+    ledger.record(&nonce, 3, b"file a, chunk 3");
+    ledger.record(&nonce, 3, b"file b, chunk 3");
+}
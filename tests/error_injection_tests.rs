@@ -19,7 +19,7 @@ use axum::{
     http::{Method, Request, StatusCode},
     Router,
 };
-use common::{create_cipher, default_config, setup_temp_dir, CHUNK_SIZE};
+use common::{create_cipher, default_config, encrypt_chunk, setup_temp_dir, CHUNK_SIZE};
 use http_body_util::BodyExt;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -232,7 +232,7 @@ async fn test_chunk_size_mismatch() {
     let nonce = Nonce::new();
 
     let mut encrypted = oversized_chunk.clone();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted, 0)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, 0, CHUNK_SIZE as u64)
         .expect("Failed to encrypt chunk");
 
     let request = build_multipart_request(
@@ -1,8 +1,9 @@
 mod common;
 
 use archdrop::common::Manifest;
+use archdrop::send::DryRunReport;
 use base64::Engine;
-use common::default_config;
+use common::{default_config, CHUNK_SIZE};
 use tempfile::TempDir;
 
 #[tokio::test]
@@ -12,7 +13,7 @@ async fn test_manifest_single_file() {
     let content = b"Hello, World!";
     std::fs::write(&test_file, content).unwrap();
 
-    let manifest = Manifest::new(vec![test_file.clone()], None, default_config())
+    let mut manifest = Manifest::new(vec![test_file.clone()], None, default_config())
         .await
         .expect("Manifest creation should succeed");
 
@@ -21,6 +22,23 @@ async fn test_manifest_single_file() {
     assert_eq!(manifest.files[0].size, content.len() as u64);
     assert_eq!(manifest.files[0].index, 0);
     assert!(!manifest.files[0].nonce.is_empty());
+    // Building the manifest does not read the file
+    assert!(manifest.files[0].hash.is_none());
+    assert!(serde_json::to_value(&manifest).unwrap()["files"][0]
+        .get("hash")
+        .is_none());
+
+    // SHA-256 of "Hello, World!", sent to the browser for verification
+    manifest.hash_files().await.unwrap();
+    assert_eq!(
+        manifest.files[0].hash.as_deref(),
+        Some("dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f")
+    );
+    let json = serde_json::to_value(&manifest).unwrap();
+    assert_eq!(
+        json["files"][0]["hash"],
+        manifest.files[0].hash.clone().unwrap()
+    );
 }
 
 #[tokio::test]
@@ -147,3 +165,270 @@ async fn test_manifest_nonces_are_base64() {
     // Nonce should be 7 bytes
     assert_eq!(decoded.len(), 8);
 }
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_manifest_rejects_unreadable_file_early() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("secret.txt");
+    std::fs::write(&test_file, b"no peeking").unwrap();
+    std::fs::set_permissions(&test_file, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+    // Privileged users (e.g. root in containers) bypass file modes
+    if std::fs::File::open(&test_file).is_ok() {
+        return;
+    }
+
+    let err = Manifest::new(vec![test_file.clone()], None, default_config())
+        .await
+        .err()
+        .expect("unreadable file should fail manifest build");
+    let message = err.to_string();
+    assert!(message.contains("Permission denied"), "got: {message}");
+    assert!(message.contains(&test_file.display().to_string()));
+}
+
+#[tokio::test]
+async fn test_manifest_over_directory_excludes_cert_and_key_files() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join("report.txt"), b"data").unwrap();
+    std::fs::write(temp_dir.path().join("archdrop-cert.pem"), b"cert").unwrap();
+    std::fs::write(temp_dir.path().join("archdrop-key.pem"), b"key").unwrap();
+
+    let files = archdrop::send::collect_send_files(
+        vec![temp_dir.path().to_path_buf()],
+        &archdrop::send::ExcludeRules::default(),
+    )
+    .expect("directory walk should succeed");
+    let manifest = Manifest::new(files, Some(temp_dir.path()), default_config())
+        .await
+        .expect("Manifest creation should succeed");
+
+    let names: Vec<&str> = manifest.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["report.txt"]);
+}
+
+#[tokio::test]
+async fn test_signed_manifest_verifies_and_tampering_fails() {
+    use archdrop::crypto::signing::SigningKey;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file1 = temp_dir.path().join("report.pdf");
+    let file2 = temp_dir.path().join("notes.txt");
+    std::fs::write(&file1, b"pdf bytes").unwrap();
+    std::fs::write(&file2, b"notes").unwrap();
+
+    let key = SigningKey::generate().expect("generate key");
+    let pinned = key.public_key_base64();
+    let mut manifest = Manifest::new(vec![file1, file2], None, default_config())
+        .await
+        .unwrap();
+    manifest.hash_files().await.unwrap();
+    manifest.sign(&key);
+
+    // Survives the JSON round trip the web client sees
+    let json = serde_json::to_string(&manifest).unwrap();
+    let received: Manifest = serde_json::from_str(&json).unwrap();
+    received
+        .verify_signature(&pinned)
+        .expect("valid signature should verify");
+
+    let mut tampered = received.clone();
+    tampered.files[0].size += 1;
+    assert!(tampered.verify_signature(&pinned).is_err());
+
+    let mut rehashed = received.clone();
+    rehashed.files[0].hash = Some("00".repeat(32));
+    assert!(rehashed.verify_signature(&pinned).is_err());
+
+    let mut renamed = received.clone();
+    renamed.files[1].relative_path = "evil.exe".to_string();
+    assert!(renamed.verify_signature(&pinned).is_err());
+
+    let other = SigningKey::generate().unwrap().public_key_base64();
+    assert!(received.verify_signature(&other).is_err());
+
+    let mut unsigned = received;
+    unsigned.signature = None;
+    assert!(unsigned.verify_signature(&pinned).is_err());
+}
+
+#[tokio::test]
+async fn test_whole_file_mac_accepts_correct_reassembly_and_rejects_swapped_bytes() {
+    use archdrop::crypto::hash::{calculate_file_hash, verify_file_mac};
+    use archdrop::crypto::types::EncryptionKey;
+
+    let temp_dir = TempDir::new().unwrap();
+    let source = temp_dir.path().join("data.bin");
+    let content: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    std::fs::write(&source, &content).unwrap();
+
+    let key = EncryptionKey::new();
+    let mut manifest = Manifest::new(vec![source], None, default_config())
+        .await
+        .unwrap();
+    manifest.hash_files().await.unwrap();
+    assert!(manifest.files[0].mac.is_none());
+    manifest.add_file_macs(&key).unwrap();
+    let mac = manifest.files[0]
+        .mac
+        .clone()
+        .expect("mac after add_file_macs");
+    let json = serde_json::to_value(&manifest).unwrap();
+    assert_eq!(json["files"][0]["mac"], mac);
+
+    let reassembled = temp_dir.path().join("reassembled.bin");
+    std::fs::write(&reassembled, &content).unwrap();
+    let digest = calculate_file_hash(&reassembled).unwrap();
+    assert!(verify_file_mac(&key, &digest, &mac));
+
+    // Swapped bytes keep the length and byte counts but not the MAC
+    let mut swapped = content.clone();
+    swapped.swap(1, 2048);
+    std::fs::write(&reassembled, &swapped).unwrap();
+    let digest = calculate_file_hash(&reassembled).unwrap();
+    assert!(!verify_file_mac(&key, &digest, &mac));
+
+    // Another session's key cannot produce a matching MAC
+    let digest = calculate_file_hash(temp_dir.path().join("data.bin").as_path()).unwrap();
+    assert!(!verify_file_mac(&EncryptionKey::new(), &digest, &mac));
+}
+
+#[tokio::test]
+async fn test_download_name_override_renames_single_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let source = temp_dir.path().join("archdrop-1234.zip");
+    std::fs::write(&source, b"zip bytes").unwrap();
+
+    let mut manifest = Manifest::new(vec![source], None, default_config())
+        .await
+        .unwrap();
+    manifest.hash_files().await.unwrap();
+    let hash = manifest.files[0].hash.clone();
+    manifest.set_download_name("photos.zip").unwrap();
+
+    assert_eq!(manifest.files[0].name, "photos.zip");
+    assert_eq!(manifest.files[0].relative_path, "photos.zip");
+    assert_eq!(manifest.files[0].hash, hash, "contents are unchanged");
+}
+
+#[tokio::test]
+async fn test_download_name_override_rejects_traversal_and_many_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = temp_dir.path().join("a.txt");
+    let b = temp_dir.path().join("b.txt");
+    std::fs::write(&a, b"a").unwrap();
+    std::fs::write(&b, b"b").unwrap();
+
+    let mut single = Manifest::new(vec![a.clone()], None, default_config())
+        .await
+        .unwrap();
+    assert!(single.set_download_name("../../etc/passwd").is_err());
+    assert!(single.set_download_name("nested/name.txt").is_err());
+    assert_eq!(single.files[0].name, "a.txt");
+
+    let mut several = Manifest::new(vec![a, b], None, default_config())
+        .await
+        .unwrap();
+    let err = several.set_download_name("both.txt").unwrap_err();
+    assert!(err.to_string().contains("exactly one file"));
+}
+
+#[tokio::test]
+async fn test_dry_run_report_lists_files_and_totals() {
+    let temp_dir = TempDir::new().unwrap();
+    let big = temp_dir.path().join("big.bin");
+    let small = temp_dir.path().join("small.txt");
+    std::fs::write(&big, vec![0u8; CHUNK_SIZE * 2 + 1]).unwrap();
+    std::fs::write(&small, b"hi").unwrap();
+
+    let manifest = Manifest::new(vec![big, small], None, default_config())
+        .await
+        .unwrap();
+    let report = DryRunReport::from_manifest(&manifest);
+
+    let files: Vec<_> = report
+        .files
+        .iter()
+        .map(|f| (f.relative_path.as_str(), f.size, f.chunks))
+        .collect();
+    assert_eq!(
+        files,
+        vec![
+            ("big.bin", CHUNK_SIZE as u64 * 2 + 1, 3),
+            ("small.txt", 2, 1)
+        ]
+    );
+    assert_eq!(report.total_bytes, CHUNK_SIZE as u64 * 2 + 3);
+    assert_eq!(report.total_chunks, 4);
+
+    let mut text = Vec::new();
+    report.write_text(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert!(text.contains("small.txt  2 B (1 chunks)"), "{text}");
+    assert!(text.contains("Total: 2 files"), "{text}");
+
+    let mut json = Vec::new();
+    report.write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["files"][0]["name"], "big.bin");
+    assert_eq!(json["files"][0]["chunks"], 3);
+    assert_eq!(json["total_chunks"], 4);
+    assert!(json["files"][0].get("nonce").is_none(), "no key material");
+}
+
+#[test]
+fn test_dry_run_cli_exits_without_binding_a_socket() {
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("notes.txt");
+    std::fs::write(&file, b"hello").unwrap();
+
+    // Hold the port a real send would listen on; serving could not bind it
+    let held = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+    let port = held.local_addr().unwrap().port().to_string();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_archdrop"))
+        .args([
+            "send",
+            "--dry-run",
+            "--json",
+            "--via",
+            "local",
+            "--port",
+            &port,
+        ])
+        .arg(&file)
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // A run that started serving would sit waiting for a receiver
+    let started = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if started.elapsed() > Duration::from_secs(30) {
+            child.kill().unwrap();
+            panic!("dry run did not exit; it is serving");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files"][0]["name"], "notes.txt");
+    assert_eq!(report["total_bytes"], 5);
+    assert_eq!(report["total_chunks"], 1);
+    drop(held);
+}
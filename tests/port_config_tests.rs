@@ -19,6 +19,10 @@ fn cli_port_applies_to_effective_transport() {
         let overrides = ConfigOverrides {
             transport: Some(Transport::Local),
             port: Some(9999),
+            min_tls: None,
+            concurrency: None,
+            chunk_size: None,
+            max_connections: None,
         };
         let config = load_config().unwrap();
         let config = apply_overrides(config, &overrides);
@@ -53,6 +57,10 @@ fn cli_port_applies_only_to_selected_transport() {
             let overrides = ConfigOverrides {
                 transport: Some(Transport::Cloudflare),
                 port: Some(4444),
+                min_tls: None,
+                concurrency: None,
+                chunk_size: None,
+                max_connections: None,
             };
 
             let config = load_config().expect("load config");
@@ -83,6 +91,10 @@ fn cli_port_applies_to_default_transport_when_via_omitted() {
             let overrides = ConfigOverrides {
                 transport: None,
                 port: Some(4444),
+                min_tls: None,
+                concurrency: None,
+                chunk_size: None,
+                max_connections: None,
             };
 
             let config = load_config().expect("load config");
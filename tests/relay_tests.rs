@@ -0,0 +1,156 @@
+mod common;
+
+use archdrop::crypto::types::{EncryptionKey, Nonce};
+use archdrop::relay::RelayState;
+use archdrop::server::routes;
+use common::{create_cipher, decrypt_chunk, encrypt_chunk};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Serve `state` on a loopback port and return its base URL.
+async fn spawn_relay(state: &RelayState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = routes::create_relay_router(state);
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{}/relay", addr)
+}
+
+fn upload(url: &str, token: &str, body: Vec<u8>) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .post(url)
+        .bearer_auth(token)
+        .body(body)
+}
+
+fn download(url: &str, token: &str) -> reqwest::RequestBuilder {
+    reqwest::Client::new().get(url).bearer_auth(token)
+}
+
+/// Wait until `count` peers are parked on the relay.
+async fn wait_for_pending(state: &RelayState, count: usize) {
+    for _ in 0..200 {
+        if state.pending() == count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("expected {count} waiting peers, found {}", state.pending());
+}
+
+#[tokio::test]
+async fn two_clients_exchange_ciphertext_through_the_relay() {
+    let state = RelayState::new();
+    let url = spawn_relay(&state).await;
+    let token = uuid::Uuid::new_v4().to_string();
+
+    // Larger than axum's default body limit, so it has to stream
+    let plaintext: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let key = EncryptionKey::new();
+    let cipher = create_cipher(&key);
+    let nonce = Nonce::new();
+    let mut ciphertext = plaintext.clone();
+    encrypt_chunk(
+        &cipher,
+        &nonce,
+        &mut ciphertext,
+        0,
+        0,
+        plaintext.len() as u64,
+    )
+    .unwrap();
+    let sent = ciphertext.clone();
+
+    // Receiver first: it waits for the sender, then reads as bytes arrive
+    let receiver = tokio::spawn({
+        let request = download(&url, &token);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            response.bytes().await.unwrap().to_vec()
+        }
+    });
+    wait_for_pending(&state, 1).await;
+    let sender = upload(&url, &token, ciphertext).send().await.unwrap();
+
+    assert_eq!(sender.status(), StatusCode::OK);
+    let json: serde_json::Value = sender.json().await.unwrap();
+    assert_eq!(json["bytes"], sent.len() as u64);
+
+    let mut relayed = receiver.await.unwrap();
+    assert_eq!(relayed, sent, "relay forwards the ciphertext untouched");
+    assert_ne!(relayed[..64], plaintext[..64]);
+
+    decrypt_chunk(&cipher, &nonce, &mut relayed, 0, 0, plaintext.len() as u64).unwrap();
+    assert_eq!(relayed, plaintext);
+    assert_eq!(state.pending(), 0);
+}
+
+#[tokio::test]
+async fn sender_may_arrive_first_and_other_tokens_do_not_get_its_upload() {
+    let state = RelayState::new().with_rendezvous_timeout(Duration::from_millis(300));
+    let url = spawn_relay(&state).await;
+    let token = uuid::Uuid::new_v4().to_string();
+
+    let sender = tokio::spawn(upload(&url, &token, b"sealed bytes".to_vec()).send());
+    wait_for_pending(&state, 1).await;
+
+    // A different token waits on its own slot and times out empty-handed
+    let stranger = tokio::spawn(download(&url, &uuid::Uuid::new_v4().to_string()).send());
+    wait_for_pending(&state, 2).await;
+
+    let response = download(&url, &token).send().await.unwrap();
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"sealed bytes");
+    assert_eq!(sender.await.unwrap().unwrap().status(), StatusCode::OK);
+
+    let stranger = stranger.await.unwrap().unwrap();
+    assert_eq!(stranger.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn unpaired_sender_times_out_and_frees_its_slot() {
+    let state = RelayState::new().with_rendezvous_timeout(Duration::from_millis(100));
+    let url = spawn_relay(&state).await;
+
+    let response = upload(&url, &uuid::Uuid::new_v4().to_string(), b"x".to_vec())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(state.pending(), 0);
+}
+
+#[tokio::test]
+async fn waiting_peers_are_capped_but_partners_still_pair() {
+    let state = RelayState::new().with_max_pending(1);
+    let url = spawn_relay(&state).await;
+    let token = uuid::Uuid::new_v4().to_string();
+
+    let sender = tokio::spawn(upload(&url, &token, b"first".to_vec()).send());
+    wait_for_pending(&state, 1).await;
+
+    let other = upload(&url, &uuid::Uuid::new_v4().to_string(), b"x".to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(other.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let second_sender = upload(&url, &token, b"y".to_vec()).send().await.unwrap();
+    assert_eq!(second_sender.status(), StatusCode::CONFLICT);
+
+    let response = download(&url, &token).send().await.unwrap();
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"first");
+    assert_eq!(sender.await.unwrap().unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn short_or_missing_tokens_are_refused() {
+    let url = spawn_relay(&RelayState::new()).await;
+
+    let response = download(&url, "short").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -1,13 +1,14 @@
 mod common;
 
 use archdrop::common::Manifest;
-use archdrop::common::Session;
+use archdrop::common::{ClaimError, PersistedSessionStatus, Session};
 use archdrop::crypto::types::EncryptionKey;
 use archdrop::receive::ReceiveAppState;
 use archdrop::send::SendAppState;
 use archdrop::server::progress::ProgressTracker;
 use common::default_config;
 use std::sync::Arc;
+use std::time::Duration;
 use tempfile::TempDir;
 
 #[tokio::test]
@@ -136,3 +137,172 @@ async fn test_send_session_get_file() {
     // Out of bounds should return None
     assert!(state.get_file(999).is_none());
 }
+
+#[tokio::test]
+async fn test_active_session_reloads_unclaimed_without_lock() {
+    let key = EncryptionKey::new();
+    let session = Session::new(key.clone());
+    let token = session.token().to_string();
+    let lock_token = session.claim(&token).expect("claim should succeed");
+
+    let reloaded = Session::restore(key, session.snapshot());
+
+    assert!(!reloaded.is_active(&token, &lock_token));
+    assert!(reloaded.claim(&token).is_ok());
+}
+
+#[test]
+fn test_claim_just_before_link_expiry_succeeds() {
+    let session = Session::new(EncryptionKey::new()).with_ttl(Duration::from_millis(200));
+    assert!(session.claim(session.token()).is_ok());
+}
+
+#[test]
+fn test_claim_just_after_link_expiry_is_rejected() {
+    let session = Session::new(EncryptionKey::new()).with_ttl(Duration::from_millis(20));
+    std::thread::sleep(Duration::from_millis(40));
+
+    assert!(session.is_expired());
+    assert_eq!(
+        session.claim(session.token()).unwrap_err(),
+        ClaimError::Expired
+    );
+    assert!(session.is_unclaimed());
+}
+
+#[test]
+fn test_multi_use_session_accepts_claims_up_to_capacity() {
+    let session = Session::new(EncryptionKey::new()).with_download_capacity(3);
+    let token = session.token().to_string();
+
+    let locks: Vec<String> = (0..3)
+        .map(|_| session.claim(&token).expect("claim within capacity"))
+        .collect();
+
+    assert_eq!(session.claim(&token), Err(ClaimError::AlreadyClaimed));
+    assert_eq!(session.active_claims(), 3);
+    for lock in &locks {
+        assert!(session.is_active(&token, lock));
+    }
+    assert_ne!(locks[0], locks[1], "each claim gets its own lock token");
+}
+
+#[test]
+fn test_multi_use_completion_uses_up_one_download() {
+    let session = Session::new(EncryptionKey::new()).with_download_capacity(2);
+    let token = session.token().to_string();
+    let first = session.claim(&token).unwrap();
+    let second = session.claim(&token).unwrap();
+
+    assert!(session.complete(&token, &first));
+    assert!(
+        !session.complete(&token, &first),
+        "a retry must not count twice"
+    );
+    assert!(!session.is_completed());
+    assert_eq!(session.remaining_downloads(), Some(1));
+    assert!(!session.is_active(&token, &first));
+    assert!(session.is_active(&token, &second));
+
+    // The one remaining download is held, so nobody else gets in
+    assert_eq!(session.claim(&token), Err(ClaimError::AlreadyClaimed));
+
+    assert!(session.complete(&token, &second));
+    assert!(session.is_completed());
+    assert_eq!(session.claim(&token), Err(ClaimError::Completed));
+}
+
+#[test]
+fn test_multi_use_release_frees_the_slot() {
+    let session = Session::new(EncryptionKey::new()).with_download_capacity(2);
+    let token = session.token().to_string();
+    let first = session.claim(&token).unwrap();
+    let _second = session.claim(&token).unwrap();
+
+    assert!(session.release(&token, &first));
+    assert_eq!(session.remaining_downloads(), Some(2));
+    assert!(session.claim(&token).is_ok());
+}
+
+#[test]
+fn test_multi_use_claim_leaves_earlier_claims_live() {
+    let session = Session::new(EncryptionKey::new()).with_download_capacity(2);
+    let token = session.token().to_string();
+    let first = session.claim(&token).unwrap();
+    let second = session.claim(&token).unwrap();
+
+    assert!(session.holds_claim(&first));
+    assert!(session.holds_claim(&second));
+    assert_eq!(session.snapshot().status, PersistedSessionStatus::Active);
+    assert!(session.reattach(&token).is_none());
+    assert!(session.is_active(&token, &first));
+}
+
+#[test]
+fn test_multi_use_idle_claim_expires_and_frees_its_slot() {
+    let session = Session::new(EncryptionKey::new())
+        .with_download_capacity(2)
+        .with_claim_idle_timeout(Duration::from_millis(100));
+    let token = session.token().to_string();
+    let idle = session.claim(&token).unwrap();
+    let busy = session.claim(&token).unwrap();
+    assert_eq!(session.claim(&token), Err(ClaimError::AlreadyClaimed));
+
+    // Only the busy claim keeps making requests
+    for _ in 0..3 {
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(session.is_active(&token, &busy));
+    }
+
+    assert!(!session.is_active(&token, &idle));
+    assert!(session.is_active(&token, &busy));
+    let replacement = session.claim(&token).expect("idle slot was freed");
+    assert_eq!(session.active_claims(), 2);
+    assert!(session.is_active(&token, &replacement));
+    assert!(!session.complete(&token, &idle));
+}
+
+#[test]
+fn test_claim_idle_timeout_applies_in_any_builder_order() {
+    let session = Session::new(EncryptionKey::new())
+        .with_claim_idle_timeout(Duration::from_millis(50))
+        .with_download_capacity(2);
+    let token = session.token().to_string();
+    let idle = session.claim(&token).unwrap();
+
+    std::thread::sleep(Duration::from_millis(80));
+
+    assert!(!session.holds_claim(&idle));
+    assert_eq!(session.active_claims(), 0);
+}
+
+#[test]
+fn test_download_capacity_of_one_keeps_single_claim() {
+    let session = Session::new(EncryptionKey::new()).with_download_capacity(1);
+    let token = session.token().to_string();
+    let lock = session.claim(&token).unwrap();
+
+    assert_eq!(session.remaining_downloads(), None);
+    assert_eq!(session.claim(&token), Err(ClaimError::AlreadyClaimed));
+    assert!(session.complete(&token, &lock));
+    assert!(session.is_completed());
+}
+
+#[test]
+fn test_revoked_multi_use_session_drops_every_claim() {
+    let session = Session::new(EncryptionKey::new()).with_download_capacity(2);
+    let token = session.token().to_string();
+    let first = session.claim(&token).unwrap();
+    let second = session.claim(&token).unwrap();
+
+    // The link token is what recipients hold; it must not revoke
+    assert!(!session.revoke(&token));
+    assert!(session.revoke(session.revoke_secret()));
+
+    assert!(!session.is_active(&token, &first));
+    assert!(!session.is_active(&token, &second));
+    assert_eq!(session.active_claims(), 0);
+    assert_eq!(session.claim(&token), Err(ClaimError::Revoked));
+    // Persisted as finished so a restart cannot bring the link back
+    assert_eq!(session.snapshot().status, PersistedSessionStatus::Completed);
+}
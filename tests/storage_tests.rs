@@ -1,6 +1,6 @@
 mod common;
 
-use archdrop::receive::ChunkStorage;
+use archdrop::receive::{verify_received, ChunkStorage};
 use common::setup_temp_dir;
 
 //===============
@@ -26,10 +26,14 @@ async fn test_store_chunk_in_order() {
         .await
         .expect("Failed to create ChunkStorage");
 
-    let metadata = tokio::fs::metadata(&file_path)
+    let metadata = tokio::fs::metadata(storage.partial_path())
         .await
         .expect("Failed to get file metadata");
     assert_eq!(metadata.len(), CHUNK_3MB, "File should be preallocated");
+    assert!(
+        !file_path.exists(),
+        "Final name is only used after finalize"
+    );
 
     // Store chunks in sequence
     let chunk_data = create_chunk_data(0xAA, 1);
@@ -277,6 +281,179 @@ async fn test_finalize_disarms_drop() {
     assert_eq!(hash.len(), 64, "SHA256 hash should be 64 hex chars");
 }
 
+#[tokio::test]
+async fn test_interrupted_finalize_never_creates_final_file() {
+    let temp_dir = setup_temp_dir();
+    let file_path = temp_dir.path().join("atomic.bin");
+
+    let partial_path = {
+        let mut storage = ChunkStorage::new(file_path.clone(), CHUNK_3MB, CHUNK_1MB as u64)
+            .await
+            .expect("Failed to create ChunkStorage");
+        let partial_path = storage.partial_path().clone();
+        assert_eq!(
+            partial_path.file_name().expect("partial file name"),
+            "atomic.bin.partial"
+        );
+
+        storage
+            .store_chunk(0, &create_chunk_data(0xAA, 1))
+            .await
+            .expect("Failed to store chunk 0");
+        storage
+            .store_chunk(1, &create_chunk_data(0xBB, 1))
+            .await
+            .expect("Failed to store chunk 1");
+        assert!(partial_path.exists());
+        assert!(
+            !file_path.exists(),
+            "Chunks must not land under the final name"
+        );
+
+        // Finalize before the last chunk arrives fails without renaming
+        storage
+            .finalize()
+            .await
+            .expect_err("finalize should reject missing chunks");
+        assert!(
+            !file_path.exists(),
+            "Failed finalize must not publish the file"
+        );
+
+        partial_path
+        // storage drops here, as on a crash or cancelled upload
+    };
+
+    assert!(!partial_path.exists(), "Partial file should be removed");
+    assert!(!file_path.exists(), "Final file should never appear");
+}
+
+#[tokio::test]
+async fn test_finalize_renames_partial_into_place() {
+    let temp_dir = setup_temp_dir();
+    let file_path = temp_dir.path().join("done.bin");
+
+    let mut storage = ChunkStorage::new(file_path.clone(), CHUNK_1MB as u64, CHUNK_1MB as u64)
+        .await
+        .expect("Failed to create ChunkStorage");
+    storage
+        .store_chunk(0, &create_chunk_data(0x5A, 1))
+        .await
+        .expect("Failed to store chunk 0");
+    storage
+        .finalize()
+        .await
+        .expect("Failed to finalize storage");
+
+    assert!(file_path.exists());
+    assert!(!storage.partial_path().exists());
+    drop(storage);
+    assert!(file_path.exists(), "Drop after finalize keeps the file");
+}
+
+#[tokio::test]
+async fn test_finalize_never_replaces_a_file_that_appeared_meanwhile() {
+    let temp_dir = setup_temp_dir();
+    let file_path = temp_dir.path().join("taken.bin");
+
+    let mut storage = ChunkStorage::new(file_path.clone(), CHUNK_1MB as u64, CHUNK_1MB as u64)
+        .await
+        .expect("Failed to create ChunkStorage");
+    storage
+        .store_chunk(0, &create_chunk_data(0x5A, 1))
+        .await
+        .expect("Failed to store chunk 0");
+
+    // Someone else takes the final name while the upload is in flight
+    std::fs::write(&file_path, b"not ours").unwrap();
+    let err = storage
+        .finalize()
+        .await
+        .expect_err("finalize must not clobber the new file");
+    assert!(err.to_string().contains("already exists"), "{err}");
+    assert_eq!(std::fs::read(&file_path).unwrap(), b"not ours");
+
+    let partial_path = storage.partial_path().to_path_buf();
+    drop(storage);
+    assert!(!partial_path.exists());
+    assert_eq!(std::fs::read(&file_path).unwrap(), b"not ours");
+}
+
+#[tokio::test]
+async fn test_verify_accepts_intact_file() {
+    let temp_dir = setup_temp_dir();
+    let file_path = temp_dir.path().join("intact.bin");
+
+    let mut storage = ChunkStorage::new(file_path.clone(), CHUNK_1MB as u64, CHUNK_1MB as u64)
+        .await
+        .expect("Failed to create ChunkStorage");
+    storage
+        .store_chunk(0, &create_chunk_data(0x11, 1))
+        .await
+        .expect("Failed to store chunk 0");
+    let hash = storage.finalize().await.expect("Failed to finalize");
+
+    verify_received(&file_path, CHUNK_1MB as u64, &hash)
+        .await
+        .expect("Intact file should verify");
+    assert!(file_path.exists());
+}
+
+#[tokio::test]
+async fn test_verify_moves_truncated_file_aside() {
+    let temp_dir = setup_temp_dir();
+    let file_path = temp_dir.path().join("short.bin");
+
+    let mut storage = ChunkStorage::new(file_path.clone(), CHUNK_3MB, CHUNK_1MB as u64)
+        .await
+        .expect("Failed to create ChunkStorage");
+    for i in 0..3 {
+        storage
+            .store_chunk(i, &create_chunk_data(i as u8, 1))
+            .await
+            .expect("Failed to store chunk");
+    }
+    let hash = storage.finalize().await.expect("Failed to finalize");
+
+    // Lose the tail of the reassembled file after it was written
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&file_path)
+        .unwrap();
+    file.set_len(CHUNK_3MB - 1).unwrap();
+    drop(file);
+
+    let err = verify_received(&file_path, CHUNK_3MB, &hash)
+        .await
+        .expect_err("Truncated file must fail verification");
+    assert!(err.to_string().contains("expected 3145728 bytes"), "{err}");
+    assert!(!file_path.exists(), "Bad file must not stay under its name");
+    assert!(temp_dir.path().join("short.bin.corrupt").exists());
+}
+
+#[tokio::test]
+async fn test_verify_moves_altered_file_aside() {
+    let temp_dir = setup_temp_dir();
+    let file_path = temp_dir.path().join("flipped.bin");
+
+    let mut storage = ChunkStorage::new(file_path.clone(), CHUNK_1MB as u64, CHUNK_1MB as u64)
+        .await
+        .expect("Failed to create ChunkStorage");
+    storage
+        .store_chunk(0, &create_chunk_data(0x22, 1))
+        .await
+        .expect("Failed to store chunk 0");
+    let hash = storage.finalize().await.expect("Failed to finalize");
+
+    std::fs::write(&file_path, create_chunk_data(0x23, 1)).unwrap();
+
+    let err = verify_received(&file_path, CHUNK_1MB as u64, &hash)
+        .await
+        .expect_err("Altered file must fail verification");
+    assert!(err.to_string().contains("SHA-256 mismatch"), "{err}");
+    assert!(temp_dir.path().join("flipped.bin.corrupt").exists());
+}
+
 #[tokio::test]
 async fn test_cleanup_explicit() {
     let temp_dir = setup_temp_dir();
@@ -343,8 +520,9 @@ async fn test_concurrent_chunk_writes() {
     assert_eq!(storage.chunk_count(), num_chunks as usize);
 
     // Verify no data corruption by checking patterns
+    let partial_path = storage.partial_path().clone();
     drop(storage);
-    let contents = tokio::fs::read(&file_path)
+    let contents = tokio::fs::read(&partial_path)
         .await
         .expect("Failed to read test file");
 
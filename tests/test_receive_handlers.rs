@@ -1,7 +1,8 @@
 mod common;
 
+use archdrop::common::config::{receive_body_limit, TransferSettings};
 use archdrop::crypto::types::{EncryptionKey, Nonce};
-use archdrop::receive::ReceiveAppState;
+use archdrop::receive::{ConflictPolicy, ReceiveAppState, TarSink};
 use archdrop::server::progress::ProgressTracker;
 use archdrop::server::routes;
 use axum::{
@@ -9,9 +10,9 @@ use axum::{
     http::{Method, Request, StatusCode},
     Router,
 };
-use common::{create_cipher, default_config, setup_temp_dir, CHUNK_SIZE};
+use common::{create_cipher, default_config, encrypt_chunk, setup_temp_dir, CHUNK_SIZE};
 use http_body_util::BodyExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tower::ServiceExt;
 
@@ -175,8 +176,15 @@ async fn test_complete_file_upload() {
     // Send chunk
     let cipher = create_cipher(&key);
     let mut encrypted = file_data.to_vec();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted, 0)
-        .expect("Failed to encrypt chunk");
+    encrypt_chunk(
+        &cipher,
+        &nonce,
+        &mut encrypted,
+        0,
+        0,
+        file_data.len() as u64,
+    )
+    .expect("Failed to encrypt chunk");
 
     let request = with_lock_token(
         build_multipart_request(
@@ -273,7 +281,7 @@ async fn test_out_of_order_chunks() {
 
     // Chunk 2
     let mut encrypted2 = chunk2.clone();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted2, 2)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted2, 0, 2, file_size)
         .expect("Failed to encrypt chunk 2");
     let request = with_lock_token(
         build_multipart_request(
@@ -295,7 +303,7 @@ async fn test_out_of_order_chunks() {
 
     // Chunk 0
     let mut encrypted0 = chunk0.clone();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted0, 0)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted0, 0, 0, file_size)
         .expect("Failed to encrypt chunk 0");
     let request = with_lock_token(
         build_multipart_request(
@@ -317,7 +325,7 @@ async fn test_out_of_order_chunks() {
 
     // Chunk 1
     let mut encrypted1 = chunk1.clone();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted1, 1)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted1, 0, 1, file_size)
         .expect("Failed to encrypt chunk 1");
     let request = with_lock_token(
         build_multipart_request(
@@ -414,13 +422,8 @@ async fn test_concurrent_chunks_same_file() {
             // Encrypt
             let cipher = create_cipher(&key);
             let mut encrypted = chunk_data.clone();
-            archdrop::crypto::encrypt_chunk_in_place(
-                &cipher,
-                &nonce,
-                &mut encrypted,
-                chunk_idx as u32,
-            )
-            .expect("Failed to encrypt chunk");
+            encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, chunk_idx, file_size)
+                .expect("Failed to encrypt chunk");
 
             // Upload
             let request = with_lock_token(
@@ -518,8 +521,15 @@ async fn test_chunk_wrong_nonce() {
     // Encrypt with WRONG nonce
     let cipher = create_cipher(&key);
     let mut encrypted = data.to_vec();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &wrong_nonce, &mut encrypted, 0)
-        .expect("Failed to encrypt chunk");
+    encrypt_chunk(
+        &cipher,
+        &wrong_nonce,
+        &mut encrypted,
+        0,
+        0,
+        data.len() as u64,
+    )
+    .expect("Failed to encrypt chunk");
 
     // Upload chunk with correct nonce in metadata but wrong encrypted data
     let request = with_lock_token(
@@ -561,7 +571,7 @@ async fn test_chunk_without_manifest() {
     let nonce = Nonce::new();
     let cipher = create_cipher(&key);
     let mut encrypted = data.to_vec();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted, 0)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, 0, data.len() as u64)
         .expect("Failed to encrypt chunk");
 
     // Skip manifest, send chunk directly
@@ -623,7 +633,7 @@ async fn test_duplicate_chunk_detection() {
     // Encrypt chunk
     let cipher = create_cipher(&key);
     let mut encrypted = data.to_vec();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted, 0)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, 0, data.len() as u64)
         .expect("Failed to encrypt chunk");
 
     // Upload chunk 0
@@ -712,7 +722,7 @@ async fn test_premature_finalize_does_not_break_chunk_retries() {
 
     // Upload first chunk only
     let mut encrypted0 = chunk0.clone();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted0, 0)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted0, 0, 0, file_size)
         .expect("Failed to encrypt chunk 0");
     let request = with_lock_token(
         build_multipart_request(
@@ -748,7 +758,7 @@ async fn test_premature_finalize_does_not_break_chunk_retries() {
 
     // Retry remaining chunk should still work after failed finalize
     let mut encrypted1 = chunk1.clone();
-    archdrop::crypto::encrypt_chunk_in_place(&cipher, &nonce, &mut encrypted1, 1)
+    encrypt_chunk(&cipher, &nonce, &mut encrypted1, 0, 1, file_size)
         .expect("Failed to encrypt chunk 1");
     let request = with_lock_token(
         build_multipart_request(
@@ -876,6 +886,63 @@ async fn test_manifest_rejects_nonce_counter_overflow() {
     );
 }
 
+#[tokio::test]
+async fn test_completed_session_reloaded_from_disk_refuses_a_second_upload() {
+    use archdrop::common::{DataDir, Session, SessionSnapshot};
+
+    let temp_dir = setup_temp_dir();
+    let key = EncryptionKey::new();
+    let (app, state) = create_test_app(temp_dir.path().to_path_buf(), key.clone());
+    let token = state.session.token().to_string();
+    let manifest = serde_json::json!({
+        "files": [{ "relative_path": "once.bin", "size": 0 }]
+    });
+
+    let request = build_json_request("/receive/manifest", manifest.clone(), &token);
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock_token = extract_json(response).await["lockToken"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let request = with_lock_token(
+        build_json_request("/receive/complete", serde_json::json!({}), &token),
+        &lock_token,
+    );
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Persist the session and reload it as a restarted process would
+    let data_dir = DataDir::new(temp_dir.path().join("state"));
+    data_dir
+        .write_state("sessions", "receive.json", &state.session.snapshot())
+        .unwrap();
+    let snapshot: SessionSnapshot = data_dir
+        .read_state("sessions", "receive.json")
+        .unwrap()
+        .expect("snapshot written");
+    let reloaded = ReceiveAppState::with_session(
+        Session::restore(key, snapshot),
+        temp_dir.path().to_path_buf(),
+        Arc::new(ProgressTracker::new()),
+        default_config(),
+    );
+    let app = routes::create_receive_router(&reloaded);
+
+    let request = build_json_request("/receive/manifest", manifest, &token);
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let json = extract_json(response).await;
+    assert!(json.to_string().contains("session completed"), "{json}");
+
+    let request = with_lock_token(
+        build_json_request("/receive/complete", serde_json::json!({}), &token),
+        &lock_token,
+    );
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
 #[tokio::test]
 async fn test_manifest_accepts_small_transfer() {
     let temp_dir = setup_temp_dir();
@@ -992,6 +1059,159 @@ async fn test_manifest_rejects_on_insufficient_space() {
     );
 }
 
+#[tokio::test]
+async fn test_manifest_rejects_size_over_reported_free_space() {
+    let temp_dir = setup_temp_dir();
+    let key = EncryptionKey::new();
+    // Pretend the destination disk has exactly 2 GiB free
+    let state = ReceiveAppState::builder(
+        key,
+        temp_dir.path().to_path_buf(),
+        Arc::new(ProgressTracker::new()),
+        default_config(),
+    )
+    .with_space_probe(|_| Some(2 * 1024 * 1024 * 1024))
+    .build();
+    let app = routes::create_receive_router(&state);
+    let token = state.session.token().to_string();
+
+    // Fits on its own, but not with the safety margin on top
+    let manifest = serde_json::json!({
+        "files": [{ "relative_path": "big.bin", "size": 1536u64 * 1024 * 1024 }]
+    });
+    let request = build_json_request("/receive/manifest", manifest, &token);
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+    let json = extract_json(response).await;
+    assert_eq!(json["error"]["type"], "insufficient_storage");
+    assert!(
+        !temp_dir.path().join("big.bin.partial").exists(),
+        "nothing is allocated before the space check"
+    );
+}
+
+#[tokio::test]
+async fn test_body_limit_scales_with_chunk_size() {
+    let temp_dir = setup_temp_dir();
+    let key = EncryptionKey::new();
+    // Larger than the old fixed 25 MiB request cap
+    let chunk_size = 32 * 1024 * 1024;
+    let config = TransferSettings {
+        chunk_size,
+        ..default_config()
+    };
+    let state = ReceiveAppState::new(
+        key.clone(),
+        temp_dir.path().to_path_buf(),
+        Arc::new(ProgressTracker::new()),
+        config,
+    );
+    let app = routes::create_receive_router(&state);
+    let token = state.session.token().to_string();
+
+    let manifest = serde_json::json!({
+        "files": [{ "relative_path": "big.bin", "size": chunk_size }]
+    });
+    let response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock_token = extract_json(response).await["lockToken"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // A full-size chunk is accepted
+    let nonce = Nonce::new();
+    let mut encrypted = create_test_data(0x42, chunk_size as usize);
+    encrypt_chunk(
+        &create_cipher(&key),
+        &nonce,
+        &mut encrypted,
+        0,
+        0,
+        chunk_size,
+    )
+    .unwrap();
+    let request = with_lock_token(
+        build_multipart_request(
+            "/receive/chunk",
+            "big.bin",
+            0,
+            1,
+            chunk_size,
+            &nonce.to_base64(),
+            encrypted,
+            &token,
+        ),
+        &lock_token,
+    );
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A body just past the computed limit is refused
+    let oversized = create_test_data(0x42, receive_body_limit(chunk_size) + 1);
+    let request = with_lock_token(
+        build_multipart_request(
+            "/receive/chunk",
+            "big.bin",
+            0,
+            1,
+            chunk_size,
+            &nonce.to_base64(),
+            oversized,
+            &token,
+        ),
+        &lock_token,
+    );
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_chunk_past_session_chunk_size_is_refused() {
+    let temp_dir = setup_temp_dir();
+    let key = EncryptionKey::new();
+    let (app, state) = create_test_app(temp_dir.path().to_path_buf(), key);
+    let token = state.session.token().to_string();
+
+    let manifest = serde_json::json!({
+        "files": [{ "relative_path": "big.bin", "size": CHUNK_SIZE * 2 }]
+    });
+    let response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock_token = extract_json(response).await["lockToken"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Within the router body limit, but more than one chunk plus its tag
+    let oversized = create_test_data(0x42, CHUNK_SIZE + 17);
+    let request = with_lock_token(
+        build_multipart_request(
+            "/receive/chunk",
+            "big.bin",
+            0,
+            2,
+            (CHUNK_SIZE * 2) as u64,
+            &Nonce::new().to_base64(),
+            oversized,
+            &token,
+        ),
+        &lock_token,
+    );
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(!temp_dir.path().join("big.bin").exists());
+}
+
 #[tokio::test]
 async fn test_manifest_empty_files_list() {
     let temp_dir = setup_temp_dir();
@@ -1019,3 +1239,544 @@ async fn test_manifest_empty_files_list() {
         "Empty manifest should be accepted"
     );
 }
+
+#[tokio::test]
+async fn test_resume_token_reattaches_with_received_chunk_state() {
+    let temp_dir = setup_temp_dir();
+    let key = EncryptionKey::new();
+    let (app, state) = create_test_app(temp_dir.path().to_path_buf(), key.clone());
+    let token = state.session.token().to_string();
+
+    let file_size = (2 * CHUNK_SIZE) as u64;
+    let nonce = Nonce::new();
+    let manifest = serde_json::json!({
+        "files": [{ "relative_path": "resume.bin", "size": file_size }]
+    });
+    let manifest_response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .expect("Failed to send manifest");
+    let manifest_json = extract_json(manifest_response).await;
+    let lock_token = manifest_json["lockToken"].as_str().unwrap().to_string();
+    let resume_token = manifest_json["resumeToken"].as_str().unwrap().to_string();
+
+    let cipher = create_cipher(&key);
+    let upload_chunk = |chunk_index: usize, lock: String| {
+        let mut encrypted = create_test_data(chunk_index as u8, CHUNK_SIZE);
+        encrypt_chunk(&cipher, &nonce, &mut encrypted, 0, chunk_index, file_size)
+            .expect("Failed to encrypt chunk");
+        with_lock_token(
+            build_multipart_request(
+                "/receive/chunk",
+                "resume.bin",
+                chunk_index,
+                2,
+                file_size,
+                &nonce.to_base64(),
+                encrypted,
+                &token,
+            ),
+            &lock,
+        )
+    };
+
+    let response = app
+        .clone()
+        .oneshot(upload_chunk(0, lock_token.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A wrong resume token is rejected and leaves the original lock usable
+    let bad = build_json_request(
+        "/receive/resume",
+        serde_json::json!({ "resumeToken": "not-the-token" }),
+        &token,
+    );
+    let response = app.clone().oneshot(bad).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Re-attach as a recreated browser session would
+    let resume = build_json_request(
+        "/receive/resume",
+        serde_json::json!({ "resumeToken": resume_token }),
+        &token,
+    );
+    let response = app.clone().oneshot(resume).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let resumed = extract_json(response).await;
+    let new_lock = resumed["lockToken"].as_str().unwrap().to_string();
+    assert_ne!(new_lock, lock_token);
+    assert_eq!(resumed["files"][0]["relative_path"], "resume.bin");
+    assert_eq!(resumed["files"][0]["received"], serde_json::json!([0]));
+    assert_eq!(resumed["files"][0]["nonce"], nonce.to_base64());
+
+    // The old lock no longer works; the new one continues the same file
+    let response = app
+        .clone()
+        .oneshot(upload_chunk(1, lock_token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(upload_chunk(1, new_lock.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let finalize = with_lock_token(
+        build_finalize_request("/receive/finalize", "resume.bin", &token),
+        &new_lock,
+    );
+    let response = app.clone().oneshot(finalize).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let written = std::fs::read(temp_dir.path().join("resume.bin")).unwrap();
+    assert_eq!(written.len(), 2 * CHUNK_SIZE);
+    assert!(written[..CHUNK_SIZE].iter().all(|b| *b == 0));
+    assert!(written[CHUNK_SIZE..].iter().all(|b| *b == 1));
+}
+
+#[tokio::test]
+async fn test_tar_output_extracts_to_received_files() {
+    let temp_dir = setup_temp_dir();
+    let tar_path = temp_dir.path().join("out.tar");
+    let sink = Arc::new(TarSink::create(&tar_path).expect("create tar sink"));
+    let staging = sink.staging_dir().to_path_buf();
+
+    let key = EncryptionKey::new();
+    let state = ReceiveAppState::builder(
+        key.clone(),
+        staging.clone(),
+        Arc::new(ProgressTracker::new()),
+        default_config(),
+    )
+    .with_tar_sink(sink)
+    .build();
+    let app = routes::create_receive_router(&state);
+    let token = state.session.token().to_string();
+
+    // Two chunks for the nested file, uploaded out of order
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("notes.txt", b"hello tar".to_vec()),
+        ("dir/data.bin", {
+            let mut data = create_test_data(7, CHUNK_SIZE);
+            data.extend(create_test_data(9, 100));
+            data
+        }),
+    ];
+    let manifest = serde_json::json!({
+        "files": files
+            .iter()
+            .map(|(path, data)| serde_json::json!({"relative_path": path, "size": data.len()}))
+            .collect::<Vec<_>>()
+    });
+    let response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock_token = extract_json(response).await["lockToken"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Staged plaintext is readable by the receiving user only
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&staging), 0o700);
+        let staged: Vec<PathBuf> = [staging.clone(), staging.join("dir")]
+            .iter()
+            .flat_map(|dir| std::fs::read_dir(dir).unwrap())
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .collect();
+        assert_eq!(staged.len(), 2);
+        for path in staged {
+            assert_eq!(mode(&path), 0o600, "{}", path.display());
+        }
+    }
+
+    let cipher = create_cipher(&key);
+    for (file_index, (path, data)) in files.iter().enumerate() {
+        let nonce = Nonce::new();
+        let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+        for chunk_index in (0..chunks.len()).rev() {
+            let mut encrypted = chunks[chunk_index].to_vec();
+            encrypt_chunk(
+                &cipher,
+                &nonce,
+                &mut encrypted,
+                file_index,
+                chunk_index,
+                data.len() as u64,
+            )
+            .unwrap();
+            let request = with_lock_token(
+                build_multipart_request(
+                    "/receive/chunk",
+                    path,
+                    chunk_index,
+                    chunks.len(),
+                    data.len() as u64,
+                    &nonce.to_base64(),
+                    encrypted,
+                    &token,
+                ),
+                &lock_token,
+            );
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let finalize = with_lock_token(
+            build_finalize_request("/receive/finalize", path, &token),
+            &lock_token,
+        );
+        let response = app.clone().oneshot(finalize).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let complete = Request::builder()
+        .method(Method::POST)
+        .uri("/receive/complete")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("X-Transfer-Lock", &lock_token)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(complete).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Staged copies are gone once framed
+    assert!(!staging.join("notes.txt").exists());
+    assert!(!staging.join("dir/data.bin").exists());
+
+    let extract_dir = temp_dir.path().join("extracted");
+    tar::Archive::new(std::fs::File::open(&tar_path).unwrap())
+        .unpack(&extract_dir)
+        .expect("tar stream should extract");
+    for (path, data) in &files {
+        assert_eq!(&std::fs::read(extract_dir.join(path)).unwrap(), data);
+    }
+}
+
+#[tokio::test]
+async fn test_received_out_lists_written_files() {
+    let temp_dir = setup_temp_dir();
+    let output_dir = temp_dir.path().join("out");
+    std::fs::create_dir_all(&output_dir).unwrap();
+    let received_out = temp_dir.path().join("received.json");
+
+    let key = EncryptionKey::new();
+    let state = ReceiveAppState::builder(
+        key.clone(),
+        output_dir.clone(),
+        Arc::new(ProgressTracker::new()),
+        default_config(),
+    )
+    .with_received_out(received_out.clone())
+    .build();
+    let app = routes::create_receive_router(&state);
+    let token = state.session.token().to_string();
+
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("notes.txt", b"hello".to_vec()),
+        ("dir/data.bin", create_test_data(3, CHUNK_SIZE + 10)),
+    ];
+    let manifest = serde_json::json!({
+        "files": files
+            .iter()
+            .map(|(path, data)| serde_json::json!({"relative_path": path, "size": data.len()}))
+            .collect::<Vec<_>>()
+    });
+    let response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock_token = extract_json(response).await["lockToken"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let cipher = create_cipher(&key);
+    for (file_index, (path, data)) in files.iter().enumerate() {
+        let nonce = Nonce::new();
+        let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+        for (chunk_index, chunk) in chunks.iter().enumerate() {
+            let mut encrypted = chunk.to_vec();
+            encrypt_chunk(
+                &cipher,
+                &nonce,
+                &mut encrypted,
+                file_index,
+                chunk_index,
+                data.len() as u64,
+            )
+            .unwrap();
+            let request = with_lock_token(
+                build_multipart_request(
+                    "/receive/chunk",
+                    path,
+                    chunk_index,
+                    chunks.len(),
+                    data.len() as u64,
+                    &nonce.to_base64(),
+                    encrypted,
+                    &token,
+                ),
+                &lock_token,
+            );
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let finalize = with_lock_token(
+            build_finalize_request("/receive/finalize", path, &token),
+            &lock_token,
+        );
+        let response = app.clone().oneshot(finalize).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert!(!received_out.exists(), "written only at completion");
+
+    let complete = Request::builder()
+        .method(Method::POST)
+        .uri("/receive/complete")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("X-Transfer-Lock", &lock_token)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(complete).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&received_out).unwrap()).unwrap();
+    let listed = report["files"].as_array().expect("files array");
+    assert_eq!(listed.len(), files.len());
+    for ((name, data), entry) in files.iter().zip(listed) {
+        let path = PathBuf::from(entry["path"].as_str().expect("path"));
+        assert_eq!(entry["name"], *name);
+        assert_eq!(entry["size"], data.len() as u64);
+        assert_eq!(path, output_dir.join(name));
+        assert_eq!(&std::fs::read(&path).unwrap(), data);
+        assert_eq!(
+            entry["sha256"],
+            archdrop::crypto::hash::calculate_file_hash(&path).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_finalize_rejects_file_corrupted_after_reassembly() {
+    let temp_dir = setup_temp_dir();
+    let key = EncryptionKey::new();
+    let (app, state) = create_test_app(temp_dir.path().to_path_buf(), key.clone());
+    let token = state.session.token().to_string();
+
+    let data = b"integrity matters".to_vec();
+    let hash = archdrop::crypto::hash::calculate_file_hash(&{
+        let source = temp_dir.path().join("source.bin");
+        std::fs::write(&source, &data).unwrap();
+        source
+    })
+    .unwrap();
+    let manifest = serde_json::json!({
+        "files": [{"relative_path": "checked.bin", "size": data.len(), "hash": hash}]
+    });
+    let response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock_token = extract_json(response).await["lockToken"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let nonce = Nonce::new();
+    let mut encrypted = data.clone();
+    encrypt_chunk(
+        &create_cipher(&key),
+        &nonce,
+        &mut encrypted,
+        0,
+        0,
+        data.len() as u64,
+    )
+    .unwrap();
+    let request = with_lock_token(
+        build_multipart_request(
+            "/receive/chunk",
+            "checked.bin",
+            0,
+            1,
+            data.len() as u64,
+            &nonce.to_base64(),
+            encrypted,
+            &token,
+        ),
+        &lock_token,
+    );
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // tokio::fs writes land in the background; wait for the chunk first
+    let partial = temp_dir.path().join("checked.bin.partial");
+    let mut on_disk = std::fs::read(&partial).unwrap();
+    for _ in 0..100 {
+        if on_disk == data {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        on_disk = std::fs::read(&partial).unwrap();
+    }
+    assert_eq!(on_disk, data);
+
+    // Flip a byte in the reassembled file behind the server's back
+    on_disk[0] ^= 0xFF;
+    std::fs::write(&partial, on_disk).unwrap();
+
+    let finalize = with_lock_token(
+        build_finalize_request("/receive/finalize", "checked.bin", &token),
+        &lock_token,
+    );
+    let response = app.clone().oneshot(finalize).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = extract_json(response).await;
+    assert!(body.to_string().contains("SHA-256 mismatch"), "{body}");
+
+    // Neither the corrupt file nor its partial copy is left behind
+    assert!(!temp_dir.path().join("checked.bin").exists());
+    assert!(!partial.exists());
+}
+
+/// Upload `report.txt` into a destination that already has one.
+///
+/// Returns the temp dir and the manifest response; skipped files are not sent.
+async fn upload_over_existing(
+    policy: ConflictPolicy,
+) -> (tempfile::TempDir, ReceiveAppState, serde_json::Value) {
+    let temp_dir = setup_temp_dir();
+    std::fs::write(temp_dir.path().join("report.txt"), b"old contents").unwrap();
+
+    let key = EncryptionKey::new();
+    let state = ReceiveAppState::builder(
+        key.clone(),
+        temp_dir.path().to_path_buf(),
+        Arc::new(ProgressTracker::new()),
+        default_config(),
+    )
+    .with_conflict_policy(policy)
+    .build();
+    let app = routes::create_receive_router(&state);
+    let token = state.session.token().to_string();
+
+    let data = b"new contents".to_vec();
+    let manifest = serde_json::json!({
+        "files": [{"relative_path": "report.txt", "size": data.len()}]
+    });
+    let response = app
+        .clone()
+        .oneshot(build_json_request("/receive/manifest", manifest, &token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let manifest_response = extract_json(response).await;
+    if manifest_response["skipped"] == serde_json::json!(["report.txt"]) {
+        return (temp_dir, state, manifest_response);
+    }
+    let lock_token = manifest_response["lockToken"].as_str().unwrap().to_string();
+
+    let nonce = Nonce::new();
+    let mut encrypted = data.clone();
+    encrypt_chunk(
+        &create_cipher(&key),
+        &nonce,
+        &mut encrypted,
+        0,
+        0,
+        data.len() as u64,
+    )
+    .unwrap();
+    let request = with_lock_token(
+        build_multipart_request(
+            "/receive/chunk",
+            "report.txt",
+            0,
+            1,
+            data.len() as u64,
+            &nonce.to_base64(),
+            encrypted,
+            &token,
+        ),
+        &lock_token,
+    );
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let finalize = with_lock_token(
+        build_finalize_request("/receive/finalize", "report.txt", &token),
+        &lock_token,
+    );
+    let response = app.oneshot(finalize).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    (temp_dir, state, manifest_response)
+}
+
+#[tokio::test]
+async fn test_on_conflict_rename_keeps_both_files() {
+    let (temp_dir, _state, manifest) = upload_over_existing(ConflictPolicy::Rename).await;
+
+    assert_eq!(manifest["skipped"], serde_json::json!([]));
+    let dir = temp_dir.path();
+    assert_eq!(
+        std::fs::read(dir.join("report.txt")).unwrap(),
+        b"old contents"
+    );
+    assert_eq!(
+        std::fs::read(dir.join("report (1).txt")).unwrap(),
+        b"new contents"
+    );
+}
+
+#[tokio::test]
+async fn test_on_conflict_overwrite_replaces_existing_file() {
+    let (temp_dir, _state, _) = upload_over_existing(ConflictPolicy::Overwrite).await;
+
+    let dir = temp_dir.path();
+    assert_eq!(
+        std::fs::read(dir.join("report.txt")).unwrap(),
+        b"new contents"
+    );
+    assert!(!dir.join("report (1).txt").exists());
+    assert!(!dir.join("report.txt.partial").exists());
+}
+
+#[tokio::test]
+async fn test_on_conflict_skip_leaves_existing_file_and_reports_it() {
+    let (temp_dir, state, manifest) = upload_over_existing(ConflictPolicy::Skip).await;
+
+    assert_eq!(manifest["skipped"], serde_json::json!(["report.txt"]));
+    assert_eq!(manifest["total_chunks"], 0);
+    let dir = temp_dir.path();
+    assert_eq!(
+        std::fs::read(dir.join("report.txt")).unwrap(),
+        b"old contents"
+    );
+    assert_eq!(std::fs::read_dir(dir).unwrap().count(), 1, "nothing staged");
+
+    let snapshot = state.progress.snapshot();
+    assert!(matches!(
+        snapshot.files[0].status,
+        archdrop::common::FileStatus::Skipped(ref reason) if reason == "already exists"
+    ));
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use archdrop::common::config::Transport;
+use archdrop::common::TransferEvent;
+use archdrop::crypto::types::{EncryptionKey, Nonce};
+use archdrop::server::TransferBuilder;
+use common::{create_cipher, decrypt_chunk, setup_temp_dir};
+use std::collections::HashMap;
+
+/// `token`, `key`, `nonce` and `v` from a link's `#` fragment.
+fn fragment_params(url: &str) -> HashMap<String, String> {
+    let (_, fragment) = url.split_once('#').expect("link should carry a fragment");
+    fragment
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn builder_serves_a_full_transfer_to_an_http_client() {
+    let temp_dir = setup_temp_dir();
+    let path = temp_dir.path().join("notes.txt");
+    let content = b"sent through the library API".repeat(100);
+    std::fs::write(&path, &content).unwrap();
+
+    let transfer = TransferBuilder::new()
+        .add_file(&path)
+        .transport(Transport::Local)
+        .build()
+        .await
+        .expect("builder should start the server");
+    let url = transfer.url().to_string();
+    assert!(url.contains(&format!(":{}/send#", transfer.port())));
+    assert!(!transfer.qr_code().is_empty());
+    let mut progress = transfer.progress_receiver();
+
+    let params = fragment_params(&url);
+    assert_eq!(params["v"], "2", "new links bind chunks to their position");
+    let token = &params["token"];
+    let key = EncryptionKey::from_base64(&params["key"]).unwrap();
+    let base = url.split_once("/send#").unwrap().0.to_string();
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    let manifest: serde_json::Value = client
+        .get(format!("{}/send/manifest", base))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(manifest["files"][0]["name"], "notes.txt");
+    let lock_token = manifest["lockToken"].as_str().unwrap();
+    let nonce = Nonce::from_base64(manifest["files"][0]["nonce"].as_str().unwrap()).unwrap();
+
+    let mut chunk = client
+        .get(format!("{}/send/0/chunk/0", base))
+        .bearer_auth(token)
+        .header("X-Transfer-Lock", lock_token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap()
+        .to_vec();
+    let size = content.len() as u64;
+    decrypt_chunk(&create_cipher(&key), &nonce, &mut chunk, 0, 0, size)
+        .expect("chunk should decrypt with the link's key");
+    assert_eq!(chunk, content);
+
+    client
+        .post(format!("{}/send/complete", base))
+        .bearer_auth(token)
+        .header("X-Transfer-Lock", lock_token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let outcome = transfer.wait().await;
+    assert!(
+        matches!(outcome, TransferEvent::Completed { ref stats } if stats.files == 1),
+        "unexpected outcome: {outcome:?}"
+    );
+    progress
+        .wait_for(|snapshot| snapshot.is_finished())
+        .await
+        .expect("progress should report the finished transfer");
+
+    // The server is gone once wait() returns
+    assert!(client.get(format!("{}/health", base)).send().await.is_err());
+}
+
+#[tokio::test]
+async fn builder_without_files_is_rejected() {
+    let err = TransferBuilder::new().build().await.err().unwrap();
+    assert!(err.to_string().contains("at least one file"), "{err:#}");
+}
+
+#[tokio::test]
+async fn shutdown_before_any_download_reports_cancelled() {
+    let temp_dir = setup_temp_dir();
+    let path = temp_dir.path().join("unsent.bin");
+    std::fs::write(&path, [7u8; 64]).unwrap();
+
+    let transfer = TransferBuilder::new()
+        .add_file(&path)
+        .build()
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        transfer.shutdown().await,
+        TransferEvent::Cancelled
+    ));
+}
+
+#[tokio::test]
+async fn unclaimed_link_expires_and_wait_returns() {
+    let temp_dir = setup_temp_dir();
+    let path = temp_dir.path().join("expiring.bin");
+    std::fs::write(&path, [7u8; 64]).unwrap();
+
+    let transfer = TransferBuilder::new()
+        .add_file(&path)
+        .link_ttl(std::time::Duration::from_millis(200))
+        .build()
+        .await
+        .unwrap();
+
+    let outcome = tokio::time::timeout(std::time::Duration::from_secs(10), transfer.wait())
+        .await
+        .expect("wait() should return once the link expires");
+    assert!(
+        matches!(outcome, TransferEvent::Failed { ref reason } if reason == "Link expired"),
+        "{outcome:?}"
+    );
+}
@@ -175,3 +175,223 @@ fn handlers_are_sync_into_response_values() {
     assert_into_response(web::serve_shared_js());
     assert_into_response(web::serve_shared_css());
 }
+
+#[test]
+fn shared_js_argon2_parameters_match_server() {
+    use archdrop::crypto::password::{ARGON2_ITERATIONS, ARGON2_MEMORY_KIB, ARGON2_PARALLELISM};
+
+    for (name, value) in [
+        ("ARGON2_MEMORY_KIB", ARGON2_MEMORY_KIB),
+        ("ARGON2_ITERATIONS", ARGON2_ITERATIONS),
+        ("ARGON2_PARALLELISM", ARGON2_PARALLELISM),
+    ] {
+        let declaration = format!("const {} = {}\n", name, value);
+        assert!(SHARED_JS.contains(&declaration), "shared.js: {declaration}");
+    }
+}
+
+/// Run `script` after shared.js under Node and return its stdout, or `None`
+/// when Node is not installed.
+fn run_with_shared_js(script: &str) -> Option<String> {
+    let program = format!(
+        "require('vm').runInThisContext({});\n(async () => {{ {} }})().catch(err => {{ console.error(err); process.exit(1) }})",
+        serde_json::to_string(SHARED_JS).unwrap(),
+        script
+    );
+    let output = match std::process::Command::new("node")
+        .args(["-e", &program])
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("node not found; skipping shared.js check");
+            return None;
+        }
+        Err(err) => panic!("run node: {err}"),
+    };
+    assert!(
+        output.status.success(),
+        "node failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(String::from_utf8(output.stdout).unwrap().trim().to_string())
+}
+
+#[test]
+fn shared_js_rejects_unknown_fragment_versions() {
+    let script = "globalThis.history = { replaceState() {} }
+        globalThis.document = { title: '' }
+        globalThis.location = { pathname: '/send', search: '' }
+        for (const v of ['', '&v=1', '&v=2', '&v=3', '&v=0', '&v=two', '&v=2x']) {
+            _fragmentToken = null
+            _fragmentSuite = 1
+            globalThis.window = { location: { hash: '#token=t&key=k' + v } }
+            _parseFragment()
+            console.log(`${_fragmentToken}/${_fragmentKey}/${_fragmentSuite}`)
+        }";
+    let Some(output) = run_with_shared_js(script) else {
+        return;
+    };
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        ["t/k/1", "t/k/1", "t/k/2", "//1", "//1", "//1", "//1"]
+    );
+}
+
+#[test]
+fn download_js_counts_down_to_link_expired() {
+    let script = format!(
+        "const label = {{ hidden: true, textContent: '', classList: {{ add(c) {{ label.expiredClass = c }} }} }}
+        globalThis.history = {{ replaceState() {{}} }}
+        globalThis.location = {{ pathname: '/send', search: '' }}
+        globalThis.window = {{ location: {{ hash: '#token=t&key=k' }} }}
+        globalThis.document = {{ title: '', addEventListener() {{}}, getElementById: () => label }}
+        globalThis.fetch = async (url, init) => {{
+            console.log(`${{url}} ${{init.headers.Authorization}}`)
+            return {{ ok: true, json: async () => ({{ expiresInMs: deadline - now, expired: false }}) }}
+        }}
+        let now = 1000000
+        const deadline = now + 61500
+        Date.now = () => now
+        let tick = null
+        globalThis.setInterval = fn => {{ tick = fn; return 1 }}
+        globalThis.clearInterval = () => console.log('stopped')
+        require('vm').runInThisContext({})
+        console.log(await startLinkCountdown())
+        console.log(label.textContent)
+        now += 60000
+        await tick()
+        console.log(label.textContent)
+        now += 1500
+        await tick()
+        console.log(`${{label.textContent}}/${{label.expiredClass}}/${{label.hidden}}`)",
+        serde_json::to_string(DOWNLOAD_JS).unwrap()
+    );
+    let Some(output) = run_with_shared_js(&script) else {
+        return;
+    };
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        [
+            "/send/ttl Bearer t",
+            "false",
+            "Link expires in 1:02",
+            "/send/ttl Bearer t",
+            "Link expires in 0:02",
+            "stopped",
+            "Link expired/expired/false",
+        ]
+    );
+}
+
+#[test]
+fn download_js_discards_file_system_writes_that_fail_the_hash_check() {
+    let script = format!(
+        "globalThis.navigator = {{ userAgent: 'node' }}
+        globalThis.document = {{ addEventListener() {{}} }}
+        require('vm').runInThisContext({})
+        const chunk = new Uint8Array([1, 2, 3])
+        const digest = require('crypto').createHash('sha256').update(chunk).digest('hex')
+        for (const expected of ['00'.repeat(32), digest]) {{
+            const calls = []
+            globalThis.window = {{
+                showSaveFilePicker: async () => ({{
+                    createWritable: async () => ({{
+                        write: async () => calls.push('write'),
+                        close: async () => calls.push('close'),
+                        abort: async () => calls.push('abort'),
+                    }}),
+                }}),
+            }}
+            const manager = new DownloadManager('t', {{ chunk_size: 3, concurrency: 1 }})
+            manager.fetchAndDecrypt = async () => chunk
+            manager.expectedDigest = async () => ({{ sha256: expected }})
+            manager.updateProgress = () => {{}}
+            const entry = {{ index: 0, name: 'a.bin', size: 3 }}
+            const outcome = await manager.downloadToFileSystem(entry, null, null)
+                .then(() => 'ok', err => err.message.split(':')[0])
+            console.log(`${{outcome}} ${{calls.join(',')}}`)
+        }}",
+        serde_json::to_string(DOWNLOAD_JS).unwrap()
+    );
+    let Some(output) = run_with_shared_js(&script) else {
+        return;
+    };
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        ["SHA-256 mismatch for a.bin write,abort", "ok write,close"]
+    );
+}
+
+#[test]
+fn shared_js_file_mac_matches_server() {
+    use archdrop::crypto::{hash::file_mac, types::EncryptionKey};
+
+    // Same vector as the known-answer test in src/crypto/hash.rs
+    let key_bytes: [u8; 32] = std::array::from_fn(|i| i as u8);
+    let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    let expected = "4e17d5e6be2501ed95d75582cfa602739ad4102108f385c68263def47ba55676";
+    assert_eq!(
+        file_mac(&EncryptionKey::from_bytes(key_bytes), digest).unwrap(),
+        expected
+    );
+
+    let script = format!(
+        "const macKey = await deriveFileMacKey(new Uint8Array({key:?}), ['sign', 'verify'])
+        const digest = new Uint8Array('{digest}'.match(/../g).map(b => parseInt(b, 16)))
+        const mac = new Uint8Array(await crypto.subtle.sign('HMAC', macKey, digest))
+        console.log(Array.from(mac, b => b.toString(16).padStart(2, '0')).join(''))
+        console.log(await verifyFileMac(macKey, '{digest}', '{expected}'))",
+        key = key_bytes,
+    );
+    let Some(output) = run_with_shared_js(&script) else {
+        return;
+    };
+    assert_eq!(output, format!("{expected}\ntrue"));
+}
+
+const JS_HEX: &str =
+    "const hex = bytes => Array.from(bytes, b => b.toString(16).padStart(2, '0')).join('')";
+
+#[test]
+fn shared_js_argon2id_matches_rfc_vectors() {
+    // RFC 7693 appendix A (BLAKE2b-512 of "abc") and RFC 9106 section 5.3
+    let script = format!(
+        "{JS_HEX}
+        console.log(hex(blake2b(new TextEncoder().encode('abc'), 64)))
+        console.log(hex(argon2id(new Uint8Array(32).fill(1), new Uint8Array(16).fill(2), {{
+            memoryKib: 32, iterations: 3, parallelism: 4, outLen: 32,
+            secret: new Uint8Array(8).fill(3), associatedData: new Uint8Array(12).fill(4)
+        }})))"
+    );
+    let Some(output) = run_with_shared_js(&script) else {
+        return;
+    };
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        [
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659",
+        ]
+    );
+}
+
+#[test]
+fn shared_js_passphrase_key_matches_server() {
+    use archdrop::crypto::password::{derive_key, KeySalt};
+
+    // The link's salt and the production parameters, through both implementations
+    let salt = KeySalt::from_base64("AAECAwQFBgcICQoLDA0ODw").unwrap();
+    let key = derive_key("correct horse battery staple", &salt).unwrap();
+    let expected: String = key.as_bytes().iter().map(|b| format!("{b:02x}")).collect();
+
+    let script = format!(
+        "{JS_HEX}
+        console.log(hex(deriveKeyFromPassphrase('correct horse battery staple', '{}')))",
+        salt.to_base64()
+    );
+    let Some(output) = run_with_shared_js(&script) else {
+        return;
+    };
+    assert_eq!(output, expected);
+}